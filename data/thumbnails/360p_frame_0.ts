@@ -0,0 +1 @@
+360p frame 0
@@ -0,0 +1,4 @@
+720p frame 0
+720p frame 1
+720p frame 2
+720p frame 3
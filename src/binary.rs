@@ -0,0 +1,46 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// Binary encodings of the JSON output, for passing parsed playlists to other
+// services. Structs are encoded as maps keyed by field name in both, as in JSON.
+
+pub fn to_msgpack<T: Serialize + ?Sized>(v: &T) -> Result<Vec<u8>, String> {
+    rmp_serde::to_vec_named(v).map_err(|e| format!("Failed to encode MessagePack: {}", e))
+}
+
+pub fn from_msgpack<T: DeserializeOwned>(data: &[u8]) -> Result<T, String> {
+    rmp_serde::from_slice(data).map_err(|e| format!("Failed to decode MessagePack: {}", e))
+}
+
+pub fn to_cbor<T: Serialize + ?Sized>(v: &T) -> Result<Vec<u8>, String> {
+    let mut ret = vec![];
+    ciborium::into_writer(v, &mut ret).map_err(|e| format!("Failed to encode CBOR: {}", e))?;
+    Ok(ret)
+}
+
+pub fn from_cbor<T: DeserializeOwned>(data: &[u8]) -> Result<T, String> {
+    ciborium::from_reader(data).map_err(|e| format!("Failed to decode CBOR: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::MultivariantPlaylist;
+    use crate::parser;
+    use super::{from_cbor, from_msgpack, to_cbor, to_msgpack};
+
+    #[test]
+    fn test_round_trip() {
+        let pl = parser::parse_playlist(include_str!("../data/master_unenc_hdr10_all.m3u8")).unwrap();
+        let msgpack = to_msgpack(&pl).unwrap();
+        assert_eq!(from_msgpack::<MultivariantPlaylist>(&msgpack).unwrap(), pl);
+        let cbor = to_cbor(&pl).unwrap();
+        assert_eq!(from_cbor::<MultivariantPlaylist>(&cbor).unwrap(), pl);
+        // smaller than the JSON
+        let json = serde_json::to_vec(&pl).unwrap();
+        assert!(msgpack.len() < json.len() && cbor.len() < json.len());
+        // maps keyed by field name: fixmap then "independent_segments"
+        assert_eq!(msgpack[0] & 0xf0, 0x80);
+        assert_eq!(&msgpack[2..22], b"independent_segments");
+        assert!(from_cbor::<MultivariantPlaylist>(&msgpack).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use std::fs;
+use std::path::Path;
+
+use crate::parser;
+
+// Checks for projects shipping static playlists as assets, so an invalid
+// playlist fails their tests or build:
+//
+//     m3u_parser::assert_valid!(include_str!("../assets/master.m3u8"));
+//
+// or, from build.rs:
+//
+//     println!("cargo:rerun-if-changed=assets/master.m3u8");
+//     m3u_parser::check::check_file("assets/master.m3u8").unwrap();
+
+/* Parse the playlist, multivariant or media, and validate it. Returns the errors, one per line. */
+pub fn check(data: &str) -> Result<(), String> {
+    let report = match parser::parse(data).map_err(|e| e.to_string())?.validate() {
+        Ok(_) => return Ok(()),
+        Err(report) => report,
+    };
+    Err(report.errors().map(|f| f.to_string()).collect::<Vec<_>>().join("\n"))
+}
+
+/* Check the playlist at `path`. Errors are prefixed with the path. */
+pub fn check_file(path: impl AsRef<Path>) -> Result<(), String> {
+    let path = path.as_ref();
+    let data = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    check(&data).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/* Panic when the playlist is invalid, see check() */
+#[macro_export]
+macro_rules! assert_valid {
+    ($data:expr) => {
+        if let Err(e) = $crate::check::check($data) {
+            panic!("Invalid playlist: {}", e)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, check_file};
+
+    #[test]
+    fn test_check() {
+        crate::assert_valid!(include_str!("../data/master_unenc_hdr10_all.m3u8"));
+        crate::assert_valid!(include_str!("../data/ll_hls_playlist.m3u8"));
+        assert!(check(include_str!("../data/validation_error.m3u8")).is_err());
+        assert!(check(include_str!("../data/missing_extm3u.m3u8")).is_err());
+        assert!(check_file("data/media_playlist.m3u8").is_ok());
+        let e = check_file("data/truncated.m3u8").unwrap_err();
+        assert!(e.starts_with("data/truncated.m3u8: "));
+        assert!(check_file("data/nonexistent.m3u8").is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid playlist")]
+    fn test_assert_valid() {
+        crate::assert_valid!("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1000\n");
+    }
+}
@@ -0,0 +1,516 @@
+// Builders and parsing of codec identifiers as used in the CODECS attribute (RFC 6381)
+
+use std::fmt;
+
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AvcProfile {
+    Baseline, ConstrainedBaseline, Main, High,
+}
+
+impl AvcProfile {
+    fn profile_idc(self) -> u8 {
+        match self {
+            AvcProfile::Baseline | AvcProfile::ConstrainedBaseline => 0x42,
+            AvcProfile::Main => 0x4D,
+            AvcProfile::High => 0x64,
+        }
+    }
+
+    fn constraint_flags(self) -> u8 {
+        match self {
+            AvcProfile::Baseline => 0x00,
+            AvcProfile::ConstrainedBaseline => 0xE0,
+            AvcProfile::Main => 0x40,
+            AvcProfile::High => 0x00,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HevcProfile {
+    Main, Main10,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tier {
+    Main, High,
+}
+
+/* 
+AVC: avc1.PPCCLL, where PP is profile_idc, CC constraint flags and LL level_idc,
+all hexadecimal. Level is given as level_idc, i.e. 31 for level 3.1.
+*/
+pub fn avc1(profile: AvcProfile, level: u8) -> String {
+    format!("avc1.{:02X}{:02X}{:02X}", profile.profile_idc(), profile.constraint_flags(), level)
+}
+
+fn hevc(fourcc: &str, profile: HevcProfile, tier: Tier, level: u8) -> String {
+    // profile compatibility flags are written bit-reversed, as in ISO/IEC 14496-15 Annex E
+    let (profile_idc, compatibility) = match profile {
+        HevcProfile::Main => (1, 6),
+        HevcProfile::Main10 => (2, 4),
+    };
+    let tier = match tier {
+        Tier::Main => 'L',
+        Tier::High => 'H',
+    };
+    // constraint byte B0: progressive, non-packed, frame-only source
+    format!("{}.{}.{}.{}{}.B0", fourcc, profile_idc, compatibility, tier, level)
+}
+
+/* 
+HEVC with parameter sets in the sample entry: hvc1.P.C.TLL.B0
+Level is general_level_idc, i.e. 30 times the level number (150 for level 5).
+*/
+pub fn hvc1(profile: HevcProfile, tier: Tier, level: u8) -> String {
+    hevc("hvc1", profile, tier, level)
+}
+
+/* HEVC with in-band parameter sets, same syntax as hvc1 */
+pub fn hev1(profile: HevcProfile, tier: Tier, level: u8) -> String {
+    hevc("hev1", profile, tier, level)
+}
+
+/* MPEG-4 audio: mp4a.40.AOT, e.g. 2 for AAC-LC, 5 for HE-AAC, 29 for HE-AACv2 */
+pub fn mp4a(aot: u8) -> String {
+    format!("mp4a.40.{}", aot)
+}
+
+/* 
+AV1: av01.P.LLT.DD, where P is seq_profile, LL seq_level_idx (two digits),
+T the tier (M or H) and DD the bit depth (two digits).
+*/
+pub fn av01(profile: u8, level: u8, tier: Tier, bit_depth: u8) -> String {
+    let tier = match tier {
+        Tier::Main => 'M',
+        Tier::High => 'H',
+    };
+    format!("av01.{}.{:02}{}.{:02}", profile, level, tier, bit_depth)
+}
+
+// Level limits as (level, largest picture, luma sample rate). AVC in macroblocks,
+// H.264 Table A-1, HEVC in luma samples, H.265 Table A.8, AV1 in luma samples
+// (MaxPicSize and MaxDisplayRate), AV1 Annex A.
+const AVC_LEVELS: [(u32, u64, u64); 20] = [
+    (9, 99, 1485), (10, 99, 1485), (11, 396, 3000), (12, 396, 6000), (13, 396, 11880),
+    (20, 396, 11880), (21, 792, 19800), (22, 1620, 20250), (30, 1620, 40500), (31, 3600, 108000),
+    (32, 5120, 216000), (40, 8192, 245760), (41, 8192, 245760), (42, 8704, 522240), (50, 22080, 589824),
+    (51, 36864, 983040), (52, 36864, 2073600), (60, 139264, 4177920), (61, 139264, 8355840), (62, 139264, 16711680),
+];
+const HEVC_LEVELS: [(u32, u64, u64); 13] = [
+    (30, 36864, 552960), (60, 122880, 3686400), (63, 245760, 7372800), (90, 552960, 16588800),
+    (93, 983040, 33177600), (120, 2228224, 66846720), (123, 2228224, 133693440), (150, 8912896, 267386880),
+    (153, 8912896, 534773760), (156, 8912896, 1069547520), (180, 35651584, 1069547520),
+    (183, 35651584, 2139095040), (186, 35651584, 4278190080),
+];
+const AV1_LEVELS: [(u32, u64, u64); 14] = [
+    (0, 147456, 4423680), (1, 278784, 8363520), (4, 665856, 19975680), (5, 1065024, 31950720),
+    (8, 2359296, 70778880), (9, 2359296, 141557760), (12, 8912896, 267386880), (13, 8912896, 534773760),
+    (14, 8912896, 1069547520), (15, 8912896, 1069547520), (16, 35651584, 1069547520),
+    (17, 35651584, 2139095040), (18, 35651584, 4278190080), (19, 35651584, 4278190080),
+];
+
+/* Join individual codec identifiers into a CODECS attribute value */
+pub fn codecs_string<S: AsRef<str>>(codecs: &[S]) -> String {
+    codecs.iter().map(|c| c.as_ref()).collect::<Vec<_>>().join(",")
+}
+
+// Compression efficiency of video codecs relative to AVC, by sample entry: the
+// factor of quality per bit at the same bandwidth
+pub const VIDEO_EFFICIENCY: [(&str, f64); 8] = [
+    ("avc1", 1.0), ("avc3", 1.0), ("vp09", 1.4), ("hvc1", 1.5), ("hev1", 1.5),
+    ("dvh1", 1.5), ("dvhe", 1.5), ("av01", 1.7)];
+
+/* Efficiency of the first video codec in the list with one in VIDEO_EFFICIENCY, 1 otherwise */
+pub fn video_efficiency(codecs: Option<&Codecs>) -> f64 {
+    codecs.iter().flat_map(|c| c.iter())
+        .find_map(|c| VIDEO_EFFICIENCY.iter().find(|(fourcc, _)| *fourcc == c.fourcc()))
+        .map_or(1.0, |(_, e)| *e)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CodecFamily {
+    Avc, Hevc, DolbyVision, Av1, Vp9,
+    Aac, Mp3, Ac3, Ec3, Ac4, Opus, Flac, Alac,
+    WebVtt, Ttml,
+    Unknown,
+}
+
+impl CodecFamily {
+    /* Family of a sample entry, the part of the codec identifier before the first dot */
+    pub fn from_fourcc(fourcc: &str) -> Self {
+        match fourcc {
+            "avc1" | "avc3" => CodecFamily::Avc,
+            "hvc1" | "hev1" => CodecFamily::Hevc,
+            "dvh1" | "dvhe" | "dva1" | "dvav" => CodecFamily::DolbyVision,
+            "av01" => CodecFamily::Av1,
+            "vp09" => CodecFamily::Vp9,
+            "mp4a" => CodecFamily::Aac, // see Codec::parse for MP3
+            "ac-3" => CodecFamily::Ac3,
+            "ec-3" => CodecFamily::Ec3,
+            "ac-4" => CodecFamily::Ac4,
+            "Opus" | "opus" => CodecFamily::Opus,
+            "fLaC" => CodecFamily::Flac,
+            "alac" => CodecFamily::Alac,
+            "wvtt" => CodecFamily::WebVtt,
+            "stpp" => CodecFamily::Ttml,
+            _ => CodecFamily::Unknown,
+        }
+    }
+
+    pub fn is_video(self) -> bool {
+        matches!(self, CodecFamily::Avc | CodecFamily::Hevc | CodecFamily::DolbyVision | CodecFamily::Av1 | CodecFamily::Vp9)
+    }
+
+    pub fn is_audio(self) -> bool {
+        matches!(self, CodecFamily::Aac | CodecFamily::Mp3 | CodecFamily::Ac3 | CodecFamily::Ec3 | CodecFamily::Ac4
+            | CodecFamily::Opus | CodecFamily::Flac | CodecFamily::Alac)
+    }
+
+    /* Name used on the command line, e.g. "hevc" or "ec-3" */
+    pub fn parse(name: &str) -> Option<Self> {
+        let families = [
+            ("avc", CodecFamily::Avc), ("hevc", CodecFamily::Hevc), ("dolby-vision", CodecFamily::DolbyVision),
+            ("av1", CodecFamily::Av1), ("vp9", CodecFamily::Vp9), ("aac", CodecFamily::Aac), ("mp3", CodecFamily::Mp3),
+            ("ac-3", CodecFamily::Ac3), ("ec-3", CodecFamily::Ec3), ("ac-4", CodecFamily::Ac4),
+            ("opus", CodecFamily::Opus), ("flac", CodecFamily::Flac), ("alac", CodecFamily::Alac),
+            ("webvtt", CodecFamily::WebVtt), ("ttml", CodecFamily::Ttml),
+        ];
+        families.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, f)| *f)
+            .or_else(|| Some(CodecFamily::from_fourcc(name)).filter(|f| *f != CodecFamily::Unknown))
+    }
+}
+
+/*
+Codec identifier of a CODECS attribute, with profile, tier and level when the family
+has them. Values are as coded in the identifier: profile_idc and level_idc for AVC
+(31 for level 3.1), general_level_idc for HEVC (150 for level 5), seq_level_idx for AV1,
+the audio object type as profile for AAC (2 for AAC-LC).
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Codec {
+    pub text: String, // as written
+    pub family: CodecFamily,
+    pub profile: Option<u32>,
+    pub tier: Option<Tier>,
+    pub level: Option<u32>,
+}
+
+impl Codec {
+    pub fn parse(text: &str) -> Self {
+        let mut parts = text.split('.');
+        let fourcc = parts.next().unwrap_or("");
+        let params: Vec<&str> = parts.collect();
+        let mut family = CodecFamily::from_fourcc(fourcc);
+        let dec = |i: usize| params.get(i).and_then(|p| p.parse::<u32>().ok());
+        let (profile, tier, level) = match family {
+            CodecFamily::Avc if params.len() == 1 && params[0].len() == 6 => {
+                let hex = |i: usize| params[0].get(i..i + 2).and_then(|h| u32::from_str_radix(h, 16).ok());
+                (hex(0), None, hex(4))
+            },
+            CodecFamily::Avc => (dec(0), None, dec(1)), // legacy avc1.66.30
+            CodecFamily::Hevc => {
+                // general_profile_space is a letter prefix of the profile
+                let profile = params.first().and_then(|p| p.trim_start_matches(['A', 'B', 'C']).parse().ok());
+                let tier_level = params.get(2).copied().unwrap_or("");
+                let tier = match tier_level.chars().next() {
+                    Some('L') => Some(Tier::Main),
+                    Some('H') => Some(Tier::High),
+                    _ => None,
+                };
+                (profile, tier, tier.and_then(|_| tier_level[1..].parse().ok()))
+            },
+            CodecFamily::Av1 => {
+                let level_tier = params.get(1).copied().unwrap_or("");
+                let tier = match level_tier.chars().last() {
+                    Some('M') => Some(Tier::Main),
+                    Some('H') => Some(Tier::High),
+                    _ => None,
+                };
+                (dec(0), tier, tier.and_then(|_| level_tier[..level_tier.len() - 1].parse().ok()))
+            },
+            CodecFamily::DolbyVision | CodecFamily::Vp9 => (dec(0), None, dec(1)),
+            CodecFamily::Aac => match params.first() {
+                Some(&"40") => (dec(1), None, None),
+                Some(&"69") | Some(&"6B") | Some(&"6b") => {
+                    family = CodecFamily::Mp3;
+                    (None, None, None)
+                },
+                _ => (None, None, None),
+            },
+            _ => (None, None, None),
+        };
+        Codec{ text: text.to_string(), family, profile, tier, level }
+    }
+
+    /* Sample entry, e.g. "hvc1" */
+    pub fn fourcc(&self) -> &str {
+        self.text.split('.').next().unwrap_or("")
+    }
+
+    /*
+    Whether the identifier starts with the pattern, up to a dot if not whole
+    ("hvc1" or "hvc1.2.4" match hvc1.2.4.L93.90, "mp4a.40.2" does not match mp4a.40.29),
+    or is of the family when the pattern is a family name like "hevc"
+    */
+    pub fn matches(&self, pattern: &str) -> bool {
+        if CodecFamily::from_fourcc(pattern) == CodecFamily::Unknown {
+            if let Some(family) = CodecFamily::parse(pattern) {
+                return self.family == family
+            }
+        }
+        let text = self.text.to_ascii_lowercase();
+        let pattern = pattern.to_ascii_lowercase();
+        text == pattern || text.strip_prefix(&pattern).is_some_and(|rest| rest.starts_with('.'))
+    }
+
+    /* Whether the profile and level expected for the family could be read */
+    pub fn is_well_formed(&self) -> bool {
+        match self.family {
+            CodecFamily::Avc | CodecFamily::DolbyVision | CodecFamily::Vp9 => self.profile.is_some() && self.level.is_some(),
+            CodecFamily::Hevc | CodecFamily::Av1 => self.profile.is_some() && self.tier.is_some() && self.level.is_some(),
+            CodecFamily::Aac => self.profile.is_some(),
+            _ => true,
+        }
+    }
+
+    /*
+    Why the identifier is malformed, or None. RFC 6381: a four character sample entry,
+    then elements separated by dots, each of letters and digits. The profile and level
+    expected for the family must be readable too, see is_well_formed().
+    */
+    pub fn syntax_error(&self) -> Option<String> {
+        let mut parts = self.text.split('.');
+        let fourcc = parts.next().unwrap_or("");
+        if fourcc.len() != 4 || !fourcc.bytes().all(|b| b.is_ascii_graphic()) {
+            return Some(format!("sample entry {} is not four characters", fourcc))
+        }
+        if let Some(e) = parts.find(|e| e.is_empty() || !e.bytes().all(|b| b.is_ascii_alphanumeric())) {
+            return Some(if e.is_empty() { "empty element".to_string() } else { format!("element {} is not alphanumeric", e) })
+        }
+        if !self.is_well_formed() {
+            let expected = match self.family {
+                CodecFamily::Hevc | CodecFamily::Av1 => "profile, tier and level",
+                CodecFamily::Aac => "audio object type",
+                _ => "profile and level",
+            };
+            return Some(format!("no {}", expected))
+        }
+        None
+    }
+
+    // Level as usually written, e.g. 3.1 for AVC level_idc 31 or HEVC general_level_idc 93
+    fn level_name(&self) -> Option<String> {
+        let (major, minor) = match (self.family, self.level?) {
+            (CodecFamily::Avc, 9) => return Some("1b".to_string()),
+            (CodecFamily::Avc, l) => (l / 10, l % 10),
+            (CodecFamily::Hevc, l) => (l / 30, l % 30 / 3),
+            (CodecFamily::Av1, l) => (2 + l / 4, l % 4),
+            _ => return None,
+        };
+        Some(if minor == 0 { major.to_string() } else { format!("{}.{}", major, minor) })
+    }
+
+    /*
+    Why pictures of `width` x `height` at `frame_rate` exceed the level, or None. Checked for
+    AVC, HEVC and AV1 with a known level: the largest picture and the luma sample rate.
+    */
+    pub fn level_error(&self, width: u64, height: u64, frame_rate: Option<f64>) -> Option<String> {
+        let (levels, size): (&[(u32, u64, u64)], u64) = match self.family {
+            CodecFamily::Avc => (&AVC_LEVELS, width.div_ceil(16).saturating_mul(height.div_ceil(16))),
+            CodecFamily::Hevc => (&HEVC_LEVELS, width.saturating_mul(height)),
+            CodecFamily::Av1 => (&AV1_LEVELS, width.saturating_mul(height)),
+            _ => return None,
+        };
+        let level = self.level?;
+        let &(_, max_size, max_rate) = levels.iter().find(|(l, _, _)| *l == level)?;
+        let name = self.level_name()?;
+        if size > max_size {
+            return Some(format!("{}x{} above the largest picture of level {}", width, height, name))
+        }
+        match frame_rate {
+            Some(fps) if size as f64 * fps > max_rate as f64 =>
+                Some(format!("{}x{} at {} fps above the sample rate of level {}", width, height, fps, name)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+// CODECS attribute, comma-separated codec identifiers. Serialized as written, empty
+// identifiers too, e.g. of a trailing comma, which iter() skips.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "serde", serde(into = "String", try_from = "String"))]
+pub struct Codecs(pub Vec<Codec>);
+
+impl Codecs {
+    pub fn parse(s: &str) -> Self {
+        Codecs(s.split(',').map(|c| Codec::parse(c.trim())).collect())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Codec> {
+        self.0.iter().filter(|c| !c.text.is_empty())
+    }
+
+    // Number of empty identifiers
+    pub fn empty(&self) -> usize {
+        self.0.iter().filter(|c| c.text.is_empty()).count()
+    }
+
+    pub fn contains(&self, family: CodecFamily) -> bool {
+        self.iter().any(|c| c.family == family)
+    }
+
+    pub fn has_video(&self) -> bool {
+        self.iter().any(|c| c.family.is_video())
+    }
+
+    pub fn video(&self) -> Option<&Codec> {
+        self.iter().find(|c| c.family.is_video())
+    }
+
+    pub fn audio(&self) -> impl Iterator<Item = &Codec> {
+        self.iter().filter(|c| c.family.is_audio())
+    }
+}
+
+impl fmt::Display for Codecs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", codecs_string(&self.0.iter().map(|c| c.text.as_str()).collect::<Vec<_>>()))
+    }
+}
+
+impl From<Codecs> for String {
+    fn from(codecs: Codecs) -> Self {
+        codecs.to_string()
+    }
+}
+
+impl TryFrom<String> for Codecs {
+    type Error = String;
+    fn try_from(s: String) -> Result<Self, String> {
+        Ok(Codecs::parse(&s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_avc1() {
+        assert_eq!(avc1(AvcProfile::High, 40), "avc1.640028");
+        assert_eq!(avc1(AvcProfile::Main, 31), "avc1.4D401F");
+        assert_eq!(avc1(AvcProfile::ConstrainedBaseline, 30), "avc1.42E01E");
+    }
+
+    #[test]
+    fn test_video_efficiency() {
+        assert_eq!(video_efficiency(Some(&Codecs::parse("mp4a.40.2,hvc1.2.4.L90.90"))), 1.5);
+        assert_eq!(video_efficiency(Some(&Codecs::parse("mp4a.40.2"))), 1.0);
+        assert_eq!(video_efficiency(None), 1.0);
+    }
+
+    #[test]
+    fn test_hevc() {
+        assert_eq!(hvc1(HevcProfile::Main10, Tier::Main, 150), "hvc1.2.4.L150.B0");
+        assert_eq!(hev1(HevcProfile::Main, Tier::High, 93), "hev1.1.6.H93.B0");
+    }
+
+    #[test]
+    fn test_audio_and_av1() {
+        assert_eq!(mp4a(2), "mp4a.40.2");
+        assert_eq!(av01(0, 4, Tier::Main, 8), "av01.0.04M.08");
+        assert_eq!(av01(0, 13, Tier::High, 10), "av01.0.13H.10");
+    }
+
+    #[test]
+    fn test_codecs_string() {
+        let s = codecs_string(&[mp4a(2), hvc1(HevcProfile::Main10, Tier::Main, 90)]);
+        assert_eq!(s, "mp4a.40.2,hvc1.2.4.L90.B0");
+    }
+
+    #[test]
+    fn test_parse_codecs() {
+        let codecs = Codecs::parse("mp4a.40.2, hvc1.2.4.L150.90");
+        assert_eq!(codecs.to_string(), "mp4a.40.2,hvc1.2.4.L150.90");
+        let video = codecs.video().unwrap();
+        assert_eq!((video.family, video.profile, video.tier, video.level), (CodecFamily::Hevc, Some(2), Some(Tier::Main), Some(150)));
+        assert_eq!(video.fourcc(), "hvc1");
+        let audio: Vec<&Codec> = codecs.audio().collect();
+        assert_eq!((audio[0].family, audio[0].profile), (CodecFamily::Aac, Some(2)));
+        assert!(codecs.contains(CodecFamily::Aac) && !codecs.contains(CodecFamily::Ec3));
+
+        let avc = Codec::parse(&avc1(AvcProfile::High, 40));
+        assert_eq!((avc.family, avc.profile, avc.level), (CodecFamily::Avc, Some(0x64), Some(40)));
+        assert_eq!(Codec::parse("avc1.66.30").profile, Some(66));
+        let av1 = Codec::parse(&av01(0, 13, Tier::High, 10));
+        assert_eq!((av1.profile, av1.tier, av1.level), (Some(0), Some(Tier::High), Some(13)));
+        assert_eq!(Codec::parse("dvh1.05.06").level, Some(6));
+        assert_eq!(Codec::parse("mp4a.6B").family, CodecFamily::Mp3);
+        assert_eq!(Codec::parse("ec-3").family, CodecFamily::Ec3);
+        assert!(Codec::parse("ec-3").is_well_formed());
+        assert_eq!(Codec::parse("xyz1.2").family, CodecFamily::Unknown);
+        assert!(!Codec::parse("hvc1.2.4").is_well_formed());
+        assert!(!Codec::parse("avc1.64").is_well_formed());
+
+        assert_eq!(Codec::parse("hvc1.2.4.L93.90").syntax_error(), None);
+        assert_eq!(Codec::parse("ac-3").syntax_error(), None);
+        assert_eq!(Codec::parse("stpp.ttml.im1t").syntax_error(), None);
+        assert_eq!(Codec::parse("mp4a.40.2_").syntax_error().as_deref(), Some("element 2_ is not alphanumeric"));
+        assert_eq!(Codec::parse("avc1..64001f").syntax_error().as_deref(), Some("empty element"));
+        assert_eq!(Codec::parse("h264").syntax_error(), None);
+        assert_eq!(Codec::parse("H.264").syntax_error().as_deref(), Some("sample entry H is not four characters"));
+        assert_eq!(Codec::parse("hvc1.2.4").syntax_error().as_deref(), Some("no profile, tier and level"));
+        assert_eq!(Codec::parse("mp4a.40").syntax_error().as_deref(), Some("no audio object type"));
+
+        let hevc = Codec::parse("hvc1.2.4.L93.90");
+        assert!(hevc.matches("hvc1") && hevc.matches("HVC1.2.4") && hevc.matches("hevc"));
+        assert!(!hevc.matches("hev1") && !hevc.matches("hvc1.2.4.L9") && !hevc.matches("avc"));
+        assert!(!Codec::parse("mp4a.40.29").matches("mp4a.40.2"));
+        assert!(Codec::parse("ec-3").matches("ec-3"));
+        let codecs = Codecs::parse("avc1.640028,,mp4a.40.2");
+        assert_eq!((codecs.iter().count(), codecs.empty()), (2, 1));
+        assert_eq!(codecs.to_string(), "avc1.640028,,mp4a.40.2");
+
+        assert_eq!(CodecFamily::parse("HEVC"), Some(CodecFamily::Hevc));
+        assert_eq!(CodecFamily::parse("dvh1"), Some(CodecFamily::DolbyVision));
+        assert_eq!(CodecFamily::parse("h266"), None);
+    }
+
+    #[test]
+    fn test_level_error() {
+        // 1080p fits level 4 as 120x68 macroblocks
+        assert_eq!(Codec::parse("avc1.640028").level_error(1920, 1080, Some(30.0)), None);
+        assert_eq!(Codec::parse("avc1.640028").level_error(3840, 2160, None).as_deref(),
+            Some("3840x2160 above the largest picture of level 4"));
+        assert_eq!(Codec::parse("avc1.640028").level_error(1920, 1080, Some(60.0)).as_deref(),
+            Some("1920x1080 at 60 fps above the sample rate of level 4"));
+        assert_eq!(Codec::parse("avc1.42E01F").level_error(1280, 720, Some(30.0)), None);
+        assert_eq!(Codec::parse("hvc1.2.4.L93.90").level_error(1920, 1080, None).as_deref(),
+            Some("1920x1080 above the largest picture of level 3.1"));
+        assert_eq!(Codec::parse("hvc1.2.4.L150.90").level_error(3840, 2160, Some(30.0)), None);
+        assert!(Codec::parse("hvc1.2.4.L150.90").level_error(3840, 2160, Some(60.0)).is_some());
+        assert_eq!(Codec::parse("hvc1.2.4.L153.90").level_error(3840, 2160, Some(60.0)), None);
+        assert_eq!(Codec::parse(&av01(0, 8, Tier::Main, 8)).level_error(3840, 2160, None).as_deref(),
+            Some("3840x2160 above the largest picture of level 4"));
+        assert_eq!(Codec::parse(&av01(0, 13, Tier::Main, 10)).level_error(3840, 2160, Some(60.0)), None);
+        assert!(Codec::parse("avc1.640028").level_error(u64::MAX, u64::MAX, None).is_some());
+        assert!(Codec::parse("hvc1.2.4.L150.90").level_error(u64::MAX, 2, None).is_some());
+        // no limits known
+        assert_eq!(Codec::parse("dvh1.05.06").level_error(7680, 4320, Some(120.0)), None);
+        assert_eq!(Codec::parse("avc1.640063").level_error(7680, 4320, None), None);
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::format::{InstreamId, MediaType, MultivariantPlaylist};
+
+// Languages offered by the renditions of a multivariant playlist
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CaptionsCoverage {
+    pub language: Option<String>,
+    pub services: Vec<InstreamId>, // CEA-608 channels first, then CEA-708 services
+    pub groups: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CoverageReport {
+    pub audio_languages: Vec<String>,
+    pub subtitle_languages: Vec<String>,
+    pub captions: Vec<CaptionsCoverage>, // by language, renditions without LANGUAGE first
+}
+
+pub fn coverage(playlist: &MultivariantPlaylist) -> CoverageReport {
+    let languages = |type_: MediaType| -> Vec<String> {
+        playlist.media.iter().filter(|m| m.type_ == type_)
+            .filter_map(|m| m.language.clone())
+            .collect::<BTreeSet<String>>().into_iter().collect()
+    };
+    let mut captions: BTreeMap<Option<String>, (BTreeSet<InstreamId>, BTreeSet<String>)> = BTreeMap::new();
+    for m in playlist.media.iter().filter(|m| m.type_ == MediaType::ClosedCaptions) {
+        let (services, groups) = captions.entry(m.language.clone()).or_default();
+        services.extend(m.instream_id);
+        groups.insert(m.group_id.clone());
+    }
+    CoverageReport{
+        audio_languages: languages(MediaType::Audio),
+        subtitle_languages: languages(MediaType::Subtitles),
+        captions: captions.into_iter().map(|(language, (services, groups))| CaptionsCoverage{
+            language,
+            services: services.into_iter().collect(),
+            groups: groups.into_iter().collect(),
+        }).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::InstreamId;
+    use crate::parser;
+    use super::coverage;
+
+    #[test]
+    fn test_coverage() {
+        let pl = parser::parse_playlist(include_str!("../data/captions.m3u8")).unwrap();
+        let report = coverage(&pl);
+        assert_eq!(report.audio_languages, ["en", "es"]);
+        assert_eq!(report.subtitle_languages, ["fr"]);
+        assert_eq!(report.captions.len(), 3);
+        assert_eq!(report.captions[0].language, None);
+        assert_eq!(report.captions[0].services, [InstreamId::Cea708(2)]);
+        assert_eq!(report.captions[1].language.as_deref(), Some("en"));
+        assert_eq!(report.captions[1].services, [InstreamId::Cea608(1), InstreamId::Cea708(1)]);
+        assert_eq!(report.captions[1].groups, ["cc"]);
+        assert_eq!(report.captions[2].services, [InstreamId::Cea608(3)]);
+        #[cfg(feature = "serde")]
+        assert_eq!(serde_json::to_value(&report.captions[1].services).unwrap(), serde_json::json!(["CC1", "SERVICE1"]));
+    }
+}
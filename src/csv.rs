@@ -0,0 +1,9 @@
+// Quoting of CSV fields as in RFC 4180, for the csv outputs
+
+pub(crate) fn field(v: &str) -> String {
+    if v.contains([',', '"', '\n']) {
+        format!("\"{}\"", v.replace('"', "\"\""))
+    } else {
+        v.to_string()
+    }
+}
@@ -0,0 +1,144 @@
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+
+use crate::uri::PlaylistUri;
+
+// Loading of playlists and media resources from local files or http:/https: urls
+
+pub fn is_url(uri: &str) -> bool {
+    uri.starts_with("http://") || uri.starts_with("https://")
+}
+
+/* Resolve a (possibly relative) URI found in a playlist against the
+   URI of the playlist itself. Local paths are resolved against the
+   directory containing the playlist file. */
+pub fn resolve(base: &str, reference: &str) -> Result<String, String> {
+    if is_url(reference) {
+        return Ok(reference.to_string())
+    }
+    if is_url(base) {
+        let base = PlaylistUri::parse(base)?;
+        let reference = PlaylistUri::parse(reference)?;
+        return Ok(reference.join(&base).to_string())
+    }
+    let dir = Path::new(base).parent().unwrap_or(Path::new(""));
+    Ok(dir.join(reference).to_string_lossy().into_owned())
+}
+
+/* Decompress gzip data, and zstd data with the zstd feature, other data is returned as is */
+pub fn decompress(uri: &str, data: Vec<u8>) -> Result<Vec<u8>, String> {
+    #[cfg(feature = "zstd")]
+    if data.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        return zstd::decode_all(data.as_slice()).map_err(|e| format!("Failed to decompress {}: {}", uri, e))
+    }
+    if !data.starts_with(&[0x1f, 0x8b]) {
+        return Ok(data)
+    }
+    let mut ret = vec![];
+    GzDecoder::new(data.as_slice()).read_to_end(&mut ret)
+        .map_err(|e| format!("Failed to decompress {}: {}", uri, e))?;
+    Ok(ret)
+}
+
+/* Fetch a text resource, compressed files are decompressed */
+pub fn fetch_string(uri: &str) -> Result<String, String> {
+    let bytes = decompress(uri, fetch_bytes(uri, None)?)?;
+    String::from_utf8(bytes).map_err(|_| format!("{} is not valid UTF-8", uri))
+}
+
+/* Fetch the resource, or only `length` bytes of it starting at `offset`
+   when a range is given. An empty range is not requested. */
+pub fn fetch_bytes(uri: &str, range: Option<(u64, u64)>) -> Result<Vec<u8>, String> {
+    let mut data = vec![];
+    if let Some((_, 0)) = range {
+        return Ok(data)
+    }
+    if is_url(uri) {
+        let mut request = ureq::get(uri);
+        if let Some((offset, length)) = range {
+            request = request.set("Range", &format!("bytes={}-{}", offset, offset.saturating_add(length - 1)));
+        }
+        let response = request.call().map_err(|e| format!("Failed to fetch {}: {}", uri, e))?;
+        let partial = response.status() == 206;
+        response.into_reader().read_to_end(&mut data)
+            .map_err(|e| format!("Failed to read {}: {}", uri, e))?;
+        // Servers ignoring the Range header send the whole resource
+        if let (Some((offset, length)), false) = (range, partial) {
+            let start = (offset as usize).min(data.len());
+            let end = (offset.saturating_add(length) as usize).min(data.len());
+            data = data[start..end].to_vec();
+        }
+    } else {
+        let mut file = fs::File::open(uri).map_err(|e| format!("Failed to open {}: {}", uri, e))?;
+        match range {
+            Some((offset, length)) => {
+                file.seek(SeekFrom::Start(offset)).map_err(|e| format!("Failed to seek {}: {}", uri, e))?;
+                file.take(length).read_to_end(&mut data)
+                    .map_err(|e| format!("Failed to read {}: {}", uri, e))?;
+            }
+            None => {
+                file.read_to_end(&mut data).map_err(|e| format!("Failed to read {}: {}", uri, e))?;
+            }
+        }
+    }
+    Ok(data)
+}
+
+/* Size of the resource in bytes, without downloading it when served over http */
+pub fn content_length(uri: &str) -> Result<u64, String> {
+    if is_url(uri) {
+        let response = ureq::head(uri).call().map_err(|e| format!("Failed to fetch {}: {}", uri, e))?;
+        match response.header("Content-Length").and_then(|v| v.parse().ok()) {
+            Some(len) => Ok(len),
+            None => Ok(fetch_bytes(uri, None)?.len() as u64),
+        }
+    } else {
+        fs::metadata(uri).map(|m| m.len()).map_err(|e| format!("Failed to open {}: {}", uri, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress, fetch_bytes, fetch_string, resolve};
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve("https://example.com/hls/master.m3u8", "v1/index.m3u8"),
+            Ok("https://example.com/hls/v1/index.m3u8".to_string()));
+        assert_eq!(resolve("https://example.com/hls/master.m3u8", "/other/index.m3u8"),
+            Ok("https://example.com/other/index.m3u8".to_string()));
+        assert_eq!(resolve("https://example.com/master.m3u8", "http://cdn.example.com/a.m3u8"),
+            Ok("http://cdn.example.com/a.m3u8".to_string()));
+        assert_eq!(resolve("data/thumbnails/master.m3u8", "iframes_360p.m3u8"),
+            Ok("data/thumbnails/iframes_360p.m3u8".to_string()));
+        assert_eq!(resolve("master.m3u8", "iframes_360p.m3u8"),
+            Ok("iframes_360p.m3u8".to_string()));
+    }
+
+    #[test]
+    fn test_fetch_range() {
+        let data = fetch_bytes("data/thumbnails/360p_frame_1.ts", Some((5, 5))).expect("Failed to read");
+        assert_eq!(data, b"frame");
+        // no request is made, the port is closed
+        assert_eq!(fetch_bytes("http://127.0.0.1:1/seg.ts", Some((0, 0))), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_fetch_gzip() {
+        let data = fetch_string("data/media_playlist.m3u8.gz").expect("Failed to read");
+        assert_eq!(data, include_str!("../data/media_playlist.m3u8"));
+        assert!(fetch_string("data/thumbnails/360p_frame_1.ts").is_ok_and(|d| d.contains("frame")));
+        assert_eq!(decompress("x", b"#EXTM3U\n".to_vec()), Ok(b"#EXTM3U\n".to_vec()));
+    }
+
+    #[test]
+    #[cfg(feature = "zstd")]
+    fn test_decompress_zstd() {
+        let data = include_bytes!("../data/media_playlist.m3u8");
+        assert_eq!(decompress("x.zst", zstd::encode_all(&data[..], 0).unwrap()), Ok(data.to_vec()));
+        assert!(decompress("x.zst", vec![0x28, 0xb5, 0x2f, 0xfd, 0]).unwrap_err().starts_with("Failed to decompress x.zst"));
+    }
+}
@@ -0,0 +1,287 @@
+use chrono::{DateTime, Utc};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::fetch;
+use crate::format::{Media, MediaType, MultivariantPlaylist, StreamInf};
+use crate::media_playlist::{self, MediaPlaylist};
+use crate::parser;
+use crate::writer;
+
+// Following a live media playlist: reloading it and comparing the
+// EXT-X-PROGRAM-DATE-TIME of new segments with the time they showed up.
+// Multivariant playlists are watched for added and removed variants.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriftSample {
+    pub sequence: u64,
+    pub uri: String,
+    // Seconds between the end of the segment according to its PDT and its arrival.
+    // Includes packaging and delivery latency, negative when the encoder clock is ahead.
+    pub drift: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriftStats {
+    pub samples: usize,
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+    pub jitter: f64, // standard deviation of drift
+}
+
+impl DriftStats {
+    pub fn new(samples: &[DriftSample]) -> Option<Self> {
+        if samples.is_empty() {
+            return None
+        }
+        let n = samples.len() as f64;
+        let mean = samples.iter().map(|s| s.drift).sum::<f64>() / n;
+        let var = samples.iter().map(|s| (s.drift - mean).powi(2)).sum::<f64>() / n;
+        Some(Self{
+            samples: samples.len(),
+            mean,
+            min: samples.iter().map(|s| s.drift).fold(f64::INFINITY, f64::min),
+            max: samples.iter().map(|s| s.drift).fold(f64::NEG_INFINITY, f64::max),
+            jitter: var.sqrt(),
+        })
+    }
+
+    /* Whether the encoder clock looks skewed, drifting more than `max_drift` seconds on average */
+    pub fn is_skewed(&self, max_drift: f64) -> bool {
+        self.mean.abs() > max_drift
+    }
+}
+
+// Change between two loads of a playlist, see events()
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "event", rename_all = "snake_case"))]
+pub enum Event {
+    NewSegment { sequence: u64, uri: String, duration: f64, drift: Option<f64> },
+    SegmentRemoved { sequence: u64, uri: String },
+    EndList,
+    VariantAdded { uri: String, bandwidth: u64 },
+    VariantRemoved { uri: String, bandwidth: u64 },
+    RenditionAdded { media_type: MediaType, group_id: String, name: String, uri: Option<String> },
+    RenditionRemoved { media_type: MediaType, group_id: String, name: String, uri: Option<String> },
+}
+
+impl Event {
+    fn variant(si: &StreamInf, added: bool) -> Self {
+        let (uri, bandwidth) = (si.uri.to_string(), si.bandwidth);
+        if added { Event::VariantAdded{ uri, bandwidth } } else { Event::VariantRemoved{ uri, bandwidth } }
+    }
+
+    fn rendition(m: &Media, added: bool) -> Self {
+        let (media_type, group_id, name) = (m.type_.clone(), m.group_id.clone(), m.name.clone());
+        let uri = m.uri.as_ref().map(|u| u.to_string());
+        if added {
+            Event::RenditionAdded{ media_type, group_id, name, uri }
+        } else {
+            Event::RenditionRemoved{ media_type, group_id, name, uri }
+        }
+    }
+}
+
+/* Changes of the playlist since `previous`, removed segments first. `samples` are the
+   drift samples of the new segments, as returned by Follower::update. */
+pub fn events(previous: &MediaPlaylist, playlist: &MediaPlaylist, samples: &[DriftSample]) -> Vec<Event> {
+    let mut ret = vec![];
+    for (i, seg) in previous.segments.iter().enumerate() {
+        let sequence = previous.sequence(i);
+        if sequence < playlist.media_sequence {
+            ret.push(Event::SegmentRemoved{ sequence, uri: seg.uri.to_string() });
+        }
+    }
+    let next = previous.sequence(previous.segments.len());
+    for (i, seg) in playlist.segments.iter().enumerate() {
+        let sequence = playlist.sequence(i);
+        if sequence >= next {
+            let drift = samples.iter().find(|s| s.sequence == sequence).map(|s| s.drift);
+            ret.push(Event::NewSegment{ sequence, uri: seg.uri.to_string(), duration: seg.duration, drift });
+        }
+    }
+    if playlist.end_list && !previous.end_list {
+        ret.push(Event::EndList);
+    }
+    ret
+}
+
+/* Variants and renditions of the multivariant playlist removed since `previous`, then those added.
+   Entries edited in place, keeping their stable id or URI, are neither. */
+pub fn variant_events(previous: &MultivariantPlaylist, playlist: &MultivariantPlaylist) -> Vec<Event> {
+    let variants = writer::pair_entries(&previous.stream_inf, &playlist.stream_inf,
+        writer::same_stream_inf, writer::similar_stream_inf);
+    let renditions = writer::pair_entries(&previous.media, &playlist.media, writer::same_media, writer::similar_media);
+    let mut ret: Vec<Event> = previous.stream_inf.iter().zip(&variants)
+        .filter(|(_, j)| j.is_none()).map(|(si, _)| Event::variant(si, false))
+        .chain(previous.media.iter().zip(&renditions).filter(|(_, j)| j.is_none()).map(|(m, _)| Event::rendition(m, false)))
+        .collect();
+    let kept = |pairs: &[Option<usize>], j: usize| pairs.contains(&Some(j));
+    ret.extend(playlist.stream_inf.iter().enumerate().filter(|(j, _)| !kept(&variants, *j))
+        .map(|(_, si)| Event::variant(si, true)));
+    ret.extend(playlist.media.iter().enumerate().filter(|(j, _)| !kept(&renditions, *j))
+        .map(|(_, m)| Event::rendition(m, true)));
+    ret
+}
+
+pub struct Follower {
+    pub uri: String,
+    last_sequence: Option<u64>,
+    pub samples: Vec<DriftSample>,
+}
+
+impl Follower {
+    pub fn new(uri: &str) -> Self {
+        Self{ uri: uri.to_string(), last_sequence: None, samples: vec![] }
+    }
+
+    /*
+    Record the segments appended since the previous update as arrived at `arrival`.
+    Segments present at the first update have unknown arrival time and are skipped,
+    as are segments without a date time, explicit or extrapolated.
+    */
+    pub fn update(&mut self, playlist: &MediaPlaylist, arrival: DateTime<Utc>) -> Vec<DriftSample> {
+        let mut ret = vec![];
+        let date_times = playlist.date_times();
+        for (i, seg) in playlist.segments.iter().enumerate() {
+            let sequence = playlist.sequence(i);
+            if self.last_sequence.is_some_and(|last| sequence > last) {
+                let end = date_times[i]
+                    .and_then(|dt| dt.with_timezone(&Utc).checked_add_signed(media_playlist::seconds(seg.duration)?));
+                if let Some(end) = end {
+                    let drift = (arrival - end).num_microseconds().unwrap_or(i64::MAX) as f64 / 1e6;
+                    ret.push(DriftSample{ sequence, uri: seg.uri.to_string(), drift });
+                }
+            }
+        }
+        let last = playlist.sequence(playlist.segments.len()).checked_sub(1);
+        if last.is_some() {
+            self.last_sequence = self.last_sequence.max(last);
+        }
+        self.samples.extend(ret.iter().cloned());
+        ret
+    }
+
+    /* Reload the playlist and update with the current time */
+    pub fn poll(&mut self) -> Result<(MediaPlaylist, Vec<DriftSample>), String> {
+        let contents = fetch::fetch_string(&self.uri)?;
+        let arrival = Utc::now();
+        let playlist = parser::parse_media_playlist(&contents).map_err(|e| e.to_string())?;
+        let new = self.update(&playlist, arrival);
+        Ok((playlist, new))
+    }
+
+    pub fn stats(&self) -> Option<DriftStats> {
+        DriftStats::new(&self.samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+    use crate::format::MediaType;
+    use crate::media_playlist::{ProgramDateTime, Skip};
+    use crate::parser;
+    use super::{events, variant_events, Event, Follower};
+
+    fn at(v: &str) -> DateTime<Utc> {
+        parser::parse_date_time(v).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_follow_drift() {
+        let data = include_str!("../data/live_playlist.m3u8");
+        let mut pl = parser::parse_media_playlist(data).unwrap();
+        let mut f = Follower::new("live_playlist.m3u8");
+        // initial load only sets the starting point
+        assert!(f.update(&pl, at("2024-05-01T12:00:50Z")).is_empty());
+        assert_eq!(f.stats(), None);
+
+        // sliding window: one segment dropped, two appended, the second without PDT
+        let previous = pl.clone();
+        let mut next = pl.segments[5].clone();
+        pl.segments.remove(0);
+        pl.media_sequence += 1;
+        next.uri = "live_1206.ts".parse().unwrap();
+        next.program_date_time = ProgramDateTime::parse("2024-05-01T12:00:46.506Z").ok();
+        pl.segments.push(next.clone());
+        next.uri = "live_1207.ts".parse().unwrap();
+        next.program_date_time = None;
+        pl.segments.push(next);
+        let new = f.update(&pl, at("2024-05-01T12:01:00Z"));
+        let changes = events(&previous, &pl, &new);
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0], Event::SegmentRemoved{ sequence: 1200, uri: "live_1200.ts".to_string() });
+        assert!(matches!(&changes[2], Event::NewSegment{ sequence: 1207, drift: Some(_), .. }));
+        #[cfg(feature = "serde")]
+        assert_eq!(serde_json::to_value(&changes[0]).unwrap(),
+            serde_json::json!({"event": "segment_removed", "sequence": 1200, "uri": "live_1200.ts"}));
+
+        assert_eq!(new.len(), 2);
+        assert_eq!(new[0].sequence, 1206);
+        assert!((new[0].drift - 7.488).abs() < 1e-6);
+        assert!((new[1].drift - 1.482).abs() < 1e-6);
+
+        // nothing new on reload
+        assert!(f.update(&pl, at("2024-05-01T12:01:02Z")).is_empty());
+        let mut ended = pl.clone();
+        ended.end_list = true;
+        assert_eq!(events(&pl, &ended, &[]), [Event::EndList]);
+        let stats = f.stats().unwrap();
+        assert_eq!(stats.samples, 2);
+        assert!((stats.mean - 4.485).abs() < 1e-6);
+        assert!((stats.jitter - 3.003).abs() < 1e-6);
+        assert!(!stats.is_skewed(5.0));
+        assert!(stats.is_skewed(4.0));
+    }
+
+    #[test]
+    fn test_follow_delta_update() {
+        let data = include_str!("../data/live_playlist.m3u8");
+        let pl = parser::parse_media_playlist(data).unwrap();
+        let mut f = Follower::new("live_playlist.m3u8");
+        assert!(f.update(&pl, at("2024-05-01T12:00:50Z")).is_empty());
+
+        // same window with the first four segments skipped, and one appended
+        let mut delta = pl.clone();
+        delta.segments.drain(..4);
+        delta.skip = Some(Skip{ skipped_segments: 4, recently_removed_dateranges: vec![] });
+        let mut next = pl.segments[5].clone();
+        next.uri = "live_1206.ts".parse().unwrap();
+        delta.segments.push(next);
+        let new = f.update(&delta, at("2024-05-01T12:01:00Z"));
+        assert_eq!(new.iter().map(|n| n.sequence).collect::<Vec<_>>(), [1206]);
+        let changes = events(&pl, &delta, &new);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(&changes[0], Event::NewSegment{ sequence: 1206, .. }));
+    }
+
+    #[test]
+    fn test_variant_events() {
+        let previous = parser::parse_playlist(include_str!("../data/captions.m3u8")).unwrap();
+        let mut pl = previous.clone();
+        assert!(variant_events(&previous, &pl).is_empty());
+        let removed = pl.stream_inf.remove(0);
+        let mut added = pl.stream_inf[0].clone();
+        (added.uri, added.bandwidth) = ("video/4k.m3u8".parse().unwrap(), 20_000_000);
+        pl.stream_inf.push(added);
+        // edited in place
+        pl.stream_inf[0].average_bandwidth = Some(1);
+        let sub = pl.media.iter().position(|m| m.type_ == MediaType::Subtitles).unwrap();
+        let sub = pl.media.remove(sub);
+
+        let changes = variant_events(&previous, &pl);
+        assert_eq!(changes, [
+            Event::VariantRemoved{ uri: removed.uri.to_string(), bandwidth: removed.bandwidth },
+            Event::RenditionRemoved{ media_type: MediaType::Subtitles, group_id: sub.group_id.clone(), name: sub.name.clone(),
+                uri: sub.uri.as_ref().map(|u| u.to_string()) },
+            Event::VariantAdded{ uri: "video/4k.m3u8".to_string(), bandwidth: 20_000_000 },
+        ]);
+        #[cfg(feature = "serde")]
+        assert_eq!(serde_json::to_value(&changes[2]).unwrap(),
+            serde_json::json!({"event": "variant_added", "uri": "video/4k.m3u8", "bandwidth": 20_000_000}));
+        assert_eq!(variant_events(&pl, &previous).len(), 3);
+    }
+}
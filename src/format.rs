@@ -1,122 +1,944 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 
+use regex::Regex;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
+#[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
 
+use crate::codecs::{Codec, CodecFamily, Codecs};
+use crate::uri::PlaylistUri;
+use crate::validator::{BandwidthBounds, Entry, Finding, ValidationReport, Validator};
+use crate::writer::Writer;
+
 // Partial implementation of Multivariant Playlist format as defined in RFC 8216bis
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+// Values unknown to this version, e.g. from a later revision of the RFC or vendor
+// specific, are kept in Other and reported by the validator
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub enum MediaType {
+    #[default]
     Audio, Video, Subtitles, ClosedCaptions,
+    Other(String),
+}
+
+impl MediaType {
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "AUDIO" => MediaType::Audio,
+            "VIDEO" => MediaType::Video,
+            "SUBTITLES" => MediaType::Subtitles,
+            "CLOSED-CAPTIONS" => MediaType::ClosedCaptions,
+            _ => MediaType::Other(s.to_string()),
+        }
+    }
+
+    /* Value of TYPE, as written in the playlist */
+    pub fn as_str(&self) -> &str {
+        match self {
+            MediaType::Audio => "AUDIO",
+            MediaType::Video => "VIDEO",
+            MediaType::Subtitles => "SUBTITLES",
+            MediaType::ClosedCaptions => "CLOSED-CAPTIONS",
+            MediaType::Other(s) => s,
+        }
+    }
+}
+
+// INSTREAM-ID of a CLOSED-CAPTIONS rendition: a CEA-608 channel (CC1-CC4) or
+// a CEA-708 service (SERVICE1-SERVICE63). Serialized as written in the playlist.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "serde", serde(into = "String", try_from = "String"))]
+pub enum InstreamId {
+    Cea608(u8),
+    Cea708(u8),
+}
+
+impl InstreamId {
+    pub fn parse(s: &str) -> Option<Self> {
+        let (id, max, digits): (fn(u8) -> Self, u8, &str) = if let Some(digits) = s.strip_prefix("CC") {
+            (InstreamId::Cea608, 4, digits)
+        } else if let Some(digits) = s.strip_prefix("SERVICE") {
+            (InstreamId::Cea708, 63, digits)
+        } else {
+            return None
+        };
+        // parse() would accept a sign
+        if digits.is_empty() || digits.starts_with('0') || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None
+        }
+        digits.parse().ok().filter(|n| (1..=max).contains(n)).map(id)
+    }
+}
+
+impl std::fmt::Display for InstreamId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstreamId::Cea608(n) => write!(f, "CC{}", n),
+            InstreamId::Cea708(n) => write!(f, "SERVICE{}", n),
+        }
+    }
+}
+
+impl From<InstreamId> for String {
+    fn from(id: InstreamId) -> Self {
+        id.to_string()
+    }
+}
+
+impl TryFrom<String> for InstreamId {
+    type Error = String;
+    fn try_from(s: String) -> Result<Self, String> {
+        InstreamId::parse(&s).ok_or(format!("Invalid INSTREAM-ID {}", s))
+    }
+}
+
+// CHANNELS of an AUDIO rendition: the channel count, then the audio coding identifiers
+// (JOC for Dolby Atmos) and the special usage identifiers (BINAURAL, IMMERSIVE, DOWNMIX),
+// separated by slashes, e.g. "2", "16/JOC" or "2/-/BINAURAL". Serialized as in the playlist.
+// Values not following this syntax, e.g. from a later version, are kept as written in `other`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "serde", serde(into = "String", try_from = "String"))]
+pub struct Channels {
+    pub count: u64,
+    pub coding: Vec<String>,
+    pub usage: Vec<String>,
+    pub other: Option<String>,
+}
+
+impl Channels {
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut params = s.split('/');
+        let count = params.next().filter(|c| !c.is_empty() && c.bytes().all(|b| b.is_ascii_digit()))?;
+        // comma-separated identifiers, "-" when there are none
+        let identifiers = |p: Option<&str>| match p {
+            None | Some("-") => Some(vec![]),
+            Some(p) => p.split(',')
+                .map(|id| (!id.is_empty() && id.bytes().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'-'))
+                    .then(|| id.to_string()))
+                .collect(),
+        };
+        let ret = Channels{ count: count.parse().ok()?, coding: identifiers(params.next())?,
+            usage: identifiers(params.next())?, other: None };
+        params.next().is_none().then_some(ret)
+    }
+
+    /* Parse `s`, or keep it in `other` with what can be read of the count and identifiers */
+    pub fn parse_lenient(s: &str) -> Self {
+        Channels::parse(s).unwrap_or_else(|| {
+            let mut params = s.split('/');
+            let count = params.next().and_then(|c| c.parse().ok()).unwrap_or(0);
+            let mut identifiers = || params.next().map_or_else(Vec::new, |p| p.split(',')
+                .filter(|id| !id.is_empty() && *id != "-").map(str::to_string).collect());
+            Channels{ count, coding: identifiers(), usage: identifiers(), other: Some(s.to_string()) }
+        })
+    }
+
+    // Object-based or binaural audio, e.g. Dolby Atmos
+    pub fn is_spatial(&self) -> bool {
+        self.coding.iter().any(|c| c == "JOC") || self.usage.iter().any(|u| u == "IMMERSIVE" || u == "BINAURAL")
+    }
+}
+
+impl std::fmt::Display for Channels {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(other) = &self.other {
+            return write!(f, "{}", other)
+        }
+        write!(f, "{}", self.count)?;
+        if !self.coding.is_empty() || !self.usage.is_empty() {
+            write!(f, "/{}", if self.coding.is_empty() { "-".to_string() } else { self.coding.join(",") })?;
+        }
+        if !self.usage.is_empty() {
+            write!(f, "/{}", self.usage.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Channels> for String {
+    fn from(ch: Channels) -> Self {
+        ch.to_string()
+    }
+}
+
+impl TryFrom<String> for Channels {
+    type Error = String;
+    fn try_from(s: String) -> Result<Self, String> {
+        Ok(Channels::parse_lenient(&s))
+    }
+}
+
+// Filter on CHANNELS: an exact value such as "2" or "16/JOC", or a comparison of
+// the channel count such as ">=6", "<=2" or "=16", which matches "16/JOC" too
+#[derive(Debug, PartialEq, Clone)]
+pub enum ChannelsFilter {
+    Exact(Channels),
+    Count(u64),
+    AtLeast(u64),
+    AtMost(u64),
+}
+
+impl ChannelsFilter {
+    pub fn parse(s: &str) -> Option<Self> {
+        let count = |v: &str| v.trim().parse::<u64>().ok();
+        if let Some(v) = s.strip_prefix(">=") {
+            Some(ChannelsFilter::AtLeast(count(v)?))
+        } else if let Some(v) = s.strip_prefix("<=") {
+            Some(ChannelsFilter::AtMost(count(v)?))
+        } else if let Some(v) = s.strip_prefix("==").or_else(|| s.strip_prefix('=')) {
+            Some(ChannelsFilter::Count(count(v)?))
+        } else if let Some(v) = s.strip_prefix('>') {
+            Some(ChannelsFilter::AtLeast(count(v)?.checked_add(1)?))
+        } else if let Some(v) = s.strip_prefix('<') {
+            Some(ChannelsFilter::AtMost(count(v)?.checked_sub(1)?))
+        } else {
+            Channels::parse(s).map(ChannelsFilter::Exact)
+        }
+    }
+
+    pub fn matches(&self, ch: &Channels) -> bool {
+        match self {
+            ChannelsFilter::Exact(v) => v == ch,
+            ChannelsFilter::Count(n) => ch.count == *n,
+            ChannelsFilter::AtLeast(n) => ch.count >= *n,
+            ChannelsFilter::AtMost(n) => ch.count <= *n,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct Media {
     pub type_: MediaType,
-    pub uri: String,
+    pub uri: Option<PlaylistUri>, // must be absent for CLOSED-CAPTIONS
     pub group_id: String,
     pub language: Option<String>,
+    pub assoc_language: Option<String>,
+    pub stable_rendition_id: Option<String>,
     pub name: String,
+    #[cfg_attr(feature = "serde", serde(default))]
     pub default: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
     pub autoselect: bool,
-    pub channels: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub forced: bool,
+    pub instream_id: Option<InstreamId>,
+    pub bit_depth: Option<u64>,
+    pub sample_rate: Option<u64>,
+    pub characteristics: Option<String>,
+    pub channels: Option<Channels>,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+impl Media {
+    // NAME is unique within a group of the same TYPE, which identifies the rendition
+    pub fn key(&self) -> (&MediaType, &str, &str) {
+        (&self.type_, &self.group_id, &self.name)
+    }
+}
+
+// FRAME-RATE, a decimal kept exact so 23.976 or 29.97 compare equal and are written
+// back as in the playlist: mantissa / 10^scale, without trailing zeros in the fraction.
+// Serialized as a number.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "serde", serde(into = "f64", try_from = "f64"))]
+pub struct FrameRate {
+    mantissa: u64,
+    scale: u32,
+}
+
+impl FrameRate {
+    // 10^scale must fit in u64, as checked by parse()
+    fn new(mut mantissa: u64, mut scale: u32) -> Self {
+        while scale > 0 && mantissa % 10 == 0 {
+            mantissa /= 10;
+            scale -= 1;
+        }
+        FrameRate{ mantissa, scale }
+    }
+
+    /* Decimal as written in the playlist, e.g. "23.976" */
+    pub fn parse(s: &str) -> Option<Self> {
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+        let digits = |d: &str| d.bytes().all(|b| b.is_ascii_digit());
+        if int.is_empty() || !digits(int) || !digits(frac) || s.ends_with('.') {
+            return None
+        }
+        let scale = frac.len() as u32;
+        let mantissa = int.parse::<u64>().ok()?.checked_mul(10u64.checked_pow(scale)?)?
+            .checked_add(if frac.is_empty() { 0 } else { frac.parse().ok()? })?;
+        Some(Self::new(mantissa, scale))
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.mantissa as f64 / 10f64.powi(self.scale as i32)
+    }
+}
+
+impl PartialOrd for FrameRate {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FrameRate {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let scale = self.scale.max(other.scale);
+        let at = |fr: &FrameRate| fr.mantissa as u128 * 10u128.pow(scale - fr.scale);
+        at(self).cmp(&at(other))
+    }
+}
+
+impl std::fmt::Display for FrameRate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unit = 10u64.pow(self.scale);
+        if self.scale == 0 {
+            write!(f, "{}", self.mantissa)
+        } else {
+            write!(f, "{}.{:0width$}", self.mantissa / unit, self.mantissa % unit, width = self.scale as usize)
+        }
+    }
+}
+
+impl std::str::FromStr for FrameRate {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, String> {
+        FrameRate::parse(s).ok_or(format!("Invalid FRAME-RATE {}", s))
+    }
+}
+
+impl From<FrameRate> for f64 {
+    fn from(fr: FrameRate) -> Self {
+        fr.as_f64()
+    }
+}
+
+// Shortest representation of the float, so 23.976 stays 23.976
+impl TryFrom<f64> for FrameRate {
+    type Error = String;
+    fn try_from(v: f64) -> Result<Self, String> {
+        v.to_string().parse()
+    }
+}
+
+// SCORE, a decimal kept exact as FRAME-RATE, higher for variants to be preferred.
+// Serialized as a number.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "serde", serde(into = "f64", try_from = "f64"))]
+pub struct Score(FrameRate);
+
+impl Score {
+    pub fn parse(s: &str) -> Option<Self> {
+        FrameRate::parse(s).map(Score)
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.0.as_f64()
+    }
+}
+
+impl std::fmt::Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<Score> for f64 {
+    fn from(s: Score) -> Self {
+        s.as_f64()
+    }
+}
+
+impl TryFrom<f64> for Score {
+    type Error = String;
+    fn try_from(v: f64) -> Result<Self, String> {
+        Score::parse(&v.to_string()).ok_or(format!("Invalid SCORE {}", v))
+    }
+}
+
+// Ordered by pixel count, then by width
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct Resolution {
     pub w: u64,
     pub h: u64,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+// Shorthands accepted for a resolution, 16:9
+const NAMED_RESOLUTIONS: &[(&str, u64, u64)] = &[
+    ("240p", 426, 240), ("360p", 640, 360), ("480p", 854, 480), ("540p", 960, 540),
+    ("720p", 1280, 720), ("hd", 1280, 720), ("1080p", 1920, 1080), ("fhd", 1920, 1080),
+    ("1440p", 2560, 1440), ("2k", 2560, 1440), ("2160p", 3840, 2160), ("4k", 3840, 2160),
+    ("uhd", 3840, 2160), ("4320p", 7680, 4320), ("8k", 7680, 4320),
+];
+
+impl Resolution {
+    /* Resolution for a shorthand like 1080p or 4k, case insensitive */
+    pub fn named(name: &str) -> Option<Self> {
+        NAMED_RESOLUTIONS.iter().find(|(n, _, _)| n.eq_ignore_ascii_case(name)).map(|&(_, w, h)| Resolution{ w, h })
+    }
+
+    /* Saturates at u64::MAX for absurd resolutions */
+    pub fn pixels(&self) -> u64 {
+        self.w.saturating_mul(self.h)
+    }
+
+    /* Width over height, infinite when the height is 0 */
+    pub fn aspect_ratio(&self) -> f64 {
+        self.w as f64 / self.h as f64
+    }
+}
+
+impl PartialOrd for Resolution {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Resolution {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // exact, unlike pixels()
+        let pixels = |r: &Resolution| r.w as u128 * r.h as u128;
+        (pixels(self), self.w).cmp(&(pixels(other), other.w))
+    }
+}
+
+// Unknown values are kept in Other, as for MediaType
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub enum VideoRange {
     SDR, HLG, PQ,
+    Other(String),
+}
+
+impl VideoRange {
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "SDR" => VideoRange::SDR,
+            "HLG" => VideoRange::HLG,
+            "PQ" => VideoRange::PQ,
+            _ => VideoRange::Other(s.to_string()),
+        }
+    }
+
+    /* Value of VIDEO-RANGE, as written in the playlist */
+    pub fn as_str(&self) -> &str {
+        match self {
+            VideoRange::SDR => "SDR",
+            VideoRange::HLG => "HLG",
+            VideoRange::PQ => "PQ",
+            VideoRange::Other(s) => s,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+// PATHWAY-ID of variants without the attribute
+pub const DEFAULT_PATHWAY_ID: &str = ".";
+
+// HDCP-LEVEL, ordered from no output protection to TYPE-1
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum HdcpLevel {
+    None,
+    Type0,
+    Type1,
+}
+
+impl HdcpLevel {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "NONE" => Some(HdcpLevel::None),
+            "TYPE-0" => Some(HdcpLevel::Type0),
+            "TYPE-1" => Some(HdcpLevel::Type1),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HdcpLevel::None => "NONE",
+            HdcpLevel::Type0 => "TYPE-0",
+            HdcpLevel::Type1 => "TYPE-1",
+        }
+    }
+}
+
+// CLOSED-CAPTIONS of a variant, a GROUP-ID or NONE when there are no captions in the video
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum ClosedCaptions {
+    Group(String),
+    None,
+}
+
+impl ClosedCaptions {
+    pub fn group(&self) -> Option<&str> {
+        match self {
+            ClosedCaptions::Group(g) => Some(g),
+            ClosedCaptions::None => None,
+        }
+    }
+}
+
+// Serialized with its inferred label and stable id, which are computed, not stored
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(transform = derived_properties::<true>))]
 pub struct StreamInf {
-    pub uri: String,
+    pub uri: PlaylistUri,
     pub bandwidth: u64,
     pub average_bandwidth: Option<u64>,
-    pub codecs: Option<String>,
+    pub score: Option<Score>,
+    pub codecs: Option<Codecs>,
     pub resolution: Option<Resolution>,
-    pub frame_rate: Option<f64>, // could be decimal for precision
+    pub frame_rate: Option<FrameRate>,
+    pub hdcp_level: Option<HdcpLevel>,
+    pub allowed_cpc: Option<String>, // e.g. "com.example.drm1:SMART-TV/PC", as in the playlist
     pub video_range: Option<VideoRange>,
+    pub stable_variant_id: Option<String>,
     pub audio: Option<String>,
-    pub closed_captions: Option<String>,
+    pub video: Option<String>,
+    pub subtitles: Option<String>,
+    pub closed_captions: Option<ClosedCaptions>,
+    pub pathway_id: Option<String>, // content steering pathway, "." when absent
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for StreamInf {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let StreamInf{uri, bandwidth, average_bandwidth, score, codecs, resolution, frame_rate, hdcp_level, allowed_cpc,
+            video_range, stable_variant_id, audio, video, subtitles, closed_captions, pathway_id} = self;
+        let mut s = serializer.serialize_struct("StreamInf", 18)?;
+        s.serialize_field("uri", uri)?;
+        s.serialize_field("bandwidth", bandwidth)?;
+        s.serialize_field("average_bandwidth", average_bandwidth)?;
+        s.serialize_field("score", score)?;
+        s.serialize_field("codecs", codecs)?;
+        s.serialize_field("resolution", resolution)?;
+        s.serialize_field("frame_rate", frame_rate)?;
+        s.serialize_field("hdcp_level", hdcp_level)?;
+        s.serialize_field("allowed_cpc", allowed_cpc)?;
+        s.serialize_field("video_range", video_range)?;
+        s.serialize_field("stable_variant_id", stable_variant_id)?;
+        s.serialize_field("audio", audio)?;
+        s.serialize_field("video", video)?;
+        s.serialize_field("subtitles", subtitles)?;
+        s.serialize_field("closed_captions", closed_captions)?;
+        s.serialize_field("pathway_id", pathway_id)?;
+        s.serialize_field("label", &self.infer_label())?;
+        s.serialize_field("id", &self.stable_id())?;
+        s.end()
+    }
+}
+
+// The computed label (variants only) and id in the schema of the serialized form
+#[cfg(feature = "schema")]
+fn derived_properties<const LABEL: bool>(schema: &mut schemars::Schema) {
+    let mut names = vec!["id"];
+    if LABEL {
+        names.insert(0, "label");
+    }
+    for name in names {
+        if let Some(properties) = schema.get_mut("properties").and_then(|p| p.as_object_mut()) {
+            properties.insert(name.to_string(), serde_json::json!({"type": "string", "readOnly": true}));
+        }
+        if let Some(required) = schema.get_mut("required").and_then(|r| r.as_array_mut()) {
+            required.push(name.into());
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+// Language tag equal to `lang` or with `lang` as primary subtag, case insensitive
+fn language_matches(tag: &Option<String>, lang: &str) -> bool {
+    tag.as_ref().is_some_and(|t| {
+        t.eq_ignore_ascii_case(lang) || t.split('-').next().is_some_and(|primary| primary.eq_ignore_ascii_case(lang))
+    })
+}
+
+// FNV-1a, as std hashers are not guaranteed to be stable between releases
+fn stable_hash(parts: &[&str]) -> String {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for p in parts {
+        for b in p.bytes().chain(std::iter::once(0)) {
+            h ^= b as u64;
+            h = h.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{:016x}", h)
+}
+
+fn codecs_str(codecs: &Option<Codecs>) -> String {
+    codecs.as_ref().map(|c| c.to_string()).unwrap_or_default()
+}
+
+fn resolution_str(res: &Option<Resolution>) -> String {
+    res.as_ref().map(|r| format!("{}x{}", r.w, r.h)).unwrap_or_default()
+}
+
+// Audio-only variants for poor networks should stay below this, as recommended by Apple
+pub const AUDIO_ONLY_MAX_BANDWIDTH: u64 = 192_000;
+
+// Typical bitrate of audio codecs, for variants synthesized from a rendition
+fn audio_bandwidth_estimate(codec: &Codec) -> Option<u64> {
+    match (codec.family, codec.profile) {
+        (CodecFamily::Aac, Some(5 | 29)) => Some(64_000), // HE-AAC
+        (CodecFamily::Aac | CodecFamily::Mp3, _) => Some(128_000),
+        (CodecFamily::Opus, _) => Some(96_000),
+        (CodecFamily::Ac3, _) => Some(192_000),
+        (CodecFamily::Ec3, _) => Some(256_000),
+        (CodecFamily::Alac | CodecFamily::Flac, _) => Some(1_000_000),
+        _ => None,
+    }
+}
+
+impl StreamInf {
+    /* 
+    Identifier derived from URI, BANDWIDTH, CODECS and RESOLUTION, so it does not
+    depend on the position of the variant in the playlist.
+    */
+    pub fn stable_id(&self) -> String {
+        stable_hash(&["STREAM-INF", self.uri.as_str(), &self.bandwidth.to_string(),
+            &codecs_str(&self.codecs), &resolution_str(&self.resolution)])
+    }
+
+    /* Rendition groups of the variant: AUDIO, VIDEO, SUBTITLES and CLOSED-CAPTIONS but NONE */
+    pub fn groups(&self) -> impl Iterator<Item = (MediaType, &str)> {
+        [(MediaType::Audio, self.audio.as_deref()), (MediaType::Video, self.video.as_deref()),
+            (MediaType::Subtitles, self.subtitles.as_deref()),
+            (MediaType::ClosedCaptions, self.closed_captions.as_ref().and_then(|cc| cc.group()))]
+            .into_iter().filter_map(|(type_, group)| Some((type_, group?)))
+    }
+
+    /* Whether the rendition is in one of the groups of the variant */
+    pub fn references(&self, m: &Media) -> bool {
+        self.groups().any(|(type_, group)| type_ == m.type_ && group == m.group_id)
+    }
+
+    // Variants with RESOLUTION or a video codec in CODECS carry video
+    pub fn has_video(&self) -> bool {
+        self.resolution.is_some() || self.codecs.as_ref().is_some_and(|c| c.has_video())
+    }
+
+    /* 
+    Human readable quality label, such as "1080p60 HDR" or "audio-only 128k".
+    Frame rate is appended only above 30 fps, HDR is either PQ or HLG.
+    */
+    pub fn infer_label(&self) -> String {
+        let kbps = (self.bandwidth + 500) / 1000;
+        let Some(res) = &self.resolution else {
+            return if self.has_video() {
+                format!("{}k", kbps)
+            } else {
+                format!("audio-only {}k", kbps)
+            }
+        };
+        let mut label = format!("{}p", res.h);
+        if let Some(fr) = self.frame_rate.map(|fr| fr.as_f64()) {
+            if fr.round() > 30.0 {
+                label += &format!("{}", fr.round());
+            }
+        }
+        if matches!(self.video_range, Some(VideoRange::PQ) | Some(VideoRange::HLG)) {
+            label += " HDR";
+        }
+        label
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(transform = derived_properties::<false>))]
 pub struct IFrameStreamInf {
-    pub uri: String,
+    pub uri: PlaylistUri,
     pub bandwidth: u64,
-    pub codecs: Option<String>,
+    pub codecs: Option<Codecs>,
     pub resolution: Option<Resolution>,
+    pub hdcp_level: Option<HdcpLevel>,
+    pub video_range: Option<VideoRange>,
+    pub stable_variant_id: Option<String>,
+    pub video: Option<String>,
+    pub pathway_id: Option<String>,
+}
+
+// Serialized with its stable id, as StreamInf
+#[cfg(feature = "serde")]
+impl Serialize for IFrameStreamInf {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let IFrameStreamInf{uri, bandwidth, codecs, resolution, hdcp_level, video_range, stable_variant_id, video, pathway_id} = self;
+        let mut s = serializer.serialize_struct("IFrameStreamInf", 10)?;
+        s.serialize_field("uri", uri)?;
+        s.serialize_field("bandwidth", bandwidth)?;
+        s.serialize_field("codecs", codecs)?;
+        s.serialize_field("resolution", resolution)?;
+        s.serialize_field("hdcp_level", hdcp_level)?;
+        s.serialize_field("video_range", video_range)?;
+        s.serialize_field("stable_variant_id", stable_variant_id)?;
+        s.serialize_field("video", video)?;
+        s.serialize_field("pathway_id", pathway_id)?;
+        s.serialize_field("id", &self.stable_id())?;
+        s.end()
+    }
+}
+
+impl IFrameStreamInf {
+    /* Same as StreamInf::stable_id, but never equal to the id of a StreamInf */
+    pub fn stable_id(&self) -> String {
+        stable_hash(&["I-FRAME-STREAM-INF", self.uri.as_str(), &self.bandwidth.to_string(),
+            &codecs_str(&self.codecs), &resolution_str(&self.resolution)])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum BestBy {
+    #[default]
+    Bandwidth,
+    Resolution, // largest, then highest bandwidth
+}
+
+// Choice of the variant kept by MultivariantPlaylist::select_best
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct BestCriteria {
+    pub by: BestBy,
+    pub max_bandwidth: Option<u64>, // variants above are not considered
+}
+
+// Filter on EXT-X-I-FRAME-STREAM-INF alone, see MultivariantPlaylist::select_i_frame_streams.
+// Bounds are included, I-frame variants without RESOLUTION pass the resolution bounds
+// and those without VIDEO-RANGE are SDR.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct IFrameCriteria {
+    pub min_bandwidth: Option<u64>,
+    pub max_bandwidth: Option<u64>,
+    pub min_resolution: Option<Resolution>,
+    pub max_resolution: Option<Resolution>,
     pub video_range: Option<VideoRange>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl IFrameCriteria {
+    pub fn matches(&self, ifsi: &IFrameStreamInf) -> bool {
+        let res = |f: &dyn Fn(&Resolution) -> bool| ifsi.resolution.as_ref().map_or(true, f);
+        self.min_bandwidth.map_or(true, |min| ifsi.bandwidth >= min)
+            && self.max_bandwidth.map_or(true, |max| ifsi.bandwidth <= max)
+            && self.min_resolution.as_ref().map_or(true, |min| res(&|r| r.w >= min.w && r.h >= min.h))
+            && self.max_resolution.as_ref().map_or(true, |max| res(&|r| r.w <= max.w && r.h <= max.h))
+            && self.video_range.as_ref().map_or(true, |v| ifsi.video_range.as_ref().unwrap_or(&VideoRange::SDR) == v)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum SortOrder {
+    Ascending,
+    #[default]
+    Descending,
+}
+
+// Line kept verbatim in passthrough mode, lineno is base 0
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct OpaqueLine {
+    pub lineno: usize,
+    pub text: String,
+    // Entries before the line, where it is written back: renditions, variants and
+    // I-frame variants of a multivariant playlist, segments of a media playlist
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub after: usize,
+}
+
+// Lines are equal when their text is, wherever they are in the playlist
+impl PartialEq for OpaqueLine {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+impl Eq for OpaqueLine {}
+
+impl std::hash::Hash for OpaqueLine {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.text.hash(state);
+    }
+}
+
+/*
+Text of a playlist parsed with ParseOptions::preserve, with the line numbers (base 0)
+of each tag and the playlist as parsed, see Writer::write_preserving.
+*/
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub text: String,
+    pub original: Box<MultivariantPlaylist>,
+    pub media_lines: Vec<usize>,
+    pub stream_inf_lines: Vec<(usize, usize)>, // tag and URI
+    pub i_frame_stream_inf_lines: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct MultivariantPlaylist {
     pub independent_segments: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub version: Option<u64>,
     pub media: Vec<Media>,
     pub stream_inf: Vec<StreamInf>,
     pub i_frame_stream_inf: Vec<IFrameStreamInf>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unrecognized: Vec<OpaqueLine>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub source: Option<Source>,
+}
+
+impl Default for MultivariantPlaylist {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Playlists are equal when their tags are, the source they were parsed from is not compared
+impl PartialEq for MultivariantPlaylist {
+    fn eq(&self, other: &Self) -> bool {
+        self.independent_segments == other.independent_segments && self.version == other.version && self.media == other.media
+            && self.stream_inf == other.stream_inf && self.i_frame_stream_inf == other.i_frame_stream_inf
+            && self.unrecognized == other.unrecognized
+    }
+}
+
+impl Eq for MultivariantPlaylist {}
+
+impl std::hash::Hash for MultivariantPlaylist {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.independent_segments.hash(state);
+        self.version.hash(state);
+        self.media.hash(state);
+        self.stream_inf.hash(state);
+        self.i_frame_stream_inf.hash(state);
+        self.unrecognized.hash(state);
+    }
 }
 
 impl MultivariantPlaylist {
     pub fn new() -> Self {
         Self{
             independent_segments: false,
+            version: None,
             media: vec![],
             stream_inf: vec![],
-            i_frame_stream_inf: vec![]
+            i_frame_stream_inf: vec![],
+            unrecognized: vec![],
+            source: None,
         }
     }
 
-    /* 
-    Perform basic validation of the playlist:
-
-    In EXT-X-STREAM-INF:
-    *  AUDIO value MUST match the value of the
-        GROUP-ID attribute of an EXT-X-MEDIA tag elsewhere in the
-        Multivariant Playlist whose TYPE attribute is AUDIO.
-    * CLOSED-CAPTIONS can be either a quoted-string or an enumerated-string
-        with the value NONE.  If the value is a quoted-string, it MUST
-        match the value of the GROUP-ID attribute of an EXT-X-MEDIA tag
-        elsewhere in the Playlist whose TYPE attribute is CLOSED-CAPTIONS
-    
-    TODO: consider implementing more validation.
-    */
-    pub fn validate(self: &Self) -> Result<(), String> {
-        let mut group_ids = HashMap::<MediaType, HashSet<&str>>::new();
+    /* All findings of the validator, errors and warnings, see validator::Validator for the rules */
+    pub fn findings(&self) -> Vec<Finding> {
+        self.findings_with_bounds(&BandwidthBounds::default())
+    }
+
+    /* Same as findings(), with BANDWIDTH checked against the given bounds */
+    pub fn findings_with_bounds(&self, bounds: &BandwidthBounds) -> Vec<Finding> {
+        self.findings_with_options(bounds, false)
+    }
+
+    /* Same as findings_with_bounds(), in strict mode unknown enumerated values are errors and the other MUST-level rules are checked */
+    pub fn findings_with_options(&self, bounds: &BandwidthBounds, strict: bool) -> Vec<Finding> {
+        let v = Validator::with_bandwidth_bounds(bounds.clone());
+        self.findings_with_validator(if strict { v.strict() } else { v })
+    }
+
+    /* Findings of a validator configured by the caller, e.g. with a base URL */
+    pub fn findings_with_validator(&self, mut v: Validator) -> Vec<Finding> {
+        let mut findings = vec![];
+        if let Some(version) = self.version {
+            findings.extend(v.feed(Entry::Version(version)));
+        }
         for m in &self.media {
-            if let Some(s) = group_ids.get_mut(&m.type_) {
-                s.insert(&m.group_id);
-            } else {
-                group_ids.insert(m.type_.clone(), HashSet::from([m.group_id.as_str()]));
-            }
+            findings.extend(v.feed(Entry::Media(m)));
         }
         for si in &self.stream_inf {
-            if let Some(au) = &si.audio {
-                if !group_ids.get(&MediaType::Audio).map(|s| s.contains(au.as_str()))
-                    .unwrap_or(false) {
-                    return Err(format!("Reference to unknown AUDIO group {}", au).to_string())
-                }
-            }
-            if let Some(cc) = &si.closed_captions {
-                if !group_ids.get(&MediaType::ClosedCaptions).map(|s| s.contains(cc.as_str()))
-                    .unwrap_or(false) {
-                    return Err(format!("Reference to unknown CLOSED-CAPTIONS group {}", cc).to_string())
-                }
-            }
+            findings.extend(v.feed(Entry::StreamInf(si)));
         }
+        for ifsi in &self.i_frame_stream_inf {
+            findings.extend(v.feed(Entry::IFrameStreamInf(ifsi)));
+        }
+        findings.extend(v.finish());
+        findings
+    }
 
-        Ok(())
+    /* 
+    Perform basic validation of the playlist, see validator::Validator for the rules.
+    Returns all the findings, as an error if any of them is one.
+    */
+    pub fn validate(self: &Self) -> Result<ValidationReport, ValidationReport> {
+        ValidationReport::from(self.findings()).into_result()
     }
 
-    /* Filter by audio GROUP-ID */
-    pub fn select_audio_group(self: &Self, ag: &str) -> Result<Self, String> {
+    // Playlist with the same playlist-level properties, but no media and streams
+    fn empty_like(&self) -> Self {
         let mut ret = Self::new();
         ret.independent_segments = self.independent_segments;
+        ret.version = self.version;
+        ret.unrecognized = self.unrecognized.clone();
+        ret.source = self.source.clone();
+        ret
+    }
+
+    pub fn find_variant(&self, id: &str) -> Option<&StreamInf> {
+        self.stream_inf.iter().find(|si| si.stable_id() == id)
+    }
+
+    /*
+    Variant, I-frame variant or rendition with the given STABLE-VARIANT-ID or
+    STABLE-RENDITION-ID, which the playlist keeps across updates, searched in that order
+    */
+    pub fn find_by_stable_id(&self, id: &str) -> Option<Entry<'_>> {
+        let id = Some(id);
+        if let Some(si) = self.stream_inf.iter().find(|si| si.stable_variant_id.as_deref() == id) {
+            return Some(Entry::StreamInf(si))
+        }
+        if let Some(ifsi) = self.i_frame_stream_inf.iter().find(|ifsi| ifsi.stable_variant_id.as_deref() == id) {
+            return Some(Entry::IFrameStreamInf(ifsi))
+        }
+        self.media.iter().find(|m| m.stable_rendition_id.as_deref() == id).map(Entry::Media)
+    }
+
+    /* Keep only the EXT-X-STREAM-INF and EXT-X-I-FRAME-STREAM-INF with given ids */
+    pub fn select_ids(&self, ids: &[String]) -> Result<Self, String> {
+        let mut ret = self.empty_like();
+        ret.media = self.media.clone();
+        for id in ids {
+            if !self.stream_inf.iter().any(|si| si.stable_id() == *id)
+                && !self.i_frame_stream_inf.iter().any(|ifsi| ifsi.stable_id() == *id) {
+                return Err(format!("No stream with id {}", id).to_string());
+            }
+        }
+        ret.stream_inf = self.stream_inf.iter().filter(|si| ids.contains(&si.stable_id())).cloned().collect();
+        ret.i_frame_stream_inf = self.i_frame_stream_inf.iter().filter(|ifsi| ids.contains(&ifsi.stable_id())).cloned().collect();
+        Ok(ret)
+    }
+
+    /* Filter by audio GROUP-ID */
+    pub fn select_audio_group(self: &Self, ag: &str) -> Result<Self, String> {
+        let mut ret = self.empty_like();
         let mut found = false;
         for m in &self.media {
             if m.type_ != MediaType::Audio || m.group_id==ag {
@@ -134,100 +956,644 @@ impl MultivariantPlaylist {
                 found = true;
             }
         }
-        if !found {
-            return Err(format!("Audio group {} has no STREAM-ID associated", ag).to_string());
+        if !found {
+            return Err(format!("Audio group {} has no STREAM-ID associated", ag).to_string());
+        }
+        ret.i_frame_stream_inf = self.i_frame_stream_inf.clone();
+        Ok(ret)
+    }
+
+    /* Filter by audio CHANNELS, see ChannelsFilter */
+    pub fn select_audio_by_channels(self: &Self, ch: &str) -> Result<Self, String> {
+        let filter = ChannelsFilter::parse(ch).ok_or(format!("Invalid channels filter {}", ch))?;
+        let mut ret = self.empty_like();
+        let mut found = false;
+        let mut agroups = HashSet::<&str>::new();
+        for m in &self.media {
+            if m.type_ != MediaType::Audio || m.channels.as_ref().is_some_and(|v| filter.matches(v)) {
+                ret.media.push(m.clone());
+                agroups.insert(&m.group_id);
+                found = true;
+            }
+        }
+        if !found {
+            return Err(format!("Audio media with channels {} not found", ch).to_string());
+        }
+        found = false;
+        for si in &self.stream_inf {
+            if si.audio.is_none() || agroups.contains(&si.audio.as_ref().unwrap().as_str()) {
+                ret.stream_inf.push(si.clone());
+                found = true;
+            }
+        }
+        if !found {
+            return Err(format!("No STREAM-ID associated with audio having CHANNELS {}", ch).to_string());
+        }
+        ret.i_frame_stream_inf = self.i_frame_stream_inf.clone();
+        Ok(ret)
+    }
+
+    /*
+    Filter audio EXT-X-MEDIA by LANGUAGE or ASSOC-LANGUAGE, case insensitive, "es" also
+    matching "es-419", and EXT-X-STREAM-INF by the audio groups left
+    */
+    pub fn select_audio_language(&self, lang: &str) -> Result<Self, String> {
+        let matches = |tag: &Option<String>| language_matches(tag, lang);
+        let mut ret = self.empty_like();
+        let mut found = false;
+        let mut agroups = HashSet::<&str>::new();
+        for m in &self.media {
+            if m.type_ != MediaType::Audio {
+                ret.media.push(m.clone());
+            } else if matches(&m.language) || matches(&m.assoc_language) {
+                ret.media.push(m.clone());
+                agroups.insert(&m.group_id);
+                found = true;
+            }
+        }
+        if !found {
+            return Err(format!("Audio media with language {} not found", lang).to_string());
+        }
+        found = false;
+        for si in &self.stream_inf {
+            if si.audio.is_none() || agroups.contains(&si.audio.as_ref().unwrap().as_str()) {
+                ret.stream_inf.push(si.clone());
+                found = true;
+            }
+        }
+        if !found {
+            return Err(format!("No STREAM-ID associated with audio having language {}", lang).to_string());
+        }
+        ret.i_frame_stream_inf = self.i_frame_stream_inf.clone();
+        Ok(ret)
+    }
+
+    /*
+    Keep the DEFAULT=YES rendition of every audio group, the first one if there are
+    several, e.g. for minimal playlists in smoke tests. Fails on groups without one.
+    */
+    pub fn select_default_audio(&self) -> Result<Self, String> {
+        let mut groups = HashSet::<&str>::new();
+        for m in self.media.iter().filter(|m| m.type_ == MediaType::Audio) {
+            groups.insert(&m.group_id);
+        }
+        let mut missing: Vec<&str> = groups.iter().copied()
+            .filter(|g| !self.media.iter().any(|m| m.type_ == MediaType::Audio && m.group_id == *g && m.default))
+            .collect();
+        if !missing.is_empty() {
+            missing.sort();
+            return Err(format!("No DEFAULT=YES rendition in audio group {}", missing.join(", ")));
+        }
+        let mut kept = HashSet::<&str>::new();
+        let mut ret = self.clone();
+        ret.media = self.media.iter()
+            .filter(|m| m.type_ != MediaType::Audio || (m.default && kept.insert(&m.group_id)))
+            .cloned().collect();
+        Ok(ret)
+    }
+
+    // Keep SUBTITLES renditions for which `keep` is true, variants referencing a group left empty lose SUBTITLES
+    fn retain_subtitles(&self, keep: impl Fn(&Media) -> bool) -> Self {
+        let mut ret = self.clone();
+        ret.media.retain(|m| m.type_ != MediaType::Subtitles || keep(m));
+        let groups: HashSet<&str> = ret.media.iter().filter(|m| m.type_ == MediaType::Subtitles)
+            .map(|m| m.group_id.as_str()).collect();
+        for si in &mut ret.stream_inf {
+            if si.subtitles.as_ref().is_some_and(|g| !groups.contains(g.as_str())) {
+                si.subtitles = None;
+            }
+        }
+        ret
+    }
+
+    /* Filter SUBTITLES EXT-X-MEDIA by GROUP-ID, other groups are removed from EXT-X-STREAM-INF */
+    pub fn select_subtitle_group(&self, group: &str) -> Result<Self, String> {
+        if !self.media.iter().any(|m| m.type_ == MediaType::Subtitles && m.group_id == group) {
+            return Err(format!("Subtitles group {} not found", group));
+        }
+        Ok(self.retain_subtitles(|m| m.group_id == group))
+    }
+
+    /* Filter SUBTITLES EXT-X-MEDIA by LANGUAGE or ASSOC-LANGUAGE, as select_audio_language */
+    pub fn select_subtitle_language(&self, lang: &str) -> Result<Self, String> {
+        let matches = |m: &Media| language_matches(&m.language, lang) || language_matches(&m.assoc_language, lang);
+        if !self.media.iter().any(|m| m.type_ == MediaType::Subtitles && matches(m)) {
+            return Err(format!("Subtitles media with language {} not found", lang));
+        }
+        Ok(self.retain_subtitles(matches))
+    }
+
+    /* Keep the FORCED=YES SUBTITLES EXT-X-MEDIA, for forced narrative only, see retain_subtitles */
+    pub fn select_forced_subtitles(&self) -> Result<Self, String> {
+        if !self.media.iter().any(|m| m.type_ == MediaType::Subtitles && m.forced) {
+            return Err("No forced subtitles".to_string());
+        }
+        Ok(self.retain_subtitles(|m| m.forced))
+    }
+
+    /* Remove SUBTITLES EXT-X-MEDIA and the SUBTITLES attribute of EXT-X-STREAM-INF */
+    pub fn without_subtitles(&self) -> Self {
+        self.retain_subtitles(|_| false)
+    }
+
+    /* Remove CLOSED-CAPTIONS EXT-X-MEDIA, and set CLOSED-CAPTIONS=NONE in every EXT-X-STREAM-INF */
+    pub fn remove_closed_captions(&self) -> Self {
+        let mut ret = self.clone();
+        ret.media.retain(|m| m.type_ != MediaType::ClosedCaptions);
+        for si in &mut ret.stream_inf {
+            si.closed_captions = Some(ClosedCaptions::None);
+        }
+        ret
+    }
+
+    // Variants best first, in playlist order on ties
+    fn ranked(&self, by: BestBy, max_bandwidth: Option<u64>) -> Vec<&StreamInf> {
+        let mut ret: Vec<&StreamInf> = self.stream_inf.iter()
+            .filter(|si| max_bandwidth.map_or(true, |max| si.bandwidth <= max)).collect();
+        let key = |si: &StreamInf| match by {
+            BestBy::Bandwidth => (None, si.bandwidth),
+            BestBy::Resolution => (si.resolution.clone(), si.bandwidth),
+        };
+        ret.sort_by_key(|si| std::cmp::Reverse(key(si)));
+        ret
+    }
+
+    // Keep the given variants, in playlist order, the renditions of the groups they
+    // reference and the EXT-X-I-FRAME-STREAM-INF of their resolutions
+    fn keep_variants(&self, kept: &[&StreamInf]) -> Self {
+        let mut ret = self.empty_like();
+        ret.media = self.media.clone();
+        ret.stream_inf = self.stream_inf.iter().filter(|si| kept.iter().any(|k| std::ptr::eq(*k, *si))).cloned().collect();
+        ret.i_frame_stream_inf = self.i_frame_stream_inf.iter()
+            .filter(|ifsi| ifsi.resolution.is_some() && kept.iter().any(|si| si.resolution == ifsi.resolution))
+            .cloned().collect();
+        ret.prune_unreferenced_media()
+    }
+
+    /*
+    Remove the AUDIO, VIDEO, SUBTITLES and CLOSED-CAPTIONS renditions of groups no
+    EXT-X-STREAM-INF references, e.g. after filtering variants. VIDEO renditions
+    referenced by an EXT-X-I-FRAME-STREAM-INF are kept.
+    */
+    pub fn prune_unreferenced_media(&self) -> Self {
+        let mut ret = self.clone();
+        ret.media.retain(|m| matches!(m.type_, MediaType::Other(_))
+            || self.stream_inf.iter().any(|si| si.references(m))
+            || (m.type_ == MediaType::Video && self.i_frame_stream_inf.iter().any(|ifsi| ifsi.video.as_ref() == Some(&m.group_id))));
+        ret
+    }
+
+    /*
+    Remove the EXT-X-STREAM-INF whose AUDIO group has no rendition left, e.g. after
+    retain_media, as players cannot play them. Variants without AUDIO are kept.
+    */
+    pub fn select_with_audio(&self) -> Result<Self, String> {
+        let mut ret = self.clone();
+        ret.stream_inf.retain(|si| si.audio.is_none()
+            || self.media.iter().any(|m| m.type_ == MediaType::Audio && si.references(m)));
+        if ret.stream_inf.is_empty() {
+            return Err("No streams with audio renditions".to_string())
+        }
+        Ok(ret)
+    }
+
+    /* Keep the single EXT-X-STREAM-INF best according to `criteria`, the first one on ties, see keep_variants */
+    pub fn select_best(&self, criteria: &BestCriteria) -> Result<Self, String> {
+        let Some(best) = self.ranked(criteria.by, criteria.max_bandwidth).first().copied() else {
+            return Err(match criteria.max_bandwidth {
+                Some(max) => format!("No streams with bandwidth lower than {}", max),
+                None => "No streams".to_string(),
+            })
+        };
+        Ok(self.keep_variants(&[best]))
+    }
+
+    /* Keep the `n` best EXT-X-STREAM-INF, by bandwidth or resolution, see keep_variants */
+    pub fn select_top_n(&self, n: usize, by: BestBy) -> Result<Self, String> {
+        if n == 0 {
+            return Err("At least one variant must be kept".to_string())
+        }
+        let mut top = self.ranked(by, None);
+        top.truncate(n);
+        Ok(self.keep_variants(&top))
+    }
+
+    // Keep the URIs for which is_match is `keep`. Renditions without URI are kept, variants
+    // whose audio group is left empty are removed, and lose SUBTITLES when it is the subtitles group.
+    fn filter_uris(&self, re: &Regex, keep: bool) -> Result<Self, String> {
+        let kept = |uri: &PlaylistUri| re.is_match(uri.as_str()) == keep;
+        let mut ret = self.retain_subtitles(|m| m.uri.as_ref().map_or(true, kept));
+        ret.media.retain(|m| m.type_ == MediaType::Subtitles || m.uri.as_ref().map_or(true, kept));
+        let audio: HashSet<String> = ret.media.iter().filter(|m| m.type_ == MediaType::Audio)
+            .map(|m| m.group_id.clone()).collect();
+        let had_audio = |g: &String| self.media.iter().any(|m| m.type_ == MediaType::Audio && m.group_id == *g);
+        ret.stream_inf.retain(|si| kept(&si.uri) && si.audio.as_ref().map_or(true, |g| audio.contains(g) || !had_audio(g)));
+        if ret.stream_inf.is_empty() {
+            return Err(format!("No streams left with URIs {} {}", if keep { "matching" } else { "not matching" }, re));
+        }
+        ret.i_frame_stream_inf.retain(|ifsi| kept(&ifsi.uri));
+        Ok(ret)
+    }
+
+    /* Keep the EXT-X-STREAM-INF, EXT-X-I-FRAME-STREAM-INF and EXT-X-MEDIA with a URI matching, see filter_uris */
+    pub fn select_uri_matching(&self, re: &Regex) -> Result<Self, String> {
+        self.filter_uris(re, true)
+    }
+
+    /* Remove the EXT-X-STREAM-INF, EXT-X-I-FRAME-STREAM-INF and EXT-X-MEDIA with a URI matching, see filter_uris */
+    pub fn select_uri_not_matching(&self, re: &Regex) -> Result<Self, String> {
+        self.filter_uris(re, false)
+    }
+
+    /*
+    Replace the matches of `re` in the URIs of EXT-X-STREAM-INF, EXT-X-I-FRAME-STREAM-INF
+    and EXT-X-MEDIA, e.g. to move them to another host. `replacement` can refer to
+    capture groups as $1 or ${name}. Fails when no URI matches or a result is not a URI.
+    */
+    pub fn rewrite_uris(&self, re: &Regex, replacement: &str) -> Result<Self, String> {
+        let mut ret = self.clone();
+        let mut matched = false;
+        let mut rewrite = |uri: &mut PlaylistUri| -> Result<(), String> {
+            if re.is_match(uri.as_str()) {
+                matched = true;
+                *uri = PlaylistUri::parse(&re.replace_all(uri.as_str(), replacement))?;
+            }
+            Ok(())
+        };
+        ret.stream_inf.iter_mut().try_for_each(|si| rewrite(&mut si.uri))?;
+        ret.i_frame_stream_inf.iter_mut().try_for_each(|ifsi| rewrite(&mut ifsi.uri))?;
+        ret.media.iter_mut().filter_map(|m| m.uri.as_mut()).try_for_each(&mut rewrite)?;
+        if !matched {
+            return Err(format!("No URIs matching {}", re))
+        }
+        Ok(ret)
+    }
+
+    /* Remove EXT-X-I-FRAME-STREAM-INF, for clients without trick play */
+    pub fn without_iframe_streams(&self) -> Self {
+        let mut ret = self.clone();
+        ret.i_frame_stream_inf.clear();
+        ret
+    }
+
+    /* Filter by bandwidth (maximum specified) */
+    pub fn select_max_bandwidth(self: &Self, bw: u64) -> Result<Self, String> {
+        let mut ret = self.empty_like();
+        ret.media = self.media.clone();
+        let mut found = false;
+        for si in &self.stream_inf {
+            if si.bandwidth <= bw {
+                ret.stream_inf.push(si.clone());
+                found = true;
+            }
+        }
+        if !found {
+            return Err(format!("No streams with bandwidth lower than {}", bw).to_string());
+        }
+        ret.i_frame_stream_inf = self.i_frame_stream_inf.clone();
+        Ok(ret)
+    }
+
+    /* Filter by bandwidth (minimum specified) */
+    pub fn select_min_bandwidth(&self, bw: u64) -> Result<Self, String> {
+        let mut ret = self.empty_like();
+        ret.media = self.media.clone();
+        let mut found = false;
+        for si in &self.stream_inf {
+            if si.bandwidth >= bw {
+                ret.stream_inf.push(si.clone());
+                found = true;
+            }
+        }
+        if !found {
+            return Err(format!("No streams with bandwidth higher than {}", bw).to_string());
+        }
+        ret.i_frame_stream_inf = self.i_frame_stream_inf.clone();
+        Ok(ret)
+    }
+
+    /* Filter EXT-X-STREAM-INF and EXT-X-I-FRAME-STREAM-INF by bandwidth, bounds included */
+    pub fn select_bandwidth_range(&self, min: u64, max: u64) -> Result<Self, String> {
+        let mut ret = self.empty_like();
+        ret.media = self.media.clone();
+        let in_range = |bw: u64| min <= bw && bw <= max;
+        ret.stream_inf = self.stream_inf.iter().filter(|si| in_range(si.bandwidth)).cloned().collect();
+        if ret.stream_inf.is_empty() {
+            return Err(format!("No streams with bandwidth between {} and {}", min, max));
+        }
+        ret.i_frame_stream_inf = self.i_frame_stream_inf.iter().filter(|ifsi| in_range(ifsi.bandwidth)).cloned().collect();
+        Ok(ret)
+    }
+
+    /* Filter EXT-X-STREAM-INF and EXT-X-I-FRAME-STREAM-INF by resolution (exact) */
+    pub fn select_resolution(self: &Self, res: &Resolution) -> Result<Self, String> {
+        let mut ret = self.empty_like();
+        ret.media = self.media.clone();
+        let mut found = false;
+        for si in &self.stream_inf {
+            if si.resolution.as_ref().is_some_and(|v| *v==*res) {
+                ret.stream_inf.push(si.clone());
+                found = true;
+            }
+        }
+        if !found {
+            return Err(format!("No streams with resolution {:?}", res).to_string());
+        }
+        for ifsi in &self.i_frame_stream_inf {
+            if ifsi.resolution.as_ref().is_some_and(|v| *v==*res) {
+                ret.i_frame_stream_inf.push(ifsi.clone());
+            }
+        }
+        Ok(ret)
+    }
+
+    /*
+    Filter EXT-X-STREAM-INF and EXT-X-I-FRAME-STREAM-INF by resolution, keeping those
+    not wider nor taller than `res`. Variants without RESOLUTION, e.g. audio-only, are kept.
+    */
+    pub fn select_max_resolution(&self, res: &Resolution) -> Result<Self, String> {
+        let mut ret = self.empty_like();
+        ret.media = self.media.clone();
+        let fits = |v: &Option<Resolution>| v.as_ref().map_or(true, |v| v.w <= res.w && v.h <= res.h);
+        ret.stream_inf = self.stream_inf.iter().filter(|si| fits(&si.resolution)).cloned().collect();
+        if !ret.stream_inf.iter().any(|si| si.resolution.is_some()) {
+            return Err(format!("No streams with resolution within {}x{}", res.w, res.h));
+        }
+        ret.i_frame_stream_inf = self.i_frame_stream_inf.iter().filter(|ifsi| fits(&ifsi.resolution)).cloned().collect();
+        Ok(ret)
+    }
+
+    /*
+    Filter EXT-X-STREAM-INF and EXT-X-I-FRAME-STREAM-INF by resolution, keeping those
+    at least as wide and as tall as `res`. Variants without RESOLUTION are kept.
+    */
+    pub fn select_min_resolution(&self, res: &Resolution) -> Result<Self, String> {
+        let mut ret = self.empty_like();
+        ret.media = self.media.clone();
+        let covers = |v: &Option<Resolution>| v.as_ref().map_or(true, |v| v.w >= res.w && v.h >= res.h);
+        ret.stream_inf = self.stream_inf.iter().filter(|si| covers(&si.resolution)).cloned().collect();
+        if !ret.stream_inf.iter().any(|si| si.resolution.is_some()) {
+            return Err(format!("No streams with resolution of at least {}x{}", res.w, res.h));
+        }
+        ret.i_frame_stream_inf = self.i_frame_stream_inf.iter().filter(|ifsi| covers(&ifsi.resolution)).cloned().collect();
+        Ok(ret)
+    }
+
+    /* Filter EXT-X-STREAM-INF by frame rate (maximum specified), variants without FRAME-RATE are kept */
+    pub fn select_max_frame_rate(&self, fps: f64) -> Result<Self, String> {
+        let mut ret = self.empty_like();
+        ret.media = self.media.clone();
+        ret.stream_inf = self.stream_inf.iter().filter(|si| si.frame_rate.map_or(true, |fr| fr.as_f64() <= fps))
+            .cloned().collect();
+        if ret.stream_inf.is_empty() {
+            return Err(format!("No streams with frame rate up to {}", fps));
         }
         ret.i_frame_stream_inf = self.i_frame_stream_inf.clone();
         Ok(ret)
     }
 
-    /* Filter by audio CHANNELS */
-    pub fn select_audio_by_channels(self: &Self, ch: &str) -> Result<Self, String> {
-        let mut ret = Self::new();
-        ret.independent_segments = self.independent_segments;
-        let mut found = false;
-        let mut agroups = HashSet::<&str>::new();
-        for m in &self.media {
-            if m.type_ != MediaType::Audio || m.channels.as_ref().is_some_and(|v| v==ch) {
-                ret.media.push(m.clone());
-                agroups.insert(&m.group_id);
-                found = true;
-            }
+    /*
+    Keep the EXT-X-STREAM-INF and EXT-X-I-FRAME-STREAM-INF of a content steering
+    pathway, those without PATHWAY-ID being on the default pathway ".", and the
+    renditions they reference, e.g. to look into a single CDN.
+    */
+    pub fn select_pathway(&self, pathway: &str) -> Result<Self, String> {
+        let on_pathway = |id: &Option<String>| id.as_deref().unwrap_or(DEFAULT_PATHWAY_ID) == pathway;
+        let mut ret = self.clone();
+        ret.retain_variants(|si| on_pathway(&si.pathway_id));
+        if ret.stream_inf.is_empty() {
+            return Err(format!("No streams on pathway {}", pathway));
         }
-        if !found {
-            return Err(format!("Audio media with channels {} not found", ch).to_string());
+        ret.retain_i_frame_streams(|ifsi| on_pathway(&ifsi.pathway_id));
+        Ok(ret.prune_unreferenced_media())
+    }
+
+    /*
+    Filter EXT-X-STREAM-INF by HDCP-LEVEL, keeping those requiring at most `level`,
+    e.g. HdcpLevel::None for unprotected outputs. Variants without HDCP-LEVEL are kept.
+    */
+    pub fn select_max_hdcp_level(&self, level: HdcpLevel) -> Result<Self, String> {
+        let mut ret = self.clone();
+        ret.retain_variants(|si| si.hdcp_level.map_or(true, |l| l <= level));
+        if ret.stream_inf.is_empty() {
+            return Err(format!("No streams with HDCP-LEVEL up to {}", level.as_str()));
         }
-        found = false;
-        for si in &self.stream_inf {
-            if si.audio.is_none() || agroups.contains(&si.audio.as_ref().unwrap().as_str()) {
-                ret.stream_inf.push(si.clone());
-                found = true;
-            }
+        Ok(ret)
+    }
+
+    /*
+    Filter EXT-X-I-FRAME-STREAM-INF only, e.g. to trim the trick play ladder, see
+    IFrameCriteria. EXT-X-STREAM-INF and EXT-X-MEDIA are left as they are.
+    */
+    pub fn select_i_frame_streams(&self, criteria: &IFrameCriteria) -> Result<Self, String> {
+        let mut ret = self.clone();
+        ret.retain_i_frame_streams(|ifsi| criteria.matches(ifsi));
+        if ret.i_frame_stream_inf.is_empty() && !self.i_frame_stream_inf.is_empty() {
+            return Err("No I-frame streams matching the criteria".to_string())
         }
-        if !found {
-            return Err(format!("No STREAM-ID associated with audio having CHANNELS {}", ch).to_string());
+        Ok(ret)
+    }
+
+    /* Filter EXT-X-STREAM-INF and EXT-X-I-FRAME-STREAM-INF by VIDEO-RANGE, SDR when absent */
+    pub fn select_video_range(&self, range: &VideoRange) -> Result<Self, String> {
+        let mut ret = self.empty_like();
+        ret.media = self.media.clone();
+        let matching = |v: &Option<VideoRange>| v.as_ref().unwrap_or(&VideoRange::SDR) == range;
+        ret.stream_inf = self.stream_inf.iter().filter(|si| matching(&si.video_range)).cloned().collect();
+        if ret.stream_inf.is_empty() {
+            return Err(format!("No streams with video range {}", range.as_str()));
         }
-        ret.i_frame_stream_inf = self.i_frame_stream_inf.clone();
+        ret.i_frame_stream_inf = self.i_frame_stream_inf.iter().filter(|ifsi| matching(&ifsi.video_range)).cloned().collect();
         Ok(ret)
     }
 
-    /* Filter by bandwidth (maximum specified) */
-    pub fn select_max_bandwidth(self: &Self, bw: u64) -> Result<Self, String> {
-        let mut ret = Self::new();
-        ret.independent_segments = self.independent_segments;
+    /* Filter EXT-X-STREAM-INF by inferred quality label (exact) */
+    pub fn select_label(&self, label: &str) -> Result<Self, String> {
+        let mut ret = self.empty_like();
         ret.media = self.media.clone();
         let mut found = false;
         for si in &self.stream_inf {
-            if si.bandwidth <= bw {
+            if si.infer_label() == label {
                 ret.stream_inf.push(si.clone());
                 found = true;
             }
         }
         if !found {
-            return Err(format!("No streams with bandwidth lower than {}", bw).to_string());
+            return Err(format!("No streams with label {}", label).to_string());
         }
         ret.i_frame_stream_inf = self.i_frame_stream_inf.clone();
         Ok(ret)
     }
 
-    /* Filter EXT-X-STREAM-INF and EXT-X-I-FRAME-STREAM-INF by resolution (exact) */
-    pub fn select_resolution(self: &Self, res: &Resolution) -> Result<Self, String> {
-        let mut ret = Self::new();
-        ret.independent_segments = self.independent_segments;
+    /* Filter EXT-X-STREAM-INF by codec family in CODECS, and EXT-X-I-FRAME-STREAM-INF for video codecs */
+    pub fn select_codec(&self, family: CodecFamily) -> Result<Self, String> {
+        let mut ret = self.empty_like();
         ret.media = self.media.clone();
-        let mut found = false;
-        for si in &self.stream_inf {
-            if si.resolution.as_ref().is_some_and(|v| *v==*res) {
-                ret.stream_inf.push(si.clone());
-                found = true;
-            }
+        let has = |codecs: &Option<Codecs>| codecs.as_ref().is_some_and(|c| c.contains(family));
+        ret.stream_inf = self.stream_inf.iter().filter(|si| has(&si.codecs)).cloned().collect();
+        if ret.stream_inf.is_empty() {
+            return Err(format!("No streams with codec {:?}", family));
         }
-        if !found {
-            return Err(format!("No streams with resolution {:?}", res).to_string());
+        ret.i_frame_stream_inf = self.i_frame_stream_inf.iter().filter(|ifsi| !family.is_video() || has(&ifsi.codecs))
+            .cloned().collect();
+        Ok(ret)
+    }
+
+    /*
+    Filter EXT-X-STREAM-INF by their CODECS, empty when the attribute is absent. The
+    EXT-X-I-FRAME-STREAM-INF kept are those without CODECS or whose video codec is
+    in the CODECS of a variant kept.
+    */
+    pub fn select_codecs(&self, pred: impl Fn(&Codecs) -> bool) -> Result<Self, String> {
+        let mut ret = self.empty_like();
+        ret.media = self.media.clone();
+        let none = Codecs(vec![]);
+        ret.stream_inf = self.stream_inf.iter().filter(|si| pred(si.codecs.as_ref().unwrap_or(&none))).cloned().collect();
+        if ret.stream_inf.is_empty() {
+            return Err("No streams with matching codecs".to_string());
         }
-        for ifsi in &self.i_frame_stream_inf {
-            if ifsi.resolution.as_ref().is_some_and(|v| *v==*res) {
-                ret.i_frame_stream_inf.push(ifsi.clone());
+        let kept = |video: &Codec| ret.stream_inf.iter().any(|si| si.codecs.as_ref().is_some_and(|c| c.iter().any(|c| c == video)));
+        ret.i_frame_stream_inf = self.i_frame_stream_inf.iter()
+            .filter(|ifsi| ifsi.codecs.as_ref().and_then(|c| c.video()).map_or(true, kept))
+            .cloned().collect();
+        Ok(ret)
+    }
+
+    /*
+    Add an audio-only EXT-X-STREAM-INF for poor networks, pointing to an existing
+    audio rendition, unless there is one already. The rendition is the DEFAULT one,
+    or the first, of the audio group with the cheapest codec, as found in the CODECS
+    of the variants referencing it. Its BANDWIDTH is estimated from the codec.
+    */
+    pub fn add_audio_only_fallback(&self) -> Result<Self, String> {
+        if self.stream_inf.iter().any(|si| !si.has_video() && si.bandwidth <= AUDIO_ONLY_MAX_BANDWIDTH) {
+            return Ok(self.clone())
+        }
+        let mut best: Option<(u64, &Codec, &str)> = None; // bandwidth, codec, group
+        for si in &self.stream_inf {
+            let (Some(group), Some(codecs)) = (&si.audio, &si.codecs) else { continue };
+            for codec in codecs.iter() {
+                let Some(bw) = audio_bandwidth_estimate(codec) else { continue };
+                if !best.is_some_and(|(b, _, _)| bw >= b) {
+                    best = Some((bw, codec, group));
+                }
             }
         }
+        let Some((bandwidth, codec, group)) = best.filter(|(bw, _, _)| *bw <= AUDIO_ONLY_MAX_BANDWIDTH) else {
+            return Err("No audio rendition suitable for an audio-only variant".to_string())
+        };
+        let renditions: Vec<&Media> = self.media.iter()
+            .filter(|m| m.type_ == MediaType::Audio && m.group_id == group && m.uri.is_some())
+            .collect();
+        let Some(m) = renditions.iter().find(|m| m.default).or(renditions.first()) else {
+            return Err(format!("No audio rendition with URI in group {}", group))
+        };
+        let si = StreamInf{
+            uri: m.uri.clone().unwrap_or_default(),
+            bandwidth,
+            average_bandwidth: None,
+            score: None,
+            codecs: Some(Codecs(vec![codec.clone()])),
+            resolution: None,
+            frame_rate: None,
+            hdcp_level: None,
+            allowed_cpc: None,
+            video_range: None,
+            stable_variant_id: None,
+            audio: Some(group.to_string()),
+            video: None,
+            subtitles: None,
+            closed_captions: None,
+            pathway_id: None,
+        };
+        let mut ret = self.clone();
+        ret.stream_inf.push(si);
         Ok(ret)
     }
 
-    /* Sort EXT-X-STREAM-INF by bandwidth, descending */
-    pub fn sort_by_bandwidth(self: &mut Self) {
-        self.stream_inf.sort_by(|a, b| b.bandwidth.cmp(&a.bandwidth));
+    /*
+    Replace BANDWIDTH where it is zero or below AVERAGE-BANDWIDTH by the suggested
+    value, see BandwidthBounds::suggested_bandwidth. Implausible values are only
+    reported by findings().
+    */
+    pub fn fix_bandwidths(&self, bounds: &BandwidthBounds) -> Self {
+        let mut ret = self.clone();
+        for si in &mut ret.stream_inf {
+            if si.bandwidth == 0 || si.average_bandwidth.is_some_and(|avg| avg > si.bandwidth) {
+                if let Some(bw) = bounds.suggested_bandwidth(si) {
+                    si.bandwidth = bw;
+                }
+            }
+        }
+        ret
+    }
+
+    /* The playlist as m3u8 text, as in its source when parsed with ParseOptions::preserve,
+       see writer::Writer to customize how tags are written */
+    pub fn to_m3u8(&self) -> Result<String, String> {
+        Writer::new().write_preserving(self)
     }
 
-}
+    /*
+    Sort EXT-X-STREAM-INF by bandwidth. The sort is stable: variants with the same
+    bandwidth keep their order in the playlist, which matters to players picking
+    the first one.
+    */
+    pub fn sort_by_bandwidth(&mut self, order: SortOrder) {
+        self.sort_variants_by(|s| s.bandwidth, order);
+    }
+
+    /* Sort EXT-X-STREAM-INF by AVERAGE-BANDWIDTH, or BANDWIDTH when absent, stable as sort_by_bandwidth */
+    pub fn sort_by_average_bandwidth(&mut self, order: SortOrder) {
+        self.sort_variants_by(|s| s.average_bandwidth.unwrap_or(s.bandwidth), order);
+    }
+
+    /*
+    Sort EXT-X-STREAM-INF by SCORE, stable as sort_by_bandwidth. Variants without
+    SCORE come last when descending, first when ascending.
+    */
+    pub fn sort_by_score(&mut self, order: SortOrder) {
+        self.sort_variants_by(|s| s.score, order);
+    }
+
+    fn sort_variants_by<K: Ord>(&mut self, key: impl Fn(&StreamInf) -> K, order: SortOrder) {
+        match order {
+            SortOrder::Ascending => self.stream_inf.sort_by_key(key),
+            SortOrder::Descending => self.stream_inf.sort_by_key(|s| std::cmp::Reverse(key(s))),
+        }
+    }
+
+    /*
+    Keep the EXT-X-STREAM-INF for which `f` is true, for filters without a select_
+    method. References are left as they are, see findings() for groups left empty.
+    */
+    pub fn retain_variants(&mut self, f: impl FnMut(&StreamInf) -> bool) {
+        self.stream_inf.retain(f);
+    }
+
+    /* Keep the EXT-X-I-FRAME-STREAM-INF for which `f` is true */
+    pub fn retain_i_frame_streams(&mut self, f: impl FnMut(&IFrameStreamInf) -> bool) {
+        self.i_frame_stream_inf.retain(f);
+    }
+
+    /* Keep the EXT-X-MEDIA for which `f` is true, as retain_variants */
+    pub fn retain_media(&mut self, f: impl FnMut(&Media) -> bool) {
+        self.media.retain(f);
+    }
 
+}
 
 #[cfg(test)]
 mod tests {
-    use super::{MultivariantPlaylist, Resolution};
+    use std::collections::HashSet;
+    use regex::Regex;
+    use crate::codecs::{CodecFamily, Codecs};
+    use crate::parser;
+    use crate::validator::{Entry, Severity};
+    use super::{BestBy, BestCriteria, Channels, ChannelsFilter, ClosedCaptions, FrameRate, HdcpLevel, IFrameCriteria, Media, MediaType, MultivariantPlaylist, Resolution, Score, SortOrder, StreamInf, VideoRange};
 
     fn playlist() -> MultivariantPlaylist {
-        let json = include_str!("../data/playlist.json");
-        serde_json::from_str(json).unwrap()
+        parser::parse_playlist(include_str!("../data/master_unenc_hdr10_all.m3u8")).unwrap()
     }
 
     #[test]
@@ -246,6 +1612,408 @@ mod tests {
         assert_eq!(sel.i_frame_stream_inf.len(), playlist().i_frame_stream_inf.len());
     }
 
+    #[test]
+    fn test_select_audio_channels_count() {
+        let sel = playlist().select_audio_by_channels(">=6").unwrap();
+        assert_eq!(sel.media.len(), 2); // eac3 and atmos
+        assert!(sel.media.iter().all(|m| m.channels.as_ref().is_some_and(|ch| ch.count >= 6)));
+        assert_eq!(playlist().select_audio_by_channels("16/JOC").unwrap().media.len(), 1);
+        assert_eq!(playlist().select_audio_by_channels("<6").unwrap().media.len(), 2);
+        assert!(playlist().select_audio_by_channels(">=32").is_err());
+        assert!(playlist().select_audio_by_channels("six").is_err());
+    }
+
+    #[test]
+    fn test_select_audio_language() {
+        let mut pl = playlist();
+        assert_eq!(pl.select_audio_language("EN").unwrap().media.len(), pl.media.len());
+        assert!(pl.select_audio_language("es").is_err());
+        pl.media[0].language = Some("es".to_string());
+        pl.media[1].assoc_language = Some("es-419".to_string());
+        let sel = pl.select_audio_language("es").unwrap();
+        assert_eq!(sel.media.len(), 2); // aac-128k and aac-64k
+        assert_eq!(sel.stream_inf.len(), 10+2);
+        assert_eq!(sel.i_frame_stream_inf.len(), pl.i_frame_stream_inf.len());
+        assert_eq!(pl.select_audio_language("es-419").unwrap().media.len(), 1);
+        assert!(pl.select_audio_language("e").is_err());
+    }
+
+    #[test]
+    fn test_select_subtitles() {
+        let mut pl = parser::parse_playlist(include_str!("../data/captions.m3u8")).unwrap();
+        let mut sub = pl.media[2].clone();
+        (sub.name, sub.language, sub.uri) = ("Español".to_string(), Some("es-419".to_string()), Some("subs/es.m3u8".parse().unwrap()));
+        pl.media.push(sub.clone());
+        (sub.group_id, sub.uri) = ("subs-sdh".to_string(), Some("subs/es-sdh.m3u8".parse().unwrap()));
+        pl.media.push(sub);
+        let subtitles = |pl: &MultivariantPlaylist| pl.media.iter().filter(|m| m.type_ == MediaType::Subtitles)
+            .map(|m| m.uri.as_ref().unwrap().to_string()).collect::<Vec<_>>();
+
+        let sel = pl.select_subtitle_language("es").unwrap();
+        assert_eq!(subtitles(&sel), ["subs/es.m3u8", "subs/es-sdh.m3u8"]);
+        assert_eq!(sel.media.len(), pl.media.len() - 1);
+        assert_eq!(sel.stream_inf, pl.stream_inf);
+        let sel = pl.select_subtitle_language("fr").unwrap();
+        assert_eq!(subtitles(&sel), ["subs/fr.m3u8"]);
+        assert!(pl.select_subtitle_language("de").is_err());
+
+        let sel = pl.select_subtitle_group("subs-sdh").unwrap();
+        assert_eq!(subtitles(&sel), ["subs/es-sdh.m3u8"]);
+        // the variant referenced the other group
+        assert_eq!(sel.stream_inf[0].subtitles, None);
+        assert_eq!(sel.stream_inf[0].closed_captions, Some(ClosedCaptions::Group("cc".to_string())));
+        assert!(pl.select_subtitle_group("cc").is_err());
+
+        let sel = pl.without_subtitles();
+        assert!(subtitles(&sel).is_empty());
+        assert_eq!(sel.media.len(), pl.media.len() - 3);
+        assert!(sel.stream_inf.iter().all(|si| si.subtitles.is_none()));
+
+        assert!(pl.select_forced_subtitles().is_err());
+        let mut forced = pl.media[2].clone();
+        (forced.name, forced.forced, forced.uri) = ("Français (forced)".to_string(), true, Some("subs/fr-forced.m3u8".parse().unwrap()));
+        pl.media.push(forced);
+        let sel = pl.select_forced_subtitles().unwrap();
+        assert_eq!(subtitles(&sel), ["subs/fr-forced.m3u8"]);
+        assert_eq!(sel.stream_inf[0].subtitles.as_deref(), Some("subs"));
+    }
+
+    #[test]
+    fn test_remove_closed_captions() {
+        let pl = parser::parse_playlist(include_str!("../data/captions.m3u8")).unwrap();
+        let sel = pl.remove_closed_captions();
+        assert_eq!(sel.media.len(), 3);
+        assert!(sel.media.iter().all(|m| m.type_ != MediaType::ClosedCaptions));
+        assert!(sel.stream_inf.iter().all(|si| si.closed_captions == Some(ClosedCaptions::None)));
+        assert!(sel.findings().is_empty(), "{:?}", sel.findings());
+        let text = sel.to_m3u8().unwrap();
+        assert!(text.contains(",SUBTITLES=\"subs\",CLOSED-CAPTIONS=NONE\nvideo/720p.m3u8"), "{}", text);
+        assert_eq!(parser::parse_playlist(&text).unwrap(), sel);
+    }
+
+    #[test]
+    fn test_without_iframe_streams() {
+        let pl = playlist();
+        let sel = pl.without_iframe_streams();
+        assert!(sel.i_frame_stream_inf.is_empty());
+        assert_eq!((sel.media.len(), sel.stream_inf.len()), (pl.media.len(), pl.stream_inf.len()));
+        assert!(!sel.to_m3u8().unwrap().contains("#EXT-X-I-FRAME-STREAM-INF"));
+    }
+
+    #[test]
+    fn test_select_best() {
+        let mut pl = playlist();
+        let best = pl.select_best(&BestCriteria::default()).unwrap();
+        assert_eq!(best.stream_inf.len(), 1);
+        assert_eq!(best.stream_inf[0].bandwidth, 26598940);
+        assert!(best.media.iter().all(|m| Some(&m.group_id) == best.stream_inf[0].audio.as_ref()));
+        assert!(best.findings().iter().all(|f| f.severity != Severity::Error));
+
+        let capped = BestCriteria{ max_bandwidth: Some(2000000), ..Default::default() };
+        let best = pl.select_best(&capped).unwrap();
+        assert_eq!(best.stream_inf[0].bandwidth, 1666071);
+        assert_eq!(best.i_frame_stream_inf.len(), 1);
+        assert_eq!(best.i_frame_stream_inf[0].resolution, best.stream_inf[0].resolution);
+
+        let si = pl.stream_inf.iter_mut().find(|si| si.bandwidth == 705826).unwrap();
+        si.resolution = Some(Resolution{ w: 1280, h: 720 });
+        let by_resolution = BestCriteria{ by: BestBy::Resolution, max_bandwidth: Some(2000000) };
+        assert_eq!(pl.select_best(&by_resolution).unwrap().stream_inf[0].bandwidth, 705826);
+        assert_eq!(pl.select_best(&capped).unwrap().stream_inf[0].bandwidth, 1666071);
+        assert!(pl.select_best(&BestCriteria{ max_bandwidth: Some(1000), ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn test_select_top_n() {
+        let pl = playlist();
+        let top = pl.select_top_n(3, BestBy::Bandwidth).unwrap();
+        let bandwidths: Vec<u64> = top.stream_inf.iter().map(|si| si.bandwidth).collect();
+        assert_eq!(bandwidths, [25702333, 25830920, 26598940]); // playlist order
+        let groups: HashSet<&str> = top.media.iter().map(|m| m.group_id.as_str()).collect();
+        assert_eq!(groups.len(), 3);
+        assert!(top.i_frame_stream_inf.is_empty());
+        let top = pl.select_top_n(10, BestBy::Resolution).unwrap();
+        assert!(top.stream_inf.iter().all(|si| si.resolution.as_ref().unwrap().w >= 2560));
+        assert_eq!(pl.select_top_n(100, BestBy::Bandwidth).unwrap().stream_inf, pl.stream_inf);
+        assert!(pl.select_top_n(0, BestBy::Bandwidth).is_err());
+    }
+
+    #[test]
+    fn test_select_uri_matching() {
+        let pl = playlist();
+        let re = Regex::new(r"/(900k|1650k|aac_128k)/").unwrap();
+        let sel = pl.select_uri_matching(&re).unwrap();
+        assert!(!sel.stream_inf.is_empty());
+        assert!(sel.stream_inf.iter().all(|si| re.is_match(si.uri.as_str()) && si.audio.as_deref() == Some("aac-128k")));
+        assert_eq!(sel.i_frame_stream_inf.len(), 1);
+        assert_eq!(sel.media.len(), 1);
+
+        let sel = pl.select_uri_not_matching(&Regex::new("/900k/").unwrap()).unwrap();
+        assert!(sel.stream_inf.iter().all(|si| !si.uri.as_str().contains("/900k/")));
+        assert_eq!(sel.i_frame_stream_inf.len(), 1);
+        assert_eq!(sel.media, pl.media);
+
+        // variants of an audio group left without renditions are removed
+        let sel = pl.select_uri_not_matching(&Regex::new("atmos").unwrap()).unwrap();
+        assert!(sel.media.iter().all(|m| m.group_id != "atmos"));
+        assert!(sel.stream_inf.iter().all(|si| si.audio.as_deref() != Some("atmos")));
+        assert!(sel.findings().iter().all(|f| f.severity != Severity::Error));
+        assert!(pl.select_uri_matching(&Regex::new("^https:").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut pl = playlist();
+        pl.retain_variants(|si| si.bandwidth < 2000000 && si.audio.as_deref() != Some("atmos"));
+        let bandwidths: Vec<u64> = pl.stream_inf.iter().map(|si| si.bandwidth).collect();
+        assert_eq!(bandwidths.len(), 4);
+        assert!(bandwidths.iter().all(|bw| *bw < 2000000));
+        pl.retain_media(|m| m.group_id != "eac3");
+        assert_eq!(pl.media.len(), 3);
+        // the variants of eac3 reference a missing group
+        assert!(pl.findings().iter().any(|f| f.severity == Severity::Error));
+    }
+
+    #[test]
+    fn test_select_default_audio() {
+        let pl = parser::parse_playlist(include_str!("../data/captions.m3u8")).unwrap();
+        let sel = pl.select_default_audio().unwrap();
+        let audio: Vec<&str> = sel.media.iter().filter(|m| m.type_ == MediaType::Audio).map(|m| m.name.as_str()).collect();
+        assert_eq!(audio, ["English"]);
+        assert_eq!(sel.media.len(), pl.media.len() - 1);
+        assert_eq!(sel.stream_inf, pl.stream_inf);
+
+        let mut pl = pl.clone();
+        pl.media[0].default = false;
+        assert_eq!(pl.select_default_audio().unwrap_err(), "No DEFAULT=YES rendition in audio group aac");
+        pl.media[0].default = true;
+        pl.media[1].default = true;
+        assert_eq!(pl.select_default_audio().unwrap().media[0].name, "English");
+    }
+
+    #[test]
+    fn test_prune_unreferenced_media() {
+        let pl = playlist();
+        assert_eq!(pl.prune_unreferenced_media(), pl);
+        let sel = pl.select_max_bandwidth(1000000).unwrap();
+        assert_eq!(sel.media.len(), pl.media.len());
+        let pruned = sel.prune_unreferenced_media();
+        let groups: Vec<&str> = pruned.media.iter().map(|m| m.group_id.as_str()).collect();
+        assert_eq!(groups, ["aac-64k", "eac3"]);
+
+        let mut pl = parser::parse_playlist(include_str!("../data/captions.m3u8")).unwrap();
+        pl.stream_inf.remove(0);
+        let pruned = pl.prune_unreferenced_media();
+        assert!(pruned.media.iter().all(|m| m.type_ == MediaType::Audio), "{:?}", pruned.media);
+        assert_eq!(pruned.media.len(), 2);
+
+        let mut angle = pl.media[0].clone();
+        (angle.type_, angle.group_id) = (MediaType::Video, "angles".to_string());
+        pl.media.push(angle);
+        assert_eq!(pl.prune_unreferenced_media().media.len(), 2);
+        pl.stream_inf[0].video = Some("angles".to_string());
+        assert_eq!(pl.prune_unreferenced_media().media.len(), 3);
+    }
+
+    #[test]
+    fn test_select_with_audio() {
+        let pl = playlist();
+        assert_eq!(pl.select_with_audio().unwrap(), pl);
+        let mut sel = pl.clone();
+        sel.retain_media(|m| m.group_id != "eac3");
+        let sel = sel.select_with_audio().unwrap();
+        assert_eq!(sel.stream_inf.len(), pl.stream_inf.iter().filter(|si| si.audio.as_deref() != Some("eac3")).count());
+        assert!(sel.stream_inf.len() < pl.stream_inf.len());
+        assert_eq!(sel.i_frame_stream_inf, pl.i_frame_stream_inf);
+
+        let mut sel = pl.clone();
+        sel.retain_media(|m| m.type_ != MediaType::Audio);
+        assert!(sel.select_with_audio().is_err());
+
+        // the subtitles of a variant are not its audio
+        let pl = parser::parse_playlist("#EXTM3U\n\
+            #EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID=\"subs\",NAME=\"English\",URI=\"subs.m3u8\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=800000,AUDIO=\"aac\",SUBTITLES=\"subs\"\nv.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=600000\nmuxed.m3u8\n").unwrap();
+        let sel = pl.select_with_audio().unwrap();
+        assert_eq!(sel.stream_inf.iter().map(|si| si.uri.as_str()).collect::<Vec<_>>(), ["muxed.m3u8"]);
+    }
+
+    #[test]
+    fn test_select_codec() {
+        let sel = playlist().select_codec(CodecFamily::Ec3).unwrap();
+        assert_eq!(sel.stream_inf.len(), 24);
+        assert!(sel.stream_inf.iter().all(|si| si.codecs.as_ref().unwrap().to_string().starts_with("ec-3,")));
+        assert_eq!(sel.i_frame_stream_inf.len(), playlist().i_frame_stream_inf.len());
+        let sel = playlist().select_codec(CodecFamily::Hevc).unwrap();
+        assert_eq!(sel.stream_inf.len(), playlist().stream_inf.len());
+        assert!(playlist().select_codec(CodecFamily::Av1).is_err());
+        let mut pl = playlist();
+        pl.stream_inf[0].resolution = None;
+        pl.stream_inf[0].codecs = Some(Codecs::parse("dvh1.05.06"));
+        assert!(pl.stream_inf[0].has_video());
+    }
+
+    #[test]
+    fn test_select_codecs() {
+        let pl = playlist();
+        let sel = pl.select_codecs(|c| !c.iter().any(|c| c.matches("ec-3"))).unwrap();
+        assert_eq!(sel.stream_inf.len(), 12);
+        assert_eq!(sel.i_frame_stream_inf.len(), 2);
+        let sel = pl.select_codecs(|c| c.iter().any(|c| c.matches("hvc1.2.4.L63"))).unwrap();
+        assert_eq!(sel.stream_inf.len(), 6);
+        assert_eq!(sel.i_frame_stream_inf.len(), 1);
+        assert_eq!(sel.i_frame_stream_inf[0].codecs.as_ref().unwrap().to_string(), "hvc1.2.4.L63.90");
+        assert!(pl.select_codecs(|c| c.iter().any(|c| c.matches("avc1"))).is_err());
+        // no CODECS
+        let mut pl = pl;
+        pl.stream_inf[0].codecs = None;
+        assert_eq!(pl.select_codecs(|c| c.0.is_empty()).unwrap().stream_inf.len(), 1);
+    }
+
+    #[test]
+    fn test_select_video_range() {
+        let mut pl = playlist();
+        let sel = pl.select_video_range(&VideoRange::PQ).unwrap();
+        assert_eq!(sel.stream_inf.len(), pl.stream_inf.len());
+        assert_eq!(sel.i_frame_stream_inf.len(), 2);
+        assert!(pl.select_video_range(&VideoRange::SDR).is_err());
+        pl.stream_inf[0].video_range = None;
+        pl.stream_inf[1].video_range = Some(VideoRange::SDR);
+        pl.i_frame_stream_inf[0].video_range = None;
+        let sel = pl.select_video_range(&VideoRange::SDR).unwrap();
+        assert_eq!(sel.stream_inf.len(), 2);
+        assert_eq!(sel.i_frame_stream_inf.len(), 1);
+        assert!(pl.select_video_range(&VideoRange::HLG).is_err());
+    }
+
+    #[test]
+    fn test_equality() {
+        let pl = playlist();
+        assert_eq!(pl, pl.clone());
+        let mut doubled = pl.stream_inf.clone();
+        doubled.extend(pl.stream_inf.iter().cloned());
+        assert_eq!(doubled.iter().collect::<HashSet<_>>().len(), pl.stream_inf.len());
+        let mut other = pl.clone();
+        other.media[0].forced = true;
+        assert_ne!(pl, other);
+
+        // the source kept for writing back is not compared
+        let data = include_str!("../data/master_unenc_hdr10_all.m3u8");
+        let preserved = parser::parse_playlist_with_options(data, &parser::ParseOptions{ preserve: true, ..Default::default() }).unwrap().0;
+        assert_eq!(preserved, parser::parse_playlist(data).unwrap());
+
+        // nor where unrecognized lines are
+        let options = parser::ParseOptions{ passthrough: true, ..Default::default() };
+        let parse = |data: &str| parser::parse_playlist_with_options(data, &options).unwrap().0;
+        let first = parse("#EXTM3U\n#EXT-X-FOO:1\n#EXT-X-STREAM-INF:BANDWIDTH=800000\nv.m3u8\n");
+        let moved = parse("#EXTM3U\n\n#EXT-X-STREAM-INF:BANDWIDTH=800000\nv.m3u8\n#EXT-X-FOO:1\n");
+        assert_ne!((first.unrecognized[0].lineno, first.unrecognized[0].after), (moved.unrecognized[0].lineno, moved.unrecognized[0].after));
+        assert_eq!(first, moved);
+        assert_eq!(HashSet::from([first.clone()]).len(), HashSet::from([first, moved]).len());
+
+        let si = StreamInf{ uri: "v.m3u8".parse().unwrap(), bandwidth: 800_000, ..Default::default() };
+        assert_eq!(si.infer_label(), "audio-only 800k");
+        let m = Media{ group_id: "aac".to_string(), name: "English".to_string(), ..Default::default() };
+        assert_eq!(m.type_, MediaType::Audio);
+        assert_eq!(MultivariantPlaylist::default(), MultivariantPlaylist::new());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_read_json() {
+        // the same playlist, written without CLOSED-CAPTIONS=NONE
+        let mut pl = playlist();
+        pl.stream_inf.iter_mut().for_each(|si| si.closed_captions = None);
+        let read: MultivariantPlaylist = serde_json::from_str(include_str!("../data/playlist.json")).unwrap();
+        assert_eq!(read, pl);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_json_round_trip() {
+        // JSON output read back and written as m3u8, as with the convert command
+        let data = include_str!("../data/master_unenc_hdr10_all.m3u8");
+        let pl = parser::parse_playlist(data).unwrap();
+        let read: MultivariantPlaylist = serde_json::from_str(&serde_json::to_string_pretty(&pl).unwrap()).unwrap();
+        assert_eq!(read, pl);
+        assert_eq!(parser::parse_playlist(&read.to_m3u8().unwrap()).unwrap(), pl);
+    }
+
+    #[test]
+    fn test_resolution() {
+        let hd = Resolution{ w: 1280, h: 720 };
+        assert_eq!(Resolution::named("720P"), Some(hd.clone()));
+        assert_eq!(Resolution::named("4k"), Some(Resolution{ w: 3840, h: 2160 }));
+        assert_eq!(Resolution::named("1080"), None);
+        assert!((hd.aspect_ratio() - 16.0 / 9.0).abs() < 1e-9);
+        let mut sizes = vec![Resolution{ w: 1920, h: 1080 }, Resolution{ w: 720, h: 1280 }, hd.clone(), Resolution{ w: 640, h: 360 }];
+        sizes.sort();
+        assert_eq!(sizes, [Resolution{ w: 640, h: 360 }, Resolution{ w: 720, h: 1280 }, hd, Resolution{ w: 1920, h: 1080 }]);
+        let pl = playlist();
+        let largest = pl.stream_inf.iter().filter_map(|si| si.resolution.clone()).max().unwrap();
+        assert_eq!(Some(largest), Resolution::named("4k"));
+        // absurd resolutions do not overflow
+        let huge = Resolution{ w: u64::MAX, h: 2 };
+        assert_eq!(huge.pixels(), u64::MAX);
+        assert!(huge > Resolution{ w: u64::MAX, h: 1 });
+        let pl = crate::parser::parse_playlist("#EXTM3U\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=1000000,CODECS=\"avc1.42E01E\",RESOLUTION=18446744073709551615x4\nv.m3u8\n").unwrap();
+        assert!(pl.findings().iter().any(|f| f.rule == "codec-level-exceeded"));
+    }
+
+    #[test]
+    fn test_frame_rate() {
+        let fr = FrameRate::parse("23.976").unwrap();
+        assert_eq!(fr.to_string(), "23.976");
+        assert_eq!(fr.as_f64(), 23.976);
+        assert_eq!(FrameRate::parse("29.970"), FrameRate::parse("29.97"));
+        assert_eq!(FrameRate::parse("30.0").unwrap().to_string(), "30");
+        assert_eq!(FrameRate::parse("0.05").unwrap().to_string(), "0.05");
+        assert!(fr < FrameRate::parse("24").unwrap() && FrameRate::parse("23.98").unwrap() > fr);
+        for invalid in ["", ".5", "5.", "-1", "1e3", "24fps", "99999999999999999999", "0.00000000000000000001"] {
+            assert_eq!(FrameRate::parse(invalid), None, "{}", invalid);
+        }
+        // the largest scale still compares and prints
+        let fine = FrameRate::parse("0.0000000000000000001").unwrap();
+        assert!(fine < fr && fine.to_string() == "0.0000000000000000001");
+        // serialized as a number, read back exactly
+        #[cfg(feature = "serde")] {
+            assert_eq!(serde_json::to_string(&fr).unwrap(), "23.976");
+            assert_eq!(serde_json::from_str::<FrameRate>("29.97").unwrap(), FrameRate::parse("29.97").unwrap());
+        }
+    }
+
+    #[test]
+    fn test_channels() {
+        let ch = Channels::parse("16/JOC").unwrap();
+        assert_eq!((ch.count, ch.coding.as_slice(), ch.usage.len()), (16, &["JOC".to_string()][..], 0));
+        assert!(ch.is_spatial());
+        assert_eq!(ch.to_string(), "16/JOC");
+        let ch = Channels::parse("2/-/BINAURAL,DOWNMIX").unwrap();
+        assert!(ch.coding.is_empty());
+        assert_eq!(ch.usage, ["BINAURAL", "DOWNMIX"]);
+        assert_eq!(ch.to_string(), "2/-/BINAURAL,DOWNMIX");
+        assert!(!Channels::parse("6").unwrap().is_spatial());
+        for invalid in ["", "x", "2/joc", "2/JOC/", "2/A/B/C", "-2"] {
+            assert_eq!(Channels::parse(invalid), None, "{}", invalid);
+            assert_eq!(Channels::parse_lenient(invalid).to_string(), invalid);
+        }
+        let ch = Channels::parse_lenient("2/JOC/extra/more");
+        assert_eq!((ch.count, ch.other.as_deref()), (2, Some("2/JOC/extra/more")));
+        assert!(ch.is_spatial());
+        assert_eq!(Channels::parse_lenient("16/JOC"), Channels::parse("16/JOC").unwrap());
+        assert_eq!(ChannelsFilter::parse(">2"), Some(ChannelsFilter::AtLeast(3)));
+        assert_eq!(ChannelsFilter::parse("<0"), None);
+        assert!(ChannelsFilter::parse("<=2").unwrap().matches(&Channels::parse("2/-/DOWNMIX").unwrap()));
+        assert_eq!(ChannelsFilter::parse("==2"), Some(ChannelsFilter::Count(2)));
+        assert!(ChannelsFilter::parse("=16").unwrap().matches(&Channels::parse("16/JOC").unwrap()));
+        assert!(!ChannelsFilter::parse("16").unwrap().matches(&Channels::parse("16/JOC").unwrap()));
+        for invalid in ["=", "=x", "===2", "=2/JOC"] {
+            assert_eq!(ChannelsFilter::parse(invalid), None, "{}", invalid);
+        }
+    }
+
     #[test]
     fn test_select_audio_group_not_found() {
         let sel = playlist().select_audio_group("unknown");
@@ -265,6 +2033,30 @@ mod tests {
         assert_eq!(sel.stream_inf.len(), 6);
     }
 
+    #[test]
+    fn test_select_min_bandwidth() {
+        let sel = playlist().select_audio_group("atmos").unwrap();
+        let sel = sel.select_min_bandwidth(10000000).unwrap();
+        assert_eq!(sel.stream_inf.len(), 6);
+        assert!(sel.stream_inf.iter().all(|si| si.bandwidth >= 10000000));
+        assert!(sel.select_min_bandwidth(u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_select_bandwidth_range() {
+        let pl = playlist();
+        let sel = pl.select_bandwidth_range(1000000, 8000000).unwrap();
+        assert_eq!(sel.stream_inf.len(), 16);
+        assert!(sel.stream_inf.iter().all(|si| (1000000..=8000000).contains(&si.bandwidth)));
+        assert!(sel.i_frame_stream_inf.is_empty());
+        let sel = pl.select_bandwidth_range(0, 1000000).unwrap();
+        assert_eq!(sel.stream_inf.len(), 2);
+        assert_eq!(sel.i_frame_stream_inf.len(), 2);
+        let sel = pl.select_bandwidth_range(100000, 1000000).unwrap();
+        assert_eq!(sel.i_frame_stream_inf.len(), 1);
+        assert!(pl.select_bandwidth_range(8000000, 9000000).is_err());
+    }
+
     #[test]
     fn test_select_resolution() {
         let res = Resolution{ w: 1280, h: 720 };
@@ -273,12 +2065,218 @@ mod tests {
         assert_eq!(sel.i_frame_stream_inf.len(), 1);
     }
 
+    #[test]
+    fn test_select_max_resolution() {
+        let mut pl = playlist();
+        let sel = pl.select_max_resolution(&Resolution{ w: 1280, h: 720 }).unwrap();
+        assert_eq!(sel.stream_inf.len(), 15);
+        assert_eq!(sel.i_frame_stream_inf.len(), 2);
+        // portrait screen
+        let sel = pl.select_max_resolution(&Resolution{ w: 1080, h: 1920 }).unwrap();
+        assert_eq!(sel.stream_inf.len(), 9);
+        assert_eq!(sel.i_frame_stream_inf.len(), 1);
+        assert!(pl.select_max_resolution(&Resolution{ w: 320, h: 180 }).is_err());
+        pl.stream_inf[0].resolution = None;
+        let sel = pl.select_max_resolution(&Resolution{ w: 640, h: 360 }).unwrap();
+        assert_eq!(sel.stream_inf.len(), 7);
+    }
+
+    #[test]
+    fn test_select_min_resolution() {
+        let mut pl = playlist();
+        let sel = pl.select_min_resolution(&Resolution{ w: 1280, h: 720 }).unwrap();
+        assert_eq!(sel.stream_inf.len(), 27);
+        assert_eq!(sel.i_frame_stream_inf.len(), 1);
+        assert!(pl.select_min_resolution(&Resolution{ w: 7680, h: 4320 }).is_err());
+        pl.stream_inf[0].resolution = None;
+        let sel = pl.select_min_resolution(&Resolution{ w: 3840, h: 2160 }).unwrap();
+        assert_eq!(sel.stream_inf.len(), 7);
+        assert!(sel.i_frame_stream_inf.is_empty());
+    }
+
+    #[test]
+    fn test_select_max_frame_rate() {
+        let mut pl = playlist();
+        assert_eq!(pl.select_max_frame_rate(30.0).unwrap().stream_inf.len(), pl.stream_inf.len());
+        assert!(pl.select_max_frame_rate(20.0).is_err());
+        pl.stream_inf[0].frame_rate = FrameRate::parse("59.94");
+        pl.stream_inf[1].frame_rate = FrameRate::parse("30");
+        pl.stream_inf[2].frame_rate = None;
+        let sel = pl.select_max_frame_rate(30.0).unwrap();
+        assert_eq!(sel.stream_inf.len(), pl.stream_inf.len() - 1);
+        assert_eq!(sel.i_frame_stream_inf.len(), 2);
+        assert_eq!(pl.select_max_frame_rate(20.0).unwrap().stream_inf.len(), 1);
+    }
+
+    #[test]
+    fn test_find_by_stable_id() {
+        let mut pl = playlist();
+        pl.stream_inf[2].stable_variant_id = Some("hd".to_string());
+        pl.i_frame_stream_inf[1].stable_variant_id = Some("trick".to_string());
+        pl.media[1].stable_rendition_id = Some("en".to_string());
+        assert!(matches!(pl.find_by_stable_id("hd"), Some(Entry::StreamInf(si)) if si.uri == pl.stream_inf[2].uri));
+        assert!(matches!(pl.find_by_stable_id("trick"), Some(Entry::IFrameStreamInf(ifsi)) if ifsi.bandwidth == 77758));
+        assert!(matches!(pl.find_by_stable_id("en"), Some(Entry::Media(m)) if m.group_id == pl.media[1].group_id));
+        assert!(pl.find_by_stable_id("sd").is_none());
+        pl.media[0].stable_rendition_id = Some("hd".to_string());
+        assert!(matches!(pl.find_by_stable_id("hd"), Some(Entry::StreamInf(_))));
+    }
+
+    #[test]
+    fn test_select_pathway() {
+        let mut pl = playlist();
+        for (i, si) in pl.stream_inf.iter_mut().enumerate() {
+            si.pathway_id = if i % 2 == 0 { Some("cdn-b".to_string()) } else { None };
+        }
+        pl.i_frame_stream_inf[0].pathway_id = Some("cdn-b".to_string());
+        let sel = pl.select_pathway("cdn-b").unwrap();
+        assert_eq!(sel.stream_inf.len(), 18);
+        assert!(sel.stream_inf.iter().all(|si| si.pathway_id.as_deref() == Some("cdn-b")));
+        assert_eq!(sel.i_frame_stream_inf.len(), 1);
+        assert!(sel.media.iter().all(|m| sel.stream_inf.iter().any(|si| si.references(m))));
+        let sel = pl.select_pathway(".").unwrap();
+        assert_eq!((sel.stream_inf.len(), sel.i_frame_stream_inf.len()), (18, 1));
+        assert!(pl.select_pathway("cdn-c").is_err());
+    }
+
+    #[test]
+    fn test_select_max_hdcp_level() {
+        let mut pl = playlist();
+        for (i, si) in pl.stream_inf.iter_mut().enumerate() {
+            si.hdcp_level = [None, Some(HdcpLevel::None), Some(HdcpLevel::Type0), Some(HdcpLevel::Type1)][i % 4];
+        }
+        let count = |level| pl.select_max_hdcp_level(level).unwrap().stream_inf.len();
+        assert_eq!((count(HdcpLevel::None), count(HdcpLevel::Type0), count(HdcpLevel::Type1)), (18, 27, 36));
+        for si in &mut pl.stream_inf {
+            si.hdcp_level = Some(HdcpLevel::Type1);
+        }
+        assert!(pl.select_max_hdcp_level(HdcpLevel::Type0).is_err());
+        assert_eq!(HdcpLevel::parse("TYPE-0"), Some(HdcpLevel::Type0));
+        assert_eq!(HdcpLevel::parse("TYPE-2"), None);
+    }
+
+    #[test]
+    fn test_select_i_frame_streams() {
+        let pl = playlist();
+        let criteria = IFrameCriteria{ max_bandwidth: Some(100_000), ..Default::default() };
+        let sel = pl.select_i_frame_streams(&criteria).unwrap();
+        assert_eq!(sel.stream_inf, pl.stream_inf);
+        assert_eq!(sel.media, pl.media);
+        assert_eq!(sel.i_frame_stream_inf.iter().map(|ifsi| ifsi.bandwidth).collect::<Vec<_>>(), [77758]);
+
+        let criteria = IFrameCriteria{ min_resolution: Some(Resolution{ w: 1280, h: 720 }), ..Default::default() };
+        let sel = pl.select_i_frame_streams(&criteria).unwrap();
+        assert_eq!(sel.i_frame_stream_inf.iter().map(|ifsi| ifsi.bandwidth).collect::<Vec<_>>(), [222552]);
+        let mut no_res = pl.clone();
+        no_res.i_frame_stream_inf[1].resolution = None;
+        assert_eq!(no_res.select_i_frame_streams(&criteria).unwrap().i_frame_stream_inf.len(), 2);
+
+        assert_eq!(pl.select_i_frame_streams(&IFrameCriteria::default()).unwrap(), pl);
+        let criteria = IFrameCriteria{ video_range: Some(VideoRange::SDR), ..Default::default() };
+        assert!(pl.select_i_frame_streams(&criteria).is_err());
+        assert!(pl.without_iframe_streams().select_i_frame_streams(&criteria).is_ok());
+    }
+
     #[test]
     fn test_sort_by_bandwidth() {
         let mut sel = playlist().select_audio_group("aac-128k").unwrap();
-        sel.sort_by_bandwidth();
+        sel.sort_by_bandwidth(SortOrder::Descending);
         let bw = sel.stream_inf.iter().map(|v| v.bandwidth).collect::<Vec<_>>();
         assert!(is_sorted_rev(&bw));
+        sel.sort_by_bandwidth(SortOrder::Ascending);
+        assert!(sel.stream_inf.windows(2).all(|w| w[0].bandwidth <= w[1].bandwidth));
+
+        // ties keep the playlist order, whatever the order
+        let mut pl = playlist();
+        for si in &mut pl.stream_inf {
+            si.bandwidth = 1_000_000;
+        }
+        pl.stream_inf[5].bandwidth = 2_000_000;
+        let uris = |pl: &MultivariantPlaylist| pl.stream_inf.iter().map(|si| si.uri.to_string()).collect::<Vec<_>>();
+        let mut expected = uris(&pl);
+        let top = expected.remove(5);
+        for order in [SortOrder::Ascending, SortOrder::Descending] {
+            let mut sorted = pl.clone();
+            sorted.sort_by_bandwidth(order);
+            let mut uris = uris(&sorted);
+            let first = if order == SortOrder::Descending { uris.remove(0) } else { uris.pop().unwrap() };
+            assert_eq!((first.as_str(), &uris), (top.as_str(), &expected));
+        }
+    }
+
+    #[test]
+    fn test_sort_by_average_bandwidth() {
+        let mut pl = playlist();
+        pl.stream_inf[3].average_bandwidth = None;
+        pl.stream_inf[3].bandwidth = 1;
+        pl.sort_by_average_bandwidth(SortOrder::Ascending);
+        assert_eq!(pl.stream_inf[0].bandwidth, 1);
+        let avg = pl.stream_inf.iter().map(|v| v.average_bandwidth.unwrap_or(v.bandwidth)).collect::<Vec<_>>();
+        assert!(avg.windows(2).all(|w| w[0] <= w[1]));
+        pl.sort_by_average_bandwidth(SortOrder::Descending);
+        assert_eq!(pl.stream_inf.last().unwrap().bandwidth, 1);
+    }
+
+    #[test]
+    fn test_sort_by_score() {
+        let mut pl = playlist();
+        pl.stream_inf.truncate(4);
+        for (si, score) in pl.stream_inf.iter_mut().zip(["1.5", "10", "", "1.5"]) {
+            si.score = Score::parse(score);
+        }
+        let uris = |pl: &MultivariantPlaylist| pl.stream_inf.iter().map(|si| si.uri.to_string()).collect::<Vec<_>>();
+        let before = uris(&pl);
+        let mut sorted = pl.clone();
+        sorted.sort_by_score(SortOrder::Descending);
+        assert_eq!(uris(&sorted), [1, 0, 3, 2].map(|i| before[i].clone()));
+        sorted.sort_by_score(SortOrder::Ascending);
+        assert_eq!(uris(&sorted), [2, 0, 3, 1].map(|i| before[i].clone()));
+        assert_eq!(Score::parse("10").unwrap().to_string(), "10");
+    }
+
+    #[test]
+    fn test_infer_label() {
+        let pl = playlist();
+        let mut si = pl.stream_inf[0].clone();
+        assert_eq!(si.infer_label(), "540p HDR");
+        si.frame_rate = FrameRate::parse("59.94");
+        si.video_range = Some(VideoRange::SDR);
+        si.resolution = Some(Resolution{ w: 1920, h: 1080 });
+        assert_eq!(si.infer_label(), "1080p60");
+        si.resolution = None;
+        si.bandwidth = 127_800;
+        si.codecs = Some(Codecs::parse("mp4a.40.2"));
+        assert_eq!(si.infer_label(), "audio-only 128k");
+    }
+
+    #[test]
+    fn test_select_label() {
+        let pl = playlist();
+        let sel = pl.select_label("720p HDR").unwrap();
+        assert_eq!(sel.stream_inf.len(), 6);
+        assert!(pl.select_label("720p").is_err());
+    }
+
+    #[test]
+    fn test_stable_ids() {
+        let mut pl = playlist();
+        let id = pl.stream_inf[3].stable_id();
+        assert_eq!(id.len(), 16);
+        // computed for playlists read from JSON too, and written with them
+        #[cfg(feature = "serde")] {
+            let json = serde_json::to_value(&pl.stream_inf[3]).unwrap();
+            assert_eq!((json["id"].as_str(), json["label"].as_str()), (Some(id.as_str()), Some("2160p HDR")));
+        }
+        pl.sort_by_bandwidth(SortOrder::Descending);
+        assert_eq!(pl.find_variant(&id).unwrap().uri, "hdr10/unenc/16500k/vod.m3u8");
+        let ids = pl.stream_inf.iter().map(|si| si.stable_id()).collect::<std::collections::HashSet<_>>();
+        assert_eq!(ids.len(), pl.stream_inf.len());
+        assert_ne!(pl.stream_inf[0].stable_id(), pl.i_frame_stream_inf[0].stable_id());
+
+        let sel = pl.select_ids(&[id, pl.i_frame_stream_inf[0].stable_id()]).unwrap();
+        assert_eq!(sel.stream_inf.len(), 1);
+        assert_eq!(sel.i_frame_stream_inf.len(), 1);
+        assert!(pl.select_ids(&["unknown".to_string()]).is_err());
     }
 
-}
\ No newline at end of file
+}
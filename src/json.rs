@@ -0,0 +1,588 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::sync::Mutex;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize, Serializer};
+
+// Shape of the JSON output. Field names follow the Rust model by default; they
+// can be written as the HLS attribute names (GROUP-ID, AVERAGE-BANDWIDTH) or in
+// camelCase instead. Only struct fields are renamed: keys of maps and enum
+// variants are data and are left as they are. from_str() reads every style back.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum KeyStyle {
+    #[default]
+    Rust, // group_id
+    Hls, // GROUP-ID
+    CamelCase, // groupId
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct JsonStyle {
+    pub keys: KeyStyle,
+    pub skip_none: bool, // leave out fields that are null
+}
+
+fn key(name: &str, style: KeyStyle) -> String {
+    // type_ and the like, named after keywords
+    let trimmed = name.trim_end_matches('_');
+    match style {
+        KeyStyle::Rust => name.to_string(),
+        KeyStyle::Hls => trimmed.to_ascii_uppercase().replace('_', "-"),
+        KeyStyle::CamelCase => {
+            let mut parts = trimmed.split('_');
+            let mut ret = parts.next().unwrap_or_default().to_string();
+            for p in parts {
+                let mut chars = p.chars();
+                if let Some(c) = chars.next() {
+                    ret.push(c.to_ascii_uppercase());
+                    ret.extend(chars);
+                }
+            }
+            ret
+        },
+    }
+}
+
+// Struct fields are &'static str for serde, the renamed ones are made once per style
+static FIELD_NAMES: Mutex<BTreeMap<(KeyStyle, &'static str), &'static str>> = Mutex::new(BTreeMap::new());
+
+fn field_name(name: &'static str, style: KeyStyle) -> &'static str {
+    if style == KeyStyle::Rust {
+        return name
+    }
+    let mut names = FIELD_NAMES.lock().unwrap_or_else(|e| e.into_inner());
+    names.entry((style, name)).or_insert_with(|| Box::leak(key(name, style).into_boxed_str()))
+}
+
+fn is_null<T: Serialize + ?Sized>(v: &T) -> bool {
+    serde_json::to_value(v).is_ok_and(|v| v.is_null())
+}
+
+// A value serialized with its struct fields renamed
+struct Styled<'a, T: ?Sized> {
+    value: &'a T,
+    style: &'a JsonStyle,
+}
+
+impl<T: Serialize + ?Sized> Serialize for Styled<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(StyledSerializer{ inner: serializer, style: self.style })
+    }
+}
+
+struct StyledSerializer<'a, S> {
+    inner: S,
+    style: &'a JsonStyle,
+}
+
+macro_rules! forward_serialize {
+    ($($name:ident($($arg:ident: $ty:ty),*);)*) => {$(
+        fn $name(self, $($arg: $ty),*) -> Result<S::Ok, S::Error> {
+            self.inner.$name($($arg),*)
+        }
+    )*};
+}
+
+impl<'a, S: Serializer> Serializer for StyledSerializer<'a, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Compound<'a, S::SerializeSeq>;
+    type SerializeTuple = Compound<'a, S::SerializeTuple>;
+    type SerializeTupleStruct = Compound<'a, S::SerializeTupleStruct>;
+    type SerializeTupleVariant = Compound<'a, S::SerializeTupleVariant>;
+    type SerializeMap = Compound<'a, S::SerializeMap>;
+    type SerializeStruct = Compound<'a, S::SerializeStruct>;
+    type SerializeStructVariant = Compound<'a, S::SerializeStructVariant>;
+
+    forward_serialize! {
+        serialize_bool(v: bool);
+        serialize_i8(v: i8);
+        serialize_i16(v: i16);
+        serialize_i32(v: i32);
+        serialize_i64(v: i64);
+        serialize_i128(v: i128);
+        serialize_u8(v: u8);
+        serialize_u16(v: u16);
+        serialize_u32(v: u32);
+        serialize_u64(v: u64);
+        serialize_u128(v: u128);
+        serialize_f32(v: f32);
+        serialize_f64(v: f64);
+        serialize_char(v: char);
+        serialize_str(v: &str);
+        serialize_bytes(v: &[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(name: &'static str);
+        serialize_unit_variant(name: &'static str, index: u32, variant: &'static str);
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_some(&Styled{ value, style: self.style })
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, name: &'static str, value: &T) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_newtype_struct(name, &Styled{ value, style: self.style })
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, name: &'static str, index: u32, variant: &'static str,
+        value: &T) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_newtype_variant(name, index, variant, &Styled{ value, style: self.style })
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        Ok(Compound::new(self.inner.serialize_seq(len)?, self.style))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, S::Error> {
+        Ok(Compound::new(self.inner.serialize_tuple(len)?, self.style))
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, S::Error> {
+        Ok(Compound::new(self.inner.serialize_tuple_struct(name, len)?, self.style))
+    }
+
+    fn serialize_tuple_variant(self, name: &'static str, index: u32, variant: &'static str,
+        len: usize) -> Result<Self::SerializeTupleVariant, S::Error> {
+        Ok(Compound::new(self.inner.serialize_tuple_variant(name, index, variant, len)?, self.style))
+    }
+
+    // #[serde(flatten)] writes a struct as a map of unknown length, whose keys are then field names.
+    // Maps of data have a length.
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        let mut ret = Compound::new(self.inner.serialize_map(len)?, self.style);
+        ret.fields = len.is_none();
+        Ok(ret)
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct, S::Error> {
+        Ok(Compound::new(self.inner.serialize_struct(name, len)?, self.style))
+    }
+
+    fn serialize_struct_variant(self, name: &'static str, index: u32, variant: &'static str,
+        len: usize) -> Result<Self::SerializeStructVariant, S::Error> {
+        Ok(Compound::new(self.inner.serialize_struct_variant(name, index, variant, len)?, self.style))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+struct Compound<'a, C> {
+    inner: C,
+    style: &'a JsonStyle,
+    fields: bool, // map keys are field names, see serialize_map()
+    key: Option<String>, // field name of a map, waiting for its value
+}
+
+impl<'a, C> Compound<'a, C> {
+    fn new(inner: C, style: &'a JsonStyle) -> Self {
+        Compound{ inner, style, fields: false, key: None }
+    }
+
+    fn skip<T: Serialize + ?Sized>(&self, value: &T) -> bool {
+        self.style.skip_none && is_null(value)
+    }
+}
+
+macro_rules! styled_elements {
+    ($($trait:ident::$name:ident;)*) => {$(
+        impl<C: ser::$trait> ser::$trait for Compound<'_, C> {
+            type Ok = C::Ok;
+            type Error = C::Error;
+
+            fn $name<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+                self.inner.$name(&Styled{ value, style: self.style })
+            }
+
+            fn end(self) -> Result<C::Ok, C::Error> {
+                self.inner.end()
+            }
+        }
+    )*};
+}
+
+styled_elements! {
+    SerializeSeq::serialize_element;
+    SerializeTuple::serialize_element;
+    SerializeTupleStruct::serialize_field;
+    SerializeTupleVariant::serialize_field;
+}
+
+macro_rules! styled_fields {
+    ($($trait:ident;)*) => {$(
+        impl<C: ser::$trait> ser::$trait for Compound<'_, C> {
+            type Ok = C::Ok;
+            type Error = C::Error;
+
+            fn serialize_field<T: Serialize + ?Sized>(&mut self, name: &'static str, value: &T) -> Result<(), C::Error> {
+                if self.skip(value) {
+                    return self.inner.skip_field(name)
+                }
+                self.inner.serialize_field(field_name(name, self.style.keys), &Styled{ value, style: self.style })
+            }
+
+            fn end(self) -> Result<C::Ok, C::Error> {
+                self.inner.end()
+            }
+        }
+    )*};
+}
+
+styled_fields! {
+    SerializeStruct;
+    SerializeStructVariant;
+}
+
+impl<C: ser::SerializeMap> ser::SerializeMap for Compound<'_, C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, k: &T) -> Result<(), C::Error> {
+        match serde_json::to_value(k) {
+            Ok(serde_json::Value::String(name)) if self.fields => {
+                self.key = Some(key(&name, self.style.keys));
+                Ok(())
+            },
+            _ => self.inner.serialize_key(&Styled{ value: k, style: self.style }),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        match self.key.take() {
+            Some(_) if self.skip(value) => Ok(()),
+            Some(name) => self.inner.serialize_entry(&name, &Styled{ value, style: self.style }),
+            None => self.inner.serialize_value(&Styled{ value, style: self.style }),
+        }
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+/* Pretty printed JSON with keys and null fields according to the style */
+pub fn to_writer_pretty<W: io::Write, T: Serialize + ?Sized>(writer: W, v: &T, style: &JsonStyle) -> serde_json::Result<()> {
+    Styled{ value: v, style }.serialize(&mut serde_json::Serializer::pretty(writer))
+}
+
+pub fn to_string_pretty<T: Serialize + ?Sized>(v: &T, style: &JsonStyle) -> serde_json::Result<String> {
+    let mut ret = vec![];
+    to_writer_pretty(&mut ret, v, style)?;
+    Ok(String::from_utf8(ret).expect("JSON is UTF-8"))
+}
+
+// Reading: the keys of a struct are matched against its fields in every style, the
+// other keys are passed on unchanged. The wrappers below carry this down the value.
+
+struct StyledDeserializer<D>(D);
+
+macro_rules! forward_deserialize {
+    ($($name:ident($($arg:ident: $ty:ty),*);)*) => {$(
+        fn $name<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, D::Error> {
+            self.0.$name($($arg,)* StyledVisitor{ inner: visitor, fields: None })
+        }
+    )*};
+}
+
+impl<'de, D: de::Deserializer<'de>> de::Deserializer<'de> for StyledDeserializer<D> {
+    type Error = D::Error;
+
+    forward_deserialize! {
+        deserialize_any();
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_i128();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_u128();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, name: &'static str, fields: &'static [&'static str],
+        visitor: V) -> Result<V::Value, D::Error> {
+        self.0.deserialize_struct(name, fields, StyledVisitor{ inner: visitor, fields: Some(fields) })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.0.is_human_readable()
+    }
+}
+
+struct StyledSeed<S>(S);
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for StyledSeed<S> {
+    type Value = S::Value;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<S::Value, D::Error> {
+        self.0.deserialize(StyledDeserializer(deserializer))
+    }
+}
+
+struct StyledVisitor<V> {
+    inner: V,
+    fields: Option<&'static [&'static str]>, // of the struct being read
+}
+
+macro_rules! forward_visit {
+    ($($name:ident($ty:ty);)*) => {$(
+        fn $name<E: de::Error>(self, v: $ty) -> Result<V::Value, E> {
+            self.inner.$name(v)
+        }
+    )*};
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for StyledVisitor<V> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.expecting(f)
+    }
+
+    forward_visit! {
+        visit_bool(bool);
+        visit_i8(i8);
+        visit_i16(i16);
+        visit_i32(i32);
+        visit_i64(i64);
+        visit_i128(i128);
+        visit_u8(u8);
+        visit_u16(u16);
+        visit_u32(u32);
+        visit_u64(u64);
+        visit_u128(u128);
+        visit_f32(f32);
+        visit_f64(f64);
+        visit_char(char);
+        visit_str(&str);
+        visit_borrowed_str(&'de str);
+        visit_string(String);
+        visit_bytes(&[u8]);
+        visit_borrowed_bytes(&'de [u8]);
+        visit_byte_buf(Vec<u8>);
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<V::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<V::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<V::Value, D::Error> {
+        self.inner.visit_some(StyledDeserializer(deserializer))
+    }
+
+    fn visit_newtype_struct<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<V::Value, D::Error> {
+        self.inner.visit_newtype_struct(StyledDeserializer(deserializer))
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<V::Value, A::Error> {
+        self.inner.visit_seq(StyledAccess{ inner: seq, fields: None })
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<V::Value, A::Error> {
+        self.inner.visit_map(StyledAccess{ inner: map, fields: self.fields })
+    }
+
+    fn visit_enum<A: de::EnumAccess<'de>>(self, data: A) -> Result<V::Value, A::Error> {
+        self.inner.visit_enum(StyledAccess{ inner: data, fields: None })
+    }
+}
+
+struct StyledAccess<A> {
+    inner: A,
+    fields: Option<&'static [&'static str]>,
+}
+
+impl<'de, A: de::SeqAccess<'de>> de::SeqAccess<'de> for StyledAccess<A> {
+    type Error = A::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, A::Error> {
+        self.inner.next_element_seed(StyledSeed(seed))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, A: de::MapAccess<'de>> de::MapAccess<'de> for StyledAccess<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, A::Error> {
+        let Some(fields) = self.fields else {
+            return self.inner.next_key_seed(StyledSeed(seed))
+        };
+        let Some(name) = self.inner.next_key::<String>()? else { return Ok(None) };
+        let styles = [KeyStyle::Rust, KeyStyle::Hls, KeyStyle::CamelCase];
+        let field = fields.iter().find(|f| styles.iter().any(|s| key(f, *s) == name));
+        let name: String = field.map_or(name, |f| f.to_string());
+        seed.deserialize(name.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<T::Value, A::Error> {
+        self.inner.next_value_seed(StyledSeed(seed))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, A: de::EnumAccess<'de>> de::EnumAccess<'de> for StyledAccess<A> {
+    type Error = A::Error;
+    type Variant = StyledAccess<A::Variant>;
+
+    fn variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<(T::Value, Self::Variant), A::Error> {
+        let (value, variant) = self.inner.variant_seed(seed)?;
+        Ok((value, StyledAccess{ inner: variant, fields: None }))
+    }
+}
+
+impl<'de, A: de::VariantAccess<'de>> de::VariantAccess<'de> for StyledAccess<A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), A::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, A::Error> {
+        self.inner.newtype_variant_seed(StyledSeed(seed))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, A::Error> {
+        self.inner.tuple_variant(len, StyledVisitor{ inner: visitor, fields: None })
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, A::Error> {
+        self.inner.struct_variant(fields, StyledVisitor{ inner: visitor, fields: Some(fields) })
+    }
+}
+
+/* JSON written in any key style, with or without the null fields */
+pub fn from_str<T: DeserializeOwned>(s: &str) -> serde_json::Result<T> {
+    let mut deserializer = serde_json::Deserializer::from_str(s);
+    let ret = T::deserialize(StyledDeserializer(&mut deserializer))?;
+    deserializer.end()?;
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use serde::{Deserialize, Serialize};
+    use serde_json::{json, Value};
+    use crate::format::MultivariantPlaylist;
+    use crate::parser;
+    use super::{from_str, to_string_pretty, JsonStyle, KeyStyle};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Range {
+        Pq,
+        Other(String),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Variant {
+        average_bandwidth: u64,
+        video_range: Option<Range>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Rendition {
+        type_: String,
+        group_id: String,
+        channels: Option<u64>,
+        attributes: BTreeMap<String, String>,
+        stream_inf: Vec<Option<Variant>>,
+    }
+
+    #[test]
+    fn test_key_styles() {
+        let v = Rendition{ type_: "Audio".to_string(), group_id: "aac".to_string(), channels: None,
+            attributes: BTreeMap::from([("group_id".to_string(), "data".to_string())]),
+            stream_inf: vec![Some(Variant{ average_bandwidth: 1, video_range: Some(Range::Other("XDR".to_string())) }), None,
+                Some(Variant{ average_bandwidth: 2, video_range: None })] };
+        let styled = |keys, skip_none| -> Value {
+            serde_json::from_str(&to_string_pretty(&v, &JsonStyle{ keys, skip_none }).unwrap()).unwrap()
+        };
+        // keys of maps are not renamed
+        assert_eq!(styled(KeyStyle::Hls, false), json!({"TYPE": "Audio", "GROUP-ID": "aac", "CHANNELS": null,
+            "ATTRIBUTES": {"group_id": "data"},
+            "STREAM-INF": [{"AVERAGE-BANDWIDTH": 1, "VIDEO-RANGE": {"Other": "XDR"}}, null,
+                {"AVERAGE-BANDWIDTH": 2, "VIDEO-RANGE": null}]}));
+        // null array elements are kept
+        assert_eq!(styled(KeyStyle::CamelCase, true), json!({"type": "Audio", "groupId": "aac",
+            "attributes": {"group_id": "data"},
+            "streamInf": [{"averageBandwidth": 1, "videoRange": {"Other": "XDR"}}, null, {"averageBandwidth": 2}]}));
+        assert_eq!(styled(KeyStyle::Rust, false), serde_json::to_value(&v).unwrap());
+        for keys in [KeyStyle::Rust, KeyStyle::Hls, KeyStyle::CamelCase] {
+            let text = to_string_pretty(&v, &JsonStyle{ keys, skip_none: true }).unwrap();
+            assert_eq!(from_str::<Rendition>(&text).unwrap(), v, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_playlist_output() {
+        let data = include_str!("../data/master_unenc_hdr10_all.m3u8");
+        let pl = parser::parse_playlist(data).unwrap();
+        assert_eq!(to_string_pretty(&pl, &JsonStyle::default()).unwrap(), serde_json::to_string_pretty(&pl).unwrap());
+
+        let text = to_string_pretty(&pl, &JsonStyle{ keys: KeyStyle::Hls, skip_none: true }).unwrap();
+        let v: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(v["MEDIA"][0]["GROUP-ID"], "aac-128k");
+        assert!(v["MEDIA"][0].get("INSTREAM-ID").is_none());
+        assert_eq!(v["STREAM-INF"][0]["AVERAGE-BANDWIDTH"], pl.stream_inf[0].average_bandwidth.unwrap());
+        // fields in the order of the model
+        assert!(text.find("\"TYPE\"").unwrap() < text.find("\"GROUP-ID\"").unwrap());
+
+        // read back in every style
+        for keys in [KeyStyle::Rust, KeyStyle::Hls, KeyStyle::CamelCase] {
+            for skip_none in [false, true] {
+                let text = to_string_pretty(&pl, &JsonStyle{ keys, skip_none }).unwrap();
+                assert_eq!(from_str::<MultivariantPlaylist>(&text).unwrap(), pl, "{:?}", keys);
+            }
+        }
+    }
+
+    #[test]
+    fn test_flattened_fields() {
+        #[derive(Serialize)]
+        struct Output<'a> {
+            playlist_kind: &'a str,
+            #[serde(flatten)]
+            variant: &'a Variant,
+        }
+        let v = Variant{ average_bandwidth: 1, video_range: None };
+        let text = to_string_pretty(&Output{ playlist_kind: "vod", variant: &v }, &JsonStyle{ keys: KeyStyle::Hls, skip_none: true }).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&text).unwrap(), json!({"PLAYLIST-KIND": "vod", "AVERAGE-BANDWIDTH": 1}));
+    }
+}
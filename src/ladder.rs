@@ -0,0 +1,547 @@
+use std::collections::HashMap;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use crate::codecs;
+use crate::csv;
+use crate::format::{MultivariantPlaylist, StreamInf};
+
+// Scoring of ABR ladders: the video variants sharing an audio group, ordered by
+// bandwidth. Models rate a ladder between 0 and 1, higher is better.
+
+pub trait ScoringModel {
+    fn name(&self) -> &'static str;
+    // `ladder` is sorted by ascending bandwidth and contains video variants only
+    fn score(&self, ladder: &[&StreamInf]) -> f64;
+}
+
+/* Video variants of the playlist grouped by AUDIO, each sorted by ascending bandwidth */
+pub fn ladders(playlist: &MultivariantPlaylist) -> Vec<(Option<String>, Vec<&StreamInf>)> {
+    let mut ret: Vec<(Option<String>, Vec<&StreamInf>)> = vec![];
+    for si in playlist.stream_inf.iter().filter(|si| si.resolution.is_some()) {
+        match ret.iter_mut().find(|(group, _)| *group == si.audio) {
+            Some((_, ladder)) => ladder.push(si),
+            None => ret.push((si.audio.clone(), vec![si])),
+        }
+    }
+    for (_, ladder) in ret.iter_mut() {
+        ladder.sort_by_key(|si| si.bandwidth);
+    }
+    ret
+}
+
+/* One row per EXT-X-STREAM-INF in playlist order, for reviewing the ladder in a spreadsheet */
+pub fn to_csv(playlist: &MultivariantPlaylist) -> String {
+    let mut ret = "bandwidth,resolution,codecs,frame_rate,audio,uri\n".to_string();
+    for si in &playlist.stream_inf {
+        ret += &format!("{},{},{},{},{},{}\n", si.bandwidth,
+            si.resolution.as_ref().map(|r| format!("{}x{}", r.w, r.h)).unwrap_or_default(),
+            csv::field(&si.codecs.as_ref().map(|c| c.to_string()).unwrap_or_default()),
+            si.frame_rate.map(|fr| fr.to_string()).unwrap_or_default(),
+            csv::field(si.audio.as_deref().unwrap_or_default()), csv::field(si.uri.as_str()));
+    }
+    ret
+}
+
+/*
+Heuristic on the bandwidth ratio between adjacent rungs: each step scores 1 at
+`ideal_ratio` and decays exponentially with the log distance from it, scaled by
+`tolerance`. Ladders with fewer than two rungs score 0.
+*/
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct SpacingRatio {
+    pub ideal_ratio: f64,
+    pub tolerance: f64,
+}
+
+impl Default for SpacingRatio {
+    fn default() -> Self {
+        Self{ ideal_ratio: 1.6, tolerance: 0.5 }
+    }
+}
+
+impl ScoringModel for SpacingRatio {
+    fn name(&self) -> &'static str {
+        "spacing-ratio"
+    }
+
+    fn score(&self, ladder: &[&StreamInf]) -> f64 {
+        if ladder.len() < 2 {
+            return 0.0
+        }
+        let steps = ladder.windows(2).map(|w| {
+            let ratio = w[1].bandwidth.max(1) as f64 / w[0].bandwidth.max(1) as f64;
+            (-(ratio / self.ideal_ratio).ln().abs() / self.tolerance).exp()
+        });
+        steps.sum::<f64>() / (ladder.len() - 1) as f64
+    }
+}
+
+/*
+Bits per pixel of each rung, scaled by the efficiency of its video codec relative
+to AVC, compared with `target_bpp` at 1080p. Larger pictures need fewer bits per
+pixel: the target scales with (pixels / 1080p pixels) ^ -resolution_exponent.
+A rung scores 1 on target, less when starved or wasting bits.
+*/
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct QualityPerBit {
+    pub target_bpp: f64,
+    pub resolution_exponent: f64,
+    pub default_frame_rate: f64, // when FRAME-RATE is absent
+    pub codec_efficiency: HashMap<String, f64>, // codec family, as in CODECS, to factor
+}
+
+impl Default for QualityPerBit {
+    fn default() -> Self {
+        let codec_efficiency = codecs::VIDEO_EFFICIENCY.iter().map(|(k, v)| (k.to_string(), *v)).collect();
+        Self{ target_bpp: 0.08, resolution_exponent: 0.25, default_frame_rate: 30.0, codec_efficiency }
+    }
+}
+
+impl QualityPerBit {
+    fn efficiency(&self, si: &StreamInf) -> f64 {
+        codec_families(si).iter()
+            .find_map(|c| self.codec_efficiency.get(*c))
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    pub fn rung_score(&self, si: &StreamInf) -> f64 {
+        let Some(res) = &si.resolution else { return 0.0 };
+        let pixels = res.pixels().max(1) as f64;
+        let fps = si.frame_rate.map_or(self.default_frame_rate, |fr| fr.as_f64());
+        let bpp = si.bandwidth as f64 * self.efficiency(si) / (pixels * fps);
+        let target = self.target_bpp * (pixels / (1920.0 * 1080.0)).powf(-self.resolution_exponent);
+        let r = bpp / target;
+        if r > 1.0 { 1.0 / r } else { r }
+    }
+}
+
+impl ScoringModel for QualityPerBit {
+    fn name(&self) -> &'static str {
+        "quality-per-bit"
+    }
+
+    fn score(&self, ladder: &[&StreamInf]) -> f64 {
+        if ladder.is_empty() {
+            return 0.0
+        }
+        ladder.iter().map(|si| self.rung_score(si)).sum::<f64>() / ladder.len() as f64
+    }
+}
+
+// Weights and parameters of the built-in models, loaded from JSON. A weight of 0 disables a model.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct ScoringConfig {
+    pub spacing_ratio_weight: f64,
+    pub spacing_ratio: SpacingRatio,
+    pub quality_per_bit_weight: f64,
+    pub quality_per_bit: QualityPerBit,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self{
+            spacing_ratio_weight: 1.0,
+            spacing_ratio: SpacingRatio::default(),
+            quality_per_bit_weight: 1.0,
+            quality_per_bit: QualityPerBit::default(),
+        }
+    }
+}
+
+impl ScoringConfig {
+    #[cfg(feature = "serde")]
+    pub fn from_json(data: &str) -> Result<Self, String> {
+        serde_json::from_str(data).map_err(|e| format!("Invalid scoring config: {}", e))
+    }
+
+    pub fn models(&self) -> Vec<(f64, &dyn ScoringModel)> {
+        vec![
+            (self.spacing_ratio_weight, &self.spacing_ratio as &dyn ScoringModel),
+            (self.quality_per_bit_weight, &self.quality_per_bit as &dyn ScoringModel),
+        ]
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ModelScore {
+    pub model: &'static str,
+    pub weight: f64,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct LadderScore {
+    pub audio: Option<String>,
+    pub variants: Vec<String>, // ids, by ascending bandwidth
+    pub scores: Vec<ModelScore>,
+    pub total: f64, // weighted mean of the scores
+}
+
+/* Score every ladder of the playlist with the given weighted models */
+pub fn score_ladders(playlist: &MultivariantPlaylist, models: &[(f64, &dyn ScoringModel)]) -> Vec<LadderScore> {
+    let mut ret = vec![];
+    for (audio, ladder) in ladders(playlist) {
+        let scores: Vec<ModelScore> = models.iter().filter(|(w, _)| *w > 0.0)
+            .map(|(weight, m)| ModelScore{ model: m.name(), weight: *weight, score: m.score(&ladder) })
+            .collect();
+        let weights: f64 = scores.iter().map(|s| s.weight).sum();
+        let total = if weights > 0.0 {
+            scores.iter().map(|s| s.weight * s.score).sum::<f64>() / weights
+        } else {
+            0.0
+        };
+        ret.push(LadderScore{ audio, variants: ladder.iter().map(|si| si.stable_id()).collect(), scores, total });
+    }
+    ret
+}
+
+/*
+Thresholds under which two rungs of a ladder are considered redundant. Only
+variants with the same codec families (CODECS without profile and level) and
+VIDEO-RANGE are compared, alternative codecs are not redundant. Ratios are between the larger and the smaller value, a pixel
+ratio of 1 requires the same resolution.
+*/
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct RedundancyThresholds {
+    pub bandwidth_ratio: f64,
+    pub pixel_ratio: f64,
+}
+
+impl Default for RedundancyThresholds {
+    fn default() -> Self {
+        Self{ bandwidth_ratio: 1.15, pixel_ratio: 1.0 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Redundancy {
+    pub id: String, // the redundant variant
+    pub kept: String, // the lower bandwidth variant it duplicates
+    pub bandwidth_ratio: f64,
+    pub pixel_ratio: f64,
+}
+
+fn codec_families(si: &StreamInf) -> Vec<&str> {
+    si.codecs.iter().flat_map(|c| c.iter()).map(|c| c.fourcc()).collect()
+}
+
+fn pixels(si: &StreamInf) -> f64 {
+    si.resolution.as_ref().map(|r| r.pixels().max(1) as f64).unwrap_or(1.0)
+}
+
+/*
+Walk each ladder upwards, comparing every rung with the last one kept. A rung
+close to it is redundant: the lower bandwidth variant stays, so the bottom of
+the ladder keeps its reach.
+*/
+pub fn find_redundant(playlist: &MultivariantPlaylist, thresholds: &RedundancyThresholds) -> Vec<Redundancy> {
+    let mut ret = vec![];
+    for (_, ladder) in ladders(playlist) {
+        let mut kept: Vec<&StreamInf> = vec![];
+        for si in ladder {
+            let similar = kept.iter().rev()
+                .find(|k| codec_families(k) == codec_families(si) && k.video_range == si.video_range);
+            if let Some(k) = similar {
+                let bandwidth_ratio = si.bandwidth.max(1) as f64 / k.bandwidth.max(1) as f64;
+                let (a, b) = (pixels(si), pixels(k));
+                let pixel_ratio = a.max(b) / a.min(b);
+                if bandwidth_ratio <= thresholds.bandwidth_ratio && pixel_ratio <= thresholds.pixel_ratio {
+                    ret.push(Redundancy{ id: si.stable_id(), kept: k.stable_id(), bandwidth_ratio, pixel_ratio });
+                    continue
+                }
+            }
+            kept.push(si);
+        }
+    }
+    ret
+}
+
+/* The playlist without the redundant EXT-X-STREAM-INF */
+pub fn drop_redundant(playlist: &MultivariantPlaylist, thresholds: &RedundancyThresholds) -> MultivariantPlaylist {
+    let redundant = find_redundant(playlist, thresholds);
+    let mut ret = playlist.clone();
+    ret.stream_inf.retain(|si| !redundant.iter().any(|r| r.id == si.stable_id()));
+    ret
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeepStrategy {
+    Spacing, // lowest and highest rungs, the others closest to even steps in log bandwidth
+    Lowest,
+    Highest,
+}
+
+// Indices of the `n` rungs of `ladder` to keep, ascending
+fn keep_indices(ladder: &[&StreamInf], n: usize, strategy: KeepStrategy) -> Vec<usize> {
+    let len = ladder.len();
+    if len <= n {
+        return (0..len).collect()
+    }
+    match strategy {
+        KeepStrategy::Lowest => (0..n).collect(),
+        KeepStrategy::Highest => (len - n..len).collect(),
+        KeepStrategy::Spacing if n == 1 => vec![0],
+        KeepStrategy::Spacing => {
+            let log = |i: usize| (ladder[i].bandwidth.max(1) as f64).ln();
+            let (lo, hi) = (log(0), log(len - 1));
+            let mut ret: Vec<usize> = vec![];
+            for k in 0..n {
+                let target = lo + (hi - lo) * k as f64 / (n - 1) as f64;
+                // leave enough rungs above for the remaining targets
+                let first = ret.last().map_or(0, |i| i + 1);
+                let last = len - (n - k);
+                let best = (first..=last)
+                    .min_by(|a, b| (log(*a) - target).abs().total_cmp(&(log(*b) - target).abs()))
+                    .unwrap_or(first);
+                ret.push(best);
+            }
+            ret
+        },
+    }
+}
+
+/*
+Reduce the video variants of the playlist, all audio groups together, to at most
+`max_variants` rungs chosen by `strategy`, for devices that cannot handle large
+playlists. Variants without RESOLUTION are kept, as are I-frame variants whose
+resolution is still offered. Renditions of groups no variant references anymore
+are removed.
+*/
+pub fn trim(playlist: &MultivariantPlaylist, max_variants: usize, strategy: KeepStrategy)
+    -> Result<MultivariantPlaylist, String> {
+    if max_variants == 0 {
+        return Err("At least one variant must be kept".to_string())
+    }
+    let mut ladder: Vec<&StreamInf> = playlist.stream_inf.iter().filter(|si| si.resolution.is_some()).collect();
+    ladder.sort_by_key(|si| si.bandwidth);
+    let keep = keep_indices(&ladder, max_variants, strategy);
+    let dropped: Vec<&StreamInf> = ladder.iter().enumerate().filter(|(i, _)| !keep.contains(i)).map(|(_, si)| *si).collect();
+    let mut ret = playlist.clone();
+    ret.stream_inf = playlist.stream_inf.iter().filter(|si| !dropped.iter().any(|d| std::ptr::eq(*d, *si))).cloned().collect();
+    let offered = |res| ret.stream_inf.iter().any(|si| si.resolution.as_ref() == Some(res));
+    let removed: Vec<_> = playlist.stream_inf.iter().filter_map(|si| si.resolution.as_ref())
+        .filter(|res| !offered(res)).cloned().collect();
+    ret.i_frame_stream_inf.retain(|ifsi| !ifsi.resolution.as_ref().is_some_and(|res| removed.contains(res)));
+    Ok(ret.prune_unreferenced_media())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DedupeKeep {
+    Highest,
+    Lowest,
+}
+
+/*
+Keep one variant per resolution in each ladder, the highest or lowest bandwidth one,
+e.g. when collapsing ladders with the same rungs in several codecs. Variants without
+RESOLUTION are kept, and every resolution is still offered to I-frame variants.
+*/
+pub fn dedupe_ladder(playlist: &MultivariantPlaylist, keep: DedupeKeep) -> MultivariantPlaylist {
+    let mut kept: Vec<String> = vec![];
+    for (_, ladder) in ladders(playlist) {
+        let mut best: Vec<&StreamInf> = vec![];
+        for si in ladder {
+            match best.iter_mut().find(|b| b.resolution == si.resolution) {
+                Some(b) => {
+                    let better = match keep {
+                        DedupeKeep::Highest => si.bandwidth > b.bandwidth,
+                        DedupeKeep::Lowest => si.bandwidth < b.bandwidth,
+                    };
+                    if better {
+                        *b = si;
+                    }
+                },
+                None => best.push(si),
+            }
+        }
+        kept.extend(best.iter().map(|si| si.stable_id()));
+    }
+    let mut ret = playlist.clone();
+    ret.stream_inf.retain(|si| si.resolution.is_none() || kept.contains(&si.stable_id()));
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codecs::Codecs;
+    use crate::format::{Resolution, StreamInf};
+    use crate::parser;
+    use super::*;
+
+    fn playlist() -> MultivariantPlaylist {
+        parser::parse_playlist(include_str!("../data/master_unenc_hdr10_all.m3u8")).unwrap()
+    }
+
+    #[test]
+    fn test_ladders() {
+        let pl = playlist();
+        let ladders = ladders(&pl);
+        let groups: Vec<Option<&str>> = ladders.iter().map(|(g, _)| g.as_deref()).collect();
+        assert_eq!(groups, [Some("aac-128k"), Some("aac-64k"), Some("eac3"), Some("atmos")]);
+        let (_, eac3) = &ladders[2];
+        assert_eq!(eac3.len(), 12);
+        assert_eq!(eac3[0].bandwidth, 898051);
+        assert!(eac3.windows(2).all(|w| w[0].bandwidth <= w[1].bandwidth));
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let mut pl = playlist();
+        pl.stream_inf.truncate(2);
+        pl.stream_inf[1].resolution = None;
+        pl.stream_inf[1].frame_rate = None;
+        assert_eq!(to_csv(&pl), "bandwidth,resolution,codecs,frame_rate,audio,uri\n\
+            2483789,960x540,\"mp4a.40.2,hvc1.2.4.L90.90\",23.97,aac-128k,hdr10/unenc/1650k/vod.m3u8\n\
+            15811232,,\"mp4a.40.2,hvc1.2.4.L150.90\",,aac-128k,hdr10/unenc/10000k/vod.m3u8\n");
+    }
+
+    #[test]
+    fn test_spacing_ratio() {
+        let pl = playlist();
+        let ladders = ladders(&pl);
+        let model = SpacingRatio::default();
+        let score = model.score(&ladders[2].1);
+        assert!(score > 0.0 && score < 1.0);
+        // a ladder doubling at each step is ideal for an ideal ratio of 2
+        let mut si = ladders[2].1[0].clone();
+        let rungs: Vec<StreamInf> = [1_000_000, 2_000_000, 4_000_000].iter()
+            .map(|bw| { si.bandwidth = *bw; si.clone() }).collect();
+        let rungs: Vec<&StreamInf> = rungs.iter().collect();
+        let doubling = SpacingRatio{ ideal_ratio: 2.0, ..Default::default() };
+        assert!((doubling.score(&rungs) - 1.0).abs() < 1e-9);
+        assert!(doubling.score(&rungs[..1]) == 0.0);
+    }
+
+    #[test]
+    fn test_quality_per_bit() {
+        let pl = playlist();
+        let model = QualityPerBit::default();
+        let mut si = pl.stream_inf[6].clone(); // 1920x1080 HEVC at 6.9 Mbps
+        let hevc = model.rung_score(&si);
+        assert!(hevc > 0.0 && hevc <= 1.0);
+        // without the HEVC efficiency factor the same bandwidth buys fewer bits per pixel
+        si.codecs = Some(Codecs::parse("mp4a.40.2,avc1.640028"));
+        assert!(model.rung_score(&si) != hevc);
+        si.bandwidth = (0.08 * 1920.0 * 1080.0 * 23.97) as u64;
+        assert!((model.rung_score(&si) - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_score_ladders_config() {
+        let pl = playlist();
+        let config = ScoringConfig::from_json(r#"{"spacing_ratio_weight": 0, "quality_per_bit": {"target_bpp": 0.05}}"#)
+            .unwrap();
+        assert_eq!(config.quality_per_bit.resolution_exponent, 0.25);
+        let scores = score_ladders(&pl, &config.models());
+        assert_eq!(scores.len(), 4);
+        assert_eq!(scores[0].scores.len(), 1);
+        assert_eq!(scores[0].scores[0].model, "quality-per-bit");
+        assert_eq!(scores[0].total, scores[0].scores[0].score);
+        assert_eq!(scores[0].variants[0], pl.stream_inf[0].stable_id());
+        assert!(ScoringConfig::from_json(r#"{"spacing": {}}"#).is_err());
+
+        // custom models plug in next to the built-in ones
+        struct Rungs;
+        impl ScoringModel for Rungs {
+            fn name(&self) -> &'static str { "rungs" }
+            fn score(&self, ladder: &[&StreamInf]) -> f64 { (ladder.len() as f64 / 10.0).min(1.0) }
+        }
+        let config = ScoringConfig::default();
+        let mut models = config.models();
+        models.push((2.0, &Rungs));
+        let scores = score_ladders(&pl, &models);
+        assert_eq!(scores[0].scores.iter().map(|s| s.model).collect::<Vec<_>>(),
+            ["spacing-ratio", "quality-per-bit", "rungs"]);
+        assert!(scores[0].total > 0.0 && scores[0].total <= 1.0);
+    }
+
+    #[test]
+    fn test_find_redundant() {
+        let pl = playlist();
+        let redundant = find_redundant(&pl, &RedundancyThresholds::default());
+        // 2560x1440 at 13.9 and 15.8 Mbps, in each of the four audio groups but aac-64k
+        assert_eq!(redundant.len(), 3);
+        assert_eq!(redundant[0].id, pl.stream_inf[1].stable_id());
+        assert_eq!(redundant[0].kept, pl.stream_inf[9].stable_id());
+        assert!((redundant[0].bandwidth_ratio - 1.1366).abs() < 1e-3);
+
+        // close resolutions allowed, e.g. 1920x1080 next to 2560x1440
+        let loose = RedundancyThresholds{ bandwidth_ratio: 1.3, pixel_ratio: 2.0 };
+        let redundant = find_redundant(&pl, &loose);
+        assert!(redundant.iter().any(|r| r.id == pl.stream_inf[8].stable_id() && r.kept == pl.stream_inf[7].stable_id()));
+        assert!(redundant.iter().all(|r| r.pixel_ratio <= 2.0 && r.bandwidth_ratio <= 1.3));
+
+        let pruned = drop_redundant(&pl, &RedundancyThresholds::default());
+        assert_eq!(pruned.stream_inf.len(), pl.stream_inf.len() - 3);
+        assert_eq!(pruned.i_frame_stream_inf.len(), pl.i_frame_stream_inf.len());
+        assert!(find_redundant(&pruned, &RedundancyThresholds::default()).is_empty());
+    }
+
+    #[test]
+    fn test_trim() {
+        let pl = playlist();
+        let bandwidths = |pl: &MultivariantPlaylist| -> Vec<u64> {
+            ladders(pl).into_iter().flat_map(|(_, l)| l.into_iter().map(|si| si.bandwidth)).collect()
+        };
+        let trimmed = trim(&pl, 4, KeepStrategy::Spacing).unwrap();
+        // ends of the whole ladder kept, inner rungs closest to even steps in log bandwidth,
+        // whichever their audio group
+        assert_eq!(bandwidths(&trimmed), [705826, 2312764, 7882680, 26598940]);
+        assert_eq!(trimmed.stream_inf.len(), 4);
+        // renditions of the groups left without variants go too
+        assert_eq!(trimmed.media.iter().map(|m| m.group_id.as_str()).collect::<Vec<_>>(), ["aac-64k", "atmos"]);
+
+        let trimmed = trim(&pl, 2, KeepStrategy::Lowest).unwrap();
+        assert_eq!(bandwidths(&trimmed), [705826, 898051]);
+        assert_eq!(trimmed.i_frame_stream_inf.len(), 1);
+        assert_eq!(trimmed.i_frame_stream_inf[0].resolution, Some(Resolution{ w: 640, h: 360 }));
+        let trimmed = trim(&pl, 2, KeepStrategy::Highest).unwrap();
+        assert_eq!(bandwidths(&trimmed), [25830920, 26598940]);
+        // only 3840x2160 is offered, which has no I-frame variant
+        assert!(trimmed.i_frame_stream_inf.is_empty());
+        let trimmed = trim(&pl, 1, KeepStrategy::Spacing).unwrap();
+        assert_eq!(bandwidths(&trimmed), [705826]);
+        assert_eq!(trim(&pl, 100, KeepStrategy::Spacing).unwrap(), pl);
+        assert!(trim(&pl, 0, KeepStrategy::Spacing).is_err());
+    }
+
+    #[test]
+    fn test_dedupe_ladder() {
+        let mut pl = playlist();
+        pl.stream_inf[0].resolution = None;
+        let resolutions = |pl: &MultivariantPlaylist| -> Vec<Vec<Resolution>> {
+            ladders(pl).into_iter().map(|(_, l)| l.iter().filter_map(|si| si.resolution.clone()).collect()).collect()
+        };
+        for keep in [DedupeKeep::Highest, DedupeKeep::Lowest] {
+            let deduped = dedupe_ladder(&pl, keep);
+            for (before, after) in resolutions(&pl).into_iter().zip(resolutions(&deduped)) {
+                let mut distinct = before.clone();
+                distinct.sort();
+                distinct.dedup();
+                assert_eq!(after.len(), distinct.len());
+                assert!(distinct.iter().all(|r| after.contains(r)));
+            }
+            assert!(deduped.stream_inf.iter().any(|si| si.stable_id() == pl.stream_inf[0].stable_id()));
+            assert_eq!(deduped.i_frame_stream_inf.len(), pl.i_frame_stream_inf.len());
+        }
+        let bandwidth = |keep, group: &str, res: &Resolution| dedupe_ladder(&pl, keep).stream_inf.iter()
+            .find(|si| si.audio.as_deref() == Some(group) && si.resolution.as_ref() == Some(res)).unwrap().bandwidth;
+        let fhd = Resolution{ w: 1920, h: 1080 };
+        assert!(bandwidth(DedupeKeep::Highest, "eac3", &fhd) > bandwidth(DedupeKeep::Lowest, "eac3", &fhd));
+    }
+}
@@ -0,0 +1,39 @@
+#[cfg(feature = "binary")]
+pub mod binary;
+pub mod check;
+pub mod codecs;
+pub mod coverage;
+mod csv;
+#[cfg(feature = "net")]
+pub mod fetch;
+#[cfg(all(feature = "net", feature = "chrono"))]
+pub mod follow;
+pub mod format;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod ladder;
+#[cfg(feature = "net")]
+pub mod load;
+pub mod media_playlist;
+#[cfg(all(feature = "net", feature = "serde"))]
+pub mod notify;
+pub mod parser;
+pub mod plan;
+pub mod playlist;
+pub mod prelude;
+#[cfg(feature = "net")]
+pub mod reachability;
+pub mod registry;
+pub mod report;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod select;
+pub mod suggest;
+#[cfg(feature = "net")]
+pub mod thumbnails;
+#[cfg(feature = "chrono")]
+pub mod timeline;
+pub mod tokenizer;
+pub mod uri;
+pub mod validator;
+pub mod writer;
@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::fetch;
+use crate::format::MultivariantPlaylist;
+use crate::media_playlist::MediaPlaylist;
+use crate::parser;
+use crate::playlist::Playlist;
+
+// One-call loading of playlists, fetching, parsing and validating them
+
+/* Fetch, parse and validate the playlist at a path or http:/https: url */
+pub fn load(uri: &str) -> Result<Playlist, String> {
+    let contents = fetch::fetch_string(uri)?;
+    let playlist = parser::parse(&contents).map_err(|e| format!("{}: {}", uri, e))?;
+    if let Err(report) = playlist.validate() {
+        let errors: Vec<String> = report.errors().map(|f| format!("{}: {}", uri, f)).collect();
+        return Err(errors.join("\n"))
+    }
+    Ok(playlist)
+}
+
+// Media playlist referenced by a multivariant playlist, see fetch_children
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Child {
+    pub uri: String,      // as written in the multivariant playlist
+    pub resolved: String, // against the URI of the multivariant playlist
+    pub playlist: Option<MediaPlaylist>,
+    pub error: Option<String>, // failure to load the playlist, which is then None
+}
+
+/* Load the media playlists of the variants, renditions and I-frame variants of the
+   multivariant playlist at `uri`, once per resolved URI. Failures are reported per child. */
+pub fn fetch_children(uri: &str, playlist: &MultivariantPlaylist) -> Vec<Child> {
+    let references = playlist.stream_inf.iter().map(|s| &s.uri)
+        .chain(playlist.media.iter().filter_map(|m| m.uri.as_ref()))
+        .chain(playlist.i_frame_stream_inf.iter().map(|s| &s.uri));
+    let mut ret: Vec<Child> = vec![];
+    for reference in references {
+        let resolved = fetch::resolve(uri, reference.as_str());
+        if resolved.as_ref().is_ok_and(|r| ret.iter().any(|c| c.resolved == *r)) {
+            continue
+        }
+        let loaded = resolved.clone().and_then(|r| match load(&r)? {
+            Playlist::Media(m) => Ok(m),
+            Playlist::Multivariant(_) => Err(format!("{}: not a media playlist", r)),
+        });
+        ret.push(Child{
+            uri: reference.to_string(),
+            resolved: resolved.unwrap_or_default(),
+            error: loaded.as_ref().err().cloned(),
+            playlist: loaded.ok(),
+        });
+    }
+    ret
+}
+
+/* Load the playlist and, for a multivariant playlist, its media playlists by resolved URI,
+   failing on the first one that cannot be loaded */
+pub fn load_and_resolve(uri: &str) -> Result<(Playlist, BTreeMap<String, MediaPlaylist>), String> {
+    let playlist = load(uri)?;
+    let mut media = BTreeMap::new();
+    if let Playlist::Multivariant(pl) = &playlist {
+        for child in fetch_children(uri, pl) {
+            match (child.playlist, child.error) {
+                (Some(m), _) => { media.insert(child.resolved, m); },
+                (None, e) => return Err(e.unwrap_or_default()),
+            }
+        }
+    }
+    Ok((playlist, media))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::playlist::Playlist;
+    use super::{fetch_children, load, load_and_resolve};
+
+    #[test]
+    fn test_load() {
+        assert!(matches!(load("data/media_playlist.m3u8"), Ok(Playlist::Media(_))));
+        assert!(load("data/validation_error.m3u8").unwrap_err().starts_with("data/validation_error.m3u8: "));
+        assert!(load("data/nonexistent.m3u8").is_err());
+    }
+
+    #[test]
+    fn test_load_and_resolve() {
+        let (pl, media) = load_and_resolve("data/presentation/master.m3u8").unwrap();
+        assert!(matches!(pl, Playlist::Multivariant(_)));
+        assert_eq!(media.keys().collect::<Vec<_>>(), ["data/presentation/audio/en.m3u8",
+            "data/presentation/video/360p.m3u8", "data/presentation/video/720p.m3u8"]);
+        assert_eq!(media["data/presentation/video/720p.m3u8"].segments.len(), 2);
+
+        let (_, media) = load_and_resolve("data/media_playlist.m3u8").unwrap();
+        assert!(media.is_empty());
+        // referenced playlist missing
+        assert!(load_and_resolve("data/thumbnails/master.m3u8").is_err());
+    }
+
+    #[test]
+    fn test_fetch_children() {
+        let Ok(Playlist::Multivariant(pl)) = load("data/thumbnails/master.m3u8") else { panic!() };
+        let children = fetch_children("data/thumbnails/master.m3u8", &pl);
+        assert_eq!(children.len(), 5);
+        assert_eq!(children[0].uri, "360p.m3u8");
+        assert_eq!(children[0].resolved, "data/thumbnails/360p.m3u8");
+        assert!(children[0].playlist.is_none());
+        assert!(children[0].error.as_ref().is_some_and(|e| e.contains("data/thumbnails/360p.m3u8")));
+        assert!(children[2].error.is_none());
+        assert!(children[2].playlist.as_ref().is_some_and(|p| p.i_frames_only));
+        assert_eq!(children.iter().filter(|c| c.playlist.is_some()).count(), 2);
+    }
+}
@@ -1,70 +1,983 @@
-mod parser;
-mod format;
-
 use std::fs;
-use serde_json;
-use clap::Parser;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use clap::{Parser, Subcommand, ValueEnum};
+use m3u_parser::{binary, codecs, coverage, fetch, follow, json, ladder, load, notify, parser, plan, reachability, report, schema,
+    thumbnails, timeline};
+use m3u_parser::format::{self, MultivariantPlaylist, VideoRange};
+use m3u_parser::playlist::Playlist;
+use m3u_parser::select::Selector;
+use m3u_parser::uri::PlaylistUri;
+use m3u_parser::validator::{BandwidthBounds, Finding, ValidationReport, Validator};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    args: Args,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Write the I-frame nearest to a timestamp for every resolution, using EXT-X-I-FRAME-STREAM-INF
+    Thumbnails {
+        /// Filename or http:/https: url of the multivariant playlist
+        uri: String,
+        /// Timestamp, as seconds or HH:MM:SS
+        #[arg(long)]
+        at: String,
+        /// Output directory, one file per resolution
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Print per-segment durations, sizes, discontinuities and PROGRAM-DATE-TIME of a media playlist
+    Timeline {
+        /// Filename or http:/https: url of the media playlist
+        uri: String,
+        /// Output format
+        #[arg(long, value_enum, default_value_t=TimelineFormat::Csv)]
+        format: TimelineFormat,
+        /// Only output the last N segments
+        #[arg(long)]
+        window: Option<usize>,
+        /// Measure sizes of segments not delivered as byte ranges
+        #[arg(long, default_value_t=false)]
+        measure: bool,
+        /// Only output N segments sampled across the timeline, one per even stretch
+        #[arg(long)]
+        spot_check: Option<usize>,
+        /// Seed picking the segments of --spot-check, the same seed picks the same segments
+        #[arg(long, default_value_t=0)]
+        seed: u64,
+    },
+    /// Reload a live media playlist, reporting drift between PROGRAM-DATE-TIME and arrival of new segments
+    Follow {
+        /// Filename or http:/https: url of the media playlist, or of a multivariant playlist to watch for variant changes
+        uri: String,
+        /// Seconds between reloads (default: target duration, 10 for multivariant playlists)
+        #[arg(long)]
+        interval: Option<f64>,
+        /// Stop after N reloads
+        #[arg(long)]
+        count: Option<u64>,
+        /// Flag the encoder clock as skewed above this mean drift, seconds
+        #[arg(long, default_value_t=10.0)]
+        max_drift: f64,
+        /// Print changes as JSON lines: new_segment, segment_removed and end_list events, variant_added,
+        /// variant_removed, rendition_added and rendition_removed for multivariant playlists
+        #[arg(long, default_value_t=false)]
+        events: bool,
+        /// Notify when the encoder clock is skewed: http(s) webhook, slack+https, pagerduty or smtp url (repeatable)
+        #[arg(long)]
+        notify: Vec<String>,
+    },
+    /// Print segment count, total duration and longest segment of a media playlist
+    Stats {
+        /// Filename or http:/https: url of the media playlist
+        uri: String,
+    },
+    /// Score the ABR ladders of a multivariant playlist, one per audio group
+    Analyze {
+        /// Filename or http:/https: url of the multivariant playlist
+        uri: String,
+        /// JSON file with weights and parameters of the scoring models
+        #[arg(long)]
+        scoring_config: Option<PathBuf>,
+        #[command(flatten)]
+        redundancy: RedundancyArgs,
+    },
+    /// Report languages of audio and subtitles renditions, and caption services per language
+    Coverage {
+        /// Filename or http:/https: url of the multivariant playlist
+        uri: String,
+    },
+    /// Request every variant, rendition and I-frame variant URI, reporting status, content type and size
+    Check {
+        /// Filename or http:/https: url of the multivariant playlist
+        uri: String,
+        /// Number of requests in flight at once
+        #[arg(long, default_value_t=8)]
+        concurrency: usize,
+        /// Print the results as JSON instead of a table
+        #[arg(long, default_value_t=false)]
+        json: bool,
+    },
+    /// Convert the JSON output for a multivariant playlist back to m3u8, or a multivariant playlist to JSON
+    Convert {
+        /// Filename or http:/https: url of the JSON file or the multivariant playlist
+        uri: String,
+        /// Output format, by default m3u8 for JSON and JSON for a playlist
+        #[arg(long, value_enum)]
+        to: Option<OutputFormat>,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+struct RedundancyArgs {
+    /// Variants within this bandwidth ratio of a lower one are redundant
+    #[arg(long, default_value_t=1.15)]
+    redundant_bandwidth_ratio: f64,
+    /// Variants within this pixel count ratio of a lower one are redundant (1: same resolution)
+    #[arg(long, default_value_t=1.0)]
+    redundant_pixel_ratio: f64,
+}
+
+impl RedundancyArgs {
+    fn thresholds(&self) -> ladder::RedundancyThresholds {
+        ladder::RedundancyThresholds{
+            bandwidth_ratio: self.redundant_bandwidth_ratio,
+            pixel_ratio: self.redundant_pixel_ratio,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum KeepStrategy {
+    Spacing, Lowest, Highest,
+}
+
+impl From<KeepStrategy> for ladder::KeepStrategy {
+    fn from(v: KeepStrategy) -> Self {
+        match v {
+            KeepStrategy::Spacing => ladder::KeepStrategy::Spacing,
+            KeepStrategy::Lowest => ladder::KeepStrategy::Lowest,
+            KeepStrategy::Highest => ladder::KeepStrategy::Highest,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Compression {
+    Gzip, Zstd,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum OutputFormat {
+    Json, M3u8, Csv, Md, Msgpack, Cbor,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum JsonKeys {
+    Rust, Hls, Camel,
+}
+
+impl From<JsonKeys> for json::KeyStyle {
+    fn from(v: JsonKeys) -> Self {
+        match v {
+            JsonKeys::Rust => json::KeyStyle::Rust,
+            JsonKeys::Hls => json::KeyStyle::Hls,
+            JsonKeys::Camel => json::KeyStyle::CamelCase,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum BestBy {
+    Bandwidth, Resolution,
+}
+
+impl From<BestBy> for format::BestBy {
+    fn from(v: BestBy) -> Self {
+        match v {
+            BestBy::Bandwidth => format::BestBy::Bandwidth,
+            BestBy::Resolution => format::BestBy::Resolution,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum HdcpLevel {
+    None,
+    #[value(name = "type-0")]
+    Type0,
+    #[value(name = "type-1")]
+    Type1,
+}
+
+impl From<HdcpLevel> for format::HdcpLevel {
+    fn from(v: HdcpLevel) -> Self {
+        match v {
+            HdcpLevel::None => format::HdcpLevel::None,
+            HdcpLevel::Type0 => format::HdcpLevel::Type0,
+            HdcpLevel::Type1 => format::HdcpLevel::Type1,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum SortOrder {
+    Asc, Desc,
+}
+
+impl From<SortOrder> for format::SortOrder {
+    fn from(v: SortOrder) -> Self {
+        match v {
+            SortOrder::Asc => format::SortOrder::Ascending,
+            SortOrder::Desc => format::SortOrder::Descending,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum DedupeKeep {
+    Highest, Lowest,
+}
+
+impl From<DedupeKeep> for ladder::DedupeKeep {
+    fn from(v: DedupeKeep) -> Self {
+        match v {
+            DedupeKeep::Highest => ladder::DedupeKeep::Highest,
+            DedupeKeep::Lowest => ladder::DedupeKeep::Lowest,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum VideoRangeArg {
+    Sdr, Hlg, Pq,
+}
+
+impl From<VideoRangeArg> for VideoRange {
+    fn from(v: VideoRangeArg) -> Self {
+        match v {
+            VideoRangeArg::Sdr => VideoRange::SDR,
+            VideoRangeArg::Hlg => VideoRange::HLG,
+            VideoRangeArg::Pq => VideoRange::PQ,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum TimelineFormat {
+    Csv, Json,
+}
+
+#[derive(clap::Args, Debug)]
 struct Args {
     /// Filename or http:/https: url to parse
-    #[arg(long)]
-    uri: String,
+    #[arg(long, required_unless_present = "emit_schema")]
+    uri: Option<String>,
+    /// Print the JSON Schema of the output for multivariant playlists, with Rust field names, and exit
+    #[arg(long, default_value_t=false)]
+    emit_schema: bool,
+    /// Skip malformed lines instead of failing, reporting them as warnings
+    #[arg(long, default_value_t=false)]
+    lenient: bool,
+    /// Strict RFC 8216bis conformance: unknown values and missing CODECS are errors, other MUST-level rules are checked
+    #[arg(long, default_value_t=false)]
+    strict: bool,
+    /// Keep unrecognized lines (unknown tags, comments) instead of failing
+    #[arg(long, default_value_t=false)]
+    passthrough: bool,
+    /// With --format m3u8, write the playlist as read except for the changes of filters and fixes
+    #[arg(long, default_value_t=false)]
+    preserve: bool,
     /// Filter by AUDIO-GROUP
     #[arg(long)]
     audio_group: Option<String>,
-    /// Filter by AUDIO CHANNELS
+    /// Filter by audio CHANNELS, exact (2, 16/JOC) or by channel count (>=6, <=2, =16)
     #[arg(long)]
     audio_channels: Option<String>,
+    /// Filter audio EXT-X-MEDIA by LANGUAGE or ASSOC-LANGUAGE (e.g. es, also matching es-419)
+    #[arg(long)]
+    audio_language: Option<String>,
+    /// Keep only the DEFAULT=YES rendition of every audio group
+    #[arg(long, default_value_t=false)]
+    default_audio_only: bool,
+    /// Filter SUBTITLES EXT-X-MEDIA by GROUP-ID
+    #[arg(long)]
+    subtitle_group: Option<String>,
+    /// Filter SUBTITLES EXT-X-MEDIA by LANGUAGE or ASSOC-LANGUAGE (e.g. fr, also matching fr-CA)
+    #[arg(long)]
+    subtitle_language: Option<String>,
+    /// Keep only the FORCED=YES SUBTITLES EXT-X-MEDIA (forced narrative)
+    #[arg(long, default_value_t=false)]
+    forced_subtitles: bool,
+    /// Remove SUBTITLES EXT-X-MEDIA and references to them
+    #[arg(long, default_value_t=false)]
+    no_subtitles: bool,
+    /// Remove CLOSED-CAPTIONS EXT-X-MEDIA and set CLOSED-CAPTIONS=NONE in EXT-X-STREAM-INF
+    #[arg(long, default_value_t=false)]
+    no_closed_captions: bool,
+    /// Remove EXT-X-I-FRAME-STREAM-INF
+    #[arg(long, default_value_t=false)]
+    no_iframe_streams: bool,
+    /// Filter EXT-X-I-FRAME-STREAM-INF only by bandwidth (minimum specified)
+    #[arg(long)]
+    iframe_min_bandwidth: Option<u64>,
+    /// Filter EXT-X-I-FRAME-STREAM-INF only by bandwidth (maximum specified)
+    #[arg(long)]
+    iframe_max_bandwidth: Option<u64>,
+    /// Keep EXT-X-I-FRAME-STREAM-INF at least as wide and tall as a resolution, or without RESOLUTION
+    #[arg(long)]
+    iframe_min_resolution: Option<String>,
+    /// Keep EXT-X-I-FRAME-STREAM-INF fitting within a resolution, or without RESOLUTION
+    #[arg(long)]
+    iframe_max_resolution: Option<String>,
+    /// Filter EXT-X-I-FRAME-STREAM-INF only by VIDEO-RANGE, SDR when absent
+    #[arg(long, value_enum)]
+    iframe_video_range: Option<VideoRangeArg>,
+    /// Apply --min-bandwidth and --max-bandwidth to EXT-X-I-FRAME-STREAM-INF too
+    #[arg(long, default_value_t=false)]
+    iframe_apply_bandwidth: bool,
     /// Filter EXT-X-STREAM-INF by bandwidth (maximum specified)
     #[arg(long)]
     max_bandwidth: Option<u64>,
-    /// Filter EXT-X-STREAM-INF and EXT-X-I-FRAME-STREAM-INF by resolution (exact, WxH)
+    /// Filter EXT-X-STREAM-INF by bandwidth (minimum specified)
+    #[arg(long)]
+    min_bandwidth: Option<u64>,
+    /// Filter EXT-X-STREAM-INF and EXT-X-I-FRAME-STREAM-INF by bandwidth, MIN..MAX (bounds included, either optional)
+    #[arg(long)]
+    bandwidth_range: Option<String>,
+    /// Filter EXT-X-STREAM-INF and EXT-X-I-FRAME-STREAM-INF by resolution (exact, WxH or e.g. 1080p, 4k)
     #[arg(long)]
     resolution: Option<String>,
-    /// Sort EXT-X-STREAM-INF by bandwidth (descending)
+    /// Keep EXT-X-STREAM-INF and EXT-X-I-FRAME-STREAM-INF fitting within a resolution (WxH or e.g. 1080p), or without RESOLUTION
+    #[arg(long)]
+    max_resolution: Option<String>,
+    /// Keep EXT-X-STREAM-INF and EXT-X-I-FRAME-STREAM-INF at least as wide and tall as a resolution (WxH or e.g. 720p), or without RESOLUTION
+    #[arg(long)]
+    min_resolution: Option<String>,
+    /// Filter EXT-X-STREAM-INF by FRAME-RATE (maximum specified, kept when absent)
+    #[arg(long)]
+    max_frame_rate: Option<f64>,
+    /// Keep the EXT-X-STREAM-INF and EXT-X-I-FRAME-STREAM-INF of a content steering PATHWAY-ID ("." when absent)
+    #[arg(long)]
+    pathway: Option<String>,
+    /// Filter EXT-X-STREAM-INF by HDCP-LEVEL (maximum required, kept when absent), none for unprotected outputs
+    #[arg(long, value_enum)]
+    max_hdcp_level: Option<HdcpLevel>,
+    /// Filter EXT-X-STREAM-INF by codec family in CODECS, e.g. hevc, av1, ec-3 (I-frame variants too for video codecs)
+    #[arg(long)]
+    codec: Option<String>,
+    /// Keep EXT-X-STREAM-INF with a codec matching, by prefix (hvc1, mp4a.40.2) or family (hevc) (repeatable)
+    #[arg(long)]
+    codec_include: Vec<String>,
+    /// Drop EXT-X-STREAM-INF with a codec matching, by prefix (ec-3, avc1.64) or family (dolby-vision) (repeatable)
+    #[arg(long)]
+    codec_exclude: Vec<String>,
+    /// Filter EXT-X-STREAM-INF and EXT-X-I-FRAME-STREAM-INF by VIDEO-RANGE, SDR when absent
+    #[arg(long, value_enum)]
+    video_range: Option<VideoRangeArg>,
+    /// Filter EXT-X-STREAM-INF by inferred quality label (exact, e.g. "1080p HDR")
+    #[arg(long)]
+    label: Option<String>,
+    /// Keep the EXT-X-STREAM-INF, EXT-X-I-FRAME-STREAM-INF and EXT-X-MEDIA whose URI matches a regular expression
+    #[arg(long)]
+    uri_regex: Option<String>,
+    /// Remove the EXT-X-STREAM-INF, EXT-X-I-FRAME-STREAM-INF and EXT-X-MEDIA whose URI matches a regular expression
+    #[arg(long)]
+    exclude_uri_regex: Option<String>,
+    /// Rewrite URIs as REGEX=REPLACEMENT, split at the first '=', the replacement can use $1 (repeatable)
+    #[arg(long)]
+    rewrite_uri: Vec<String>,
+    /// Keep only the EXT-X-STREAM-INF and EXT-X-I-FRAME-STREAM-INF with given ids (repeatable)
+    #[arg(long)]
+    id: Vec<String>,
+    /// Keep only the best EXT-X-STREAM-INF left by the filters, e.g. below --max-bandwidth, with its renditions
+    #[arg(long, default_value_t=false)]
+    best: bool,
+    /// Keep only the N best EXT-X-STREAM-INF left by the filters, with their renditions
+    #[arg(long)]
+    top: Option<usize>,
+    /// Best variants for --best and --top: highest bandwidth, or largest resolution then highest bandwidth
+    #[arg(long, value_enum, default_value_t=BestBy::Bandwidth)]
+    best_by: BestBy,
+    /// Remove AUDIO, VIDEO, SUBTITLES and CLOSED-CAPTIONS renditions no variant left references
     #[arg(long, default_value_t=false)]
-    sort_by_bandwidth: bool,
+    prune: bool,
+    /// Remove EXT-X-STREAM-INF whose AUDIO group has no rendition left after the filters
+    #[arg(long, default_value_t=false)]
+    with_audio: bool,
+    /// Sort EXT-X-STREAM-INF by bandwidth (descending by default), equal bandwidths keep their order
+    #[arg(long, value_enum, num_args=0..=1, default_missing_value="desc")]
+    sort_by_bandwidth: Option<SortOrder>,
+    /// Sort EXT-X-STREAM-INF by AVERAGE-BANDWIDTH, BANDWIDTH when absent (descending by default)
+    #[arg(long, value_enum, num_args=0..=1, default_missing_value="desc", conflicts_with="sort_by_bandwidth")]
+    sort_by_average_bandwidth: Option<SortOrder>,
+    /// Sort EXT-X-STREAM-INF by SCORE, variants without SCORE last (descending by default)
+    #[arg(long, value_enum, num_args=0..=1, default_missing_value="desc",
+        conflicts_with_all=["sort_by_bandwidth", "sort_by_average_bandwidth"])]
+    sort_by_score: Option<SortOrder>,
+    /// Apply automatic fixes: add an audio-only variant from an audio rendition when missing, replace BANDWIDTH when zero or below AVERAGE-BANDWIDTH
+    #[arg(long, default_value_t=false)]
+    fix: bool,
+    /// Lowest plausible bits per pixel of video variants, AVC-equivalent
+    #[arg(long, default_value_t=0.01)]
+    min_bpp: f64,
+    /// Highest plausible bits per pixel of video variants, AVC-equivalent
+    #[arg(long, default_value_t=1.0)]
+    max_bpp: f64,
+    /// Base URL to resolve relative URIs against when checking them
+    #[arg(long)]
+    base_url: Option<String>,
+    /// Drop EXT-X-STREAM-INF too close in bandwidth and resolution to a lower one
+    #[arg(long, default_value_t=false)]
+    drop_redundant: bool,
+    #[command(flatten)]
+    redundancy: RedundancyArgs,
+    /// Keep one EXT-X-STREAM-INF per resolution and audio group
+    #[arg(long, default_value_t=false)]
+    dedupe_ladder: bool,
+    /// Variant kept by --dedupe-ladder for each resolution, by bandwidth
+    #[arg(long, value_enum, default_value_t=DedupeKeep::Highest)]
+    dedupe_keep: DedupeKeep,
+    /// Keep at most N EXT-X-STREAM-INF with RESOLUTION in total, across audio groups
+    #[arg(long)]
+    max_variants: Option<usize>,
+    /// Rungs kept by --max-variants: ends of the ladder and even bandwidth steps between them, or lowest, or highest
+    #[arg(long, value_enum, default_value_t=KeepStrategy::Spacing)]
+    keep: KeepStrategy,
+    /// Load the media playlists of the variants and renditions too, output as children
+    #[arg(long, default_value_t=false)]
+    hydrate: bool,
+    /// Print the changes filters, rewrites and fixes would make, as JSON, instead of the output
+    #[arg(long, default_value_t=false)]
+    dry_run: bool,
+    /// Output format, m3u8 writes the filtered multivariant playlist back as a playlist, csv one row per variant, md a report, msgpack and cbor the JSON in binary
+    #[arg(long, value_enum, default_value_t=OutputFormat::Json)]
+    format: OutputFormat,
+    /// JSON keys as Rust field names (group_id), HLS attribute names (GROUP-ID) or camelCase (groupId)
+    #[arg(long, value_enum, default_value_t=JsonKeys::Rust)]
+    json_keys: JsonKeys,
+    /// Leave out JSON fields that are null
+    #[arg(long, default_value_t=false)]
+    skip_none: bool,
+    /// Write the output to a file instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// Compress the output file
+    #[arg(long, value_enum, requires = "output")]
+    compress: Option<Compression>,
+}
+
+// Filters and fixes of the arguments, in the order they apply, and the parameters that could not be parsed
+fn selector(args: &Args) -> (Selector, Vec<String>) {
+    let mut errors = vec![];
+    let mut resolution = |name: &str, v: &str| {
+        let res = parser::parse_resolution_param(v);
+        if res.is_none() {
+            errors.push(format!("Invalid --{} {}", name, v));
+        }
+        res
+    };
+    let resolutions = (
+        args.resolution.as_deref().and_then(|v| resolution("resolution", v)),
+        args.max_resolution.as_deref().and_then(|v| resolution("max-resolution", v)),
+        args.min_resolution.as_deref().and_then(|v| resolution("min-resolution", v)),
+    );
+    let iframes = format::IFrameCriteria{
+        min_bandwidth: args.iframe_min_bandwidth.or(args.min_bandwidth.filter(|_| args.iframe_apply_bandwidth)),
+        max_bandwidth: args.iframe_max_bandwidth.or(args.max_bandwidth.filter(|_| args.iframe_apply_bandwidth)),
+        min_resolution: args.iframe_min_resolution.as_deref().and_then(|v| resolution("iframe-min-resolution", v)),
+        max_resolution: args.iframe_max_resolution.as_deref().and_then(|v| resolution("iframe-max-resolution", v)),
+        video_range: args.iframe_video_range.map(|v| v.into()),
+    };
+    let mut sel = Selector::new();
+    if let Some(ag) = &args.audio_group {
+        sel = sel.audio_group(ag);
+    }
+    if let Some(ch) = &args.audio_channels {
+        sel = sel.audio_channels(ch);
+    }
+    if let Some(lang) = &args.audio_language {
+        sel = sel.audio_language(lang);
+    }
+    if args.default_audio_only {
+        sel = sel.default_audio();
+    }
+    if let Some(group) = &args.subtitle_group {
+        sel = sel.subtitle_group(group);
+    }
+    if let Some(lang) = &args.subtitle_language {
+        sel = sel.subtitle_language(lang);
+    }
+    if args.forced_subtitles {
+        sel = sel.forced_subtitles();
+    }
+    if args.no_subtitles {
+        sel = sel.without_subtitles();
+    }
+    if args.no_closed_captions {
+        sel = sel.without_closed_captions();
+    }
+    if args.no_iframe_streams {
+        sel = sel.without_iframe_streams();
+    }
+    if iframes != format::IFrameCriteria::default() {
+        sel = sel.i_frame_streams(iframes);
+    }
+    if let Some(bw) = args.max_bandwidth {
+        sel = sel.max_bandwidth(bw);
+    }
+    if let Some(bw) = args.min_bandwidth {
+        sel = sel.min_bandwidth(bw);
+    }
+    if let Some(range) = &args.bandwidth_range {
+        match parser::parse_bandwidth_range_param(range) {
+            Some((min, max)) => sel = sel.bandwidth_range(min, max),
+            None => errors.push(format!("Invalid --bandwidth-range {}", range)),
+        }
+    }
+    if let Some(res) = resolutions.0 {
+        sel = sel.resolution(res);
+    }
+    if let Some(res) = resolutions.1 {
+        sel = sel.max_resolution(res);
+    }
+    if let Some(res) = resolutions.2 {
+        sel = sel.min_resolution(res);
+    }
+    if let Some(fps) = args.max_frame_rate {
+        sel = sel.max_frame_rate(fps);
+    }
+    if let Some(pathway) = &args.pathway {
+        sel = sel.pathway(pathway);
+    }
+    if let Some(level) = args.max_hdcp_level {
+        sel = sel.max_hdcp_level(level.into());
+    }
+    if let Some(codec) = &args.codec {
+        match codecs::CodecFamily::parse(codec) {
+            Some(family) => sel = sel.codec(family),
+            None => errors.push(format!("Unknown codec family {}", codec)),
+        }
+    }
+    if !args.codec_include.is_empty() || !args.codec_exclude.is_empty() {
+        let (include, exclude) = (args.codec_include.clone(), args.codec_exclude.clone());
+        let matching = |c: &codecs::Codecs, patterns: &[String]| c.iter().any(|c| patterns.iter().any(|p| c.matches(p)));
+        sel = sel.codecs(move |c| (include.is_empty() || matching(c, &include)) && !matching(c, &exclude));
+    }
+    if let Some(range) = args.video_range {
+        sel = sel.video_range(range.into());
+    }
+    if let Some(label) = &args.label {
+        sel = sel.label(label);
+    }
+    for (name, re, keep) in [("uri-regex", &args.uri_regex, true), ("exclude-uri-regex", &args.exclude_uri_regex, false)] {
+        let Some(re) = re else { continue };
+        match regex::Regex::new(re) {
+            Ok(re) if keep => sel = sel.uri_matching(re),
+            Ok(re) => sel = sel.uri_not_matching(re),
+            Err(e) => errors.push(format!("Invalid --{} {}: {}", name, re, e)),
+        }
+    }
+    for rewrite in &args.rewrite_uri {
+        let Some((re, replacement)) = rewrite.split_once('=') else {
+            errors.push(format!("Invalid --rewrite-uri {}, expected REGEX=REPLACEMENT", rewrite));
+            continue
+        };
+        match regex::Regex::new(re) {
+            Ok(re) => sel = sel.rewrite_uris(re, replacement),
+            Err(e) => errors.push(format!("Invalid --rewrite-uri {}: {}", rewrite, e)),
+        }
+    }
+    if !args.id.is_empty() {
+        sel = sel.ids(&args.id);
+    }
+    if args.drop_redundant {
+        let thresholds = args.redundancy.thresholds();
+        sel = sel.then(move |pl| Ok(ladder::drop_redundant(pl, &thresholds)));
+    }
+    if args.dedupe_ladder {
+        let keep = args.dedupe_keep.into();
+        sel = sel.then(move |pl| Ok(ladder::dedupe_ladder(pl, keep)));
+    }
+    if let Some(n) = args.max_variants {
+        let keep = args.keep.into();
+        sel = sel.then(move |pl| ladder::trim(pl, n, keep));
+    }
+    if args.best {
+        sel = sel.best(format::BestCriteria{ by: args.best_by.into(), ..Default::default() });
+    }
+    if let Some(n) = args.top {
+        sel = sel.top_n(n, args.best_by.into());
+    }
+    if args.with_audio {
+        sel = sel.with_audio();
+    }
+    if args.prune {
+        sel = sel.prune_unreferenced_media();
+    }
+    if let Some(order) = args.sort_by_bandwidth {
+        sel = sel.sort_by_bandwidth(order.into());
+    }
+    if let Some(order) = args.sort_by_average_bandwidth {
+        sel = sel.sort_by_average_bandwidth(order.into());
+    }
+    if let Some(order) = args.sort_by_score {
+        sel = sel.sort_by_score(order.into());
+    }
+    (sel, errors)
+}
+
+// JSON keys and null fields as chosen by --json-keys and --skip-none
+fn json_style(args: &Args) -> json::JsonStyle {
+    json::JsonStyle{ keys: args.json_keys.into(), skip_none: args.skip_none }
+}
+
+// All the findings on stderr, exiting when there are errors
+fn report_findings(findings: Vec<Finding>) {
+    let report = ValidationReport::from(findings);
+    if !report.findings.is_empty() {
+        eprintln!("{}", report);
+    }
+    if report.has_errors() {
+        std::process::exit(1);
+    }
+}
+
+// Text is written as is, with a final line break if missing
+fn write_output(args: &Args, text: &str) {
+    let text = if text.ends_with('\n') { text.to_string() } else { format!("{}\n", text) };
+    write_bytes(args, text.as_bytes());
+}
+
+fn write_bytes(args: &Args, data: &[u8]) {
+    let Some(path) = &args.output else {
+        io::stdout().write_all(data).expect("Failed to write output");
+        return
+    };
+    let file = fs::File::create(path).expect("Failed to create output file");
+    let written = match args.compress {
+        Some(Compression::Gzip) => {
+            let mut out = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            out.write_all(data).and_then(|_| out.finish().map(|_| ()))
+        },
+        Some(Compression::Zstd) => zstd::stream::copy_encode(data, file, 0),
+        None => (&file).write_all(data),
+    };
+    written.expect("Failed to write output file");
 }
 
 fn main() {
-    let args = Args::parse();
-
-    let contents = 
-        if args.uri.starts_with("http://") || args.uri.starts_with("https://") {
-            ureq::get(&args.uri).call()
-                .expect("Failed to read url")
-                .into_string()
-                .expect("Failed to parse url from string")
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Thumbnails{ uri, at, out }) => extract_thumbnails(&uri, &at, &out),
+        Some(Command::Timeline{ uri, format, window, measure, spot_check, seed }) =>
+            print_timeline(&uri, &format, window, measure, spot_check.map(|n| (n, seed))),
+        Some(Command::Follow{ uri, interval, count, max_drift, events, notify }) =>
+            follow_playlist(&uri, interval, count, max_drift, events, &notify),
+        Some(Command::Analyze{ uri, scoring_config, redundancy }) =>
+            analyze(&uri, scoring_config.as_ref(), &redundancy.thresholds()),
+        Some(Command::Coverage{ uri }) => print_coverage(&uri),
+        Some(Command::Stats{ uri }) => print_stats(&uri),
+        Some(Command::Check{ uri, concurrency, json }) => check_reachability(&uri, concurrency, json),
+        Some(Command::Convert{ uri, to }) => convert(&uri, to),
+        None => run(&cli.args),
+    }
+}
+
+fn print_coverage(uri: &str) {
+    let contents = fetch::fetch_string(uri).expect("Failed to read playlist");
+    let m3u = parser::parse_playlist(&contents).expect("Failed to parse file");
+    println!("{}", serde_json::to_string_pretty(&coverage::coverage(&m3u)).unwrap());
+}
+
+// Media playlist output, with its classification
+#[derive(serde::Serialize)]
+struct MediaOutput<'a> {
+    kind: m3u_parser::media_playlist::PlaylistKind,
+    #[serde(flatten)]
+    playlist: &'a m3u_parser::media_playlist::MediaPlaylist,
+}
+
+// Multivariant playlist output with its media playlists, see --hydrate
+#[derive(serde::Serialize)]
+struct HydratedOutput<'a> {
+    #[serde(flatten)]
+    playlist: &'a m3u_parser::format::MultivariantPlaylist,
+    children: Vec<load::Child>,
+}
+
+#[derive(serde::Serialize)]
+struct Analysis {
+    ladders: Vec<ladder::LadderScore>,
+    redundant: Vec<ladder::Redundancy>,
+}
+
+fn analyze(uri: &str, scoring_config: Option<&PathBuf>, thresholds: &ladder::RedundancyThresholds) {
+    let contents = fetch::fetch_string(uri).expect("Failed to read playlist");
+    let m3u = parser::parse_playlist(&contents).expect("Failed to parse file");
+    let config = match scoring_config {
+        Some(path) => ladder::ScoringConfig::from_json(&fs::read_to_string(path).expect("Failed to read scoring config"))
+            .expect("Failed to load scoring config"),
+        None => ladder::ScoringConfig::default(),
+    };
+    let analysis = Analysis{
+        ladders: ladder::score_ladders(&m3u, &config.models()),
+        redundant: ladder::find_redundant(&m3u, thresholds),
+    };
+    println!("{}", serde_json::to_string_pretty(&analysis).unwrap());
+}
+
+fn follow_playlist(uri: &str, interval: Option<f64>, count: Option<u64>, max_drift: f64, events: bool,
+    notify: &[String]) {
+    let contents = fetch::fetch_string(uri).expect("Failed to read playlist");
+    if let Ok(Playlist::Multivariant(m3u)) = parser::parse(&contents) {
+        return watch_variants(uri, m3u, interval, count, events)
+    }
+    let notifiers: Vec<Box<dyn notify::Notifier>> = notify.iter()
+        .map(|url| notify::notifier(url).expect("Invalid notification target"))
+        .collect();
+    let mut skewed = false;
+    let mut follower = follow::Follower::new(uri);
+    let mut previous = None;
+    let mut reloads = 0;
+    loop {
+        let (playlist, new) = follower.poll().expect("Failed to reload playlist");
+        for f in previous.as_ref().map(|p| playlist.reload_findings(p)).unwrap_or_default() {
+            eprintln!("{}", f);
+        }
+        if events {
+            // JSON lines only on stdout
+            for e in previous.as_ref().map(|p| follow::events(p, &playlist, &new)).unwrap_or_default() {
+                println!("{}", serde_json::to_string(&e).unwrap());
+            }
         } else {
-            fs::read_to_string(&args.uri).expect("Failed to read file")
-        };
+            for s in &new {
+                println!("{} {} drift {:.3}s", s.sequence, s.uri, s.drift);
+            }
+        }
+        if let (false, Some(stats)) = (new.is_empty(), follower.stats()) {
+            if !events {
+                println!("drift mean {:.3}s min {:.3}s max {:.3}s jitter {:.3}s over {} segments",
+                    stats.mean, stats.min, stats.max, stats.jitter, stats.samples);
+            }
+            if stats.is_skewed(max_drift) {
+                eprintln!("warning: encoder clock skewed by {:.3}s on average", stats.mean);
+            }
+            // notify once each time the clock becomes skewed
+            if stats.is_skewed(max_drift) && !skewed {
+                let message = format!("{}: mean drift {:.3}s over {} segments", uri, stats.mean, stats.samples);
+                for n in &notifiers {
+                    if let Err(e) = n.notify("Encoder clock skewed", &message) {
+                        eprintln!("warning: {}", e);
+                    }
+                }
+            }
+            skewed = stats.is_skewed(max_drift);
+        }
+        if playlist.end_list || count.is_some_and(|c| reloads >= c) {
+            break
+        }
+        reloads += 1;
+        let secs = interval.unwrap_or(playlist.target_duration as f64);
+        previous = Some(playlist);
+        std::thread::sleep(std::time::Duration::from_secs_f64(secs.max(0.1)));
+    }
+}
 
-    let mut m3u = parser::parse_playlist(&contents).expect("Failed to parse file");
+// Reload a multivariant playlist, printing the variants and renditions removed or added
+fn watch_variants(uri: &str, mut previous: MultivariantPlaylist, interval: Option<f64>, count: Option<u64>, events: bool) {
+    let secs = interval.unwrap_or(10.0);
+    for _ in 0..count.unwrap_or(u64::MAX) {
+        std::thread::sleep(std::time::Duration::from_secs_f64(secs.max(0.1)));
+        let contents = fetch::fetch_string(uri).expect("Failed to reload playlist");
+        let m3u = parser::parse_playlist(&contents).expect("Failed to parse playlist");
+        for e in follow::variant_events(&previous, &m3u) {
+            if events {
+                println!("{}", serde_json::to_string(&e).unwrap());
+                continue
+            }
+            match e {
+                follow::Event::VariantAdded{ uri, bandwidth } => println!("variant added {} {}", uri, bandwidth),
+                follow::Event::VariantRemoved{ uri, bandwidth } => println!("variant removed {} {}", uri, bandwidth),
+                follow::Event::RenditionAdded{ media_type, group_id, name, .. } =>
+                    println!("rendition added {:?} {} {}", media_type, group_id, name),
+                follow::Event::RenditionRemoved{ media_type, group_id, name, .. } =>
+                    println!("rendition removed {:?} {} {}", media_type, group_id, name),
+                _ => (),
+            }
+        }
+        previous = m3u;
+    }
+}
 
-    if let Some(ag) = &args.audio_group {
-        m3u = m3u.select_audio_group(ag).expect("Failed to select audio group");
+fn convert(uri: &str, to: Option<OutputFormat>) {
+    let contents = fetch::fetch_string(uri).expect("Failed to read input");
+    let is_json = contents.trim_start().starts_with('{');
+    let m3u: MultivariantPlaylist = if is_json {
+        json::from_str(&contents).expect("Failed to read JSON playlist")
+    } else {
+        parser::parse_playlist(&contents).expect("Failed to parse file")
+    };
+    if let Err(report) = m3u.validate() {
+        report_findings(report.findings);
     }
+    match to.unwrap_or(if is_json { OutputFormat::M3u8 } else { OutputFormat::Json }) {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&m3u).unwrap()),
+        OutputFormat::M3u8 => print!("{}", m3u.to_m3u8().expect("Failed to write playlist")),
+        OutputFormat::Csv => print!("{}", ladder::to_csv(&m3u)),
+        OutputFormat::Md => print!("{}", report::markdown(&m3u, &m3u.findings())),
+        OutputFormat::Msgpack => io::stdout().write_all(&binary::to_msgpack(&m3u).unwrap()).expect("Failed to write output"),
+        OutputFormat::Cbor => io::stdout().write_all(&binary::to_cbor(&m3u).unwrap()).expect("Failed to write output"),
+    }
+}
 
-    if let Some(ch) = &args.audio_channels {
-        m3u = m3u.select_audio_by_channels(ch).expect("Failed to select audio channels");
+fn check_reachability(uri: &str, concurrency: usize, json: bool) {
+    let contents = fetch::fetch_string(uri).expect("Failed to read playlist");
+    let m3u = parser::parse_playlist(&contents).expect("Failed to parse file");
+    let results = reachability::check_all(uri, &m3u, concurrency);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&results).unwrap());
+    } else {
+        print!("{}", reachability::to_table(&results));
+    }
+    if results.iter().any(|r| !r.is_ok()) {
+        std::process::exit(1);
+    }
+}
+
+fn print_stats(uri: &str) {
+    let contents = fetch::fetch_string(uri).expect("Failed to read playlist");
+    let playlist = parser::parse_media_playlist(&contents).expect("Failed to parse file");
+    println!("segments: {}", playlist.segment_count());
+    println!("total duration: {:.3}s", playlist.total_duration());
+    if let Some(max) = playlist.max_segment_duration() {
+        println!("max segment duration: {:.3}s (target duration {}s)", max, playlist.target_duration);
+    }
+}
+
+fn print_timeline(uri: &str, format: &TimelineFormat, window: Option<usize>, measure: bool,
+    spot_check: Option<(usize, u64)>) {
+    let contents = fetch::fetch_string(uri).expect("Failed to read playlist");
+    let playlist = parser::parse_media_playlist(&contents).expect("Failed to parse file");
+    let mut entries = timeline::timeline(&playlist, window);
+    if let Some((n, seed)) = spot_check {
+        entries = timeline::sample(entries, n, seed);
+    }
+    if measure {
+        timeline::measure(&mut entries, uri).expect("Failed to measure segments");
     }
+    match format {
+        TimelineFormat::Csv => print!("{}", timeline::to_csv(&entries)),
+        TimelineFormat::Json => println!("{}", serde_json::to_string_pretty(&entries).unwrap()),
+    }
+}
+
+fn extract_thumbnails(uri: &str, at: &str, out: &PathBuf) {
+    let at = thumbnails::parse_timestamp(at).expect("Failed to parse timestamp");
+    let contents = fetch::fetch_string(uri).expect("Failed to read playlist");
+    let m3u = parser::parse_playlist(&contents).expect("Failed to parse file");
+    let thumbs = thumbnails::extract(uri, &m3u, at).expect("Failed to extract thumbnails");
+    fs::create_dir_all(out).expect("Failed to create output directory");
+    for t in &thumbs {
+        let path = out.join(t.file_name());
+        fs::write(&path, &t.data).expect("Failed to write thumbnail");
+        println!("{} ({:.3}s, {} bytes) -> {}", t.uri, t.time, t.data.len(), path.display());
+    }
+}
 
-    if let Some(bw) = &args.max_bandwidth {
-        m3u = m3u.select_max_bandwidth(*bw).expect("Failed to select by max bandwidth");
+fn run(args: &Args) {
+    if args.emit_schema {
+        println!("{}", serde_json::to_string_pretty(&schema::multivariant_playlist_schema()).unwrap());
+        return
+    }
+    let uri = args.uri.as_deref().expect("--uri is required");
+    let contents = fetch::fetch_string(uri).expect("Failed to read playlist");
+
+    let options = parser::ParseOptions{lenient: args.lenient, passthrough: args.passthrough, preserve: args.preserve};
+    let (playlist, warnings) = parser::parse_with_options(&contents, &options)
+        .expect("Failed to parse file");
+    for w in &warnings {
+        eprintln!("warning: {}", w);
+    }
+
+    // Filters only apply to multivariant playlists
+    let mut m3u = match playlist {
+        Playlist::Multivariant(m3u) => m3u,
+        Playlist::Media(_) if args.dry_run => {
+            println!("[]");
+            return
+        },
+        Playlist::Media(media) => {
+            if matches!(args.format, OutputFormat::M3u8 | OutputFormat::Csv | OutputFormat::Md) {
+                panic!("m3u8, csv and md output are only supported for multivariant playlists");
+            }
+            report_findings(media.findings());
+            let output = MediaOutput{ kind: media.kind(), playlist: &media };
+            match args.format {
+                OutputFormat::Msgpack => write_bytes(args, &binary::to_msgpack(&output).unwrap()),
+                OutputFormat::Cbor => write_bytes(args, &binary::to_cbor(&output).unwrap()),
+                _ => write_output(args, &json::to_string_pretty(&output, &json_style(args)).unwrap()),
+            }
+            return
+        }
+    };
+
+    let original = m3u.clone();
+    let (selector, mut errors) = selector(args);
+    match selector.apply(&m3u) {
+        Ok(pl) if errors.is_empty() => m3u = pl,
+        Ok(_) => (),
+        Err(e) => errors.extend(e),
+    }
+    if !errors.is_empty() {
+        for e in errors {
+            eprintln!("error: {}", e);
+        }
+        std::process::exit(1)
     }
 
-    if let Some(resstr) = &args.resolution {
-        let res = parser::parse_resolution_param(resstr).expect("Failed to parse resolution param");
-        m3u = m3u.select_resolution(&res).expect("Failed to select by resolution");
+    let bounds = BandwidthBounds{ min_bpp: args.min_bpp, max_bpp: args.max_bpp, ..BandwidthBounds::default() };
+    if args.fix {
+        m3u = m3u.fix_bandwidths(&bounds);
+        m3u = m3u.add_audio_only_fallback().expect("Failed to add audio-only variant");
     }
 
-    if args.sort_by_bandwidth {
-        m3u.sort_by_bandwidth();
+    if args.dry_run {
+        println!("{}", serde_json::to_string_pretty(&plan::plan(&original, &m3u)).unwrap());
+        return
     }
 
-    m3u.validate().expect("Format validation error");
+    if args.format != OutputFormat::Json && args.hydrate {
+        panic!("--hydrate requires JSON output");
+    }
+
+    let mut validator = Validator::with_bandwidth_bounds(bounds);
+    if args.strict {
+        validator = validator.strict();
+    }
+    if let Some(base) = &args.base_url {
+        let base = PlaylistUri::parse(base).expect("Invalid base URL");
+        if let Some(e) = base.syntax_error() {
+            panic!("Invalid base URL {}: {}", base, e);
+        }
+        validator = validator.base_url(base);
+    }
+    let findings = m3u.findings_with_validator(validator);
+    // the report lists the findings, errors included
+    if args.format == OutputFormat::Md {
+        return write_output(args, &report::markdown(&m3u, &findings));
+    }
+    report_findings(findings);
+
+    if args.format == OutputFormat::M3u8 {
+        return write_output(args, &m3u.to_m3u8().expect("Failed to write playlist"));
+    }
+    if args.format == OutputFormat::Csv {
+        return write_output(args, &ladder::to_csv(&m3u));
+    }
+    if args.format == OutputFormat::Msgpack {
+        return write_bytes(args, &binary::to_msgpack(&m3u).unwrap());
+    }
+    if args.format == OutputFormat::Cbor {
+        return write_bytes(args, &binary::to_cbor(&m3u).unwrap());
+    }
+
+    if args.hydrate {
+        let children = load::fetch_children(uri, &m3u);
+        for e in children.iter().filter_map(|c| c.error.as_ref()) {
+            eprintln!("warning: {}", e);
+        }
+        let output = HydratedOutput{ playlist: &m3u, children };
+        write_output(args, &json::to_string_pretty(&output, &json_style(args)).unwrap());
+        return
+    }
 
-    println!("{}", serde_json::to_string_pretty(&m3u).unwrap());
+    write_output(args, &json::to_string_pretty(&m3u, &json_style(args)).unwrap());
 }
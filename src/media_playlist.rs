@@ -0,0 +1,669 @@
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, FixedOffset, TimeDelta};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use std::fmt;
+use std::time::Duration;
+
+use regex::Regex;
+use regex_static::once_cell::sync::Lazy;
+
+use crate::format::OpaqueLine;
+use crate::uri::PlaylistUri;
+use crate::validator::{Finding, Location, ValidationReport};
+
+// Partial implementation of Media Playlist format as defined in RFC 8216bis
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PlaylistType {
+    Event, Vod,
+}
+
+// How a media playlist changes on reload, see MediaPlaylist::kind
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PlaylistKind {
+    Live,  // sliding window, segments are added and removed
+    Event, // segments are only added
+    Vod,   // no more changes
+}
+
+// Sub-range of the resource at the segment URI, from EXT-X-BYTERANGE
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ByteRange {
+    pub length: u64,
+    pub offset: u64,
+}
+
+// EXT-X-PROGRAM-DATE-TIME as written, an ISO 8601 date and time with a time
+// zone. Converted to a timestamp with the chrono feature.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "String", try_from = "String"))]
+pub struct ProgramDateTime(String);
+
+static RE_DATE_TIME: Lazy<Regex> = regex_static::lazy_regex!(
+    r#"^[0-9]{4}-(0[1-9]|1[0-2])-(0[1-9]|[12][0-9]|3[01])[Tt]([01][0-9]|2[0-3]):[0-5][0-9]:([0-5][0-9]|60)(\.[0-9]+)?([Zz]|[+-]([01][0-9]|2[0-3])(:?[0-5][0-9])?)$"#);
+
+impl ProgramDateTime {
+    /* <YYYY-MM-DDThh:mm:ss.SSSZ>, offsets can also be written as +hh:mm, +hhmm or +hh */
+    pub fn parse(text: &str) -> Result<Self, String> {
+        if !RE_DATE_TIME.is_match(text) {
+            return Err(format!("Invalid date and time {:?}", text))
+        }
+        Ok(Self(text.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /* None for dates that do not exist, such as February 30 */
+    #[cfg(feature = "chrono")]
+    pub fn to_date_time(&self) -> Option<DateTime<FixedOffset>> {
+        crate::parser::parse_date_time(&self.0)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<DateTime<FixedOffset>> for ProgramDateTime {
+    fn from(dt: DateTime<FixedOffset>) -> Self {
+        Self(dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+    }
+}
+
+impl fmt::Display for ProgramDateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TryFrom<String> for ProgramDateTime {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::parse(&s)
+    }
+}
+
+impl From<ProgramDateTime> for String {
+    fn from(dt: ProgramDateTime) -> Self {
+        dt.0
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum KeyMethod {
+    Aes128, SampleAes, SampleAesCtr,
+}
+
+// Encryption of a segment, from EXT-X-KEY. METHOD=NONE leaves a segment without keys.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Key {
+    pub method: KeyMethod,
+    pub uri: PlaylistUri,
+    pub iv: Option<String>, // hexadecimal, with 0x prefix
+    pub keyformat: Option<String>,
+    pub keyformatversions: Option<String>,
+}
+
+// Partial segment of Low-Latency HLS, from EXT-X-PART
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Part {
+    pub uri: PlaylistUri,
+    pub duration: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub independent: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub byte_range: Option<ByteRange>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub gap: bool,
+}
+
+// Delta updates and blocking reloads offered by the server, from EXT-X-SERVER-CONTROL
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ServerControl {
+    pub can_skip_until: Option<f64>, // seconds
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub can_skip_dateranges: bool,
+    pub hold_back: Option<f64>,
+    pub part_hold_back: Option<f64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub can_block_reload: bool,
+}
+
+// Segments left out of a delta update, from EXT-X-SKIP
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Skip {
+    pub skipped_segments: u64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub recently_removed_dateranges: Vec<String>, // DATERANGE ids
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PreloadHintType {
+    Part, Map,
+}
+
+// Resource the client can request before it is available, from EXT-X-PRELOAD-HINT
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PreloadHint {
+    pub type_: PreloadHintType,
+    pub uri: PlaylistUri,
+    pub byte_range_start: Option<u64>,
+    pub byte_range_length: Option<u64>, // to the end of the resource when absent
+}
+
+// Latest segment and part of another rendition, from EXT-X-RENDITION-REPORT.
+// Absent numbers are the same as in this playlist.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RenditionReport {
+    pub uri: PlaylistUri, // relative to this playlist
+    pub last_msn: Option<u64>,
+    pub last_part: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Segment {
+    pub uri: PlaylistUri,
+    pub duration: f64, // seconds, from EXTINF
+    pub title: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub byte_range: Option<ByteRange>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub discontinuity: bool, // preceded by EXT-X-DISCONTINUITY, see MediaPlaylist::discontinuity_sequences
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub program_date_time: Option<ProgramDateTime>, // from EXT-X-PROGRAM-DATE-TIME, see MediaPlaylist::date_times
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub keys: Vec<Key>, // in effect for the segment, one per KEYFORMAT
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub parts: Vec<Part>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub gap: bool, // preceded by EXT-X-GAP, the resource is missing
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bitrate: Option<u64>, // kbps, from the last EXT-X-BITRATE
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MediaPlaylist {
+    pub version: Option<u64>,
+    pub target_duration: u64,
+    pub media_sequence: u64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub discontinuity_sequence: u64, // of the first segment
+    pub playlist_type: Option<PlaylistType>,
+    pub end_list: bool,
+    pub independent_segments: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub i_frames_only: bool, // segments are single I-frames, for trick play
+    pub segments: Vec<Segment>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub part_target: Option<f64>, // from EXT-X-PART-INF
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub trailing_parts: Vec<Part>, // parts of the segment still being produced
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub server_control: Option<ServerControl>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub skip: Option<Skip>, // segments before the first one listed are skipped
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub preload_hints: Vec<PreloadHint>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rendition_reports: Vec<RenditionReport>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unrecognized: Vec<OpaqueLine>,
+}
+
+impl Default for MediaPlaylist {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// None for durations too long to add to a date time
+#[cfg(feature = "chrono")]
+pub(crate) fn seconds(s: f64) -> Option<TimeDelta> {
+    let us = (s * 1e6).round();
+    (us.abs() < i64::MAX as f64).then(|| TimeDelta::microseconds(us as i64))
+}
+
+impl MediaPlaylist {
+    pub fn new() -> Self {
+        Self{
+            version: None,
+            target_duration: 0,
+            media_sequence: 0,
+            discontinuity_sequence: 0,
+            playlist_type: None,
+            end_list: false,
+            independent_segments: false,
+            i_frames_only: false,
+            segments: vec![],
+            part_target: None,
+            trailing_parts: vec![],
+            server_control: None,
+            skip: None,
+            preload_hints: vec![],
+            rendition_reports: vec![],
+            unrecognized: vec![],
+        }
+    }
+
+    /* VOD when EXT-X-PLAYLIST-TYPE is VOD or the playlist has EXT-X-ENDLIST, event
+       for EXT-X-PLAYLIST-TYPE EVENT without EXT-X-ENDLIST, live otherwise */
+    pub fn kind(&self) -> PlaylistKind {
+        match (&self.playlist_type, self.end_list) {
+            (Some(PlaylistType::Vod), _) | (_, true) => PlaylistKind::Vod,
+            (Some(PlaylistType::Event), false) => PlaylistKind::Event,
+            (None, false) => PlaylistKind::Live,
+        }
+    }
+
+    pub fn is_live(&self) -> bool {
+        self.kind() == PlaylistKind::Live
+    }
+
+    pub fn is_event(&self) -> bool {
+        self.kind() == PlaylistKind::Event
+    }
+
+    pub fn is_vod(&self) -> bool {
+        self.kind() == PlaylistKind::Vod
+    }
+
+    /* Media sequence number of the segment at `index` in `segments`, which come after those skipped by EXT-X-SKIP.
+       Saturates at u64::MAX. */
+    pub fn sequence(&self, index: usize) -> u64 {
+        self.media_sequence.saturating_add(self.skip.as_ref().map_or(0, |s| s.skipped_segments)).saturating_add(index as u64)
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /* Sum of the EXTINF durations, seconds */
+    pub fn total_duration(&self) -> f64 {
+        self.segments.iter().map(|s| s.duration).sum()
+    }
+
+    /* Longest EXTINF duration, None without segments */
+    pub fn max_segment_duration(&self) -> Option<f64> {
+        self.segments.iter().map(|s| s.duration).reduce(f64::max)
+    }
+
+    /*
+    Playlist of the segments overlapping the window between `from` and `to`, measured
+    from the start of the first segment. Media and discontinuity sequence numbers
+    follow the first segment kept, which gets an EXT-X-PROGRAM-DATE-TIME when its
+    date time is known. Parts and preload hints are dropped unless the window
+    reaches the last segment.
+    */
+    pub fn slice(&self, from: Duration, to: Duration) -> Self {
+        let (from, to) = (from.as_secs_f64(), to.as_secs_f64());
+        let mut start = 0.0;
+        let mut kept = vec![];
+        for (i, seg) in self.segments.iter().enumerate() {
+            if start < to && start + seg.duration > from {
+                kept.push(i);
+            }
+            start += seg.duration;
+        }
+        let mut ret = self.clone();
+        let (Some(&first), Some(&last)) = (kept.first(), kept.last()) else {
+            ret.segments.clear();
+            ret.skip = None;
+            ret.trailing_parts.clear();
+            ret.preload_hints.clear();
+            return ret
+        };
+        ret.segments = self.segments[first..=last].to_vec();
+        if first > 0 {
+            ret.media_sequence = self.sequence(first);
+            // a discontinuity before the first segment only counts in the sequence number
+            ret.discontinuity_sequence = self.discontinuity_sequences()[first];
+            ret.segments[0].discontinuity = false;
+            ret.skip = None;
+        }
+        #[cfg(feature = "chrono")]
+        if ret.segments[0].program_date_time.is_none() {
+            ret.segments[0].program_date_time = self.date_times()[first].map(ProgramDateTime::from);
+        }
+        if last + 1 < self.segments.len() {
+            ret.trailing_parts.clear();
+            ret.preload_hints.clear();
+        }
+        ret
+    }
+
+    /* Discontinuity sequence number of every segment, incremented after each EXT-X-DISCONTINUITY.
+       Saturates at u64::MAX. */
+    pub fn discontinuity_sequences(&self) -> Vec<u64> {
+        let mut seq = self.discontinuity_sequence;
+        self.segments.iter().map(|seg| {
+            if seg.discontinuity {
+                seq = seq.saturating_add(1);
+            }
+            seq
+        }).collect()
+    }
+
+    /*
+    Wall-clock time of every segment: its PROGRAM-DATE-TIME, otherwise the end of
+    the previous segment. Segments before the first PROGRAM-DATE-TIME are
+    extrapolated backwards from it. None past a duration that leaves the range of
+    date times.
+    */
+    #[cfg(feature = "chrono")]
+    pub fn date_times(&self) -> Vec<Option<DateTime<FixedOffset>>> {
+        let mut ret = Vec::with_capacity(self.segments.len());
+        let mut next: Option<DateTime<FixedOffset>> = None;
+        for seg in &self.segments {
+            let dt = seg.program_date_time.as_ref().and_then(ProgramDateTime::to_date_time).or(next);
+            next = dt.and_then(|dt| dt.checked_add_signed(seconds(seg.duration)?));
+            ret.push(dt);
+        }
+        let Some(first) = ret.iter().position(|dt| dt.is_some()) else { return ret };
+        let mut dt = ret[first];
+        for i in (0..first).rev() {
+            dt = dt.and_then(|dt| dt.checked_sub_signed(seconds(self.segments[i].duration)?));
+            ret[i] = dt;
+        }
+        ret
+    }
+
+    /*
+    Checked rules of segments:
+    * The EXTINF duration of each segment, rounded to the nearest integer, MUST
+        be less than or equal to the target duration.
+    * A VOD playlist cannot change, it should have EXT-X-ENDLIST (warning).
+
+    Checked rules of I-frame playlists:
+    * EXT-X-I-FRAMES-ONLY REQUIRES EXT-X-VERSION 4 or greater.
+    * Segments without EXT-X-BYTERANGE must be resources holding a single
+        I-frame, which cannot be checked from the playlist (warning).
+
+    Checked rules of Low-Latency HLS:
+    * CAN-SKIP-UNTIL MUST be at least six times the target duration, and is
+        required by CAN-SKIP-DATERANGES and EXT-X-SKIP.
+    * HOLD-BACK MUST be at least three times the target duration.
+    * PART-HOLD-BACK is REQUIRED with EXT-X-PART-INF and MUST be at least twice
+        the part target duration. At least three times is recommended (warning).
+    * Servers producing partial segments should support blocking reloads (warning).
+    */
+    pub fn findings(&self) -> Vec<Finding> {
+        let mut ret = vec![];
+        for (i, seg) in self.segments.iter().enumerate() {
+            if seg.duration.round() > self.target_duration as f64 {
+                ret.push(Finding::error("extinf-above-target-duration",
+                    format!("EXTINF {} of segment {} exceeds EXT-X-TARGETDURATION {}",
+                        seg.duration, self.sequence(i), self.target_duration))
+                    .at(Location{ tag: "EXTINF", index: i }));
+            }
+        }
+        if self.playlist_type == Some(PlaylistType::Vod) && !self.end_list {
+            ret.push(Finding::warning("vod-without-endlist", "EXT-X-PLAYLIST-TYPE VOD without EXT-X-ENDLIST".to_string()));
+        }
+        if self.i_frames_only {
+            if self.version.unwrap_or(1) < 4 {
+                ret.push(Finding::error("i-frames-only-version", "EXT-X-I-FRAMES-ONLY requires EXT-X-VERSION 4".to_string()));
+            }
+            let whole = self.segments.iter().filter(|s| s.byte_range.is_none()).count();
+            if whole > 0 {
+                ret.push(Finding::warning("i-frame-segment-without-byterange", format!(
+                    "{} segments of the I-frame playlist without EXT-X-BYTERANGE, each must be a single I-frame", whole)));
+            }
+        }
+        let default = ServerControl::default();
+        let sc = self.server_control.as_ref().unwrap_or(&default);
+        let td = self.target_duration as f64;
+        match sc.can_skip_until {
+            Some(v) if v < 6.0 * td =>
+                ret.push(Finding::error("can-skip-until-too-low", format!("CAN-SKIP-UNTIL {} below six times the target duration", v))),
+            Some(_) => (),
+            None => {
+                if sc.can_skip_dateranges {
+                    ret.push(Finding::error("can-skip-dateranges-without-can-skip-until",
+                        "CAN-SKIP-DATERANGES without CAN-SKIP-UNTIL".to_string()));
+                }
+                if self.skip.is_some() {
+                    ret.push(Finding::error("skip-without-can-skip-until", "EXT-X-SKIP without CAN-SKIP-UNTIL".to_string()));
+                }
+            }
+        }
+        if let Some(v) = sc.hold_back.filter(|v| *v < 3.0 * td) {
+            ret.push(Finding::error("hold-back-too-low", format!("HOLD-BACK {} below three times the target duration", v)));
+        }
+        if let Some(pt) = self.part_target {
+            match sc.part_hold_back {
+                None => ret.push(Finding::error("part-hold-back-missing", "EXT-X-PART-INF without PART-HOLD-BACK".to_string())),
+                Some(v) if v < 2.0 * pt =>
+                    ret.push(Finding::error("part-hold-back-too-low", format!("PART-HOLD-BACK {} below twice the part target", v))),
+                Some(v) if v < 3.0 * pt =>
+                    ret.push(Finding::warning("part-hold-back-low", format!("PART-HOLD-BACK {} below three times the part target", v))),
+                Some(_) => (),
+            }
+            if !sc.can_block_reload {
+                ret.push(Finding::warning("parts-without-blocking-reload",
+                    "Partial segments without CAN-BLOCK-RELOAD".to_string()));
+            }
+        }
+        ret
+    }
+
+    /*
+    Rules between two loads of the same media playlist, `previous` loaded first:
+    * EXT-X-MEDIA-SEQUENCE and EXT-X-DISCONTINUITY-SEQUENCE MUST NOT decrease.
+    * Segments still listed keep their URI and duration.
+    * A playlist with EXT-X-ENDLIST MUST NOT change.
+    */
+    pub fn reload_findings(&self, previous: &MediaPlaylist) -> Vec<Finding> {
+        let mut ret = vec![];
+        if self.media_sequence < previous.media_sequence {
+            ret.push(Finding::error("media-sequence-decreased", format!("EXT-X-MEDIA-SEQUENCE decreased from {} to {}",
+                previous.media_sequence, self.media_sequence)));
+        }
+        if self.discontinuity_sequence < previous.discontinuity_sequence {
+            ret.push(Finding::error("discontinuity-sequence-decreased", format!("EXT-X-DISCONTINUITY-SEQUENCE decreased from {} to {}",
+                previous.discontinuity_sequence, self.discontinuity_sequence)));
+        }
+        for (i, seg) in self.segments.iter().enumerate() {
+            let sequence = self.sequence(i);
+            let Some(prev) = sequence.checked_sub(previous.sequence(0))
+                .and_then(|j| previous.segments.get(j as usize)) else { continue };
+            if prev.uri != seg.uri || prev.duration != seg.duration {
+                ret.push(Finding::error("segment-changed", format!("Segment {} changed from {} to {}", sequence, prev.uri, seg.uri))
+                    .at(Location{ tag: "EXTINF", index: i }));
+            }
+        }
+        let changed = self.media_sequence != previous.media_sequence
+            || self.sequence(self.segments.len()) != previous.sequence(previous.segments.len());
+        if previous.end_list && changed {
+            ret.push(Finding::error("changed-after-endlist", "Playlist changed after EXT-X-ENDLIST".to_string()));
+        }
+        ret
+    }
+
+    /* All findings, as an error if any of them is one */
+    pub fn validate(&self) -> Result<ValidationReport, ValidationReport> {
+        ValidationReport::from(self.findings()).into_result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::validator::Severity;
+    use crate::parser;
+    use super::{MediaPlaylist, PlaylistKind, PlaylistType, ServerControl, Skip};
+
+    fn messages(pl: &MediaPlaylist, severity: Severity) -> Vec<String> {
+        pl.findings().into_iter().filter(|f| f.severity == severity).map(|f| f.message).collect()
+    }
+
+    #[test]
+    fn test_segment_findings() {
+        let mut pl = parser::parse_media_playlist(include_str!("../data/media_playlist.m3u8")).unwrap();
+        // 10.01 rounds to the target duration
+        assert!(pl.findings().is_empty());
+        pl.segments[1].duration = 10.5;
+        assert_eq!(messages(&pl, Severity::Error), ["EXTINF 10.5 of segment 1 exceeds EXT-X-TARGETDURATION 10"]);
+        pl.segments[1].duration = 10.0;
+        pl.end_list = false;
+        assert_eq!(messages(&pl, Severity::Warning), ["EXT-X-PLAYLIST-TYPE VOD without EXT-X-ENDLIST"]);
+    }
+
+    #[test]
+    fn test_reload_findings() {
+        let previous = parser::parse_media_playlist(include_str!("../data/live_playlist.m3u8")).unwrap();
+        let mut pl = previous.clone();
+        pl.segments.remove(0);
+        pl.media_sequence += 1;
+        assert!(pl.reload_findings(&previous).is_empty());
+        assert_eq!(previous.reload_findings(&pl)[0].message, "EXT-X-MEDIA-SEQUENCE decreased from 1201 to 1200");
+
+        pl.segments[0].uri = "other.ts".parse().unwrap();
+        pl.discontinuity_sequence = 6;
+        let messages: Vec<String> = pl.reload_findings(&previous).into_iter().map(|f| f.message).collect();
+        assert_eq!(messages, ["EXT-X-DISCONTINUITY-SEQUENCE decreased from 7 to 6",
+            "Segment 1201 changed from live_1201.ts to other.ts"]);
+
+        // delta update of the same window
+        let mut delta = previous.clone();
+        delta.segments.drain(..4);
+        delta.skip = Some(Skip{ skipped_segments: 4, recently_removed_dateranges: vec![] });
+        assert!(delta.reload_findings(&previous).is_empty());
+        delta.segments[0].uri = "other.ts".parse().unwrap();
+        let messages: Vec<String> = delta.reload_findings(&previous).into_iter().map(|f| f.message).collect();
+        assert_eq!(messages, ["Segment 1204 changed from ad_1.ts to other.ts"]);
+
+        let vod = parser::parse_media_playlist(include_str!("../data/media_playlist.m3u8")).unwrap();
+        let mut pl = vod.clone();
+        assert!(pl.reload_findings(&vod).is_empty());
+        pl.segments.pop();
+        assert_eq!(pl.reload_findings(&vod)[0].message, "Playlist changed after EXT-X-ENDLIST");
+    }
+
+    #[test]
+    fn test_kind() {
+        let pl = parser::parse_media_playlist(include_str!("../data/media_playlist.m3u8")).unwrap();
+        assert!(pl.is_vod() && !pl.is_live() && !pl.is_event());
+        let mut pl = parser::parse_media_playlist(include_str!("../data/live_playlist.m3u8")).unwrap();
+        assert!(pl.is_live() && !pl.is_vod() && !pl.is_event());
+        pl.playlist_type = Some(PlaylistType::Event);
+        assert_eq!(pl.kind(), PlaylistKind::Event);
+        pl.end_list = true;
+        assert_eq!(pl.kind(), PlaylistKind::Vod);
+    }
+
+    #[test]
+    fn test_durations() {
+        let pl = parser::parse_media_playlist(include_str!("../data/media_playlist.m3u8")).unwrap();
+        assert_eq!(pl.segment_count(), 5);
+        assert!((pl.total_duration() - 44.168).abs() < 1e-9);
+        assert_eq!(pl.max_segment_duration(), Some(10.01));
+        assert_eq!(MediaPlaylist::new().max_segment_duration(), None);
+        assert_eq!(MediaPlaylist::new().total_duration(), 0.0);
+    }
+
+    #[test]
+    fn test_sequence() {
+        let mut pl = parser::parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n\
+            #EXT-X-MEDIA-SEQUENCE:18446744073709551615\n#EXTINF:10,\na.ts\n#EXTINF:10,\nb.ts\n").unwrap();
+        assert_eq!(pl.sequence(0), u64::MAX);
+        assert_eq!(pl.sequence(1), u64::MAX);
+        pl.media_sequence = 10;
+        pl.skip = Some(Skip{ skipped_segments: u64::MAX - 10, recently_removed_dateranges: vec![] });
+        assert_eq!(pl.sequence(2), u64::MAX);
+    }
+
+    #[test]
+    fn test_slice() {
+        let data = include_str!("../data/live_playlist.m3u8");
+        let pl = parser::parse_media_playlist(data).unwrap();
+        let secs = Duration::from_secs_f64;
+        // 18.0 to 25.0 overlaps ad_0.ts (17.984 to 23.984) and the segment after it
+        let clip = pl.slice(secs(18.0), secs(25.0));
+        assert_eq!(clip.segments.len(), 2);
+        assert_eq!(clip.segments[0].uri, "ad_0.ts");
+        assert_eq!(clip.media_sequence, 1203);
+        assert_eq!(clip.discontinuity_sequence, 8);
+        assert!(!clip.segments[0].discontinuity);
+        assert_eq!(clip.discontinuity_sequences(), [8, 8]);
+        assert!(clip.validate().is_ok());
+        #[cfg(feature = "chrono")]
+        assert_eq!(pl.slice(secs(6.5), secs(7.0)).segments[0].program_date_time.as_ref().map(|dt| dt.as_str()),
+            Some("2024-05-01T12:00:06.006Z"));
+
+        // segments ending at the window start are left out
+        assert_eq!(pl.slice(secs(17.984), secs(18.0)).segments[0].uri, "ad_0.ts");
+        assert_eq!(pl.slice(Duration::ZERO, Duration::MAX).segments.len(), 6);
+        assert_eq!(pl.slice(Duration::ZERO, Duration::MAX).media_sequence, 1200);
+        let empty = pl.slice(secs(100.0), secs(200.0));
+        assert!(empty.segments.is_empty());
+        assert_eq!(empty.media_sequence, 1200);
+    }
+
+    #[test]
+    fn test_i_frames_only_findings() {
+        let data = include_str!("../data/thumbnails/iframes_720p.m3u8");
+        let mut pl = parser::parse_media_playlist(data).unwrap();
+        assert!(pl.i_frames_only);
+        assert!(pl.findings().is_empty());
+        pl.version = Some(3);
+        assert_eq!(messages(&pl, Severity::Error), ["EXT-X-I-FRAMES-ONLY requires EXT-X-VERSION 4"]);
+
+        let data = include_str!("../data/thumbnails/iframes_360p.m3u8");
+        let pl = parser::parse_media_playlist(data).unwrap();
+        assert!(pl.validate().is_ok());
+        assert_eq!(messages(&pl, Severity::Warning),
+            ["4 segments of the I-frame playlist without EXT-X-BYTERANGE, each must be a single I-frame"]);
+
+        let pl = parser::parse_media_playlist(include_str!("../data/media_playlist.m3u8")).unwrap();
+        assert!(!pl.i_frames_only);
+    }
+
+    #[test]
+    fn test_server_control_findings() {
+        let mut pl = MediaPlaylist::new();
+        pl.target_duration = 4;
+        assert!(pl.findings().is_empty());
+
+        pl.server_control = Some(ServerControl{ can_skip_until: Some(12.0), hold_back: Some(8.0), ..Default::default() });
+        assert_eq!(messages(&pl, Severity::Error), [
+            "CAN-SKIP-UNTIL 12 below six times the target duration",
+            "HOLD-BACK 8 below three times the target duration",
+        ]);
+
+        pl.server_control = Some(ServerControl{ can_skip_dateranges: true, ..Default::default() });
+        pl.skip = Some(Skip{ skipped_segments: 3, recently_removed_dateranges: vec![] });
+        assert_eq!(messages(&pl, Severity::Error), [
+            "CAN-SKIP-DATERANGES without CAN-SKIP-UNTIL",
+            "EXT-X-SKIP without CAN-SKIP-UNTIL",
+        ]);
+        pl.skip = None;
+
+        pl.part_target = Some(1.0);
+        pl.server_control = None;
+        assert_eq!(messages(&pl, Severity::Error), ["EXT-X-PART-INF without PART-HOLD-BACK"]);
+        pl.server_control = Some(ServerControl{ part_hold_back: Some(1.5), ..Default::default() });
+        assert_eq!(messages(&pl, Severity::Error), ["PART-HOLD-BACK 1.5 below twice the part target"]);
+        pl.server_control = Some(ServerControl{ part_hold_back: Some(2.5), ..Default::default() });
+        assert!(pl.validate().is_ok());
+        assert_eq!(messages(&pl, Severity::Warning), [
+            "PART-HOLD-BACK 2.5 below three times the part target",
+            "Partial segments without CAN-BLOCK-RELOAD",
+        ]);
+    }
+}
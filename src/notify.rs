@@ -0,0 +1,282 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::uri::PlaylistUri;
+
+// Notification targets for alerts raised while following a playlist, selected by
+// the scheme of their URL:
+//
+//     https://example.com/hook                   JSON {"subject", "message"} posted as is
+//     slack+https://hooks.slack.com/services/..  Slack incoming webhook
+//     pagerduty://<routing key>                  PagerDuty Events API v2, a trigger event
+//     smtp://mail.example.com:25/ops@example.com?from=m3u@example.com
+//                                                plain SMTP, no TLS nor authentication
+
+pub trait Notifier {
+    fn notify(&self, subject: &str, message: &str) -> Result<(), String>;
+}
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn post_json(url: &str, payload: &Value) -> Result<(), String> {
+    let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+    agent.post(url).set("Content-Type", "application/json").send_string(&payload.to_string())
+        .map(|_| ()).map_err(|e| format!("Failed to notify {}: {}", url, e))
+}
+
+pub struct Webhook {
+    pub url: String,
+}
+
+impl Webhook {
+    pub fn payload(&self, subject: &str, message: &str) -> Value {
+        json!({"subject": subject, "message": message})
+    }
+}
+
+impl Notifier for Webhook {
+    fn notify(&self, subject: &str, message: &str) -> Result<(), String> {
+        post_json(&self.url, &self.payload(subject, message))
+    }
+}
+
+pub struct Slack {
+    pub url: String, // incoming webhook
+}
+
+impl Slack {
+    pub fn payload(&self, subject: &str, message: &str) -> Value {
+        json!({"text": format!("*{}*\n{}", subject, message)})
+    }
+}
+
+impl Notifier for Slack {
+    fn notify(&self, subject: &str, message: &str) -> Result<(), String> {
+        post_json(&self.url, &self.payload(subject, message))
+    }
+}
+
+pub struct PagerDuty {
+    pub routing_key: String,
+    pub url: String, // Events API endpoint
+}
+
+impl PagerDuty {
+    pub fn new(routing_key: &str) -> Self {
+        Self{ routing_key: routing_key.to_string(), url: "https://events.pagerduty.com/v2/enqueue".to_string() }
+    }
+
+    pub fn payload(&self, subject: &str, message: &str) -> Value {
+        json!({
+            "routing_key": self.routing_key,
+            "event_action": "trigger",
+            "payload": {
+                "summary": subject,
+                "source": "m3u_parser",
+                "severity": "warning",
+                "custom_details": {"message": message},
+            },
+        })
+    }
+}
+
+impl Notifier for PagerDuty {
+    fn notify(&self, subject: &str, message: &str) -> Result<(), String> {
+        post_json(&self.url, &self.payload(subject, message))
+    }
+}
+
+pub struct Smtp {
+    pub server: String, // host:port
+    pub from: String,
+    pub to: String,
+    pub timeout: Duration, // of connecting and of each read and write
+}
+
+impl Smtp {
+    /* Headers and body of the mail, lines starting with a dot are escaped */
+    pub fn mail(&self, subject: &str, message: &str) -> String {
+        let mut ret = format!("From: {}\r\nTo: {}\r\nSubject: {}\r\n\r\n", self.from, self.to, subject);
+        for line in message.lines() {
+            if line.starts_with('.') {
+                ret.push('.');
+            }
+            ret += line;
+            ret += "\r\n";
+        }
+        ret
+    }
+}
+
+// Send a command, or only read the greeting, and check the reply code
+fn smtp_command(stream: &mut TcpStream, reader: &mut impl BufRead, command: Option<&str>, expected: &[&str])
+    -> Result<(), String> {
+    if let Some(c) = command {
+        stream.write_all(format!("{}\r\n", c).as_bytes()).map_err(|e| format!("SMTP: {}", e))?;
+    }
+    let mut line = String::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line).map_err(|e| format!("SMTP: {}", e))?;
+        // multiline replies continue with "code-"
+        if line.len() < 4 || line.as_bytes()[3] != b'-' {
+            break
+        }
+    }
+    if !expected.iter().any(|code| line.starts_with(code)) {
+        return Err(format!("SMTP: unexpected reply {:?} to {}", line.trim_end(), command.unwrap_or("connection")))
+    }
+    Ok(())
+}
+
+impl Notifier for Smtp {
+    fn notify(&self, subject: &str, message: &str) -> Result<(), String> {
+        let connect_error = |e: std::io::Error| format!("Failed to connect to {}: {}", self.server, e);
+        let mut stream = Err(format!("Failed to connect to {}: no address", self.server));
+        for addr in self.server.to_socket_addrs().map_err(connect_error)? {
+            stream = TcpStream::connect_timeout(&addr, self.timeout).map_err(connect_error);
+            if stream.is_ok() {
+                break
+            }
+        }
+        let mut stream = stream?;
+        stream.set_read_timeout(Some(self.timeout)).and_then(|_| stream.set_write_timeout(Some(self.timeout)))
+            .map_err(|e| format!("SMTP: {}", e))?;
+        let mut reader = BufReader::new(stream.try_clone().map_err(|e| format!("SMTP: {}", e))?);
+        smtp_command(&mut stream, &mut reader, None, &["220"])?;
+        smtp_command(&mut stream, &mut reader, Some("HELO m3u_parser"), &["250"])?;
+        smtp_command(&mut stream, &mut reader, Some(&format!("MAIL FROM:<{}>", self.from)), &["250"])?;
+        smtp_command(&mut stream, &mut reader, Some(&format!("RCPT TO:<{}>", self.to)), &["250", "251"])?;
+        smtp_command(&mut stream, &mut reader, Some("DATA"), &["354"])?;
+        smtp_command(&mut stream, &mut reader, Some(&format!("{}.", self.mail(subject, message))), &["250"])?;
+        smtp_command(&mut stream, &mut reader, Some("QUIT"), &["221"])
+    }
+}
+
+// Percent-decode an URL component, None when it is not valid UTF-8 once decoded
+fn decode(s: &str) -> Option<String> {
+    let mut ret = vec![];
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        if b != b'%' {
+            ret.push(b);
+            continue
+        }
+        let hex = [bytes.next()?, bytes.next()?];
+        ret.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+    }
+    String::from_utf8(ret).ok()
+}
+
+/* Notifier for the URL, by scheme, see above */
+pub fn notifier(url: &str) -> Result<Box<dyn Notifier>, String> {
+    let uri = PlaylistUri::parse(url)?;
+    let authority = uri.authority().filter(|a| !a.is_empty());
+    match (uri.scheme(), authority) {
+        (Some("http" | "https"), Some(_)) => Ok(Box::new(Webhook{ url: url.to_string() })),
+        (Some("slack+https"), Some(_)) => Ok(Box::new(Slack{ url: url["slack+".len()..].to_string() })),
+        (Some("pagerduty"), Some(key)) => Ok(Box::new(PagerDuty::new(key))),
+        (Some("smtp"), Some(server)) => {
+            let to = decode(uri.path().trim_start_matches('/')).filter(|to| !to.is_empty());
+            let from = uri.query_pairs().into_iter().find(|(n, _)| *n == "from").and_then(|(_, v)| decode(v));
+            match (to, from) {
+                (Some(to), Some(from)) => Ok(Box::new(Smtp{
+                    server: if server.contains(':') { server.to_string() } else { format!("{}:25", server) },
+                    from,
+                    to,
+                    timeout: TIMEOUT,
+                })),
+                _ => Err(format!("{}: expected smtp://host[:port]/recipient?from=sender", url)),
+            }
+        },
+        _ => Err(format!("Unsupported notification target {}", url)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::time::Duration;
+    use serde_json::json;
+    use super::{decode, notifier, Notifier, PagerDuty, Slack, Smtp, Webhook};
+
+    #[test]
+    fn test_payloads() {
+        let hook = Webhook{ url: "https://example.com/hook".to_string() };
+        assert_eq!(hook.payload("Skew", "drift 12s"), json!({"subject": "Skew", "message": "drift 12s"}));
+        let slack = Slack{ url: "https://hooks.slack.com/services/T/B/X".to_string() };
+        assert_eq!(slack.payload("Skew", "drift 12s"), json!({"text": "*Skew*\ndrift 12s"}));
+        let pd = PagerDuty::new("key123").payload("Skew", "drift 12s");
+        assert_eq!(pd["routing_key"], "key123");
+        assert_eq!(pd["event_action"], "trigger");
+        assert_eq!(pd["payload"]["summary"], "Skew");
+        let smtp = Smtp{ server: "localhost:25".to_string(), from: "a@example.com".to_string(), to: "b@example.com".to_string(),
+            timeout: Duration::from_secs(1) };
+        assert_eq!(smtp.mail("Skew", "drift 12s\n.hidden"),
+            "From: a@example.com\r\nTo: b@example.com\r\nSubject: Skew\r\n\r\ndrift 12s\r\n..hidden\r\n");
+    }
+
+    #[test]
+    fn test_notifier() {
+        assert!(notifier("https://example.com/hook").is_ok());
+        assert!(notifier("slack+https://hooks.slack.com/services/T/B/X").is_ok());
+        assert!(notifier("pagerduty://key123").is_ok());
+        assert!(notifier("smtp://mail.example.com/ops@example.com?from=m3u@example.com").is_ok());
+        assert!(notifier("smtp://mail.example.com/ops@example.com").is_err());
+        assert!(notifier("pagerduty://").is_err());
+        assert!(notifier("ftp://example.com/x").is_err());
+        assert!(notifier("hook.json").is_err());
+        assert!(notifier("smtp://mail.example.com/ops%40example.com?from=%ZZ").is_err());
+        assert_eq!(decode("ops%2Balerts%40example.com"), Some("ops+alerts@example.com".to_string()));
+        assert_eq!(decode("m3u@example.com%"), None);
+    }
+
+    #[test]
+    fn test_smtp_timeout() {
+        // accepts the connection but never greets
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let smtp = Smtp{ server: listener.local_addr().unwrap().to_string(), from: "a@example.com".to_string(),
+            to: "b@example.com".to_string(), timeout: Duration::from_millis(100) };
+        assert!(smtp.notify("Skew", "drift 12s").unwrap_err().starts_with("SMTP: "));
+    }
+
+    #[test]
+    fn test_smtp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut received = vec![];
+            stream.write_all(b"220 test\r\n").unwrap();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 0 {
+                let reply: &[u8] = match line.trim_end() {
+                    "DATA" => b"354 go on\r\n",
+                    "." => b"250 queued\r\n",
+                    "QUIT" => b"221 bye\r\n",
+                    l if l.starts_with("HELO") => b"250-test\r\n250 ok\r\n",
+                    l if l.starts_with("MAIL") || l.starts_with("RCPT") => b"250 ok\r\n",
+                    _ => b"",
+                };
+                stream.write_all(reply).unwrap();
+                received.push(line.trim_end().to_string());
+                line.clear();
+                if received.last().is_some_and(|l| l == "QUIT") {
+                    break
+                }
+            }
+            received
+        });
+        let url = format!("smtp://127.0.0.1:{}/ops%2Balerts@example.com?from=m3u%40example.com", port);
+        notifier(&url).unwrap().notify("Skew", "drift 12s").unwrap();
+        let received = server.join().unwrap();
+        assert_eq!(received[..3], ["HELO m3u_parser", "MAIL FROM:<m3u@example.com>", "RCPT TO:<ops+alerts@example.com>"]);
+        assert!(received.contains(&"Subject: Skew".to_string()));
+        assert_eq!(received.last().unwrap(), "QUIT");
+    }
+}
@@ -1,157 +1,119 @@
 use core::fmt;
-use std::collections::HashMap;
-
-use regex_static::once_cell::sync::Lazy;
-use regex::{Regex, Captures};
-use enum_extract_macro::EnumExtract;
-
-#[derive(Debug, EnumExtract)]
-enum AttributeValue<'a> {
-    Integer(u64),
-    Float(f64),
-    QuotedString(&'a str),
-    EnumeratedString(&'a str),
-    DecimalResolution(u64, u64),
-}
-
-type AttributeMap<'a> = HashMap<&'a str, AttributeValue<'a>>;
-
-#[derive(Debug, EnumExtract)]
-enum ParsedLine<'a> {
-    ExtM3U,
-    Tag(&'a str),
-    TagWithAttributes(&'a str, AttributeMap<'a>),
-    Uri(&'a str),
-    Empty,
-}
-
-// Consume regex in the beginning of the stream. If success, return caputres and tail of the string.
-// For optimization, the regexes should start with '^', but it is not necessary
-fn consume<'a>(s: &'a str, re: &Regex) -> Option<(Captures<'a>, &'a str)> {
-    if let Some(m) = re.captures_at(s, 0) {
-        let g0 = &m.get(0).unwrap();
-        if g0.start() == 0 { // verify once again that capture starts as 0
-            let tail = &s[g0.len()..];
-            return Some((m, tail))
-        }
-    }
-    None
-}
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, FixedOffset};
 
-static RE_RESOLUTION: Lazy<Regex> = regex_static::lazy_regex!(r#"^([0-9]+)x([0-9]+)$"#);
+use crate::codecs::Codecs;
+use crate::format;
+use crate::media_playlist::{
+    ByteRange, Key, KeyMethod, MediaPlaylist, Part, PlaylistType, PreloadHint, PreloadHintType, ProgramDateTime,
+    RenditionReport, Segment, ServerControl, Skip,
+};
+use crate::playlist::Playlist;
+use crate::uri::PlaylistUri;
+use crate::registry::{self, AttributeError};
+use crate::suggest;
+use crate::tokenizer::{AttributeMap, AttributeValue, Expected, ParsedLine, SyntaxErrorKind, parse_line, parse_resolution};
 
-fn parse_resolution(res: &str) -> Option<AttributeValue> {
-    if let Some(m) = RE_RESOLUTION.captures(res) {
-        Some(AttributeValue::DecimalResolution(
-            m.get(1)?.as_str().parse().ok()?,
-            m.get(2)?.as_str().parse().ok()?))
-    } else {
-        None
-    }
-}
-
-static RE_ATTRIBUTE_VALUE: Lazy<Regex> = 
-    regex_static::lazy_regex!(r#"^([0-9]+\.[0-9]+)|^"([^"]+)"|^([[:alpha:]-]+)|^([0-9]+x[0-9]+)|^([0-9]+)"#);
-
-// TODO: more verbose parse error
-fn parse_attribute_value<'a>(value: &'a str) -> Option<(&'a str, AttributeValue<'a>)> {
-    if let Some((m, tail)) = consume(value, &RE_ATTRIBUTE_VALUE) {
-        let av =
-            if let Some(mf) = m.get(1) {
-                AttributeValue::Float(mf.as_str().parse::<f64>().ok()?)
-            } else if let Some(mqs) = m.get(2) {
-                AttributeValue::QuotedString(mqs.as_str())
-            } else if let Some(mes) = m.get(3) {
-                AttributeValue::EnumeratedString(mes.as_str())
-            } else if let Some(mres) = m.get(4) {
-                parse_resolution(mres.as_str()).unwrap()
-            } else if let Some(mdec) = m.get(5) {
-                AttributeValue::Integer(mdec.as_str().parse::<u64>().ok()?)
-            } else {
-                panic!("unexpected parser state")
-            };
-        Some((tail, av))
-    } else {
-        None
-    }
+// Machine-readable reason of a parse failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    MissingExtM3U,
+    Syntax(Expected),
+    DuplicateAttribute,
+    InvalidTag(&'static str),
+    MissingAttribute(&'static str, &'static str), // tag, attribute
+    InvalidAttribute(&'static str, &'static str), // tag, attribute
+    MissingTag(&'static str),
+    DuplicateTag(&'static str),
+    ExpectedUri,
+    InvalidUri,
+    UnexpectedLine,
+    Truncated,
+    EmptyPlaylist,
 }
 
-static RE_ATTRIBUTE_NAME : Lazy<Regex> = regex_static::lazy_regex!(r#"^([[:alpha:]-]+)="#);
-
-fn parse_attributes<'a>(value: &'a str) -> Option<AttributeMap<'a>> {
-    let mut tail = value;
-    let mut result = AttributeMap::new();
-    while !tail.is_empty() {
-        let Some((mkey, t)) = consume(tail, &RE_ATTRIBUTE_NAME)
-        else { return None };
-        let key = mkey.get(1).unwrap().as_str();
-        tail = t;
-        let Some((t, av)) = parse_attribute_value(tail)
-        else { return None };
-        result.insert(key, av);
-        if t.is_empty() { break }
-        if !t.starts_with(",") { return None } // consume trailing comma
-        tail = &t[1..];
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::MissingExtM3U => write!(f, "No #EXTM3U"),
+            ParseErrorKind::Syntax(e) => write!(f, "Failed to parse line, expected {}", e),
+            ParseErrorKind::DuplicateAttribute => write!(f, "Attribute repeated in the attribute list"),
+            ParseErrorKind::InvalidTag(tag) => write!(f, "Failed to interpret {}", tag),
+            ParseErrorKind::MissingAttribute(tag, name) => write!(f, "Missing mandatory attribute {} of {}", name, tag),
+            ParseErrorKind::InvalidAttribute(tag, name) => write!(f, "Invalid value of attribute {} of {}", name, tag),
+            ParseErrorKind::MissingTag(tag) => write!(f, "Missing mandatory tag {}", tag),
+            ParseErrorKind::DuplicateTag(tag) => write!(f, "More than one {}", tag),
+            ParseErrorKind::ExpectedUri => write!(f, "Expected URI line not found"),
+            ParseErrorKind::InvalidUri => write!(f, "Invalid URI"),
+            ParseErrorKind::UnexpectedLine => write!(f, "Unexpected line"),
+            ParseErrorKind::Truncated => write!(f, "File truncated without an expected URI line"),
+            ParseErrorKind::EmptyPlaylist => write!(f, "Empty playlist"),
+        }
     }
-    Some(result)
 }
 
-static RE_TAG_NAME: Lazy<Regex> = regex_static::lazy_regex!(r#"^#(EXT-X-[[:alpha:]-]+)($|:)"#);
-static RE_URI: Lazy<Regex> = regex_static::lazy_regex!(r#"^([[:alnum:]/.:])+$"#);
-
-fn parse_line<'a>(line: &'a str) -> Option<ParsedLine<'a>> {
-    if line.is_empty() {
-        return Some(ParsedLine::Empty);
-    }
-    if line == "#EXTM3U" {
-        return Some(ParsedLine::ExtM3U);
-    }
-    if let Some((mtag, tail)) = consume(line, &RE_TAG_NAME) {
-        let tag = mtag.get(1).unwrap().as_str();
-        if tail.is_empty() {
-            return Some(ParsedLine::Tag(tag));
-        }
-        if let Some (attr) = parse_attributes(tail) {
-            return Some(ParsedLine::TagWithAttributes(tag, attr))
-        } else {
-            return None
+impl From<SyntaxErrorKind> for ParseErrorKind {
+    fn from(kind: SyntaxErrorKind) -> Self {
+        match kind {
+            SyntaxErrorKind::Expected(e) => ParseErrorKind::Syntax(e),
+            SyntaxErrorKind::DuplicateAttribute => ParseErrorKind::DuplicateAttribute,
         }
     }
-    if let Some(_) = RE_URI.captures(line) {
-        return Some(ParsedLine::Uri(line))
-    }
-    None
 }
 
-use crate::format;
+// Longest piece of the offending text kept in ParseError
+const SNIPPET_LEN: usize = 40;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ParseError {
-    message: &'static str,
-    lineno: usize,
+    pub kind: ParseErrorKind,
+    pub lineno: usize,  // base 0
+    pub column: usize,  // base 0, in bytes
+    pub offset: usize,  // from the start of the data, in bytes
+    pub snippet: String,
+    pub suggestion: Option<String>, // likely intended name of a misspelled tag or attribute
 }
 
 impl ParseError {
-    pub fn new(message: &'static str, lineno: usize) -> Self {
-        ParseError{message, lineno}
+    // `line` is the offending line and `line_offset` its offset in the data
+    pub fn new(kind: ParseErrorKind, lineno: usize, line_offset: usize, line: &str, column: usize) -> Self {
+        let tail = line.get(column..).unwrap_or("");
+        let snippet = match tail.char_indices().nth(SNIPPET_LEN) {
+            Some((end, _)) => &tail[..end],
+            None => tail,
+        };
+        ParseError{kind, lineno, column, offset: line_offset + column, snippet: snippet.to_string(), suggestion: None}
+    }
+
+    pub fn with_suggestion(self, suggestion: Option<&str>) -> Self {
+        ParseError{suggestion: suggestion.map(|s| s.to_string()), ..self}
+    }
+
+    // Error that is not bound to any line
+    pub fn at_eof(kind: ParseErrorKind, data: &str) -> Self {
+        let lineno = data.split('\n').count() - 1;
+        ParseError{kind, lineno, column: 0, offset: data.len(), snippet: String::new(), suggestion: None}
     }
 }
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // lineno+1 as we want base 1 line numbers
-        write!(f, "{} at line {}", self.message, self.lineno+1)
+        // lineno+1 and column+1 as we want base 1 positions
+        write!(f, "{} at line {}, column {}", self.kind, self.lineno+1, self.column+1)?;
+        if !self.snippet.is_empty() {
+            write!(f, " near {:?}", self.snippet)?;
+        }
+        if let Some(s) = &self.suggestion {
+            write!(f, ", did you mean {}?", s)?;
+        }
+        Ok(())
     }
 }
 
+impl std::error::Error for ParseError {}
+
 fn as_media_type(v: &AttributeValue) -> Option<format::MediaType> {
-    match *(v.as_enumerated_string().ok()?) {
-        "AUDIO" => Some(format::MediaType::Audio),
-        "VIDEO" => Some(format::MediaType::Video),
-        "SUBTITLES" => Some(format::MediaType::Subtitles),
-        "CLOSED-CAPTIONS" => Some(format::MediaType::ClosedCaptions),
-        _ => None
-    }
+    Some(format::MediaType::parse(v.as_enumerated_string().ok()?))
 }
 
 fn as_bool(v: &AttributeValue) -> Option<bool> {
@@ -163,12 +125,7 @@ fn as_bool(v: &AttributeValue) -> Option<bool> {
 }
 
 fn as_video_range(v: &AttributeValue) -> Option<format::VideoRange> {
-    match *(v.as_enumerated_string().ok()?) {
-        "SDR" => Some(format::VideoRange::SDR),
-        "HLG" => Some(format::VideoRange::HLG),
-        "PQ" => Some(format::VideoRange::PQ),
-        _ => None
-    }
+    Some(format::VideoRange::parse(v.as_enumerated_string().ok()?))
 }
 
 fn as_resolution(v: &AttributeValue) -> Option<format::Resolution> {
@@ -176,231 +133,734 @@ fn as_resolution(v: &AttributeValue) -> Option<format::Resolution> {
     Some(format::Resolution{w: *res.0, h: *res.1})
 }
 
+/* WxH, or a shorthand like 1080p or 4k, see Resolution::named */
 pub fn parse_resolution_param(s: &str) -> Option<format::Resolution> {
+    if let Some(res) = format::Resolution::named(s) {
+        return Some(res)
+    }
     let av = parse_resolution(s)?;
     as_resolution(&av)
 }
 
+/* MIN..MAX in bits per second, either bound can be left out: 1000000..8000000, ..8000000 */
+pub fn parse_bandwidth_range_param(s: &str) -> Option<(u64, u64)> {
+    let (min, max) = s.split_once("..")?;
+    let bound = |v: &str, default: u64| if v.is_empty() { Some(default) } else { v.parse().ok() };
+    let (min, max) = (bound(min, 0)?, bound(max, u64::MAX)?);
+    if min > max {
+        return None
+    }
+    Some((min, max))
+}
+
+fn as_uri(v: &AttributeValue) -> Option<PlaylistUri> {
+    PlaylistUri::parse(v.as_quoted_string().ok()?).ok()
+}
+
 fn intepret_ext_x_media(attr: &AttributeMap) -> Option<format::Media> {
     Some(format::Media{
         type_: as_media_type(attr.get("TYPE")?)?,
-        uri: attr.get("URI")?.as_quoted_string().ok()?.to_string(),
+        uri: match attr.get("URI") {
+            Some(v) => Some(as_uri(v)?),
+            None => None,
+        },
         group_id: attr.get("GROUP-ID")?.as_quoted_string().ok()?.to_string(),
         language: attr.get("LANGUAGE").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
+        assoc_language: attr.get("ASSOC-LANGUAGE").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
+        stable_rendition_id: attr.get("STABLE-RENDITION-ID").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
         name: attr.get("NAME")?.as_quoted_string().ok()?.to_string(),
-        default: attr.get("DEFAULT").map_or(None, as_bool)?,
-        autoselect: attr.get("AUTOSELECT").map_or(None, as_bool)?,
-        channels: attr.get("CHANNELS").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
+        // DEFAULT, AUTOSELECT and FORCED have an implicit value of NO
+        default: attr.get("DEFAULT").map_or(Some(false), as_bool)?,
+        autoselect: attr.get("AUTOSELECT").map_or(Some(false), as_bool)?,
+        forced: attr.get("FORCED").map_or(Some(false), as_bool)?,
+        instream_id: match attr.get("INSTREAM-ID") {
+            Some(v) => Some(format::InstreamId::parse(v.as_quoted_string().ok()?)?),
+            None => None,
+        },
+        bit_depth: attr.get("BIT-DEPTH").map_or(None, |v| Some(*v.as_integer().ok()?)),
+        sample_rate: attr.get("SAMPLE-RATE").map_or(None, |v| Some(*v.as_integer().ok()?)),
+        characteristics: attr.get("CHARACTERISTICS").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
+        channels: match attr.get("CHANNELS") {
+            Some(v) => Some(format::Channels::parse_lenient(v.as_quoted_string().ok()?)),
+            None => None,
+        },
     })
 }
 
 fn interpret_ext_x_stream_inf(attr: &AttributeMap) -> Option<format::StreamInf> {
     Some(format::StreamInf{
-        uri: String::new(), // to be filled later
+        uri: PlaylistUri::default(), // to be filled later
         bandwidth: *attr.get("BANDWIDTH")?.as_integer().ok()?,
         average_bandwidth: attr.get("AVERAGE-BANDWIDTH").map_or(None, |v| Some(*v.as_integer().ok()?)),
-        codecs: attr.get("CODECS").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
+        score: match attr.get_raw("SCORE") {
+            Some(v) => Some(format::Score::parse(v)?),
+            None => None,
+        },
+        codecs: match attr.get("CODECS") {
+            Some(v) => Some(Codecs::parse(v.as_quoted_string().ok()?)),
+            None => None,
+        },
         resolution: attr.get("RESOLUTION").map_or(None, as_resolution),
-        frame_rate: attr.get("FRAME-RATE").map_or(None,  |v| Some(*v.as_float().ok()?)),
+        frame_rate: match attr.get_raw("FRAME-RATE") {
+            Some(v) => Some(format::FrameRate::parse(v)?),
+            None => None,
+        },
+        hdcp_level: attr.get("HDCP-LEVEL").map_or(None, |v| format::HdcpLevel::parse(v.as_enumerated_string().ok()?)),
+        allowed_cpc: attr.get("ALLOWED-CPC").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
         video_range: attr.get("VIDEO-RANGE").map_or(None, |v| as_video_range(v)),
+        stable_variant_id: attr.get("STABLE-VARIANT-ID").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
         audio: attr.get("AUDIO").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
+        video: attr.get("VIDEO").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
+        subtitles: attr.get("SUBTITLES").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
         closed_captions: attr.get("CLOSED-CAPTIONS").map_or(None,
             |v| {
                 match *v {
-                    AttributeValue::QuotedString(s) => Some(s.to_string()),
-                    AttributeValue::EnumeratedString("NONE") => None,
+                    AttributeValue::QuotedString(s) => Some(format::ClosedCaptions::Group(s.to_string())),
+                    AttributeValue::EnumeratedString("NONE") => Some(format::ClosedCaptions::None),
                     _ => None,
                 }
             }),
+        pathway_id: attr.get("PATHWAY-ID").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
     })
 }
 
 fn interpret_ext_x_i_frame_stream_inf(attr: &AttributeMap) -> Option<format::IFrameStreamInf> {
     Some(format::IFrameStreamInf{
-        uri: attr.get("URI")?.as_quoted_string().ok()?.to_string(),
+        uri: as_uri(attr.get("URI")?)?,
         bandwidth: *attr.get("BANDWIDTH")?.as_integer().ok()?,
-        codecs: attr.get("CODECS").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
+        codecs: match attr.get("CODECS") {
+            Some(v) => Some(Codecs::parse(v.as_quoted_string().ok()?)),
+            None => None,
+        },
         resolution: attr.get("RESOLUTION").map_or(None, as_resolution),
+        hdcp_level: attr.get("HDCP-LEVEL").map_or(None, |v| format::HdcpLevel::parse(v.as_enumerated_string().ok()?)),
         video_range: attr.get("VIDEO-RANGE").map_or(None, |v| as_video_range(v)),
+        stable_variant_id: attr.get("STABLE-VARIANT-ID").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
+        video: attr.get("VIDEO").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
+        pathway_id: attr.get("PATHWAY-ID").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
+    })
+}
+
+// None for METHOD=NONE, which removes the keys in effect
+fn interpret_ext_x_key(attr: &AttributeMap) -> Option<Option<Key>> {
+    let method = match *attr.get("METHOD")?.as_enumerated_string().ok()? {
+        "NONE" => return Some(None),
+        "AES-128" => KeyMethod::Aes128,
+        "SAMPLE-AES" => KeyMethod::SampleAes,
+        "SAMPLE-AES-CTR" => KeyMethod::SampleAesCtr,
+        _ => return None,
+    };
+    let iv = match attr.get("IV") {
+        // 128-bit initialization vector
+        Some(v) => Some(v.as_hexadecimal_sequence().ok().filter(|v| v.len() == 34)?.to_string()),
+        None => None,
+    };
+    Some(Some(Key{
+        method,
+        uri: as_uri(attr.get("URI")?)?,
+        iv,
+        keyformat: attr.get("KEYFORMAT").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
+        keyformatversions: attr.get("KEYFORMATVERSIONS").map_or(None, |v| Some(v.as_quoted_string().ok()?.to_string())),
+    }))
+}
+
+fn as_decimal(v: &AttributeValue) -> Option<f64> {
+    match *v {
+        AttributeValue::Float(f) => Some(f),
+        AttributeValue::Integer(i) => Some(i as f64),
+        _ => None,
+    }
+}
+
+fn interpret_ext_x_part_inf(attr: &AttributeMap) -> Option<f64> {
+    as_decimal(attr.get("PART-TARGET")?)
+}
+
+// The byte range is returned as written, its offset may be implied by the previous part
+fn interpret_ext_x_part(attr: &AttributeMap) -> Option<(Part, Option<RawByteRange>)> {
+    let range = match attr.get("BYTERANGE") {
+        Some(v) => Some(interpret_byterange(v.as_quoted_string().ok()?)?),
+        None => None,
+    };
+    Some((Part{
+        uri: as_uri(attr.get("URI")?)?,
+        duration: as_decimal(attr.get("DURATION")?)?,
+        independent: attr.contains_key("INDEPENDENT"),
+        byte_range: None,
+        gap: attr.contains_key("GAP"),
+    }, range))
+}
+
+fn interpret_ext_x_server_control(attr: &AttributeMap) -> Option<ServerControl> {
+    let seconds = |name| match attr.get(name) {
+        Some(v) => as_decimal(v).map(Some),
+        None => Some(None),
+    };
+    Some(ServerControl{
+        can_skip_until: seconds("CAN-SKIP-UNTIL")?,
+        can_skip_dateranges: attr.contains_key("CAN-SKIP-DATERANGES"),
+        hold_back: seconds("HOLD-BACK")?,
+        part_hold_back: seconds("PART-HOLD-BACK")?,
+        can_block_reload: attr.contains_key("CAN-BLOCK-RELOAD"),
     })
 }
 
+fn interpret_ext_x_skip(attr: &AttributeMap) -> Option<Skip> {
+    let dateranges = match attr.get("RECENTLY-REMOVED-DATERANGES") {
+        Some(v) => v.as_quoted_string().ok()?.split('\t').map(|id| id.to_string()).collect(),
+        None => vec![],
+    };
+    Some(Skip{
+        skipped_segments: *attr.get("SKIPPED-SEGMENTS")?.as_integer().ok()?,
+        recently_removed_dateranges: dateranges,
+    })
+}
+
+fn interpret_ext_x_preload_hint(attr: &AttributeMap) -> Option<PreloadHint> {
+    let type_ = match *(attr.get("TYPE")?.as_enumerated_string().ok()?) {
+        "PART" => PreloadHintType::Part,
+        "MAP" => PreloadHintType::Map,
+        _ => return None,
+    };
+    Some(PreloadHint{
+        type_,
+        uri: as_uri(attr.get("URI")?)?,
+        byte_range_start: attr.get("BYTERANGE-START").map_or(None, |v| Some(*v.as_integer().ok()?)),
+        byte_range_length: attr.get("BYTERANGE-LENGTH").map_or(None, |v| Some(*v.as_integer().ok()?)),
+    })
+}
+
+fn interpret_ext_x_rendition_report(attr: &AttributeMap) -> Option<RenditionReport> {
+    Some(RenditionReport{
+        uri: as_uri(attr.get("URI")?)?,
+        last_msn: attr.get("LAST-MSN").map_or(None, |v| Some(*v.as_integer().ok()?)),
+        last_part: attr.get("LAST-PART").map_or(None, |v| Some(*v.as_integer().ok()?)),
+    })
+}
+
+// Check attributes against the registry, then interpret them
+fn interpret<T>(tag: &'static str, attr: &AttributeMap, f: fn(&AttributeMap) -> Option<T>)
+    -> Result<T, ParseErrorKind> {
+    let spec = registry::tag(tag).expect("interpreted tags are registered");
+    spec.check(attr).map_err(|e| match e {
+        AttributeError::Missing(name) => ParseErrorKind::MissingAttribute(tag, name),
+        AttributeError::Invalid(name) => ParseErrorKind::InvalidAttribute(tag, name),
+    })?;
+    f(attr).ok_or(ParseErrorKind::InvalidTag(tag))
+}
+
+fn suggest_attribute(tag: &str, attr: &AttributeMap) -> Option<&'static str> {
+    suggest::suggest_attribute(tag, attr.iter().map(|a| a.name))
+}
+
+// Warnings collected in lenient mode carry the same information as errors
+pub type ParseWarning = ParseError;
+
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    // Skip malformed lines and tags, reporting them as warnings
+    pub lenient: bool,
+    // Keep lines that are not understood in MultivariantPlaylist::unrecognized
+    pub passthrough: bool,
+    // Keep the text in MultivariantPlaylist::source to write it back unchanged but for edits,
+    // implies passthrough
+    pub preserve: bool,
+}
+
+// Whether a line that is not understood is kept verbatim: in passthrough mode, which preserve
+// implies, but not a line that does not tokenize while a tag waits for its URI line
+fn keep_opaque(options: &ParseOptions, tokenized: bool, awaiting_uri: bool) -> bool {
+    (options.passthrough || options.preserve) && (tokenized || !awaiting_uri)
+}
+
 pub fn parse_playlist(data: &str) -> Result<format::MultivariantPlaylist, ParseError> {
+    parse_playlist_impl(data, &ParseOptions::default(), None)
+}
+
+/* 
+Parse as much of the playlist as possible. Malformed lines and tags are skipped,
+variants without URI are dropped, and each issue is reported as a warning.
+*/
+pub fn parse_playlist_lenient(data: &str) -> (format::MultivariantPlaylist, Vec<ParseWarning>) {
+    parse_playlist_with_options(data, &ParseOptions{lenient: true, ..Default::default()})
+        .expect("lenient parsing never fails")
+}
+
+/* 
+Parse keeping lines that are not understood (unknown tags, comments) verbatim,
+together with their line number, so they can be written back.
+*/
+pub fn parse_playlist_passthrough(data: &str) -> Result<format::MultivariantPlaylist, ParseError> {
+    parse_playlist_with_options(data, &ParseOptions{passthrough: true, ..Default::default()})
+        .map(|(playlist, _)| playlist)
+}
+
+pub fn parse_playlist_with_options(data: &str, options: &ParseOptions)
+    -> Result<(format::MultivariantPlaylist, Vec<ParseWarning>), ParseError> {
+    let mut warnings = vec![];
+    let playlist = parse_playlist_impl(data, options, options.lenient.then_some(&mut warnings))?;
+    Ok((playlist, warnings))
+}
+
+fn parse_playlist_impl(data: &str, options: &ParseOptions, mut warnings: Option<&mut Vec<ParseWarning>>)
+    -> Result<format::MultivariantPlaylist, ParseError> {
+    // In lenient mode record the problem and carry on, otherwise bail out
+    let mut fail = |e: ParseError| match warnings.as_mut() {
+        Some(w) => { w.push(e); Ok(()) },
+        None => Err(e),
+    };
     let mut playlist = format::MultivariantPlaylist::new();
+    let (mut media_lines, mut i_frame_stream_inf_lines) = (vec![], vec![]);
+    let mut stream_inf_lines: Vec<(usize, usize)> = vec![];
     let mut expect_uri = false;
+    let mut line_offset = 0;
     for (lineno, line) in data.split('\n').enumerate() {
-        let Some(parsed) = parse_line(line) else {
-            return Err(ParseError::new("Failed to parse line", lineno))
+        let offset = line_offset;
+        line_offset += line.len() + 1;
+        let err = |kind, column| ParseError::new(kind, lineno, offset, line, column);
+        let after = playlist.media.len() + playlist.stream_inf.len() + playlist.i_frame_stream_inf.len();
+        let opaque = || format::OpaqueLine{lineno, text: line.to_string(), after};
+        let parsed = match parse_line(line) {
+            Ok(parsed) => parsed,
+            Err(_) if keep_opaque(options, false, expect_uri) && lineno > 0 => {
+                playlist.unrecognized.push(opaque());
+                continue
+            },
+            Err(e) => {
+                // a line that does not even tokenize is not #EXTM3U either
+                if lineno == 0 {
+                    fail(err(ParseErrorKind::MissingExtM3U, 0))?;
+                }
+                fail(err(e.kind.into(), e.column))?;
+                continue
+            }
         };
         if lineno == 0 {
-            match parsed {
-                ParsedLine::ExtM3U => (),
-                _ => return Err(ParseError::new("No #EXTM3U", 0))
-            }
-        } else if expect_uri {
-            match parsed {
-                ParsedLine::Uri(uri) => {
-                    playlist.stream_inf.last_mut().unwrap().uri = uri.to_string();
-                    expect_uri = false;
-                },
-                _ => return Err(ParseError::new("Expected URI line not found", lineno))
+            if let ParsedLine::ExtM3U = parsed {
+                continue
             }
-        } else {
-            match parsed {
-                ParsedLine::Empty => (), // ignore empty lines
-                ParsedLine::Tag("EXT-X-INDEPENDENT-SEGMENTS") => {
-                    playlist.independent_segments = true;
-                },
-                ParsedLine::TagWithAttributes("EXT-X-MEDIA", attr) => {
-                    if let Some(m) = intepret_ext_x_media(&attr) {
-                        playlist.media.push(m)
-                    } else {
-                        return Err(ParseError::new("Failed to interpret EXT-X-MEDIA", lineno))
+            fail(err(ParseErrorKind::MissingExtM3U, 0))?;
+        }
+        if expect_uri {
+            expect_uri = false;
+            if let ParsedLine::Uri(uri) = parsed {
+                match PlaylistUri::parse(uri) {
+                    Ok(uri) => {
+                        playlist.stream_inf.last_mut().unwrap().uri = uri;
+                        stream_inf_lines.last_mut().unwrap().1 = lineno;
+                    },
+                    Err(_) => {
+                        fail(err(ParseErrorKind::InvalidUri, 0))?;
+                        playlist.stream_inf.pop();
+                        stream_inf_lines.pop();
                     }
-                },
-                ParsedLine::TagWithAttributes("EXT-X-STREAM-INF", attr) => {
-                    if let Some(m) = interpret_ext_x_stream_inf(&attr) {
+                }
+                continue
+            }
+            fail(err(ParseErrorKind::ExpectedUri, 0))?;
+            playlist.stream_inf.pop();
+            stream_inf_lines.pop();
+        }
+        match parsed {
+            ParsedLine::Empty => (), // ignore empty lines
+            ParsedLine::Tag("EXT-X-INDEPENDENT-SEGMENTS") => {
+                playlist.independent_segments = true;
+            },
+            // the first one is kept in lenient mode
+            ParsedLine::TagWithValue("EXT-X-VERSION", _) if playlist.version.is_some() =>
+                fail(err(ParseErrorKind::DuplicateTag("EXT-X-VERSION"), 0))?,
+            ParsedLine::TagWithValue("EXT-X-VERSION", v) => match v.parse() {
+                Ok(version) => playlist.version = Some(version),
+                Err(_) => fail(err(ParseErrorKind::InvalidTag("EXT-X-VERSION"), 0))?,
+            },
+            ParsedLine::TagWithAttributes(tag @ "EXT-X-MEDIA", attr) => {
+                match interpret("EXT-X-MEDIA", &attr, intepret_ext_x_media) {
+                    Ok(m) => {
+                        playlist.media.push(m);
+                        media_lines.push(lineno);
+                    },
+                    Err(kind) => fail(err(kind, 0).with_suggestion(suggest_attribute(tag, &attr)))?,
+                }
+            },
+            ParsedLine::TagWithAttributes(tag @ "EXT-X-STREAM-INF", attr) => {
+                match interpret("EXT-X-STREAM-INF", &attr, interpret_ext_x_stream_inf) {
+                    Ok(m) => {
                         playlist.stream_inf.push(m);
+                        stream_inf_lines.push((lineno, lineno));
                         expect_uri = true;
-                    } else {
-                        return Err(ParseError::new("Failed to interpret EXT-X-STREAM-INF", lineno))
-                    }
-                },
-                ParsedLine::TagWithAttributes("EXT-X-I-FRAME-STREAM-INF", attr) => {
-                    if let Some(m) = interpret_ext_x_i_frame_stream_inf(&attr) {
-                        playlist.i_frame_stream_inf.push(m)
-                    } else {
-                        return Err(ParseError::new("Failed to interpret EXT-X-I-FRAME-STREAM-INF", lineno))
-                    }
-                },
-                _ => {
-                    return Err(ParseError::new("Unexpected line", lineno))
+                    },
+                    Err(kind) => fail(err(kind, 0).with_suggestion(suggest_attribute(tag, &attr)))?,
                 }
+            },
+            ParsedLine::TagWithAttributes(tag @ "EXT-X-I-FRAME-STREAM-INF", attr) => {
+                match interpret("EXT-X-I-FRAME-STREAM-INF", &attr, interpret_ext_x_i_frame_stream_inf) {
+                    Ok(m) => {
+                        playlist.i_frame_stream_inf.push(m);
+                        i_frame_stream_inf_lines.push(lineno);
+                    },
+                    Err(kind) => fail(err(kind, 0).with_suggestion(suggest_attribute(tag, &attr)))?,
+                }
+            },
+            _ if keep_opaque(options, true, expect_uri) => {
+                playlist.unrecognized.push(opaque())
+            },
+            ParsedLine::Tag(tag) | ParsedLine::TagWithAttributes(tag, _) | ParsedLine::TagWithValue(tag, _) => {
+                fail(err(ParseErrorKind::UnexpectedLine, 0)
+                    .with_suggestion(suggest::suggest_tag(tag)))?
+            },
+            _ => {
+                fail(err(ParseErrorKind::UnexpectedLine, 0))?
             }
         }
     }
     if expect_uri {
-        return Err(ParseError::new("File truncated without an expected URI line after EXT-X-STREAM-INF", 0));
+        fail(ParseError::at_eof(ParseErrorKind::Truncated, data))?;
+        playlist.stream_inf.pop();
+        stream_inf_lines.pop();
     }
     if playlist.media.is_empty() && playlist.stream_inf.is_empty() && playlist.i_frame_stream_inf.is_empty() {
-        return Err(ParseError::new("Empty playlist", 0));
+        fail(ParseError::at_eof(ParseErrorKind::EmptyPlaylist, data))?;
+    }
+    if options.preserve {
+        playlist.source = Some(format::Source{
+            text: data.to_string(),
+            original: Box::new(playlist.clone()),
+            media_lines,
+            stream_inf_lines,
+            i_frame_stream_inf_lines,
+        });
     }
 
     Ok(playlist)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+// EXTINF:<duration>,[<title>]
+fn interpret_extinf(value: &str) -> Option<(f64, Option<String>)> {
+    let (duration, title) = value.split_once(',').unwrap_or((value, ""));
+    let duration = duration.parse::<f64>().ok().filter(|d| d.is_finite() && *d >= 0.0)?;
+    Some((duration, (!title.is_empty()).then(|| title.to_string())))
+}
 
-    #[test]
-    fn test_parse_attribute_value() {
-        if let Some((tail,AttributeValue::Float(d))) = parse_attribute_value("12.5,tail") {
-            assert_eq!(tail, ",tail");
-            assert_eq!(d, 12.5);
-        } else {
-            assert!(false)
-        }
+// Length and offset as written, see interpret_byterange
+type RawByteRange = (u64, Option<u64>);
 
-        if let Some((_,AttributeValue::DecimalResolution(w, h))) = parse_attribute_value("2560x1440") {
-            assert_eq!(w, 2560);
-            assert_eq!(h, 1440);
-        } else {
-            assert!(false)
-        }
+// EXT-X-BYTERANGE:<n>[@<o>], offset is implied by the previous segment when absent.
+// An empty range cannot be requested.
+fn interpret_byterange(value: &str) -> Option<RawByteRange> {
+    let (length, offset) = match value.split_once('@') {
+        Some((l, o)) => (l, Some(o.parse().ok()?)),
+        None => (value, None),
+    };
+    Some((length.parse().ok().filter(|l| *l > 0)?, offset))
+}
 
-        if let Some((_,AttributeValue::Integer(v))) = parse_attribute_value("10058085") {
-            assert_eq!(v, 10058085);
-        } else {
-            assert!(false)
-        }
+/* EXT-X-PROGRAM-DATE-TIME:<YYYY-MM-DDThh:mm:ss.SSSZ>, ISO 8601 also allows
+   offsets written as +hhmm or +hh */
+#[cfg(feature = "chrono")]
+pub fn parse_date_time(v: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(v).ok()
+        .or_else(|| DateTime::parse_from_str(v, "%Y-%m-%dT%H:%M:%S%.f%z").ok())
+        .or_else(|| DateTime::parse_from_str(&format!("{}00", v), "%Y-%m-%dT%H:%M:%S%.f%z").ok())
+}
 
-        if let Some((_,AttributeValue::QuotedString(v))) = parse_attribute_value(r#""mp4a.40.2,hvc1.2.4.L150.90""#) {
-            assert_eq!(v, "mp4a.40.2,hvc1.2.4.L150.90");
-        } else {
-            assert!(false)
-        }
+fn as_playlist_type(v: &str) -> Option<PlaylistType> {
+    match v {
+        "EVENT" => Some(PlaylistType::Event),
+        "VOD" => Some(PlaylistType::Vod),
+        _ => None
+    }
+}
 
+pub fn parse_media_playlist(data: &str) -> Result<MediaPlaylist, ParseError> {
+    parse_media_playlist_impl(data, &ParseOptions::default(), None)
+}
 
-        if let Some((tail,AttributeValue::EnumeratedString(v))) = parse_attribute_value("PQ,SOMETHING") {
-            assert_eq!(tail, ",SOMETHING");
-            assert_eq!(v, "PQ");
-        } else {
-            assert!(false)
-        }
-    }
+pub fn parse_media_playlist_with_options(data: &str, options: &ParseOptions)
+    -> Result<(MediaPlaylist, Vec<ParseWarning>), ParseError> {
+    let mut warnings = vec![];
+    let playlist = parse_media_playlist_impl(data, options, options.lenient.then_some(&mut warnings))?;
+    Ok((playlist, warnings))
+}
 
-    #[test]
-    fn test_parse_attribute_str() {
-        let astr = r#"BANDWIDTH=15811232,AVERAGE-BANDWIDTH=10058085,CODECS="mp4a.40.2,hvc1.2.4.L150.90",RESOLUTION=2560x1440,FRAME-RATE=23.97,VIDEO-RANGE=PQ,AUDIO="aac-128k",CLOSED-CAPTIONS=NONE"#;
-        let parsed = parse_attributes(astr);
-        assert!(parsed.is_some());
-        let parsed = parsed.unwrap();
-        if let AttributeValue::Integer(bw) = &parsed["BANDWIDTH"] {
-            assert_eq!(*bw, 15811232);
-        } else {
-            assert!(false)
-        }
-        if let AttributeValue::Integer(bw) = &parsed["AVERAGE-BANDWIDTH"] {
-            assert_eq!(*bw, 10058085);
-        } else {
-            assert!(false)
-        }
-        if let AttributeValue::QuotedString(s) = &parsed["CODECS"] {
-            assert_eq!(*s, "mp4a.40.2,hvc1.2.4.L150.90");
-        } else {
-            assert!(false)
+fn parse_media_playlist_impl(data: &str, options: &ParseOptions, mut warnings: Option<&mut Vec<ParseWarning>>)
+    -> Result<MediaPlaylist, ParseError> {
+    // In lenient mode record the problem and carry on, otherwise bail out
+    let mut fail = |e: ParseError| match warnings.as_mut() {
+        Some(w) => { w.push(e); Ok(()) },
+        None => Err(e),
+    };
+    let mut playlist = MediaPlaylist::new();
+    let mut target_duration = None;
+    let mut pending: Option<Segment> = None; // EXTINF waiting for its URI line
+    // EXT-X-BYTERANGE, same, with the error at its line if the range cannot follow the previous one
+    let mut pending_range: Option<(RawByteRange, ParseError)> = None;
+    let mut pending_discontinuity = false;
+    let mut pending_date_time: Option<ProgramDateTime> = None;
+    let mut keys: Vec<Key> = vec![]; // EXT-X-KEY in effect, applies to all following segments
+    let mut pending_parts: Vec<Part> = vec![]; // EXT-X-PART of the next segment
+    let mut pending_gap = false;
+    let mut bitrate: Option<u64> = None; // EXT-X-BITRATE in effect
+    let mut line_offset = 0;
+    for (lineno, line) in data.split('\n').enumerate() {
+        let offset = line_offset;
+        line_offset += line.len() + 1;
+        let err = |kind, column| ParseError::new(kind, lineno, offset, line, column);
+        let after = playlist.segments.len();
+        let opaque = || format::OpaqueLine{lineno, text: line.to_string(), after};
+        let parsed = match parse_line(line) {
+            Ok(parsed) => parsed,
+            Err(_) if keep_opaque(options, false, pending.is_some()) && lineno > 0 => {
+                playlist.unrecognized.push(opaque());
+                continue
+            },
+            Err(e) => {
+                // a line that does not even tokenize is not #EXTM3U either
+                if lineno == 0 {
+                    fail(err(ParseErrorKind::MissingExtM3U, 0))?;
+                }
+                fail(err(e.kind.into(), e.column))?;
+                continue
+            }
+        };
+        if lineno == 0 {
+            if let ParsedLine::ExtM3U = parsed {
+                continue
+            }
+            fail(err(ParseErrorKind::MissingExtM3U, 0))?;
         }
-        if let AttributeValue::EnumeratedString(s) = &parsed["CLOSED-CAPTIONS"] {
-            assert_eq!(*s, "NONE");
-        } else {
-            assert!(false)
+        match parsed {
+            ParsedLine::Empty => (), // ignore empty lines
+            ParsedLine::Tag("EXT-X-INDEPENDENT-SEGMENTS") => {
+                playlist.independent_segments = true;
+            },
+            ParsedLine::Tag("EXT-X-ENDLIST") => {
+                // cannot come between EXTINF and the URI of a segment
+                if pending.is_some() {
+                    fail(err(ParseErrorKind::ExpectedUri, 0))?;
+                }
+                playlist.end_list = true;
+            },
+            ParsedLine::Tag("EXT-X-I-FRAMES-ONLY") => {
+                playlist.i_frames_only = true;
+            },
+            // the first one is kept in lenient mode
+            ParsedLine::TagWithValue("EXT-X-VERSION", _) if playlist.version.is_some() =>
+                fail(err(ParseErrorKind::DuplicateTag("EXT-X-VERSION"), 0))?,
+            ParsedLine::TagWithValue("EXT-X-VERSION", v) => match v.parse() {
+                Ok(version) => playlist.version = Some(version),
+                Err(_) => fail(err(ParseErrorKind::InvalidTag("EXT-X-VERSION"), 0))?,
+            },
+            ParsedLine::TagWithValue("EXT-X-TARGETDURATION", v) => match v.parse() {
+                Ok(td) => target_duration = Some(td),
+                Err(_) => fail(err(ParseErrorKind::InvalidTag("EXT-X-TARGETDURATION"), 0))?,
+            },
+            ParsedLine::TagWithValue("EXT-X-MEDIA-SEQUENCE", v) => match v.parse() {
+                Ok(seq) => playlist.media_sequence = seq,
+                Err(_) => fail(err(ParseErrorKind::InvalidTag("EXT-X-MEDIA-SEQUENCE"), 0))?,
+            },
+            ParsedLine::TagWithValue("EXT-X-DISCONTINUITY-SEQUENCE", v) => match v.parse() {
+                Ok(seq) if playlist.segments.is_empty() && pending.is_none() => playlist.discontinuity_sequence = seq,
+                // must appear before the first segment
+                _ => fail(err(ParseErrorKind::InvalidTag("EXT-X-DISCONTINUITY-SEQUENCE"), 0))?,
+            },
+            ParsedLine::TagWithValue("EXT-X-PLAYLIST-TYPE", v) => match as_playlist_type(v) {
+                Some(t) => playlist.playlist_type = Some(t),
+                None => fail(err(ParseErrorKind::InvalidTag("EXT-X-PLAYLIST-TYPE"), 0))?,
+            },
+            ParsedLine::TagWithValue("EXTINF", v) => match interpret_extinf(v) {
+                Some((duration, title)) => {
+                    if pending.is_some() {
+                        fail(err(ParseErrorKind::ExpectedUri, 0))?;
+                    }
+                    // no segments after EXT-X-ENDLIST
+                    if playlist.end_list {
+                        fail(err(ParseErrorKind::UnexpectedLine, 0))?;
+                    }
+                    pending = Some(Segment{uri: PlaylistUri::default(), duration, title, byte_range: None,
+                        discontinuity: false, program_date_time: None, keys: vec![], parts: vec![], gap: false, bitrate: None});
+                },
+                None => fail(err(ParseErrorKind::InvalidTag("EXTINF"), 0))?,
+            },
+            ParsedLine::Tag("EXT-X-DISCONTINUITY") => {
+                pending_discontinuity = true;
+            },
+            ParsedLine::Tag("EXT-X-GAP") => {
+                pending_gap = true;
+            },
+            ParsedLine::TagWithValue("EXT-X-BITRATE", v) => match v.parse() {
+                Ok(kbps) => bitrate = Some(kbps),
+                Err(_) => fail(err(ParseErrorKind::InvalidTag("EXT-X-BITRATE"), 0))?,
+            },
+            ParsedLine::TagWithAttributes(tag @ "EXT-X-PART-INF", attr) => {
+                match interpret("EXT-X-PART-INF", &attr, interpret_ext_x_part_inf) {
+                    Ok(target) => playlist.part_target = Some(target),
+                    Err(kind) => fail(err(kind, 0).with_suggestion(suggest_attribute(tag, &attr)))?,
+                }
+            },
+            ParsedLine::TagWithAttributes(tag @ "EXT-X-PART", attr) => {
+                match interpret("EXT-X-PART", &attr, interpret_ext_x_part) {
+                    Ok((mut part, range)) => {
+                        if let Some((length, offset)) = range {
+                            // Without an offset the range continues the previous part of the same resource
+                            let previous = pending_parts.last()
+                                .or_else(|| playlist.segments.last().and_then(|s| s.parts.last()));
+                            let offset = offset.or_else(|| previous
+                                .filter(|prev| prev.uri == part.uri)
+                                .and_then(|prev| prev.byte_range.as_ref())
+                                .and_then(|prev| prev.offset.checked_add(prev.length)));
+                            // the end of the range must be addressable too
+                            match offset.filter(|o| o.checked_add(length).is_some()) {
+                                Some(offset) => part.byte_range = Some(ByteRange{length, offset}),
+                                None => fail(err(ParseErrorKind::InvalidAttribute("EXT-X-PART", "BYTERANGE"), 0))?,
+                            }
+                        }
+                        pending_parts.push(part);
+                    },
+                    Err(kind) => fail(err(kind, 0).with_suggestion(suggest_attribute(tag, &attr)))?,
+                }
+            },
+            ParsedLine::TagWithAttributes(tag @ "EXT-X-SERVER-CONTROL", attr) => {
+                match interpret("EXT-X-SERVER-CONTROL", &attr, interpret_ext_x_server_control) {
+                    Ok(sc) => playlist.server_control = Some(sc),
+                    Err(kind) => fail(err(kind, 0).with_suggestion(suggest_attribute(tag, &attr)))?,
+                }
+            },
+            ParsedLine::TagWithAttributes(tag @ "EXT-X-SKIP", attr) => {
+                match interpret("EXT-X-SKIP", &attr, interpret_ext_x_skip) {
+                    // stands for the segments before the first one listed
+                    Ok(skip) if playlist.skip.is_none() && playlist.segments.is_empty() && pending.is_none() =>
+                        playlist.skip = Some(skip),
+                    Ok(_) => fail(err(ParseErrorKind::InvalidTag("EXT-X-SKIP"), 0))?,
+                    Err(kind) => fail(err(kind, 0).with_suggestion(suggest_attribute(tag, &attr)))?,
+                }
+            },
+            ParsedLine::TagWithAttributes(tag @ "EXT-X-PRELOAD-HINT", attr) => {
+                match interpret("EXT-X-PRELOAD-HINT", &attr, interpret_ext_x_preload_hint) {
+                    Ok(hint) => playlist.preload_hints.push(hint),
+                    Err(kind) => fail(err(kind, 0).with_suggestion(suggest_attribute(tag, &attr)))?,
+                }
+            },
+            ParsedLine::TagWithAttributes(tag @ "EXT-X-RENDITION-REPORT", attr) => {
+                match interpret("EXT-X-RENDITION-REPORT", &attr, interpret_ext_x_rendition_report) {
+                    Ok(report) => playlist.rendition_reports.push(report),
+                    Err(kind) => fail(err(kind, 0).with_suggestion(suggest_attribute(tag, &attr)))?,
+                }
+            },
+            ParsedLine::TagWithAttributes(tag @ "EXT-X-KEY", attr) => {
+                match interpret("EXT-X-KEY", &attr, interpret_ext_x_key) {
+                    // A key replaces the one with the same KEYFORMAT, "identity" if absent
+                    Ok(Some(key)) => {
+                        let format = |k: &Key| k.keyformat.clone().unwrap_or("identity".to_string());
+                        keys.retain(|k| format(k) != format(&key));
+                        keys.push(key);
+                    },
+                    Ok(None) => keys.clear(),
+                    Err(kind) => fail(err(kind, 0).with_suggestion(suggest_attribute(tag, &attr)))?,
+                }
+            },
+            ParsedLine::TagWithValue("EXT-X-PROGRAM-DATE-TIME", v) => match ProgramDateTime::parse(v) {
+                Ok(dt) => pending_date_time = Some(dt),
+                Err(_) => fail(err(ParseErrorKind::InvalidTag("EXT-X-PROGRAM-DATE-TIME"), 0))?,
+            },
+            ParsedLine::TagWithValue("EXT-X-BYTERANGE", v) => match interpret_byterange(v) {
+                Some(range) => pending_range = Some((range, err(ParseErrorKind::InvalidTag("EXT-X-BYTERANGE"), 0))),
+                None => fail(err(ParseErrorKind::InvalidTag("EXT-X-BYTERANGE"), 0))?,
+            },
+            ParsedLine::Uri(uri) => match pending.take() {
+                Some(mut segment) => {
+                    match PlaylistUri::parse(uri) {
+                        Ok(uri) => segment.uri = uri,
+                        Err(_) => {
+                            fail(err(ParseErrorKind::InvalidUri, 0))?;
+                            // what applied to the dropped segment alone, a discontinuity still applies
+                            pending_range = None;
+                            pending_gap = false;
+                            pending_parts.clear();
+                            pending_date_time = None;
+                            continue
+                        }
+                    }
+                    segment.discontinuity = std::mem::take(&mut pending_discontinuity);
+                    segment.program_date_time = pending_date_time.take();
+                    segment.keys = keys.clone();
+                    segment.parts = std::mem::take(&mut pending_parts);
+                    segment.gap = std::mem::take(&mut pending_gap);
+                    // EXT-X-BITRATE does not apply to sub-ranges, their size is known
+                    segment.bitrate = bitrate.filter(|_| pending_range.is_none());
+                    if let Some(((length, offset), range_error)) = pending_range.take() {
+                        // Without an offset the range continues the previous sub-range of the same resource
+                        let offset = offset.or_else(|| playlist.segments.last()
+                            .filter(|prev| prev.uri == segment.uri)
+                            .and_then(|prev| prev.byte_range.as_ref())
+                            .and_then(|prev| prev.offset.checked_add(prev.length)));
+                        match offset.filter(|o| o.checked_add(length).is_some()) {
+                            Some(offset) => segment.byte_range = Some(ByteRange{length, offset}),
+                            None => fail(range_error)?,
+                        }
+                    }
+                    playlist.segments.push(segment);
+                },
+                None => fail(err(ParseErrorKind::UnexpectedLine, 0))?,
+            },
+            _ if keep_opaque(options, true, pending.is_some()) => {
+                playlist.unrecognized.push(opaque())
+            },
+            ParsedLine::Tag(tag) | ParsedLine::TagWithAttributes(tag, _) | ParsedLine::TagWithValue(tag, _) => {
+                fail(err(ParseErrorKind::UnexpectedLine, 0)
+                    .with_suggestion(suggest::suggest_tag(tag)))?
+            },
+            _ => {
+                fail(err(ParseErrorKind::UnexpectedLine, 0))?
+            }
         }
     }
+    if pending.is_some() {
+        fail(ParseError::at_eof(ParseErrorKind::Truncated, data))?;
+    }
+    match target_duration {
+        Some(td) => playlist.target_duration = td,
+        None => fail(ParseError::at_eof(ParseErrorKind::MissingTag("EXT-X-TARGETDURATION"), data))?,
+    }
+    playlist.trailing_parts = pending_parts;
+    let has_parts = !playlist.trailing_parts.is_empty() || playlist.segments.iter().any(|s| !s.parts.is_empty());
+    if has_parts && playlist.part_target.is_none() {
+        fail(ParseError::at_eof(ParseErrorKind::MissingTag("EXT-X-PART-INF"), data))?;
+    }
 
-    #[test]
-    fn test_parse_line() {
-        if let Some(ParsedLine::Empty) = parse_line("") {
-            assert!(true);
-        } else {
-            assert!(false);
-        }
+    Ok(playlist)
+}
 
-        if let Some(ParsedLine::ExtM3U) = parse_line("#EXTM3U") {
-            assert!(true);
-        } else {
-            assert!(false);
-        }
+// Tags that only appear in media playlists. EXT-X-TARGETDURATION is mandatory there,
+// the others make detection work for incomplete playlists too.
+const MEDIA_PLAYLIST_TAGS: &[&str] = &[
+    "#EXTINF", "#EXT-X-TARGETDURATION", "#EXT-X-MEDIA-SEQUENCE", "#EXT-X-PLAYLIST-TYPE", "#EXT-X-ENDLIST",
+    "#EXT-X-BYTERANGE", "#EXT-X-DISCONTINUITY", "#EXT-X-DISCONTINUITY-SEQUENCE", "#EXT-X-PROGRAM-DATE-TIME",
+    "#EXT-X-PART", "#EXT-X-PART-INF", "#EXT-X-SERVER-CONTROL", "#EXT-X-SKIP", "#EXT-X-PRELOAD-HINT",
+    "#EXT-X-RENDITION-REPORT", "#EXT-X-GAP", "#EXT-X-BITRATE",
+    "#EXT-X-I-FRAMES-ONLY",
+];
 
-        if let Some(ParsedLine::Tag(tag)) = parse_line("#EXT-X-INDEPENDENT-SEGMENTS") {
-            assert_eq!(tag, "EXT-X-INDEPENDENT-SEGMENTS");
-        } else {
-            assert!(false);
-        }
+pub fn is_media_playlist(data: &str) -> bool {
+    data.split('\n').any(|line| MEDIA_PLAYLIST_TAGS.iter()
+        .any(|t| line.strip_prefix(t).is_some_and(|tail| tail.is_empty() || tail.starts_with(':'))))
+}
 
-        let lmedia = r#"#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac-128k",NAME="English",LANGUAGE="en",DEFAULT=YES,AUTOSELECT=YES,CHANNELS="2",URI="audio/unenc/aac_128k/vod.m3u8""#;
-        if let Some(ParsedLine::TagWithAttributes(tag, attrs)) = parse_line(lmedia) {
-            assert_eq!(tag, "EXT-X-MEDIA");
-            if let AttributeValue::EnumeratedString(s) = attrs["TYPE"] {
-                assert_eq!(s, "AUDIO");
-            } else {
-                assert!(false);
-            }
-            if let AttributeValue::QuotedString(s) = attrs["URI"] {
-                assert_eq!(s, "audio/unenc/aac_128k/vod.m3u8");
-            } else {
-                assert!(false);
-            }
-        } else {
-            assert!(false);
-        }
+/* Parse either a multivariant or a media playlist, depending on the tags it contains */
+pub fn parse(data: &str) -> Result<Playlist, ParseError> {
+    parse_with_options(data, &ParseOptions::default()).map(|(playlist, _)| playlist)
+}
 
-        if let Some(ParsedLine::Uri(u)) = parse_line("hdr10/unenc/1650k/vod.m3u8") {
-            assert_eq!(u, "hdr10/unenc/1650k/vod.m3u8");
-        } else {
-            assert!(false);
-        }
+pub fn parse_with_options(data: &str, options: &ParseOptions) -> Result<(Playlist, Vec<ParseWarning>), ParseError> {
+    if is_media_playlist(data) {
+        parse_media_playlist_with_options(data, options).map(|(pl, w)| (Playlist::Media(pl), w))
+    } else {
+        parse_playlist_with_options(data, options).map(|(pl, w)| (Playlist::Multivariant(pl), w))
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
     #[test]
     fn test_intepret_ext_x_media() {
@@ -414,13 +874,69 @@ mod tests {
             assert_eq!(m.language.unwrap(), "en");
             assert!(m.default);
             assert!(m.autoselect);
-            assert_eq!(m.channels.unwrap(), "2");
-            assert_eq!(m.uri, "audio/unenc/aac_128k/vod.m3u8");
+            assert_eq!(m.channels.unwrap().to_string(), "2");
+            assert_eq!(m.uri.unwrap(), "audio/unenc/aac_128k/vod.m3u8");
+            assert!(!m.forced);
         } else {
             assert!(false);
         }
     }
 
+    #[test]
+    fn test_intepret_ext_x_media_full() {
+        let l = r#"#EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID="subs",NAME="Deutsch (forced)",LANGUAGE="de",ASSOC-LANGUAGE="de-AT",DEFAULT=NO,AUTOSELECT=YES,FORCED=YES,CHARACTERISTICS="public.accessibility.transcribes-spoken-dialog",STABLE-RENDITION-ID="de-forced",URI="subs/de/forced.m3u8""#;
+        let parsed = parse_line(l).unwrap();
+        let m = intepret_ext_x_media(parsed.extract_as_tag_with_attributes().1).unwrap();
+        assert_eq!(m.type_, format::MediaType::Subtitles);
+        assert!(m.forced);
+        assert_eq!(m.assoc_language.unwrap(), "de-AT");
+        assert_eq!(m.characteristics.unwrap(), "public.accessibility.transcribes-spoken-dialog");
+        assert_eq!(m.stable_rendition_id.unwrap(), "de-forced");
+
+        let l = r#"#EXT-X-MEDIA:TYPE=CLOSED-CAPTIONS,GROUP-ID="cc",NAME="English",LANGUAGE="en",DEFAULT=YES,AUTOSELECT=YES,INSTREAM-ID="CC1""#;
+        let parsed = parse_line(l).unwrap();
+        let m = intepret_ext_x_media(parsed.extract_as_tag_with_attributes().1).unwrap();
+        assert_eq!(m.type_, format::MediaType::ClosedCaptions);
+        assert_eq!(m.uri, None);
+        assert_eq!(m.instream_id, Some(format::InstreamId::Cea608(1)));
+
+        for (id, expected) in [("CC4", Some(format::InstreamId::Cea608(4))), ("SERVICE63", Some(format::InstreamId::Cea708(63))),
+                ("CC5", None), ("SERVICE0", None), ("SERVICE064", None), ("CC", None),
+                ("CCC1", None), ("CC+1", None), ("SERVICEX5", None), ("SERVICE+5", None)] {
+            let l = format!(r#"#EXT-X-MEDIA:TYPE=CLOSED-CAPTIONS,GROUP-ID="cc",NAME="English",INSTREAM-ID="{}""#, id);
+            let parsed = parse_line(&l).unwrap();
+            let m = intepret_ext_x_media(parsed.extract_as_tag_with_attributes().1);
+            assert_eq!(m.and_then(|m| m.instream_id), expected);
+        }
+
+        let l = r#"#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="flac",NAME="English",DEFAULT=YES,AUTOSELECT=YES,BIT-DEPTH=24,SAMPLE-RATE=96000,URI="a.m3u8""#;
+        let parsed = parse_line(l).unwrap();
+        let m = intepret_ext_x_media(parsed.extract_as_tag_with_attributes().1).unwrap();
+        assert_eq!(m.bit_depth, Some(24));
+        assert_eq!(m.sample_rate, Some(96000));
+    }
+
+    #[test]
+    fn test_intepret_ext_x_media_implicit_defaults() {
+        let l = r#"#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",NAME="English",URI="a.m3u8""#;
+        let parsed = parse_line(l).unwrap();
+        let m = intepret_ext_x_media(parsed.extract_as_tag_with_attributes().1).unwrap();
+        assert!(!m.default);
+        assert!(!m.autoselect);
+        assert!(!m.forced);
+
+        let l = r#"#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",NAME="English",AUTOSELECT=YES,URI="a.m3u8""#;
+        let parsed = parse_line(l).unwrap();
+        let m = intepret_ext_x_media(parsed.extract_as_tag_with_attributes().1).unwrap();
+        assert!(!m.default);
+        assert!(m.autoselect);
+
+        // explicit values still have to be valid
+        let l = r#"#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",NAME="English",DEFAULT=MAYBE,URI="a.m3u8""#;
+        let parsed = parse_line(l).unwrap();
+        assert!(intepret_ext_x_media(parsed.extract_as_tag_with_attributes().1).is_none());
+    }
+
     #[test]
     fn test_intepret_ext_x_stream_inf() {
         let l = r#"#EXT-X-STREAM-INF:BANDWIDTH=2483789,AVERAGE-BANDWIDTH=1762745,CODECS="mp4a.40.2,hvc1.2.4.L90.90",RESOLUTION=960x540,FRAME-RATE=23.97,VIDEO-RANGE=PQ,AUDIO="aac-128k",CLOSED-CAPTIONS=NONE"#;
@@ -430,15 +946,35 @@ mod tests {
             assert_eq!(m.uri, "");
             assert_eq!(m.bandwidth, 2483789);
             assert_eq!(m.average_bandwidth.unwrap(), 1762745);
-            assert_eq!(m.codecs.unwrap(), "mp4a.40.2,hvc1.2.4.L90.90");
+            assert_eq!(m.codecs.unwrap().to_string(), "mp4a.40.2,hvc1.2.4.L90.90");
             assert_eq!(m.resolution.unwrap(), format::Resolution{w: 960, h: 540});
-            assert_eq!(m.frame_rate.unwrap(), 23.97);
+            assert_eq!(m.frame_rate.unwrap().to_string(), "23.97");
+            assert_eq!(m.frame_rate.unwrap().as_f64(), 23.97);
             assert_eq!(m.video_range.unwrap(), format::VideoRange::PQ);
             assert_eq!(m.audio.unwrap(), "aac-128k");
-            assert_eq!(m.closed_captions, None);
+            assert_eq!(m.closed_captions, Some(format::ClosedCaptions::None));
+            assert_eq!(m.score, None);
         } else {
             assert!(false);
         }
+        let parsed = parse_line("#EXT-X-STREAM-INF:BANDWIDTH=2483789,SCORE=2.50").unwrap();
+        let m = interpret_ext_x_stream_inf(parsed.extract_as_tag_with_attributes().1).unwrap();
+        assert_eq!(m.score.unwrap().to_string(), "2.5");
+        let parsed = parse_line(r#"#EXT-X-STREAM-INF:BANDWIDTH=2483789,HDCP-LEVEL=TYPE-1,ALLOWED-CPC="com.example.drm1:SMART-TV/PC""#).unwrap();
+        let m = interpret_ext_x_stream_inf(parsed.extract_as_tag_with_attributes().1).unwrap();
+        assert_eq!(m.hdcp_level, Some(format::HdcpLevel::Type1));
+        assert_eq!(m.allowed_cpc.as_deref(), Some("com.example.drm1:SMART-TV/PC"));
+        let parsed = parse_line(r#"#EXT-X-STREAM-INF:BANDWIDTH=2483789,PATHWAY-ID="cdn-b""#).unwrap();
+        let m = interpret_ext_x_stream_inf(parsed.extract_as_tag_with_attributes().1).unwrap();
+        assert_eq!(m.pathway_id.as_deref(), Some("cdn-b"));
+        let parsed = parse_line(r#"#EXT-X-STREAM-INF:BANDWIDTH=2483789,STABLE-VARIANT-ID="1080p-hevc""#).unwrap();
+        let m = interpret_ext_x_stream_inf(parsed.extract_as_tag_with_attributes().1).unwrap();
+        assert_eq!(m.stable_variant_id.as_deref(), Some("1080p-hevc"));
+        let parsed = parse_line(r#"#EXT-X-STREAM-INF:BANDWIDTH=2483789,VIDEO="angles",SUBTITLES="subs""#).unwrap();
+        let m = interpret_ext_x_stream_inf(parsed.extract_as_tag_with_attributes().1).unwrap();
+        assert_eq!((m.video.as_deref(), m.subtitles.as_deref()), (Some("angles"), Some("subs")));
+        let parsed = parse_line("#EXT-X-STREAM-INF:BANDWIDTH=2483789,SCORE=high").unwrap();
+        assert!(interpret_ext_x_stream_inf(parsed.extract_as_tag_with_attributes().1).is_none());
     }
 
     #[test]
@@ -449,12 +985,40 @@ mod tests {
         if let Some(m) = interpret_ext_x_i_frame_stream_inf(attr) {
             assert_eq!(m.uri, "hdr10/unenc/3300k/vod-iframe.m3u8");
             assert_eq!(m.bandwidth, 222552);
-            assert_eq!(m.codecs.unwrap(), "hvc1.2.4.L93.90");
+            assert_eq!(m.codecs.unwrap().to_string(), "hvc1.2.4.L93.90");
             assert_eq!(m.resolution, Some(format::Resolution{w: 1280, h: 720}));
             assert_eq!(m.video_range.unwrap(), format::VideoRange::PQ);
+            assert_eq!(m.hdcp_level, None);
         } else {
             assert!(false)
         }
+        // written back
+        let data = "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=800000\nv.m3u8\n\
+            #EXT-X-I-FRAME-STREAM-INF:BANDWIDTH=80000,HDCP-LEVEL=TYPE-0,URI=\"v-iframe.m3u8\"\n";
+        let m3u = parse_playlist(data).unwrap();
+        assert_eq!(m3u.i_frame_stream_inf[0].hdcp_level, Some(format::HdcpLevel::Type0));
+        assert!(m3u.to_m3u8().unwrap().contains("#EXT-X-I-FRAME-STREAM-INF:BANDWIDTH=80000,HDCP-LEVEL=TYPE-0,URI="));
+        #[cfg(feature = "serde")]
+        assert_eq!(serde_json::from_value::<format::MultivariantPlaylist>(serde_json::to_value(&m3u).unwrap()).unwrap(), m3u);
+    }
+
+    #[test]
+    fn test_unknown_enumerated_values() {
+        let data = "#EXTM3U\n#EXT-X-MEDIA:TYPE=X-HAPTICS,GROUP-ID=\"h\",NAME=\"Haptics\",URI=\"h.m3u8\"\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"a\",NAME=\"Atmos\",CHANNELS=\"16/JOC/extra/more\",URI=\"a.m3u8\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=1000000,VIDEO-RANGE=XDR\nv.m3u8\n";
+        let m3u = parse_playlist(data).unwrap();
+        assert_eq!(m3u.media[0].type_, format::MediaType::Other("X-HAPTICS".to_string()));
+        assert_eq!(m3u.stream_inf[0].video_range, Some(format::VideoRange::Other("XDR".to_string())));
+        // written back as read
+        let written = m3u.to_m3u8().unwrap();
+        assert!(written.contains("TYPE=X-HAPTICS,") && written.contains("VIDEO-RANGE=XDR"), "{}", written);
+        let channels = m3u.media[1].channels.as_ref().unwrap();
+        assert_eq!((channels.count, channels.other.as_deref()), (16, Some("16/JOC/extra/more")));
+        assert!(written.contains("CHANNELS=\"16/JOC/extra/more\""), "{}", written);
+        assert!(m3u.findings().iter().any(|f| f.rule == "unknown-channels"));
+        let e = parse_playlist("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1000000,HDCP-LEVEL=TYPE-9\nv.m3u8\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidAttribute("EXT-X-STREAM-INF", "HDCP-LEVEL"));
     }
 
     #[test]
@@ -481,6 +1045,7 @@ hdr10/unenc/10000k/vod.m3u8
         assert_eq!(m3u.stream_inf[0].uri, "hdr10/unenc/1650k/vod.m3u8");
         assert_eq!(m3u.stream_inf[1].resolution, Some(format::Resolution{w: 2560, h: 1440}));
         assert_eq!(m3u.stream_inf[1].uri, "hdr10/unenc/10000k/vod.m3u8");
+        assert_eq!(m3u.stream_inf[1].infer_label(), "1440p HDR");
         assert_eq!(m3u.i_frame_stream_inf.len(), 1);
         assert_eq!(m3u.i_frame_stream_inf[0].uri, "hdr10/unenc/3300k/vod-iframe.m3u8");
     }
@@ -517,4 +1082,462 @@ hdr10/unenc/10000k/vod.m3u8
         assert!(validate.is_err());
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_parse_error_position() {
+        let data = "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=100,CODECS=\"avc1\"RESOLUTION=1x1\nv.m3u8\n";
+        let Err(e) = parse_playlist(data) else {
+            assert!(false);
+            return
+        };
+        assert_eq!(e.kind, ParseErrorKind::Syntax(Expected::Comma));
+        assert_eq!(e.lineno, 1);
+        assert_eq!(e.column, 45);
+        assert_eq!(e.offset, 8 + 45);
+        assert_eq!(e.snippet, "RESOLUTION=1x1");
+        assert_eq!(e.to_string(), r#"Failed to parse line, expected comma at line 2, column 46 near "RESOLUTION=1x1""#);
+
+        // at the second occurrence of the name
+        let e = parse_playlist("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1,BANDWIDTH=2\nv.m3u8\n").unwrap_err();
+        assert_eq!((e.kind, e.lineno, e.column), (ParseErrorKind::DuplicateAttribute, 1, 30));
+        assert_eq!(e.to_string(), r#"Attribute repeated in the attribute list at line 2, column 31 near "BANDWIDTH=2""#);
+    }
+
+    #[test]
+    fn test_parse_error_kinds() {
+        let e = parse_playlist(include_str!("../data/missing_extm3u.m3u8")).unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::MissingExtM3U);
+        assert_eq!(e.lineno, 0);
+
+        let e = parse_playlist(include_str!("../data/missing_stream_inf_uri.m3u8")).unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::ExpectedUri);
+
+        let e = parse_playlist(include_str!("../data/truncated.m3u8")).unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::MissingAttribute("EXT-X-I-FRAME-STREAM-INF", "URI"));
+
+        let e = parse_playlist("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::Truncated);
+        assert_eq!(e.lineno, 1);
+
+        let e = parse_playlist("#EXTM3U\n#EXT-X-MEDIA:TYPE=AUDIO\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::MissingAttribute("EXT-X-MEDIA", "GROUP-ID"));
+
+        let e = parse_playlist("#EXTM3U\n#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"a\",NAME=\"a\",DEFAULT=1\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidAttribute("EXT-X-MEDIA", "DEFAULT"));
+        assert_eq!(e.to_string(), r##"Invalid value of attribute DEFAULT of EXT-X-MEDIA at line 2, column 1 near "#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"a\",NAM""##);
+        assert_eq!(e.lineno, 1);
+
+        let data = "#EXTM3U\n#EXT-X-VERSION:6\n#EXT-X-VERSION:7\n#EXT-X-STREAM-INF:BANDWIDTH=1\nv.m3u8\n";
+        let e = parse_playlist(data).unwrap_err();
+        assert_eq!((e.kind, e.lineno), (ParseErrorKind::DuplicateTag("EXT-X-VERSION"), 2));
+        let (pl, warnings) = parse_playlist_lenient(data);
+        assert_eq!((pl.version, warnings[0].kind), (Some(6), ParseErrorKind::DuplicateTag("EXT-X-VERSION")));
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:10\n#EXT-X-VERSION:3\n").unwrap_err();
+        assert_eq!(e.to_string(), "More than one EXT-X-VERSION at line 4, column 1 near \"#EXT-X-VERSION:3\"");
+
+        let e = parse_playlist("#EXTM3U\n#EXT-X-MEDIA:=AUDIO\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::Syntax(Expected::AttributeName));
+        assert_eq!(e.column, 13);
+
+        let e = parse_playlist("#EXTM3U\n\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::EmptyPlaylist);
+
+        let e = parse_playlist("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1\nvideo\t1.m3u8\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidUri);
+        assert_eq!(e.lineno, 2);
+        // spaces are kept, the validator reports them
+        let pl = parse_playlist("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1\nvideo 1.m3u8\n").unwrap();
+        assert_eq!(pl.stream_inf[0].uri, "video 1.m3u8");
+        let e = parse_playlist("#EXTM3U\n#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"a\",NAME=\"a\",URI=\"a\u{1}b.m3u8\"\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidTag("EXT-X-MEDIA"));
+
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-ENDLIST\n#EXTINF:4,\nseg.ts\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::UnexpectedLine);
+        assert_eq!(e.lineno, 3);
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXTINF:4,\n#EXT-X-ENDLIST\nseg.ts\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::ExpectedUri);
+    }
+
+    #[test]
+    fn test_parse_playlist_lenient() {
+        let data = include_str!("../data/missing_stream_inf_uri.m3u8");
+        let (m3u, warnings) = parse_playlist_lenient(data);
+        assert_eq!(m3u.media.len(), 2);
+        assert_eq!(m3u.stream_inf.len(), 5);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, ParseErrorKind::ExpectedUri);
+
+        let data = include_str!("../data/truncated.m3u8");
+        let (m3u, warnings) = parse_playlist_lenient(data);
+        assert_eq!(m3u.stream_inf.len(), 12);
+        assert!(m3u.i_frame_stream_inf.is_empty());
+        assert_eq!(warnings.len(), 1);
+
+        let data = "#EXT-X-INDEPENDENT-SEGMENTS\n#EXT-X-MEDIA:=1\n#EXT-X-STREAM-INF:BANDWIDTH=1\nv.m3u8\n#EXT-X-STREAM-INF:BANDWIDTH=2";
+        let (m3u, warnings) = parse_playlist_lenient(data);
+        assert!(m3u.independent_segments);
+        assert_eq!(m3u.stream_inf.len(), 1);
+        assert_eq!(m3u.stream_inf[0].uri, "v.m3u8");
+        let kinds = warnings.iter().map(|w| w.kind).collect::<Vec<_>>();
+        assert_eq!(kinds, vec![
+            ParseErrorKind::MissingExtM3U,
+            ParseErrorKind::Syntax(Expected::AttributeName),
+            ParseErrorKind::Truncated]);
+
+        let (_, warnings) = parse_playlist_lenient("#EXT-X-MEDIA:=1\n#EXT-X-STREAM-INF:BANDWIDTH=1\nv.m3u8\n");
+        let kinds = warnings.iter().map(|w| w.kind).collect::<Vec<_>>();
+        assert_eq!(kinds, vec![ParseErrorKind::MissingExtM3U, ParseErrorKind::Syntax(Expected::AttributeName)]);
+        let (_, warnings) = parse_media_playlist_with_options("#EXT-X-MAP:=1\n#EXT-X-TARGETDURATION:4\n",
+            &ParseOptions{lenient: true, ..Default::default()}).unwrap();
+        assert_eq!(warnings[0].kind, ParseErrorKind::MissingExtM3U);
+    }
+
+    #[test]
+    fn test_parse_playlist_passthrough() {
+        let data = "#EXTM3U\n#EXT-X-VERSION:6\n# comment\n#EXT-X-SESSION-DATA:DATA-ID=\"com.example\",VALUE=\"x\"\n#EXT-X-STREAM-INF:BANDWIDTH=1\nv.m3u8\n";
+        assert!(parse_playlist(data).is_err());
+        let m3u = parse_playlist_passthrough(data).unwrap();
+        assert_eq!(m3u.stream_inf.len(), 1);
+        assert_eq!(m3u.version, Some(6));
+        let lines = m3u.unrecognized.iter().map(|l| (l.lineno, l.text.as_str())).collect::<Vec<_>>();
+        assert_eq!(lines, vec![
+            (2, "# comment"),
+            (3, "#EXT-X-SESSION-DATA:DATA-ID=\"com.example\",VALUE=\"x\"")]);
+
+        // known tags still have to be valid
+        let e = parse_playlist_passthrough("#EXTM3U\n#EXT-X-STREAM-INF:CODECS=\"avc1\"\nv.m3u8\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::MissingAttribute("EXT-X-STREAM-INF", "BANDWIDTH"));
+    }
+
+    #[test]
+    fn test_parse_media_playlist_passthrough() {
+        let data = "#EXTM3U\n#EXT-X-TARGETDURATION:10\n# comment\n#EXTINF:10,\n#EXT-X-CUE-OUT\na.ts\n";
+        let lines = |options: ParseOptions| parse_media_playlist_with_options(data, &options).unwrap().0
+            .unrecognized.iter().map(|l| (l.lineno, l.text.clone())).collect::<Vec<_>>();
+        let expected = vec![(2, "# comment".to_string()), (4, "#EXT-X-CUE-OUT".to_string())];
+        assert_eq!(lines(ParseOptions{passthrough: true, ..Default::default()}), expected);
+        assert_eq!(lines(ParseOptions{preserve: true, ..Default::default()}), expected);
+
+        // like a comment between EXT-X-STREAM-INF and its URI in a multivariant playlist
+        let options = ParseOptions{passthrough: true, ..Default::default()};
+        let e = parse_media_playlist_with_options("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\n# comment\na.ts\n", &options)
+            .unwrap_err();
+        assert_eq!((e.kind, e.lineno), (ParseErrorKind::Syntax(Expected::TagOrUri), 3));
+        let e = parse_playlist_with_options("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1\n# comment\nv.m3u8\n", &options)
+            .unwrap_err();
+        assert_eq!((e.kind, e.lineno), (ParseErrorKind::Syntax(Expected::TagOrUri), 2));
+    }
+
+    #[test]
+    fn test_parse_error_suggestion() {
+        let e = parse_playlist("#EXTM3U\n#EXT-X-STREAMINF:BANDWIDTH=1\nv.m3u8\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::UnexpectedLine);
+        assert_eq!(e.suggestion.as_deref(), Some("EXT-X-STREAM-INF"));
+        assert!(e.to_string().ends_with(", did you mean EXT-X-STREAM-INF?"));
+
+        let e = parse_playlist("#EXTM3U\n#EXT-X-STREAM-INF:BANDWITH=1\nv.m3u8\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::MissingAttribute("EXT-X-STREAM-INF", "BANDWIDTH"));
+        assert_eq!(e.suggestion.as_deref(), Some("BANDWIDTH"));
+
+        let e = parse_playlist("#EXTM3U\n#EXT-X-VERSON:7\n#EXT-X-STREAM-INF:BANDWIDTH=1\nv.m3u8\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::UnexpectedLine);
+        assert_eq!(e.suggestion.as_deref(), Some("EXT-X-VERSION"));
+
+        let e = parse_playlist("#EXTM3U\n#EXT-X-UNHEARD-OF\n").unwrap_err();
+        assert_eq!(e.suggestion, None);
+    }
+
+    #[test]
+    fn test_parse_media_playlist() {
+        let data = include_str!("../data/media_playlist.m3u8");
+        let pl = parse_media_playlist(data).unwrap();
+        assert_eq!(pl.version, Some(3));
+        assert_eq!(pl.target_duration, 10);
+        assert_eq!(pl.media_sequence, 0);
+        assert_eq!(pl.playlist_type, Some(PlaylistType::Vod));
+        assert!(pl.end_list);
+        assert!(pl.independent_segments);
+        assert_eq!(pl.segments.len(), 5);
+        assert_eq!(pl.segments[0].uri, "segment_000.ts");
+        assert_eq!(pl.segments[0].duration, 10.01);
+        assert_eq!(pl.segments[0].title, None);
+        assert_eq!(pl.segments[2].title.as_deref(), Some("second part"));
+        assert_eq!(pl.segments[3].duration, 10.0);
+    }
+
+    #[test]
+    fn test_parse_media_playlist_errors() {
+        let e = parse_media_playlist("#EXTM3U\n#EXTINF:10,\nsegment.ts\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::MissingTag("EXT-X-TARGETDURATION"));
+
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:ten,\nsegment.ts\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidTag("EXTINF"));
+        assert_eq!(e.lineno, 2);
+
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\nsegment.ts\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::UnexpectedLine);
+
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::Truncated);
+
+        // multivariant playlist tags are not expected
+        let data = include_str!("../data/master_unenc_hdr10_all.m3u8");
+        assert!(parse_media_playlist(data).is_err());
+    }
+
+    #[test]
+    fn test_parse_media_playlist_byterange() {
+        let data = include_str!("../data/byterange_playlist.m3u8");
+        let pl = parse_media_playlist(data).unwrap();
+        assert_eq!(pl.segments.len(), 5);
+        assert_eq!(pl.segments[0].byte_range, Some(ByteRange{length: 75232, offset: 0}));
+        // offsets continue the previous sub-range of the same resource
+        assert_eq!(pl.segments[1].byte_range, Some(ByteRange{length: 82112, offset: 75232}));
+        assert_eq!(pl.segments[2].byte_range, Some(ByteRange{length: 69864, offset: 157344}));
+        assert_eq!(pl.segments[3].byte_range, Some(ByteRange{length: 1024, offset: 0}));
+        assert_eq!(pl.segments[4].byte_range, None);
+
+        // implicit offset needs a previous sub-range of the same resource
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\n#EXT-X-BYTERANGE:100\na.ts\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidTag("EXT-X-BYTERANGE"));
+        // reported at the tag, not at the URI of the segment
+        assert_eq!((e.lineno, e.column, e.snippet.as_str()), (3, 0, "#EXT-X-BYTERANGE:100"));
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\n#EXT-X-BYTERANGE:100@0\na.ts\n\
+            #EXTINF:10,\n#EXT-X-BYTERANGE:100\nb.ts\n").unwrap_err();
+        assert_eq!((e.kind, e.lineno), (ParseErrorKind::InvalidTag("EXT-X-BYTERANGE"), 6));
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\n#EXT-X-BYTERANGE:100@x\na.ts\n").unwrap_err();
+        assert_eq!((e.kind, e.lineno), (ParseErrorKind::InvalidTag("EXT-X-BYTERANGE"), 3));
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\n#EXT-X-BYTERANGE:0@0\na.ts\n").unwrap_err();
+        assert_eq!((e.kind, e.lineno), (ParseErrorKind::InvalidTag("EXT-X-BYTERANGE"), 3));
+
+        // the range of a segment dropped in lenient mode does not apply to the next one
+        let (pl, warnings) = parse_media_playlist_with_options("#EXTM3U\n#EXT-X-TARGETDURATION:10\n\
+            #EXTINF:10,\n#EXT-X-BYTERANGE:100@0\na\tb.ts\n#EXTINF:10,\nc.ts\n",
+            &ParseOptions{lenient: true, ..Default::default()}).unwrap();
+        assert_eq!(warnings.iter().map(|w| w.kind).collect::<Vec<_>>(), [ParseErrorKind::InvalidUri]);
+        assert_eq!((pl.segments.len(), &pl.segments[0].byte_range), (1, &None));
+    }
+
+    #[test]
+    fn test_parse_media_playlist_date_time() {
+        let data = include_str!("../data/live_playlist.m3u8");
+        let pl = parse_media_playlist(data).unwrap();
+        let raw = |i: usize| pl.segments[i].program_date_time.as_ref().map(|dt| dt.as_str());
+        assert_eq!(raw(0), Some("2024-05-01T12:00:00.000Z"));
+        assert_eq!(raw(1), None);
+        assert_eq!(raw(5), Some("2024-05-01T12:00:40.500+00:00"));
+        // extrapolated from the previous segment, and across the discontinuity
+        #[cfg(feature = "chrono")] {
+            let date_times = pl.date_times();
+            assert_eq!(date_times[1], parse_date_time("2024-05-01T12:00:06.006Z"));
+            assert_eq!(date_times[4], parse_date_time("2024-05-01T12:00:36Z"));
+            assert_eq!(date_times[5], parse_date_time("2024-05-01T12:00:40.5Z"));
+        }
+
+        // backwards before the first PROGRAM-DATE-TIME
+        let pl = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:4,\na.ts\n\
+            #EXT-X-PROGRAM-DATE-TIME:2024-05-01T12:00:00Z\n#EXTINF:4,\nb.ts\n").unwrap();
+        #[cfg(feature = "chrono")]
+        assert_eq!(pl.date_times()[0], parse_date_time("2024-05-01T11:59:56Z"));
+        assert_eq!(pl.segments[0].program_date_time, None);
+
+        #[cfg(feature = "chrono")] {
+            let pl = parse_media_playlist(include_str!("../data/media_playlist.m3u8")).unwrap();
+            assert!(pl.date_times().iter().all(|dt| dt.is_none()));
+        }
+
+        // no date time past a duration out of range, forwards and backwards
+        #[cfg(feature = "chrono")] {
+            let pl = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:1e300,\na.ts\n#EXTINF:4,\nb.ts\n\
+                #EXT-X-PROGRAM-DATE-TIME:2024-05-01T12:00:00Z\n#EXTINF:1e300,\nc.ts\n#EXTINF:4,\nd.ts\n").unwrap();
+            assert_eq!(pl.date_times(), [None, parse_date_time("2024-05-01T11:59:56Z"), parse_date_time("2024-05-01T12:00:00Z"), None]);
+        }
+
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXT-X-PROGRAM-DATE-TIME:yesterday\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidTag("EXT-X-PROGRAM-DATE-TIME"));
+    }
+
+    #[test]
+    fn test_parse_media_playlist_discontinuity() {
+        let data = include_str!("../data/live_playlist.m3u8");
+        let pl = parse_media_playlist(data).unwrap();
+        assert_eq!(pl.media_sequence, 1200);
+        assert!(!pl.end_list);
+        let flags: Vec<bool> = pl.segments.iter().map(|s| s.discontinuity).collect();
+        assert_eq!(flags, [false, false, false, true, false, true]);
+        assert_eq!(pl.discontinuity_sequence, 7);
+        assert_eq!(pl.discontinuity_sequences(), [7, 7, 7, 8, 8, 9]);
+
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\na.ts\n#EXT-X-DISCONTINUITY-SEQUENCE:3\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidTag("EXT-X-DISCONTINUITY-SEQUENCE"));
+        let pl = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\na.ts\n#EXT-X-DISCONTINUITY\n#EXTINF:10,\nb.ts\n").unwrap();
+        assert_eq!(pl.discontinuity_sequences()[1], 1);
+        let pl = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXT-X-DISCONTINUITY-SEQUENCE:18446744073709551615\n\
+            #EXTINF:10,\na.ts\n#EXT-X-DISCONTINUITY\n#EXTINF:10,\nb.ts\n").unwrap();
+        assert_eq!(pl.discontinuity_sequences(), [u64::MAX, u64::MAX]);
+        assert_eq!(pl.slice(std::time::Duration::from_secs(15), std::time::Duration::MAX).discontinuity_sequence, u64::MAX);
+    }
+
+    #[test]
+    fn test_parse_media_playlist_gap_bitrate() {
+        let data = "#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXTINF:4,\na.ts\n#EXT-X-BITRATE:1200\n#EXTINF:4,\nb.ts\n\
+            #EXT-X-GAP\n#EXTINF:4,\nc.ts\n#EXT-X-BYTERANGE:1000@0\n#EXTINF:4,\nd.ts\n#EXT-X-BITRATE:800\n#EXTINF:4,\ne.ts\n";
+        let pl = parse_media_playlist(data).unwrap();
+        let gaps: Vec<bool> = pl.segments.iter().map(|s| s.gap).collect();
+        assert_eq!(gaps, [false, false, true, false, false]);
+        let bitrates: Vec<Option<u64>> = pl.segments.iter().map(|s| s.bitrate).collect();
+        assert_eq!(bitrates, [None, Some(1200), Some(1200), None, Some(800)]);
+
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-BITRATE:fast\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidTag("EXT-X-BITRATE"));
+    }
+
+    #[test]
+    fn test_parse_media_playlist_keys() {
+        let data = include_str!("../data/encrypted_playlist.m3u8");
+        let pl = parse_media_playlist(data).unwrap();
+        assert!(pl.segments[0].keys.is_empty());
+        let key = &pl.segments[1].keys[0];
+        assert_eq!(key.method, KeyMethod::Aes128);
+        assert_eq!(key.uri, "https://keys.example.com/k1");
+        assert_eq!(key.iv.as_deref(), Some("0x9c7db8778570d05c3177c349fd9236aa"));
+        assert_eq!(pl.segments[2].keys, pl.segments[1].keys);
+        // the identity key is replaced, the FairPlay one added next to it
+        let keys = &pl.segments[3].keys;
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].keyformat.as_deref(), Some("com.apple.streamingkeydelivery"));
+        assert_eq!(keys[1].method, KeyMethod::SampleAes);
+        assert_eq!(keys[1].uri, "https://keys.example.com/k2");
+        assert!(pl.segments[4].keys.is_empty());
+
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXT-X-KEY:METHOD=AES-128\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidTag("EXT-X-KEY"));
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXT-X-KEY:METHOD=AES-128,URI=\"k\",IV=0x12\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidTag("EXT-X-KEY"));
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXT-X-KEY:METHOD=AES-256,URI=\"k\"\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidAttribute("EXT-X-KEY", "METHOD"));
+    }
+
+    #[test]
+    fn test_parse_media_playlist_parts() {
+        let data = include_str!("../data/ll_hls_playlist.m3u8");
+        let pl = parse_media_playlist(data).unwrap();
+        assert_eq!(pl.part_target, Some(1.004));
+        assert_eq!(pl.segments.len(), 2);
+        let parts = &pl.segments[0].parts;
+        assert_eq!(parts.len(), 4);
+        assert!(parts[0].independent);
+        assert!(!parts[1].independent);
+        assert_eq!(parts[0].byte_range, Some(ByteRange{length: 20000, offset: 0}));
+        // ranges without offset continue the previous part
+        assert_eq!(parts[3].byte_range, Some(ByteRange{length: 19000, offset: 59000}));
+        let parts = &pl.segments[1].parts;
+        assert_eq!(parts[1].uri, "filePart267.1.mp4");
+        assert_eq!(parts[1].duration, 1.0);
+        assert!(parts[1].gap);
+        assert_eq!(parts[1].byte_range, None);
+        // parts of the segment being produced
+        assert_eq!(pl.trailing_parts.len(), 1);
+        assert_eq!(pl.trailing_parts[0].uri, "filePart268.0.mp4");
+
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-PART:DURATION=1,URI=\"p.mp4\"\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::MissingTag("EXT-X-PART-INF"));
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-PART-INF:PART-TARGET=1\n\
+            #EXT-X-PART:URI=\"p.mp4\"\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::MissingAttribute("EXT-X-PART", "DURATION"));
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-PART-INF:PART-TARGET=1\n\
+            #EXT-X-PART:DURATION=1,URI=\"p.mp4\",BYTERANGE=\"100\"\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidAttribute("EXT-X-PART", "BYTERANGE"));
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-PART-INF:PART-TARGET=1\n\
+            #EXT-X-PART:DURATION=1,URI=\"p.mp4\",BYTERANGE=\"100@18446744073709551600\"\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidAttribute("EXT-X-PART", "BYTERANGE"));
+    }
+
+    #[test]
+    fn test_parse_media_playlist_server_control() {
+        let data = include_str!("../data/ll_hls_playlist.m3u8");
+        let pl = parse_media_playlist(data).unwrap();
+        let sc = pl.server_control.as_ref().unwrap();
+        assert!(sc.can_block_reload);
+        assert!(!sc.can_skip_dateranges);
+        assert_eq!(sc.can_skip_until, Some(24.0));
+        assert_eq!(sc.part_hold_back, Some(3.012));
+        assert_eq!(sc.hold_back, None);
+        assert_eq!(pl.preload_hints.len(), 2);
+        assert_eq!(pl.preload_hints[0].type_, PreloadHintType::Part);
+        assert_eq!(pl.preload_hints[0].uri, "filePart268.1.mp4");
+        assert_eq!(pl.preload_hints[1].type_, PreloadHintType::Map);
+        assert_eq!((pl.preload_hints[1].byte_range_start, pl.preload_hints[1].byte_range_length), (Some(0), Some(720)));
+        assert_eq!(pl.skip, None);
+        assert_eq!(pl.findings(), []);
+        assert_eq!(pl.rendition_reports, [
+            RenditionReport{ uri: "../1M/waitForMSN.php".parse().unwrap(), last_msn: Some(268), last_part: Some(0) },
+            RenditionReport{ uri: "../4M/waitForMSN.php".parse().unwrap(), last_msn: None, last_part: None },
+        ]);
+
+        let delta = "#EXTM3U\n#EXT-X-VERSION:9\n#EXT-X-TARGETDURATION:4\n#EXT-X-MEDIA-SEQUENCE:100\n\
+            #EXT-X-SKIP:SKIPPED-SEGMENTS=12,RECENTLY-REMOVED-DATERANGES=\"ad1\tad2\"\n#EXTINF:4,\nseg112.mp4\n";
+        let pl = parse_media_playlist(delta).unwrap();
+        let skip = pl.skip.as_ref().unwrap();
+        assert_eq!(skip.skipped_segments, 12);
+        assert_eq!(skip.recently_removed_dateranges, ["ad1", "ad2"]);
+        assert!(pl.validate().is_err());
+
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXTINF:4,\nseg.mp4\n#EXT-X-SKIP:SKIPPED-SEGMENTS=1\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidTag("EXT-X-SKIP"));
+        let e = parse_media_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-PRELOAD-HINT:TYPE=SEGMENT,URI=\"s.mp4\"\n").unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidAttribute("EXT-X-PRELOAD-HINT", "TYPE"));
+    }
+
+    #[test]
+    fn test_parse_resolution_param() {
+        assert_eq!(parse_resolution_param("640x360"), Some(format::Resolution{w: 640, h: 360}));
+        assert_eq!(parse_resolution_param("1080p"), Some(format::Resolution{w: 1920, h: 1080}));
+        assert_eq!(parse_resolution_param("4K"), Some(format::Resolution{w: 3840, h: 2160}));
+        assert_eq!(parse_resolution_param("big"), None);
+    }
+
+    #[test]
+    fn test_parse_bandwidth_range_param() {
+        assert_eq!(parse_bandwidth_range_param("1000000..8000000"), Some((1000000, 8000000)));
+        assert_eq!(parse_bandwidth_range_param("..8000000"), Some((0, 8000000)));
+        assert_eq!(parse_bandwidth_range_param("1000000.."), Some((1000000, u64::MAX)));
+        assert_eq!(parse_bandwidth_range_param("8000000..1000000"), None);
+        assert_eq!(parse_bandwidth_range_param("1000000"), None);
+        assert_eq!(parse_bandwidth_range_param("1M..8M"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn test_parse_date_time() {
+        let expected = parse_date_time("2024-05-01T12:00:00Z").unwrap();
+        assert_eq!(parse_date_time("2024-05-01T12:00:00.000Z"), Some(expected));
+        assert_eq!(parse_date_time("2024-05-01T14:00:00+02:00"), Some(expected));
+        assert_eq!(parse_date_time("2024-05-01T14:00:00+0200"), Some(expected));
+        assert_eq!(parse_date_time("2024-05-01T14:00:00+02"), Some(expected));
+        assert_eq!(parse_date_time("2024-05-01"), None);
+        // a valid syntax for a day that does not exist
+        assert_eq!(ProgramDateTime::parse("2024-02-30T12:00:00Z").unwrap().to_date_time(), None);
+    }
+
+    #[test]
+    fn test_program_date_time_syntax() {
+        for valid in ["2024-05-01T12:00:00Z", "2024-05-01T12:00:00.000Z", "2024-05-01T14:00:00+02:00",
+            "2024-05-01T14:00:00+0200", "2024-05-01T14:00:00-02", "2024-05-01t12:00:00z"] {
+            assert_eq!(ProgramDateTime::parse(valid).unwrap().as_str(), valid);
+        }
+        for invalid in ["2024-05-01", "2024-05-01T12:00:00", "2024-13-01T12:00:00Z", "2024-05-01T24:00:00Z",
+            "2024-05-01 12:00:00Z", "yesterday", ""] {
+            assert!(ProgramDateTime::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_auto_detect() {
+        let data = include_str!("../data/media_playlist.m3u8");
+        assert!(matches!(parse(data), Ok(Playlist::Media(_))));
+        let data = include_str!("../data/master_unenc_hdr10_all.m3u8");
+        assert!(matches!(parse(data), Ok(Playlist::Multivariant(_))));
+        assert!(!is_media_playlist("#EXTM3U\n#EXTINFO:1\n"));
+    }
+
+}
@@ -0,0 +1,195 @@
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::format::MultivariantPlaylist;
+use crate::writer::{self, DefaultTagWriter, TagLine, TagWriter};
+
+// Changes between a multivariant playlist and the result of filters, rewrites and
+// fixes, so they can be reviewed before writing the output. Variants are matched by
+// id, renditions by TYPE, GROUP-ID and NAME, then the others by URI, see
+// writer::pair_entries, and last those with the same attributes but for the URI.
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "change", rename_all = "snake_case"))]
+pub enum Change {
+    Add { tag: String, line: String },
+    Remove { tag: String, line: String },
+    // `line` as it was before, values are None when the attribute is absent
+    Modify { tag: String, line: String, attribute: String, before: Option<String>, after: Option<String> },
+    // URIs of the variants present before and after, in order
+    Reorder { tag: String, before: Vec<String>, after: Vec<String> },
+}
+
+// Tag as written, followed by its URI line if any
+fn line(tag: &TagLine, uri: Option<&str>) -> String {
+    let text = DefaultTagWriter.write(tag).unwrap_or_else(|e| format!("#{}: {}", tag.name, e));
+    match uri {
+        Some(uri) => format!("{}\n{}", text, uri),
+        None => text,
+    }
+}
+
+fn value(tag: &TagLine, name: &str) -> Option<String> {
+    tag.get(name).map(|v| writer::write_value(v, None).unwrap_or_else(|e| e))
+}
+
+// Pair the entries left whose tags only differ by URI, e.g. rewritten ones
+fn pair_rewritten<T>(pairs: &mut [Option<usize>], before: &[T], after: &[T], tag: impl Fn(&T) -> TagLine) {
+    let without_uri = |t: &T| {
+        let mut ret = tag(t);
+        ret.attributes.retain(|(n, _)| n != "URI");
+        ret
+    };
+    for i in 0..before.len() {
+        if pairs[i].is_some() {
+            continue
+        }
+        let b = without_uri(&before[i]);
+        if let Some(j) = (0..after.len()).find(|j| !pairs.contains(&Some(*j)) && without_uri(&after[*j]) == b) {
+            pairs[i] = Some(j);
+        }
+    }
+}
+
+fn diff<T>(changes: &mut Vec<Change>, before: &[T], after: &[T], pairs: &[Option<usize>],
+    tag: impl Fn(&T) -> TagLine, uri: impl Fn(&T) -> Option<String>) {
+    for (b, pair) in before.iter().zip(pairs) {
+        let tb = tag(b);
+        let Some(a) = pair.map(|j| &after[j]) else {
+            changes.push(Change::Remove{ tag: tb.name.clone(), line: line(&tb, uri(b).as_deref()) });
+            continue
+        };
+        let ta = tag(a);
+        // URI line of variants
+        if uri(b) != uri(a) {
+            changes.push(Change::Modify{ tag: tb.name.clone(), line: line(&tb, uri(b).as_deref()),
+                attribute: "URI".to_string(), before: uri(b), after: uri(a) });
+        }
+        let mut names: Vec<&str> = tb.attributes.iter().map(|(n, _)| n.as_str()).collect();
+        names.extend(ta.attributes.iter().map(|(n, _)| n.as_str()).filter(|n| tb.get(n).is_none()));
+        for name in names {
+            let (vb, va) = (value(&tb, name), value(&ta, name));
+            if vb != va {
+                changes.push(Change::Modify{ tag: tb.name.clone(), line: line(&tb, uri(b).as_deref()),
+                    attribute: name.to_string(), before: vb, after: va });
+            }
+        }
+    }
+    for (_, a) in after.iter().enumerate().filter(|(j, _)| !pairs.contains(&Some(*j))) {
+        let ta = tag(a);
+        changes.push(Change::Add{ tag: ta.name.clone(), line: line(&ta, uri(a).as_deref()) });
+    }
+}
+
+/* Changes turning `before` into `after`, removals and modifications first then additions, by tag */
+pub fn plan(before: &MultivariantPlaylist, after: &MultivariantPlaylist) -> Vec<Change> {
+    let mut ret = vec![];
+    if before.version != after.version {
+        let tag = "EXT-X-VERSION".to_string();
+        if let Some(v) = before.version {
+            ret.push(Change::Remove{ tag: tag.clone(), line: format!("#{}:{}", tag, v) });
+        }
+        if let Some(v) = after.version {
+            let line = format!("#{}:{}", tag, v);
+            ret.push(Change::Add{ tag, line });
+        }
+    }
+    if before.independent_segments != after.independent_segments {
+        let tag = "EXT-X-INDEPENDENT-SEGMENTS".to_string();
+        let line = format!("#{}", tag);
+        ret.push(if after.independent_segments { Change::Add{ tag, line } } else { Change::Remove{ tag, line } });
+    }
+    let mut pairs = writer::pair_entries(&before.media, &after.media, writer::same_media, writer::similar_media);
+    pair_rewritten(&mut pairs, &before.media, &after.media, writer::media_tag);
+    diff(&mut ret, &before.media, &after.media, &pairs, writer::media_tag, |_| None);
+    let mut pairs = writer::pair_entries(&before.stream_inf, &after.stream_inf,
+        writer::same_stream_inf, writer::similar_stream_inf);
+    pair_rewritten(&mut pairs, &before.stream_inf, &after.stream_inf, writer::stream_inf_tag);
+    diff(&mut ret, &before.stream_inf, &after.stream_inf, &pairs,
+        writer::stream_inf_tag, |si| Some(si.uri.to_string()));
+    let mut i_frame_pairs = writer::pair_entries(&before.i_frame_stream_inf, &after.i_frame_stream_inf,
+        writer::same_i_frame_stream_inf, writer::similar_i_frame_stream_inf);
+    pair_rewritten(&mut i_frame_pairs, &before.i_frame_stream_inf, &after.i_frame_stream_inf, writer::i_frame_stream_inf_tag);
+    diff(&mut ret, &before.i_frame_stream_inf, &after.i_frame_stream_inf, &i_frame_pairs,
+        writer::i_frame_stream_inf_tag, |_| None);
+
+    // order of the variants kept
+    let kept: Vec<usize> = pairs.iter().flatten().copied().collect();
+    if kept.windows(2).any(|w| w[0] > w[1]) {
+        let mut sorted = kept.clone();
+        sorted.sort();
+        ret.push(Change::Reorder{
+            tag: "EXT-X-STREAM-INF".to_string(),
+            before: before.stream_inf.iter().zip(&pairs).filter(|(_, p)| p.is_some()).map(|(si, _)| si.uri.to_string()).collect(),
+            after: sorted.iter().map(|j| after.stream_inf[*j].uri.to_string()).collect(),
+        });
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+    use crate::format::SortOrder;
+    use crate::parser;
+    use crate::validator::BandwidthBounds;
+    use super::{plan, Change};
+
+    #[test]
+    fn test_plan() {
+        let data = include_str!("../data/master_unenc_hdr10_all.m3u8");
+        let pl = parser::parse_playlist(data).unwrap();
+        assert!(plan(&pl, &pl).is_empty());
+
+        let filtered = pl.select_audio_group("aac-128k").unwrap();
+        let changes = plan(&pl, &filtered);
+        let removed = changes.iter().filter(|c| matches!(c, Change::Remove{ tag, .. } if tag == "EXT-X-STREAM-INF")).count();
+        assert_eq!(removed, pl.stream_inf.len() - filtered.stream_inf.len());
+        assert!(changes.contains(&Change::Remove{ tag: "EXT-X-MEDIA".to_string(),
+            line: "#EXT-X-MEDIA:TYPE=AUDIO,URI=\"audio/unenc/aac_64k/vod.m3u8\",GROUP-ID=\"aac-64k\",LANGUAGE=\"en\",\
+                NAME=\"English\",DEFAULT=YES,AUTOSELECT=YES,CHANNELS=\"2\"".to_string() }));
+        assert!(!changes.iter().any(|c| matches!(c, Change::Add{ .. } | Change::Modify{ .. })));
+
+        let mut broken = pl.clone();
+        broken.stream_inf[0].bandwidth = 0;
+        let fixed = broken.fix_bandwidths(&BandwidthBounds::default());
+        let changes = plan(&broken, &fixed);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(&changes[0], Change::Modify{ attribute, before: Some(b), after: Some(_), line, .. }
+            if attribute == "BANDWIDTH" && b == "0" && line.ends_with("\nhdr10/unenc/1650k/vod.m3u8")));
+
+        let mut sorted = pl.clone();
+        sorted.sort_by_bandwidth(SortOrder::Descending);
+        let changes = plan(&pl, &sorted);
+        assert!(matches!(&changes[..], [Change::Reorder{ before, after, .. }]
+            if before[0] == "hdr10/unenc/1650k/vod.m3u8" && after.len() == pl.stream_inf.len()));
+        #[cfg(feature = "serde")]
+        assert_eq!(serde_json::to_value(&changes[0]).unwrap()["change"], "reorder");
+
+        // rewritten URIs are modified, not removed and added
+        let moved = pl.rewrite_uris(&Regex::new("^hdr10/").unwrap(), "https://cdn.example.com/hdr10/").unwrap();
+        let changes = plan(&pl, &moved);
+        assert_eq!(changes.len(), pl.stream_inf.len() + pl.i_frame_stream_inf.len());
+        assert!(changes.iter().all(|c| matches!(c, Change::Modify{ attribute, .. } if attribute == "URI")));
+        assert!(matches!(&changes[0], Change::Modify{ before: Some(b), after: Some(a), .. }
+            if b == "hdr10/unenc/1650k/vod.m3u8" && a == "https://cdn.example.com/hdr10/unenc/1650k/vod.m3u8"));
+    }
+
+    // JSON playlists have the ids of the playlist they were written from, with or without them
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_plan_json() {
+        let pl = parser::parse_playlist(include_str!("../data/master_unenc_hdr10_all.m3u8")).unwrap();
+        let mut json = serde_json::to_value(&pl).unwrap();
+        let read: crate::format::MultivariantPlaylist = serde_json::from_value(json.clone()).unwrap();
+        assert!(plan(&pl, &read).is_empty());
+        for si in json["stream_inf"].as_array_mut().unwrap() {
+            si.as_object_mut().unwrap().remove("id");
+        }
+        let read: crate::format::MultivariantPlaylist = serde_json::from_value(json).unwrap();
+        assert_eq!(read.find_variant(&pl.stream_inf[2].stable_id()).unwrap().uri, pl.stream_inf[2].uri);
+        let sel = read.select_ids(&[pl.stream_inf[0].stable_id()]).unwrap();
+        assert_eq!(plan(&pl, &sel).len(), pl.stream_inf.len() - 1 + pl.i_frame_stream_inf.len());
+    }
+}
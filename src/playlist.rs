@@ -0,0 +1,129 @@
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use crate::format::{MultivariantPlaylist, Resolution};
+use crate::media_playlist::{MediaPlaylist, PlaylistKind};
+use crate::validator::{Finding, Severity, ValidationReport};
+
+// Either kind of playlist, as returned by parser::parse
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum Playlist {
+    Multivariant(MultivariantPlaylist),
+    Media(MediaPlaylist),
+}
+
+// Overview of a playlist, see Playlist::summary
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Summary {
+    Multivariant {
+        variants: usize,
+        i_frame_variants: usize,
+        renditions: usize,
+        min_bandwidth: Option<u64>,
+        max_bandwidth: Option<u64>,
+        max_resolution: Option<Resolution>, // by pixel count
+        errors: usize,
+        warnings: usize,
+    },
+    Media {
+        segments: usize,
+        duration: f64, // seconds
+        target_duration: u64,
+        kind: PlaylistKind,
+        errors: usize,
+        warnings: usize,
+    },
+}
+
+impl Playlist {
+    pub fn findings(&self) -> Vec<Finding> {
+        match self {
+            Playlist::Multivariant(pl) => pl.findings(),
+            Playlist::Media(pl) => pl.findings(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<ValidationReport, ValidationReport> {
+        match self {
+            Playlist::Multivariant(pl) => pl.validate(),
+            Playlist::Media(pl) => pl.validate(),
+        }
+    }
+
+    /* Counts and ranges describing the playlist, with the number of validation findings */
+    pub fn summary(&self) -> Summary {
+        let findings = self.findings();
+        let errors = findings.iter().filter(|f| f.severity == Severity::Error).count();
+        let warnings = findings.len() - errors;
+        match self {
+            Playlist::Multivariant(pl) => Summary::Multivariant{
+                variants: pl.stream_inf.len(),
+                i_frame_variants: pl.i_frame_stream_inf.len(),
+                renditions: pl.media.len(),
+                min_bandwidth: pl.stream_inf.iter().map(|s| s.bandwidth).min(),
+                max_bandwidth: pl.stream_inf.iter().map(|s| s.bandwidth).max(),
+                max_resolution: pl.stream_inf.iter().filter_map(|s| s.resolution.clone()).max(),
+                errors,
+                warnings,
+            },
+            Playlist::Media(pl) => Summary::Media{
+                segments: pl.segment_count(),
+                duration: pl.total_duration(),
+                target_duration: pl.target_duration,
+                kind: pl.kind(),
+                errors,
+                warnings,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Summary::Multivariant{ variants, i_frame_variants, renditions, min_bandwidth, max_bandwidth,
+                max_resolution, errors, warnings } => {
+                write!(f, "multivariant playlist: {} variants, {} I-frame variants, {} renditions",
+                    variants, i_frame_variants, renditions)?;
+                if let (Some(min), Some(max)) = (min_bandwidth, max_bandwidth) {
+                    write!(f, ", bandwidth {}-{}", min, max)?;
+                }
+                if let Some(r) = max_resolution {
+                    write!(f, ", up to {}x{}", r.w, r.h)?;
+                }
+                write!(f, ", {} errors, {} warnings", errors, warnings)
+            },
+            Summary::Media{ segments, duration, target_duration, kind, errors, warnings } => {
+                let kind = match kind {
+                    PlaylistKind::Vod => "VOD",
+                    PlaylistKind::Event => "event",
+                    PlaylistKind::Live => "live",
+                };
+                write!(f, "{} media playlist: {} segments, {:.3}s, target duration {}s, {} errors, {} warnings",
+                    kind, segments, duration, target_duration, errors, warnings)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+
+    #[test]
+    fn test_summary() {
+        let pl = parser::parse(include_str!("../data/master_unenc_hdr10_all.m3u8")).unwrap();
+        assert_eq!(pl.summary().to_string(), "multivariant playlist: 36 variants, 2 I-frame variants, 4 renditions, \
+            bandwidth 705826-26598940, up to 3840x2160, 0 errors, 1 warnings");
+
+        let pl = parser::parse(include_str!("../data/media_playlist.m3u8")).unwrap();
+        assert_eq!(pl.summary().to_string(), "VOD media playlist: 5 segments, 44.168s, target duration 10s, 0 errors, 0 warnings");
+        let pl = parser::parse(include_str!("../data/live_playlist.m3u8")).unwrap();
+        assert_eq!(pl.summary().to_string(), "live media playlist: 6 segments, 34.490s, target duration 6s, 0 errors, 0 warnings");
+    }
+}
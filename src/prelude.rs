@@ -0,0 +1,15 @@
+// The types and functions most uses of the crate need:
+//
+//     use m3u_parser::prelude::*;
+//
+//     let playlist = load("master.m3u8")?;
+//     println!("{}", playlist.summary());
+
+pub use crate::format::{IFrameStreamInf, Media, MediaType, MultivariantPlaylist, Resolution, StreamInf};
+#[cfg(feature = "net")]
+pub use crate::load::{load, load_and_resolve};
+pub use crate::media_playlist::{MediaPlaylist, Segment};
+pub use crate::parser::{parse, parse_media_playlist, parse_playlist, ParseError, ParseOptions};
+pub use crate::playlist::{Playlist, Summary};
+pub use crate::uri::PlaylistUri;
+pub use crate::validator::{Finding, Severity};
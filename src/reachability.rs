@@ -0,0 +1,219 @@
+use std::fs;
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::fetch;
+use crate::format::MultivariantPlaylist;
+
+// Reachability of the media playlists referenced by a multivariant playlist:
+// status, content type and size of each, requested concurrently, to find dead
+// renditions. HEAD is tried first, then GET when the server does not support
+// it or does not give the size.
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Reachability {
+    pub tag: &'static str, // of the first reference to the URI
+    pub uri: String,       // as written in the multivariant playlist
+    pub resolved: String,  // against the URI of the multivariant playlist
+    pub status: Option<u16>, // None for local files
+    pub content_type: Option<String>,
+    pub size: Option<u64>,
+    pub error: Option<String>, // failure to resolve, connect or read, or a missing file
+}
+
+impl Reachability {
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none() && self.status.map_or(true, |s| s < 400)
+    }
+}
+
+type Probe = (Option<u16>, Option<String>, Option<u64>);
+
+fn probe_url(agent: &ureq::Agent, url: &str) -> Result<Probe, String> {
+    // error statuses are results too
+    let call = |r: ureq::Request| match r.call() {
+        Ok(r) | Err(ureq::Error::Status(_, r)) => Ok(r),
+        Err(e) => Err(format!("Failed to fetch {}: {}", url, e)),
+    };
+    let head = call(agent.head(url))?;
+    let length = head.header("Content-Length").and_then(|v| v.parse().ok());
+    if !matches!(head.status(), 405 | 501) && (length.is_some() || head.status() >= 400) {
+        return Ok((Some(head.status()), head.header("Content-Type").map(str::to_string), length))
+    }
+    let get = call(agent.get(url))?;
+    let (status, content_type) = (get.status(), get.header("Content-Type").map(str::to_string));
+    let mut data = vec![];
+    get.into_reader().read_to_end(&mut data).map_err(|e| format!("Failed to read {}: {}", url, e))?;
+    Ok((Some(status), content_type, Some(data.len() as u64)))
+}
+
+fn probe(agent: &ureq::Agent, uri: &str) -> Result<Probe, String> {
+    if fetch::is_url(uri) {
+        return probe_url(agent, uri)
+    }
+    let metadata = fs::metadata(uri).map_err(|e| format!("Failed to open {}: {}", uri, e))?;
+    Ok((None, None, Some(metadata.len())))
+}
+
+/* Request the variants, renditions and I-frame variants of the multivariant playlist at `uri`,
+   once per resolved URI and at most `concurrency` at a time. Results are the variants, then the
+   renditions, then the I-frame variants, each in playlist order. */
+pub fn check_all(uri: &str, playlist: &MultivariantPlaylist, concurrency: usize) -> Vec<Reachability> {
+    let references = playlist.stream_inf.iter().map(|s| ("EXT-X-STREAM-INF", &s.uri))
+        .chain(playlist.media.iter().filter_map(|m| Some(("EXT-X-MEDIA", m.uri.as_ref()?))))
+        .chain(playlist.i_frame_stream_inf.iter().map(|s| ("EXT-X-I-FRAME-STREAM-INF", &s.uri)));
+    let mut ret: Vec<Reachability> = vec![];
+    for (tag, reference) in references {
+        let resolved = fetch::resolve(uri, reference.as_str());
+        if resolved.as_ref().is_ok_and(|r| ret.iter().any(|c| c.resolved == *r)) {
+            continue
+        }
+        ret.push(Reachability{
+            tag,
+            uri: reference.to_string(),
+            resolved: resolved.clone().unwrap_or_default(),
+            status: None,
+            content_type: None,
+            size: None,
+            error: resolved.err(),
+        });
+    }
+
+    let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+    let next = AtomicUsize::new(0);
+    let probed: Vec<(usize, Result<Probe, String>)> = thread::scope(|s| {
+        let workers: Vec<_> = (0..concurrency.clamp(1, ret.len().max(1))).map(|_| s.spawn(|| {
+            let mut done = vec![];
+            loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(r) = ret.get(i) else { break };
+                if r.error.is_none() {
+                    done.push((i, probe(&agent, &r.resolved)));
+                }
+            }
+            done
+        })).collect();
+        workers.into_iter().flat_map(|w| w.join().expect("Worker panicked")).collect()
+    });
+    for (i, result) in probed {
+        match result {
+            Ok((status, content_type, size)) => {
+                ret[i].status = status;
+                ret[i].content_type = content_type;
+                ret[i].size = size;
+            },
+            Err(e) => ret[i].error = Some(e),
+        }
+    }
+    ret
+}
+
+/* Aligned table of the results, failures first, the errors and a summary line */
+pub fn to_table(results: &[Reachability]) -> String {
+    let mut sorted: Vec<&Reachability> = results.iter().collect();
+    sorted.sort_by_key(|r| r.is_ok());
+    let mut rows = vec![["STATUS", "CONTENT-TYPE", "SIZE", "TAG", "URI"].map(str::to_string)];
+    for r in &sorted {
+        let status = match (&r.error, r.status) {
+            (Some(_), _) => "error".to_string(),
+            (None, Some(s)) => s.to_string(),
+            (None, None) => "ok".to_string(),
+        };
+        rows.push([status, r.content_type.clone().unwrap_or_else(|| "-".to_string()),
+            r.size.map(|s| s.to_string()).unwrap_or_else(|| "-".to_string()), r.tag.to_string(), r.uri.clone()]);
+    }
+    let widths: Vec<usize> = (0..5).map(|c| rows.iter().map(|row| row[c].len()).max().unwrap_or(0)).collect();
+    let mut ret = String::new();
+    for row in &rows {
+        let cells: Vec<String> = row.iter().zip(&widths).map(|(cell, w)| format!("{:<w$}", cell, w = w)).collect();
+        ret.push_str(cells.join("  ").trim_end());
+        ret.push('\n');
+    }
+    for r in &sorted {
+        if let Some(e) = &r.error {
+            ret.push_str(&format!("{}: {}\n", r.uri, e));
+        }
+    }
+    let failed = results.iter().filter(|r| !r.is_ok()).count();
+    ret.push_str(&format!("{} URIs, {} reachable, {} failed\n", results.len(), results.len() - failed, failed));
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use crate::parser;
+    use super::{check_all, to_table};
+
+    #[test]
+    fn test_local_files() {
+        let pl = parser::parse_playlist(include_str!("../data/thumbnails/master.m3u8")).unwrap();
+        let results = check_all("data/thumbnails/master.m3u8", &pl, 2);
+        assert_eq!(results.iter().map(|r| r.uri.as_str()).collect::<Vec<_>>(),
+            ["360p.m3u8", "720p.m3u8", "iframes_360p.m3u8", "missing.m3u8", "iframes_720p.m3u8"]);
+        assert_eq!(results[2].tag, "EXT-X-I-FRAME-STREAM-INF");
+        assert_eq!(results[2].size, Some(include_str!("../data/thumbnails/iframes_360p.m3u8").len() as u64));
+        assert!(results[2].is_ok() && results[4].is_ok());
+        assert!(results[3].error.as_ref().is_some_and(|e| e.contains("data/thumbnails/missing.m3u8")));
+
+        let table = to_table(&results);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], "STATUS  CONTENT-TYPE  SIZE  TAG                       URI");
+        assert!(lines[1].starts_with("error   -             -     EXT-X-STREAM-INF          360p.m3u8"), "{}", table);
+        assert_eq!(lines.last().unwrap(), &"5 URIs, 2 reachable, 3 failed");
+    }
+
+    #[test]
+    fn test_http() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let mut requests = vec![];
+            for stream in listener.incoming().take(4) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 2 {
+                    header.clear();
+                }
+                let response = match request.split(' ').take(2).collect::<Vec<_>>()[..] {
+                    ["HEAD", "/hls/video.m3u8"] =>
+                        "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Type: application/vnd.apple.mpegurl\r\nContent-Length: 120\r\n\r\n",
+                    ["HEAD", "/hls/audio.m3u8"] => "HTTP/1.1 405 Method Not Allowed\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+                    ["GET", "/hls/audio.m3u8"] => "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Type: audio/mpegurl\r\n\r\n#EXTM3U\n",
+                    _ => "HTTP/1.1 404 Not Found\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+                requests.push(request.trim_end().to_string());
+            }
+            requests
+        });
+        let pl = parser::parse_playlist("#EXTM3U\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aac\",NAME=\"English\",URI=\"audio.m3u8\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=800000,AUDIO=\"aac\"\nvideo.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=1600000,AUDIO=\"aac\"\ngone.m3u8\n").unwrap();
+        let results = check_all(&format!("http://127.0.0.1:{}/hls/master.m3u8", port), &pl, 1);
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 4);
+
+        assert_eq!((results[0].status, results[0].size), (Some(200), Some(120)));
+        assert_eq!(results[0].content_type.as_deref(), Some("application/vnd.apple.mpegurl"));
+        assert_eq!(results[1].status, Some(404));
+        assert!(!results[1].is_ok() && results[1].error.is_none());
+        // HEAD not allowed
+        assert_eq!(results[2].uri, "audio.m3u8");
+        assert_eq!((results[2].status, results[2].size), (Some(200), Some(8)));
+        assert!(to_table(&results).ends_with("3 URIs, 2 reachable, 1 failed\n"));
+    }
+}
@@ -0,0 +1,297 @@
+// Known tags and their attributes, as defined in RFC 8216bis. The parser checks
+// attribute lists against this table before interpreting them, the validator reports
+// enumerated values it does not list, the writer orders attributes by it and the
+// suggestion engine draws known names from it. Adding an attribute here makes it
+// known to all of them; interpreting its value is still up to the parser.
+
+use crate::tokenizer::{AttributeMap, AttributeValue};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    DecimalInteger,
+    HexadecimalSequence,
+    DecimalFloatingPoint, // an integer is a valid value as well
+    SignedDecimalFloatingPoint,
+    QuotedString,
+    EnumeratedString,
+    QuotedOrEnumeratedString, // e.g. CLOSED-CAPTIONS, either a quoted group or NONE
+    DecimalResolution,
+}
+
+impl ValueType {
+    pub fn matches(&self, v: &AttributeValue) -> bool {
+        matches!((self, v),
+            (ValueType::DecimalInteger, AttributeValue::Integer(_))
+            | (ValueType::HexadecimalSequence, AttributeValue::HexadecimalSequence(_))
+            | (ValueType::DecimalFloatingPoint | ValueType::SignedDecimalFloatingPoint,
+                AttributeValue::Float(_) | AttributeValue::Integer(_))
+            | (ValueType::QuotedString | ValueType::QuotedOrEnumeratedString, AttributeValue::QuotedString(_))
+            | (ValueType::EnumeratedString | ValueType::QuotedOrEnumeratedString, AttributeValue::EnumeratedString(_))
+            | (ValueType::DecimalResolution, AttributeValue::DecimalResolution(_, _)))
+    }
+}
+
+#[derive(Debug)]
+pub struct AttributeSpec {
+    pub name: &'static str,
+    pub value_type: ValueType,
+    pub required: bool,
+    pub since_version: u8,  // minimal EXT-X-VERSION
+    pub allowed: &'static [&'static str], // allowed enumerated values, empty if any
+    pub extensible: bool, // values outside `allowed` are tolerated, e.g. from later revisions of the RFC
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagKind {
+    Basic,          // no value, e.g. #EXT-X-INDEPENDENT-SEGMENTS
+    Value,          // single value, e.g. #EXT-X-VERSION:7
+    AttributeList,  // e.g. #EXT-X-MEDIA:TYPE=AUDIO,...
+}
+
+#[derive(Debug)]
+pub struct TagSpec {
+    pub name: &'static str, // without the leading '#'
+    pub kind: TagKind,
+    pub since_version: u8,
+    pub attributes: &'static [AttributeSpec], // in the order of the RFC
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeError {
+    Missing(&'static str),
+    Invalid(&'static str),
+}
+
+impl TagSpec {
+    pub fn attribute(&self, name: &str) -> Option<&'static AttributeSpec> {
+        self.attributes.iter().find(|a| a.name == name)
+    }
+
+    pub fn attribute_names(&self) -> impl Iterator<Item = &'static str> {
+        self.attributes.iter().map(|a| a.name)
+    }
+
+    /* 
+    Check that mandatory attributes are present, and that known attributes have
+    values of the right type. Unknown attributes are ignored, as the RFC demands.
+    */
+    pub fn check(&self, attr: &AttributeMap) -> Result<(), AttributeError> {
+        for spec in self.attributes {
+            match attr.get(spec.name) {
+                None if spec.required => return Err(AttributeError::Missing(spec.name)),
+                None => (),
+                Some(v) => {
+                    if !spec.value_type.matches(v) {
+                        return Err(AttributeError::Invalid(spec.name))
+                    }
+                    if let AttributeValue::EnumeratedString(e) = v {
+                        if !spec.allowed.is_empty() && !spec.allowed.contains(e) && !spec.extensible {
+                            return Err(AttributeError::Invalid(spec.name))
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+const fn required(name: &'static str, value_type: ValueType) -> AttributeSpec {
+    AttributeSpec{name, value_type, required: true, since_version: 1, allowed: &[], extensible: false}
+}
+
+const fn optional(name: &'static str, value_type: ValueType) -> AttributeSpec {
+    AttributeSpec{name, value_type, required: false, since_version: 1, allowed: &[], extensible: false}
+}
+
+const fn enumerated(name: &'static str, required: bool, allowed: &'static [&'static str]) -> AttributeSpec {
+    AttributeSpec{name, value_type: ValueType::EnumeratedString, required, since_version: 1, allowed, extensible: false}
+}
+
+// Enumerated string with known values `allowed`, other values are kept as is
+const fn extensible(name: &'static str, required: bool, allowed: &'static [&'static str]) -> AttributeSpec {
+    AttributeSpec{name, value_type: ValueType::EnumeratedString, required, since_version: 1, allowed, extensible: true}
+}
+
+const YES_NO: &[&str] = &["YES", "NO"];
+
+use ValueType::*;
+
+pub static TAGS: &[TagSpec] = &[
+    TagSpec{name: "EXTM3U", kind: TagKind::Basic, since_version: 1, attributes: &[]},
+    TagSpec{name: "EXT-X-VERSION", kind: TagKind::Value, since_version: 1, attributes: &[]},
+    TagSpec{name: "EXT-X-INDEPENDENT-SEGMENTS", kind: TagKind::Basic, since_version: 1, attributes: &[]},
+    TagSpec{name: "EXT-X-START", kind: TagKind::AttributeList, since_version: 1, attributes: &[
+        required("TIME-OFFSET", SignedDecimalFloatingPoint),
+        enumerated("PRECISE", false, YES_NO),
+    ]},
+    TagSpec{name: "EXT-X-DEFINE", kind: TagKind::AttributeList, since_version: 8, attributes: &[
+        optional("NAME", QuotedString),
+        optional("VALUE", QuotedString),
+        optional("IMPORT", QuotedString),
+        optional("QUERYPARAM", QuotedString),
+    ]},
+    TagSpec{name: "EXT-X-MEDIA", kind: TagKind::AttributeList, since_version: 1, attributes: &[
+        extensible("TYPE", true, &["AUDIO", "VIDEO", "SUBTITLES", "CLOSED-CAPTIONS"]),
+        optional("URI", QuotedString),
+        required("GROUP-ID", QuotedString),
+        optional("LANGUAGE", QuotedString),
+        optional("ASSOC-LANGUAGE", QuotedString),
+        required("NAME", QuotedString),
+        optional("STABLE-RENDITION-ID", QuotedString),
+        enumerated("DEFAULT", false, YES_NO),
+        enumerated("AUTOSELECT", false, YES_NO),
+        enumerated("FORCED", false, YES_NO),
+        optional("INSTREAM-ID", QuotedString),
+        optional("BIT-DEPTH", DecimalInteger),
+        optional("SAMPLE-RATE", DecimalInteger),
+        optional("CHARACTERISTICS", QuotedString),
+        optional("CHANNELS", QuotedString),
+    ]},
+    TagSpec{name: "EXT-X-STREAM-INF", kind: TagKind::AttributeList, since_version: 1, attributes: &[
+        required("BANDWIDTH", DecimalInteger),
+        optional("AVERAGE-BANDWIDTH", DecimalInteger),
+        optional("SCORE", DecimalFloatingPoint),
+        optional("CODECS", QuotedString),
+        optional("SUPPLEMENTAL-CODECS", QuotedString),
+        optional("RESOLUTION", DecimalResolution),
+        optional("FRAME-RATE", DecimalFloatingPoint),
+        enumerated("HDCP-LEVEL", false, &["TYPE-0", "TYPE-1", "NONE"]),
+        optional("ALLOWED-CPC", QuotedString),
+        extensible("VIDEO-RANGE", false, &["SDR", "HLG", "PQ"]),
+        optional("REQ-VIDEO-LAYOUT", QuotedString),
+        optional("STABLE-VARIANT-ID", QuotedString),
+        optional("AUDIO", QuotedString),
+        optional("VIDEO", QuotedString),
+        optional("SUBTITLES", QuotedString),
+        AttributeSpec{name: "CLOSED-CAPTIONS", value_type: QuotedOrEnumeratedString, required: false,
+            since_version: 1, allowed: &["NONE"], extensible: false},
+        optional("PATHWAY-ID", QuotedString),
+    ]},
+    TagSpec{name: "EXT-X-I-FRAME-STREAM-INF", kind: TagKind::AttributeList, since_version: 4, attributes: &[
+        required("BANDWIDTH", DecimalInteger),
+        optional("AVERAGE-BANDWIDTH", DecimalInteger),
+        optional("SCORE", DecimalFloatingPoint),
+        optional("CODECS", QuotedString),
+        optional("SUPPLEMENTAL-CODECS", QuotedString),
+        optional("RESOLUTION", DecimalResolution),
+        enumerated("HDCP-LEVEL", false, &["TYPE-0", "TYPE-1", "NONE"]),
+        optional("ALLOWED-CPC", QuotedString),
+        extensible("VIDEO-RANGE", false, &["SDR", "HLG", "PQ"]),
+        optional("REQ-VIDEO-LAYOUT", QuotedString),
+        optional("STABLE-VARIANT-ID", QuotedString),
+        optional("VIDEO", QuotedString),
+        optional("PATHWAY-ID", QuotedString),
+        required("URI", QuotedString),
+    ]},
+    TagSpec{name: "EXT-X-SESSION-DATA", kind: TagKind::AttributeList, since_version: 1, attributes: &[
+        required("DATA-ID", QuotedString),
+        optional("VALUE", QuotedString),
+        optional("URI", QuotedString),
+        enumerated("FORMAT", false, &["JSON", "RAW"]),
+        optional("LANGUAGE", QuotedString),
+    ]},
+    TagSpec{name: "EXT-X-SESSION-KEY", kind: TagKind::AttributeList, since_version: 1, attributes: &[
+        enumerated("METHOD", true, &["AES-128", "SAMPLE-AES", "SAMPLE-AES-CTR"]),
+        optional("URI", QuotedString),
+        optional("IV", HexadecimalSequence),
+        optional("KEYFORMAT", QuotedString),
+        optional("KEYFORMATVERSIONS", QuotedString),
+    ]},
+    // Media playlist tags
+    TagSpec{name: "EXTINF", kind: TagKind::Value, since_version: 1, attributes: &[]},
+    TagSpec{name: "EXT-X-TARGETDURATION", kind: TagKind::Value, since_version: 1, attributes: &[]},
+    TagSpec{name: "EXT-X-MEDIA-SEQUENCE", kind: TagKind::Value, since_version: 1, attributes: &[]},
+    TagSpec{name: "EXT-X-PLAYLIST-TYPE", kind: TagKind::Value, since_version: 1, attributes: &[]},
+    TagSpec{name: "EXT-X-ENDLIST", kind: TagKind::Basic, since_version: 1, attributes: &[]},
+    TagSpec{name: "EXT-X-BYTERANGE", kind: TagKind::Value, since_version: 4, attributes: &[]},
+    TagSpec{name: "EXT-X-KEY", kind: TagKind::AttributeList, since_version: 1, attributes: &[
+        enumerated("METHOD", true, &["NONE", "AES-128", "SAMPLE-AES", "SAMPLE-AES-CTR"]),
+        optional("URI", QuotedString),
+        optional("IV", HexadecimalSequence),
+        optional("KEYFORMAT", QuotedString),
+        optional("KEYFORMATVERSIONS", QuotedString),
+    ]},
+    TagSpec{name: "EXT-X-DISCONTINUITY", kind: TagKind::Basic, since_version: 1, attributes: &[]},
+    TagSpec{name: "EXT-X-DISCONTINUITY-SEQUENCE", kind: TagKind::Value, since_version: 1, attributes: &[]},
+    TagSpec{name: "EXT-X-PROGRAM-DATE-TIME", kind: TagKind::Value, since_version: 1, attributes: &[]},
+    TagSpec{name: "EXT-X-I-FRAMES-ONLY", kind: TagKind::Basic, since_version: 4, attributes: &[]},
+    TagSpec{name: "EXT-X-GAP", kind: TagKind::Basic, since_version: 1, attributes: &[]},
+    TagSpec{name: "EXT-X-BITRATE", kind: TagKind::Value, since_version: 1, attributes: &[]},
+    // Low-Latency HLS
+    TagSpec{name: "EXT-X-PART-INF", kind: TagKind::AttributeList, since_version: 1, attributes: &[
+        required("PART-TARGET", DecimalFloatingPoint),
+    ]},
+    TagSpec{name: "EXT-X-PART", kind: TagKind::AttributeList, since_version: 1, attributes: &[
+        required("URI", QuotedString),
+        required("DURATION", DecimalFloatingPoint),
+        enumerated("INDEPENDENT", false, &["YES"]),
+        optional("BYTERANGE", QuotedString),
+        enumerated("GAP", false, &["YES"]),
+    ]},
+    TagSpec{name: "EXT-X-SERVER-CONTROL", kind: TagKind::AttributeList, since_version: 1, attributes: &[
+        optional("CAN-SKIP-UNTIL", DecimalFloatingPoint),
+        enumerated("CAN-SKIP-DATERANGES", false, &["YES"]),
+        optional("HOLD-BACK", DecimalFloatingPoint),
+        optional("PART-HOLD-BACK", DecimalFloatingPoint),
+        enumerated("CAN-BLOCK-RELOAD", false, &["YES"]),
+    ]},
+    TagSpec{name: "EXT-X-SKIP", kind: TagKind::AttributeList, since_version: 9, attributes: &[
+        required("SKIPPED-SEGMENTS", DecimalInteger),
+        optional("RECENTLY-REMOVED-DATERANGES", QuotedString),
+    ]},
+    TagSpec{name: "EXT-X-PRELOAD-HINT", kind: TagKind::AttributeList, since_version: 1, attributes: &[
+        enumerated("TYPE", true, &["PART", "MAP"]),
+        required("URI", QuotedString),
+        optional("BYTERANGE-START", DecimalInteger),
+        optional("BYTERANGE-LENGTH", DecimalInteger),
+    ]},
+    TagSpec{name: "EXT-X-RENDITION-REPORT", kind: TagKind::AttributeList, since_version: 1, attributes: &[
+        required("URI", QuotedString),
+        optional("LAST-MSN", DecimalInteger),
+        optional("LAST-PART", DecimalInteger),
+    ]},
+    TagSpec{name: "EXT-X-CONTENT-STEERING", kind: TagKind::AttributeList, since_version: 1, attributes: &[
+        required("SERVER-URI", QuotedString),
+        optional("PATHWAY-ID", QuotedString),
+    ]},
+];
+
+pub fn tag(name: &str) -> Option<&'static TagSpec> {
+    TAGS.iter().find(|t| t.name == name)
+}
+
+pub fn tag_names() -> impl Iterator<Item = &'static str> {
+    TAGS.iter().map(|t| t.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::parse_attributes;
+
+    #[test]
+    fn test_check() {
+        let spec = tag("EXT-X-STREAM-INF").unwrap();
+        let attr = parse_attributes(r#"BANDWIDTH=100,FRAME-RATE=24,CLOSED-CAPTIONS=NONE,X-VENDOR="x""#).unwrap();
+        assert_eq!(spec.check(&attr), Ok(()));
+        let attr = parse_attributes(r#"CODECS="avc1.640028""#).unwrap();
+        assert_eq!(spec.check(&attr), Err(AttributeError::Missing("BANDWIDTH")));
+        let attr = parse_attributes(r#"BANDWIDTH=100,RESOLUTION="1x1""#).unwrap();
+        assert_eq!(spec.check(&attr), Err(AttributeError::Invalid("RESOLUTION")));
+        let attr = parse_attributes(r#"BANDWIDTH=100,CLOSED-CAPTIONS=ALL"#).unwrap();
+        assert_eq!(spec.check(&attr), Err(AttributeError::Invalid("CLOSED-CAPTIONS")));
+        let attr = parse_attributes(r#"BANDWIDTH=100,VIDEO-RANGE=XDR"#).unwrap();
+        assert_eq!(spec.check(&attr), Ok(()));
+        let attr = parse_attributes(r#"BANDWIDTH=100,VIDEO-RANGE="PQ""#).unwrap();
+        assert_eq!(spec.check(&attr), Err(AttributeError::Invalid("VIDEO-RANGE")));
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(tag("EXT-X-MEDIA").unwrap().kind, TagKind::AttributeList);
+        assert!(tag("EXT-X-MEDIA").unwrap().attribute("FORCED").is_some());
+        assert!(tag("EXT-X-UNKNOWN").is_none());
+        assert!(tag_names().any(|t| t == "EXT-X-I-FRAME-STREAM-INF"));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::format::{MultivariantPlaylist, Resolution};
+use crate::validator::{Finding, Severity};
+
+// Markdown summary of a multivariant playlist, for pasting into tickets and
+// reviews: the variants, renditions and I-frame variants as tables, then the
+// findings of the validator.
+
+fn cell(v: &str) -> String {
+    v.replace('|', "\\|").replace('\n', " ")
+}
+
+fn table(ret: &mut String, header: &[&str], rows: Vec<Vec<String>>) {
+    if rows.is_empty() {
+        ret.push_str("None.\n\n");
+        return
+    }
+    ret.push_str(&format!("| {} |\n", header.join(" | ")));
+    ret.push_str(&format!("|{}\n", "---|".repeat(header.len())));
+    for row in rows {
+        let cells: Vec<String> = row.iter().map(|c| cell(c)).collect();
+        ret.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    ret.push('\n');
+}
+
+fn opt<T: ToString>(v: &Option<T>) -> String {
+    v.as_ref().map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn resolution(r: &Option<Resolution>) -> String {
+    r.as_ref().map(|r| format!("{}x{}", r.w, r.h)).unwrap_or_default()
+}
+
+fn yes(v: bool) -> String {
+    if v { "yes".to_string() } else { String::new() }
+}
+
+pub fn markdown(playlist: &MultivariantPlaylist, findings: &[Finding]) -> String {
+    let mut ret = "# Multivariant playlist\n\n".to_string();
+
+    ret.push_str("## Variants\n\n");
+    table(&mut ret, &["Bandwidth", "Average bandwidth", "Resolution", "Frame rate", "Codecs", "Video range", "Audio", "URI"],
+        playlist.stream_inf.iter().map(|si| vec![
+            si.bandwidth.to_string(),
+            opt(&si.average_bandwidth),
+            resolution(&si.resolution),
+            opt(&si.frame_rate),
+            opt(&si.codecs),
+            si.video_range.as_ref().map(|v| v.as_str().to_string()).unwrap_or_default(),
+            opt(&si.audio),
+            si.uri.to_string(),
+        ]).collect());
+
+    ret.push_str("## Renditions\n\n");
+    table(&mut ret, &["Type", "Group", "Name", "Language", "Default", "Channels", "URI"],
+        playlist.media.iter().map(|m| vec![
+            m.type_.as_str().to_string(),
+            m.group_id.clone(),
+            m.name.clone(),
+            opt(&m.language),
+            yes(m.default),
+            opt(&m.channels),
+            opt(&m.uri),
+        ]).collect());
+
+    ret.push_str("## I-frame variants\n\n");
+    table(&mut ret, &["Bandwidth", "Resolution", "Codecs", "URI"],
+        playlist.i_frame_stream_inf.iter().map(|ifsi| vec![
+            ifsi.bandwidth.to_string(),
+            resolution(&ifsi.resolution),
+            opt(&ifsi.codecs),
+            ifsi.uri.to_string(),
+        ]).collect());
+
+    ret.push_str("## Findings\n\n");
+    if findings.is_empty() {
+        ret.push_str("None.\n");
+    }
+    // errors first
+    for severity in [Severity::Error, Severity::Warning] {
+        for f in findings.iter().filter(|f| f.severity == severity) {
+            let label = if severity == Severity::Error { "error" } else { "warning" };
+            ret.push_str(&format!("- **{}**: {}\n", label, f.message));
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::validator::Finding;
+    use super::markdown;
+
+    #[test]
+    fn test_markdown() {
+        let data = include_str!("../data/master_unenc_hdr10_all.m3u8");
+        let mut pl = parser::parse_playlist(data).unwrap();
+        pl.stream_inf.truncate(1);
+        pl.media.truncate(1);
+        pl.media[0].name = "English | stereo".to_string();
+        pl.i_frame_stream_inf.clear();
+        let findings = [Finding::warning("audio-only-fallback", "No audio-only variant".to_string()),
+            Finding::error("bandwidth-zero", "BANDWIDTH 0".to_string())];
+        let md = markdown(&pl, &findings);
+        assert!(md.contains("| Bandwidth | Average bandwidth | Resolution | Frame rate | Codecs | Video range | Audio | URI |\n\
+            |---|---|---|---|---|---|---|---|\n\
+            | 2483789 | 1762745 | 960x540 | 23.97 | mp4a.40.2,hvc1.2.4.L90.90 | PQ | aac-128k | hdr10/unenc/1650k/vod.m3u8 |\n"), "{}", md);
+        assert!(md.contains("| AUDIO | aac-128k | English \\| stereo | en | yes | 2 | audio/unenc/aac_128k/vod.m3u8 |\n"), "{}", md);
+        assert!(md.contains("## I-frame variants\n\nNone.\n"));
+        assert!(md.ends_with("## Findings\n\n- **error**: BANDWIDTH 0\n- **warning**: No audio-only variant\n"), "{}", md);
+    }
+}
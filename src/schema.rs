@@ -0,0 +1,34 @@
+use schemars::generate::SchemaSettings;
+use serde_json::Value;
+
+use crate::format::MultivariantPlaylist;
+
+// JSON Schema of the JSON output, i.e. as serialized: fields with defaults are
+// always present. Keys are the Rust field names, see json::KeyStyle. A copy is
+// published as schema/multivariant_playlist.schema.json.
+
+pub fn multivariant_playlist_schema() -> Value {
+    SchemaSettings::draft2020_12().for_serialize().into_generator()
+        .into_root_schema_for::<MultivariantPlaylist>().to_value()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use super::multivariant_playlist_schema;
+
+    #[test]
+    fn test_schema() {
+        let schema = multivariant_playlist_schema();
+        let published: serde_json::Value = serde_json::from_str(include_str!("../schema/multivariant_playlist.schema.json")).unwrap();
+        assert_eq!(schema, published, "schema/multivariant_playlist.schema.json is out of date, regenerate it with --emit-schema");
+        assert_eq!(schema["title"], "MultivariantPlaylist");
+        let required = schema["required"].as_array().unwrap();
+        let pl = serde_json::to_value(parser::parse_playlist(include_str!("../data/master_unenc_hdr10_all.m3u8")).unwrap()).unwrap();
+        for field in required {
+            assert!(pl.get(field.as_str().unwrap()).is_some(), "{}", field);
+        }
+        assert_eq!(schema["$defs"]["PlaylistUri"]["type"], "string");
+        assert_eq!(schema["$defs"]["FrameRate"]["type"], "number");
+    }
+}
@@ -0,0 +1,273 @@
+use regex::Regex;
+
+use crate::codecs::{CodecFamily, Codecs};
+use crate::format::{BestBy, BestCriteria, ChannelsFilter, HdcpLevel, IFrameCriteria, MultivariantPlaylist, Resolution, SortOrder, VideoRange};
+
+// Chain of filters applied to a multivariant playlist in the order they were added,
+// e.g. Selector::new().audio_group("aac-128k").max_bandwidth(8_000_000).apply(&playlist).
+// A filter that fails is skipped and the next ones applied to the playlist as it was,
+// so all failures are reported together.
+
+type Step = Box<dyn Fn(&MultivariantPlaylist) -> Result<MultivariantPlaylist, String>>;
+
+#[derive(Default)]
+pub struct Selector {
+    steps: Vec<Step>,
+    errors: Vec<String>, // invalid parameters, reported by apply
+    bandwidth: (Option<u64>, Option<u64>), // bounds given, checked together
+    resolution: (Option<Resolution>, Option<Resolution>),
+}
+
+impl Selector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* Any filter, e.g. one of the ladder module */
+    pub fn then(mut self, f: impl Fn(&MultivariantPlaylist) -> Result<MultivariantPlaylist, String> + 'static) -> Self {
+        self.steps.push(Box::new(f));
+        self
+    }
+
+    pub fn audio_group(self, group: &str) -> Self {
+        let group = group.to_string();
+        self.then(move |pl| pl.select_audio_group(&group))
+    }
+
+    pub fn audio_channels(mut self, channels: &str) -> Self {
+        if ChannelsFilter::parse(channels).is_none() {
+            self.errors.push(format!("Invalid channels filter {}", channels));
+            return self
+        }
+        let channels = channels.to_string();
+        self.then(move |pl| pl.select_audio_by_channels(&channels))
+    }
+
+    pub fn audio_language(self, lang: &str) -> Self {
+        let lang = lang.to_string();
+        self.then(move |pl| pl.select_audio_language(&lang))
+    }
+
+    pub fn default_audio(self) -> Self {
+        self.then(|pl| pl.select_default_audio())
+    }
+
+    pub fn subtitle_group(self, group: &str) -> Self {
+        let group = group.to_string();
+        self.then(move |pl| pl.select_subtitle_group(&group))
+    }
+
+    pub fn subtitle_language(self, lang: &str) -> Self {
+        let lang = lang.to_string();
+        self.then(move |pl| pl.select_subtitle_language(&lang))
+    }
+
+    pub fn forced_subtitles(self) -> Self {
+        self.then(|pl| pl.select_forced_subtitles())
+    }
+
+    pub fn without_subtitles(self) -> Self {
+        self.then(|pl| Ok(pl.without_subtitles()))
+    }
+
+    pub fn without_closed_captions(self) -> Self {
+        self.then(|pl| Ok(pl.remove_closed_captions()))
+    }
+
+    pub fn without_iframe_streams(self) -> Self {
+        self.then(|pl| Ok(pl.without_iframe_streams()))
+    }
+
+    pub fn i_frame_streams(self, criteria: IFrameCriteria) -> Self {
+        self.then(move |pl| pl.select_i_frame_streams(&criteria))
+    }
+
+    pub fn max_bandwidth(mut self, bw: u64) -> Self {
+        self.bandwidth.1 = Some(bw);
+        self.then(move |pl| pl.select_max_bandwidth(bw))
+    }
+
+    pub fn min_bandwidth(mut self, bw: u64) -> Self {
+        self.bandwidth.0 = Some(bw);
+        self.then(move |pl| pl.select_min_bandwidth(bw))
+    }
+
+    pub fn bandwidth_range(self, min: u64, max: u64) -> Self {
+        self.then(move |pl| pl.select_bandwidth_range(min, max))
+    }
+
+    pub fn resolution(self, res: Resolution) -> Self {
+        self.then(move |pl| pl.select_resolution(&res))
+    }
+
+    pub fn max_resolution(mut self, res: Resolution) -> Self {
+        self.resolution.1 = Some(res.clone());
+        self.then(move |pl| pl.select_max_resolution(&res))
+    }
+
+    pub fn min_resolution(mut self, res: Resolution) -> Self {
+        self.resolution.0 = Some(res.clone());
+        self.then(move |pl| pl.select_min_resolution(&res))
+    }
+
+    pub fn max_frame_rate(self, fps: f64) -> Self {
+        self.then(move |pl| pl.select_max_frame_rate(fps))
+    }
+
+    pub fn pathway(self, pathway: &str) -> Self {
+        let pathway = pathway.to_string();
+        self.then(move |pl| pl.select_pathway(&pathway))
+    }
+
+    pub fn max_hdcp_level(self, level: HdcpLevel) -> Self {
+        self.then(move |pl| pl.select_max_hdcp_level(level))
+    }
+
+    pub fn codec(self, family: CodecFamily) -> Self {
+        self.then(move |pl| pl.select_codec(family))
+    }
+
+    pub fn codecs(self, pred: impl Fn(&Codecs) -> bool + 'static) -> Self {
+        self.then(move |pl| pl.select_codecs(&pred))
+    }
+
+    pub fn video_range(self, range: VideoRange) -> Self {
+        self.then(move |pl| pl.select_video_range(&range))
+    }
+
+    pub fn label(self, label: &str) -> Self {
+        let label = label.to_string();
+        self.then(move |pl| pl.select_label(&label))
+    }
+
+    pub fn uri_matching(self, re: Regex) -> Self {
+        self.then(move |pl| pl.select_uri_matching(&re))
+    }
+
+    pub fn uri_not_matching(self, re: Regex) -> Self {
+        self.then(move |pl| pl.select_uri_not_matching(&re))
+    }
+
+    pub fn rewrite_uris(self, re: Regex, replacement: &str) -> Self {
+        let replacement = replacement.to_string();
+        self.then(move |pl| pl.rewrite_uris(&re, &replacement))
+    }
+
+    pub fn ids(self, ids: &[String]) -> Self {
+        let ids = ids.to_vec();
+        self.then(move |pl| pl.select_ids(&ids))
+    }
+
+    pub fn best(self, criteria: BestCriteria) -> Self {
+        self.then(move |pl| pl.select_best(&criteria))
+    }
+
+    pub fn top_n(self, n: usize, by: BestBy) -> Self {
+        self.then(move |pl| pl.select_top_n(n, by))
+    }
+
+    pub fn prune_unreferenced_media(self) -> Self {
+        self.then(|pl| Ok(pl.prune_unreferenced_media()))
+    }
+
+    pub fn with_audio(self) -> Self {
+        self.then(|pl| pl.select_with_audio())
+    }
+
+    pub fn sort_by_bandwidth(self, order: SortOrder) -> Self {
+        self.then(move |pl| {
+            let mut ret = pl.clone();
+            ret.sort_by_bandwidth(order);
+            Ok(ret)
+        })
+    }
+
+    pub fn sort_by_average_bandwidth(self, order: SortOrder) -> Self {
+        self.then(move |pl| {
+            let mut ret = pl.clone();
+            ret.sort_by_average_bandwidth(order);
+            Ok(ret)
+        })
+    }
+
+    pub fn sort_by_score(self, order: SortOrder) -> Self {
+        self.then(move |pl| {
+            let mut ret = pl.clone();
+            ret.sort_by_score(order);
+            Ok(ret)
+        })
+    }
+
+    // Parameters that cannot be satisfied together, whatever the playlist
+    fn check(&self) -> Vec<String> {
+        let mut ret = self.errors.clone();
+        if let (Some(min), Some(max)) = self.bandwidth {
+            if min > max {
+                ret.push(format!("Minimum bandwidth {} above maximum bandwidth {}", min, max));
+            }
+        }
+        if let (Some(min), Some(max)) = &self.resolution {
+            if min.w > max.w || min.h > max.h {
+                ret.push(format!("Minimum resolution {}x{} does not fit within maximum resolution {}x{}",
+                    min.w, min.h, max.w, max.h));
+            }
+        }
+        ret
+    }
+
+    /* Playlist after all filters, or the failures of the parameters and filters */
+    pub fn apply(&self, playlist: &MultivariantPlaylist) -> Result<MultivariantPlaylist, Vec<String>> {
+        let mut errors = self.check();
+        if !errors.is_empty() {
+            return Err(errors)
+        }
+        let mut ret = playlist.clone();
+        for step in &self.steps {
+            match step(&ret) {
+                Ok(pl) => ret = pl,
+                Err(e) => errors.push(e),
+            }
+        }
+        if errors.is_empty() { Ok(ret) } else { Err(errors) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::{MultivariantPlaylist, Resolution, SortOrder, VideoRange};
+    use crate::ladder;
+    use crate::parser;
+    use super::Selector;
+
+    fn playlist() -> MultivariantPlaylist {
+        parser::parse_playlist(include_str!("../data/master_unenc_hdr10_all.m3u8")).unwrap()
+    }
+
+    #[test]
+    fn test_selector() {
+        let pl = playlist();
+        let sel = Selector::new().audio_group("aac-128k").max_bandwidth(8_000_000).video_range(VideoRange::PQ)
+            .sort_by_bandwidth(SortOrder::Descending).apply(&pl).unwrap();
+        let expected = pl.select_audio_group("aac-128k").unwrap().select_max_bandwidth(8_000_000).unwrap();
+        assert_eq!(sel.stream_inf.len(), expected.stream_inf.len());
+        assert!(sel.stream_inf.windows(2).all(|w| w[0].bandwidth >= w[1].bandwidth));
+        assert_eq!(Selector::new().apply(&pl).unwrap(), pl);
+
+        let trimmed = Selector::new().then(|pl| ladder::trim(pl, 2, ladder::KeepStrategy::Lowest)).apply(&pl).unwrap();
+        assert_eq!(trimmed.stream_inf.len(), 2);
+    }
+
+    #[test]
+    fn test_selector_errors() {
+        let pl = playlist();
+        let errors = Selector::new().audio_group("none").label("8K").max_bandwidth(8_000_000).video_range(VideoRange::SDR)
+            .apply(&pl).unwrap_err();
+        assert_eq!(errors, ["Audio group none not found", "No streams with label 8K", "No streams with video range SDR"]);
+
+        let errors = Selector::new().min_bandwidth(2_000_000).max_bandwidth(1_000_000).audio_channels("six")
+            .min_resolution(Resolution{ w: 1920, h: 1080 }).max_resolution(Resolution{ w: 1280, h: 720 })
+            .apply(&pl).unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0], "Invalid channels filter six");
+    }
+}
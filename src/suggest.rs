@@ -0,0 +1,66 @@
+// "Did you mean" suggestions for misspelled tag and attribute names
+
+use crate::registry;
+
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.as_bytes();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.bytes().enumerate() {
+        let mut diag = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let subst = diag + if ca == *cb { 0 } else { 1 };
+            diag = row[j + 1];
+            row[j + 1] = subst.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+// Longest edit distance still considered a misspelling
+const MAX_DISTANCE: usize = 2;
+
+/* Closest candidate, if it is close enough to be a likely misspelling of name */
+pub fn suggest<I: IntoIterator<Item = &'static str>>(name: &str, candidates: I) -> Option<&'static str> {
+    candidates.into_iter()
+        .map(|c| (levenshtein(name, c), c))
+        .filter(|(d, _)| *d > 0 && *d <= MAX_DISTANCE && *d < name.len())
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+/* Known tag resembling name */
+pub fn suggest_tag(name: &str) -> Option<&'static str> {
+    suggest(name, registry::tag_names())
+}
+
+/* Suggestion for the first unknown attribute of a known tag resembling a known one */
+pub fn suggest_attribute<'a, I: IntoIterator<Item = &'a str>>(tag: &str, names: I) -> Option<&'static str> {
+    let spec = registry::tag(tag)?;
+    names.into_iter()
+        .filter(|n| spec.attribute(n).is_none())
+        .find_map(|n| suggest(n, spec.attribute_names()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("BANDWIDTH", "BANDWIDTH"), 0);
+        assert_eq!(levenshtein("BANDWITH", "BANDWIDTH"), 1);
+        assert_eq!(levenshtein("", "URI"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_suggest() {
+        assert_eq!(suggest_tag("EXT-X-STREAMINF"), Some("EXT-X-STREAM-INF"));
+        assert_eq!(suggest_tag("EXT-X-MEDIA"), None);
+        assert_eq!(suggest_tag("EXT-X-SOMETHING-ELSE"), None);
+        assert_eq!(suggest("URL", ["URI", "AUDIO"]), Some("URI"));
+        assert_eq!(suggest_attribute("EXT-X-STREAM-INF", ["BANDWITH", "CODECS"]), Some("BANDWIDTH"));
+        assert_eq!(suggest_attribute("EXT-X-STREAM-INF", ["X-VENDOR"]), None);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::fetch;
+use crate::format::{MultivariantPlaylist, Resolution, IFrameStreamInf};
+use crate::media_playlist::{MediaPlaylist, Segment};
+use crate::parser;
+
+// Extraction of the I-frame nearest to a timestamp, one per resolution,
+// using the I-frame playlists referenced by EXT-X-I-FRAME-STREAM-INF
+
+#[derive(Debug, Clone)]
+pub struct Thumbnail {
+    pub resolution: Option<Resolution>,
+    pub uri: String,
+    pub time: f64, // presentation time of the I-frame, seconds
+    pub data: Vec<u8>,
+}
+
+impl Thumbnail {
+    /* Name of the output file: resolution, or "unknown", and the extension of the
+       media resource ("1280x720.ts") */
+    pub fn file_name(&self) -> String {
+        let base = match &self.resolution {
+            Some(res) => format!("{}x{}", res.w, res.h),
+            None => "unknown".to_string(),
+        };
+        let path = self.uri.split(['?', '#']).next().unwrap_or("");
+        let name = path.rsplit('/').next().unwrap_or("");
+        match name.rsplit_once('.') {
+            Some((_, ext)) if !ext.is_empty() => format!("{}.{}", base, ext),
+            _ => format!("{}.bin", base),
+        }
+    }
+}
+
+/* Timestamp as seconds ("300", "300.5") or [[HH:]MM:]SS[.fff] ("00:05:00") */
+pub fn parse_timestamp(s: &str) -> Result<f64, String> {
+    let mut seconds = 0.0;
+    for part in s.split(':') {
+        let v: f64 = part.parse().map_err(|_| format!("Invalid timestamp {}", s))?;
+        if v < 0.0 {
+            return Err(format!("Invalid timestamp {}", s))
+        }
+        seconds = seconds * 60.0 + v;
+    }
+    if s.split(':').count() > 3 {
+        return Err(format!("Invalid timestamp {}", s))
+    }
+    Ok(seconds)
+}
+
+/* The segment of an I-frame playlist displayed at `at` seconds, with its start time.
+   Timestamps past the end select the last I-frame. */
+pub fn frame_at(playlist: &MediaPlaylist, at: f64) -> Option<(f64, &Segment)> {
+    let mut start = 0.0;
+    for (i, seg) in playlist.segments.iter().enumerate() {
+        if at < start + seg.duration || i + 1 == playlist.segments.len() {
+            return Some((start, seg))
+        }
+        start += seg.duration;
+    }
+    None
+}
+
+// Highest bandwidth EXT-X-I-FRAME-STREAM-INF for each resolution, in playlist order
+fn per_resolution(playlist: &MultivariantPlaylist) -> Vec<&IFrameStreamInf> {
+    let mut ret: Vec<&IFrameStreamInf> = vec![];
+    for ifsi in &playlist.i_frame_stream_inf {
+        match ret.iter_mut().find(|v| v.resolution == ifsi.resolution) {
+            Some(v) => if ifsi.bandwidth > v.bandwidth { *v = ifsi },
+            None => ret.push(ifsi),
+        }
+    }
+    ret
+}
+
+/* Fetch the I-frame nearest to `at` for every resolution of the multivariant
+   playlist loaded from `uri`. */
+pub fn extract(uri: &str, playlist: &MultivariantPlaylist, at: f64) -> Result<Vec<Thumbnail>, String> {
+    if playlist.i_frame_stream_inf.is_empty() {
+        return Err("No EXT-X-I-FRAME-STREAM-INF in playlist".to_string())
+    }
+    let mut ret = vec![];
+    for ifsi in per_resolution(playlist) {
+        let iframes_uri = fetch::resolve(uri, ifsi.uri.as_str())?;
+        let contents = fetch::fetch_string(&iframes_uri)?;
+        let iframes = parser::parse_media_playlist(&contents).map_err(|e| format!("{}: {}", iframes_uri, e))?;
+        if !iframes.i_frames_only {
+            return Err(format!("{}: not an I-frame playlist", iframes_uri))
+        }
+        let Some((time, seg)) = frame_at(&iframes, at) else {
+            return Err(format!("{}: no I-frames", iframes_uri))
+        };
+        let seg_uri = fetch::resolve(&iframes_uri, seg.uri.as_str())?;
+        let range = seg.byte_range.as_ref().map(|r| (r.offset, r.length));
+        let data = fetch::fetch_bytes(&seg_uri, range)?;
+        ret.push(Thumbnail{ resolution: ifsi.resolution.clone(), uri: seg_uri, time, data });
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::format::Resolution;
+    use crate::parser;
+    use super::{extract, frame_at, parse_timestamp, Thumbnail};
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("00:05:00"), Ok(300.0));
+        assert_eq!(parse_timestamp("1:02:03.5"), Ok(3723.5));
+        assert_eq!(parse_timestamp("05:00"), Ok(300.0));
+        assert_eq!(parse_timestamp("42.25"), Ok(42.25));
+        assert!(parse_timestamp("1:2:3:4").is_err());
+        assert!(parse_timestamp("-5").is_err());
+        assert!(parse_timestamp("5m").is_err());
+    }
+
+    #[test]
+    fn test_frame_at() {
+        let contents = fs::read_to_string("data/media_playlist.m3u8").expect("Failed to read file");
+        let pl = parser::parse_media_playlist(&contents).expect("Failed to parse");
+        let Some((start, seg)) = frame_at(&pl, 0.0) else { assert!(false); return };
+        assert_eq!((start, seg.uri.as_str()), (0.0, "segment_000.ts"));
+        let Some((start, seg)) = frame_at(&pl, 25.0) else { assert!(false); return };
+        assert!((start - 20.02).abs() < 1e-9);
+        assert_eq!(seg.uri, "segment_002.ts");
+        let Some((_, seg)) = frame_at(&pl, 3600.0) else { assert!(false); return };
+        assert_eq!(seg.uri, "segment_004.ts");
+    }
+
+    #[test]
+    fn test_file_name() {
+        let mut t = Thumbnail{
+            resolution: Some(Resolution{ w: 1280, h: 720 }),
+            uri: "https://example.com/iframes/seg_1.ts?token=x".to_string(),
+            time: 0.0,
+            data: vec![],
+        };
+        assert_eq!(t.file_name(), "1280x720.ts");
+        t.resolution = None;
+        t.uri = "frames/keyframe".to_string();
+        assert_eq!(t.file_name(), "unknown.bin");
+    }
+
+    #[test]
+    fn test_extract() {
+        let uri = "data/thumbnails/master.m3u8";
+        let contents = fs::read_to_string(uri).expect("Failed to read file");
+        let pl = parser::parse_playlist(&contents).expect("Failed to parse");
+        let thumbs = extract(uri, &pl, 300.0).expect("Failed to extract");
+        assert_eq!(thumbs.len(), 2);
+        assert_eq!(thumbs[0].file_name(), "640x360.ts");
+        assert_eq!(thumbs[0].data, b"360p frame 2\n");
+        assert_eq!(thumbs[0].time, 240.0);
+        // Highest bandwidth stream of the resolution is used, only its sub-range is read
+        assert_eq!(thumbs[1].file_name(), "1280x720.ts");
+        assert_eq!(thumbs[1].data, b"720p frame 2\n");
+    }
+}
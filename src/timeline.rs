@@ -0,0 +1,181 @@
+use chrono::{DateTime, FixedOffset, SecondsFormat};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+#[cfg(feature = "net")]
+use crate::fetch;
+use crate::csv;
+use crate::media_playlist::{MediaPlaylist, ProgramDateTime};
+
+// Per-segment timeseries of a media playlist, for charting segment durations
+// and sizes over time
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TimelineEntry {
+    pub sequence: u64, // media sequence number
+    pub uri: String,
+    pub start: f64, // seconds since the first segment of the playlist
+    pub duration: f64,
+    pub size: Option<u64>, // bytes, from EXT-X-BYTERANGE or measured
+    pub discontinuity: bool,
+    pub discontinuity_sequence: u64,
+    pub program_date_time: Option<DateTime<FixedOffset>>,
+    pub date_time: Option<DateTime<FixedOffset>>, // explicit or extrapolated
+    pub gap: bool,
+    pub bitrate: Option<u64>, // kbps, from EXT-X-BITRATE
+}
+
+/* Entries for the segments of the playlist, only the last `window` ones if given */
+pub fn timeline(playlist: &MediaPlaylist, window: Option<usize>) -> Vec<TimelineEntry> {
+    let mut start = 0.0;
+    let mut ret = vec![];
+    let date_times = playlist.date_times();
+    let discontinuity_sequences = playlist.discontinuity_sequences();
+    for (i, seg) in playlist.segments.iter().enumerate() {
+        ret.push(TimelineEntry{
+            sequence: playlist.sequence(i),
+            uri: seg.uri.to_string(),
+            start,
+            duration: seg.duration,
+            size: seg.byte_range.as_ref().map(|r| r.length),
+            discontinuity: seg.discontinuity,
+            discontinuity_sequence: discontinuity_sequences[i],
+            program_date_time: seg.program_date_time.as_ref().and_then(ProgramDateTime::to_date_time),
+            date_time: date_times[i],
+            gap: seg.gap,
+            bitrate: seg.bitrate,
+        });
+        start += seg.duration;
+    }
+    if let Some(w) = window {
+        ret.drain(..ret.len().saturating_sub(w));
+    }
+    ret
+}
+
+// SplitMix64, a small generator so samples only depend on the seed
+fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/* Keep `n` entries for spot checks, one picked at random in each of `n` even
+   stretches of the timeline. The same seed picks the same entries. */
+pub fn sample(entries: Vec<TimelineEntry>, n: usize, seed: u64) -> Vec<TimelineEntry> {
+    if n >= entries.len() {
+        return entries
+    }
+    let mut state = seed;
+    let mut picked = vec![];
+    for i in 0..n {
+        let start = i * entries.len() / n;
+        let end = (i + 1) * entries.len() / n;
+        picked.push(start + (next_random(&mut state) % (end - start) as u64) as usize);
+    }
+    entries.into_iter().enumerate().filter(|(i, _)| picked.contains(i)).map(|(_, e)| e).collect()
+}
+
+/* Fill in missing sizes by asking for the length of each segment resource,
+   URIs are resolved against `base`, the URI of the playlist */
+#[cfg(feature = "net")]
+pub fn measure(entries: &mut [TimelineEntry], base: &str) -> Result<(), String> {
+    for e in entries.iter_mut().filter(|e| e.size.is_none()) {
+        e.size = Some(fetch::content_length(&fetch::resolve(base, &e.uri)?)?);
+    }
+    Ok(())
+}
+
+pub fn to_csv(entries: &[TimelineEntry]) -> String {
+    let date_time = |dt: &Option<DateTime<FixedOffset>>|
+        dt.map(|dt| dt.to_rfc3339_opts(SecondsFormat::Millis, true)).unwrap_or_default();
+    let mut ret = "sequence,uri,start,duration,size,discontinuity,discontinuity_sequence,program_date_time,date_time,gap,bitrate\n"
+        .to_string();
+    for e in entries {
+        ret += &format!("{},{},{:.3},{:.3},{},{},{},{},{},{},{}\n", e.sequence, csv::field(&e.uri), e.start, e.duration,
+            e.size.map(|s| s.to_string()).unwrap_or_default(), e.discontinuity, e.discontinuity_sequence,
+            date_time(&e.program_date_time), date_time(&e.date_time), e.gap,
+            e.bitrate.map(|b| b.to_string()).unwrap_or_default());
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::media_playlist::Skip;
+    use crate::parser;
+    use super::{sample, timeline};
+    #[cfg(feature = "net")]
+    use super::{measure, to_csv};
+
+    #[test]
+    fn test_timeline() {
+        let data = include_str!("../data/live_playlist.m3u8");
+        let pl = parser::parse_media_playlist(data).unwrap();
+        let entries = timeline(&pl, None);
+        assert_eq!(entries.len(), 6);
+        assert_eq!(entries[0].sequence, 1200);
+        assert_eq!(entries[0].program_date_time, parser::parse_date_time("2024-05-01T12:00:00Z"));
+        assert!(!entries[2].discontinuity);
+        assert!(entries[3].discontinuity);
+        assert_eq!(entries[3].uri, "ad_0.ts");
+        assert!((entries[3].start - 17.984).abs() < 1e-9);
+        assert_eq!(entries[4].program_date_time, None);
+        assert_eq!(entries[4].date_time, parser::parse_date_time("2024-05-01T12:00:36Z"));
+
+        let entries = timeline(&pl, Some(2));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].sequence, 1204);
+        assert!((entries[1].start - 28.484).abs() < 1e-9);
+        assert_eq!(timeline(&pl, Some(100)).len(), 6);
+
+        // delta update: the first four segments skipped
+        let mut delta = pl.clone();
+        delta.segments.drain(..4);
+        delta.skip = Some(Skip{ skipped_segments: 4, recently_removed_dateranges: vec![] });
+        assert_eq!(timeline(&delta, None).iter().map(|e| e.sequence).collect::<Vec<_>>(), [1204, 1205]);
+    }
+
+    #[test]
+    fn test_sample() {
+        let data = include_str!("../data/live_playlist.m3u8");
+        let pl = parser::parse_media_playlist(data).unwrap();
+        let sequences = |n, seed| sample(timeline(&pl, None), n, seed).iter().map(|e| e.sequence).collect::<Vec<_>>();
+        let picked = sequences(3, 42);
+        assert_eq!(picked.len(), 3);
+        // one per stretch of two segments
+        for (i, seq) in picked.iter().enumerate() {
+            assert!((1200 + 2 * i as u64..1202 + 2 * i as u64).contains(seq), "{:?}", picked);
+        }
+        assert_eq!(sequences(3, 42), picked);
+        assert_ne!((0..8).map(|seed| sequences(3, seed)).collect::<Vec<_>>(), vec![picked; 8]);
+        assert_eq!(sequences(10, 1).len(), 6);
+        assert!(sequences(0, 1).is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "net")]
+    fn test_measure_and_csv() {
+        let data = include_str!("../data/byterange_playlist.m3u8");
+        let pl = parser::parse_media_playlist(data).unwrap();
+        let mut entries = timeline(&pl, Some(2));
+        assert_eq!(entries[0].size, Some(1024));
+        assert_eq!(entries[1].size, None);
+        // sizes from EXT-X-BYTERANGE are kept, resources are measured otherwise
+        entries[1].uri = "thumbnails/720p_iframes.ts".to_string();
+        measure(&mut entries, "data/byterange_playlist.m3u8").unwrap();
+        assert_eq!(entries[0].size, Some(1024));
+        assert_eq!(entries[1].size, Some(52));
+        assert_eq!(to_csv(&entries), "sequence,uri,start,duration,size,discontinuity,discontinuity_sequence,program_date_time,date_time,gap,bitrate\n\
+            3,other.ts,30.000,10.000,1024,false,0,,,false,\n\
+            4,thumbnails/720p_iframes.ts,40.000,10.000,52,false,0,,,false,\n");
+
+        let data = include_str!("../data/live_playlist.m3u8");
+        let pl = parser::parse_media_playlist(data).unwrap();
+        let csv = to_csv(&timeline(&pl, Some(1)));
+        assert!(csv.ends_with("\n1205,live_1205.ts,28.484,6.006,,true,9,2024-05-01T12:00:40.500Z,2024-05-01T12:00:40.500Z,false,\n"));
+    }
+}
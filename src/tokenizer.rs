@@ -0,0 +1,425 @@
+// Line-level tokenizer of the playlist format: splits a line into a tag with its
+// attribute list, or a URI. Interpretation of the tags is left to the caller.
+
+use core::fmt;
+use std::ops::Index;
+
+use regex_static::once_cell::sync::Lazy;
+use regex::{Regex, Captures};
+use enum_extract_macro::EnumExtract;
+
+use crate::registry::{self, TagKind};
+
+#[derive(Debug, EnumExtract)]
+pub enum AttributeValue<'a> {
+    Integer(u64),
+    HexadecimalSequence(&'a str), // including the 0x prefix
+    Float(f64),
+    QuotedString(&'a str),
+    EnumeratedString(&'a str),
+    DecimalResolution(u64, u64),
+}
+
+// Raw is the value text as written in the playlist, including quotes
+#[derive(Debug)]
+pub struct Attribute<'a> {
+    pub name: &'a str,
+    pub value: AttributeValue<'a>,
+    pub raw: &'a str,
+}
+
+// Attribute list in the order of appearance. Lookup is linear, which is fine
+// for the handful of attributes a tag has.
+#[derive(Debug, Default)]
+pub struct AttributeMap<'a> {
+    attributes: Vec<Attribute<'a>>,
+}
+
+impl<'a> AttributeMap<'a> {
+    pub fn new() -> Self {
+        AttributeMap{attributes: vec![]}
+    }
+
+    // False for a repeated name, the first value is kept
+    pub fn insert(&mut self, name: &'a str, value: AttributeValue<'a>, raw: &'a str) -> bool {
+        if self.contains_key(name) {
+            return false
+        }
+        self.attributes.push(Attribute{name, value, raw});
+        true
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AttributeValue<'a>> {
+        self.attributes.iter().find(|a| a.name == name).map(|a| &a.value)
+    }
+
+    pub fn get_raw(&self, name: &str) -> Option<&'a str> {
+        self.attributes.iter().find(|a| a.name == name).map(|a| a.raw)
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Attribute<'a>> {
+        self.attributes.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.attributes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty()
+    }
+}
+
+impl<'a> Index<&str> for AttributeMap<'a> {
+    type Output = AttributeValue<'a>;
+
+    fn index(&self, name: &str) -> &Self::Output {
+        self.get(name).unwrap_or_else(|| panic!("no attribute {}", name))
+    }
+}
+
+#[derive(Debug, EnumExtract)]
+pub enum ParsedLine<'a> {
+    ExtM3U,
+    Tag(&'a str),
+    TagWithAttributes(&'a str, AttributeMap<'a>),
+    TagWithValue(&'a str, &'a str), // e.g. #EXTINF:<duration>,[<title>]
+    Uri(&'a str),
+    Empty,
+}
+
+// Consume regex in the beginning of the stream. If success, return caputres and tail of the string.
+// For optimization, the regexes should start with '^', but it is not necessary
+fn consume<'a>(s: &'a str, re: &Regex) -> Option<(Captures<'a>, &'a str)> {
+    if let Some(m) = re.captures_at(s, 0) {
+        let g0 = &m.get(0).unwrap();
+        if g0.start() == 0 { // verify once again that capture starts as 0
+            let tail = &s[g0.len()..];
+            return Some((m, tail))
+        }
+    }
+    None
+}
+
+// Token the tokenizer was looking for when it failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    AttributeName,
+    AttributeValue,
+    Comma,
+    TagOrUri,
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Expected::AttributeName => "attribute name",
+            Expected::AttributeValue => "attribute value",
+            Expected::Comma => "comma",
+            Expected::TagOrUri => "tag or URI",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxErrorKind {
+    Expected(Expected),
+    // RFC 8216bis 4.2: an AttributeName must not occur more than once in an attribute list
+    DuplicateAttribute,
+}
+
+// Tokenizer failure; column is the byte offset inside the line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub column: usize,
+    pub kind: SyntaxErrorKind,
+}
+
+impl SyntaxError {
+    fn expected(column: usize, expected: Expected) -> Self {
+        SyntaxError{column, kind: SyntaxErrorKind::Expected(expected)}
+    }
+}
+
+static RE_RESOLUTION: Lazy<Regex> = regex_static::lazy_regex!(r#"^([0-9]+)x([0-9]+)$"#);
+
+pub fn parse_resolution(res: &str) -> Option<AttributeValue<'_>> {
+    if let Some(m) = RE_RESOLUTION.captures(res) {
+        Some(AttributeValue::DecimalResolution(
+            m.get(1)?.as_str().parse().ok()?,
+            m.get(2)?.as_str().parse().ok()?))
+    } else {
+        None
+    }
+}
+
+static RE_ATTRIBUTE_VALUE: Lazy<Regex> = 
+    regex_static::lazy_regex!(r#"^(0[xX][0-9a-fA-F]+)|^([0-9]+\.[0-9]+)|^"([^"]+)"|^([[:alpha:]][[:alnum:]-]*)|^([0-9]+x[0-9]+)|^([0-9]+)"#);
+
+fn parse_attribute_value<'a>(value: &'a str) -> Option<(&'a str, AttributeValue<'a>)> {
+    if let Some((m, tail)) = consume(value, &RE_ATTRIBUTE_VALUE) {
+        let av =
+            if let Some(mhex) = m.get(1) {
+                AttributeValue::HexadecimalSequence(mhex.as_str())
+            } else if let Some(mf) = m.get(2) {
+                AttributeValue::Float(mf.as_str().parse::<f64>().ok()?)
+            } else if let Some(mqs) = m.get(3) {
+                AttributeValue::QuotedString(mqs.as_str())
+            } else if let Some(mes) = m.get(4) {
+                AttributeValue::EnumeratedString(mes.as_str())
+            } else if let Some(mres) = m.get(5) {
+                parse_resolution(mres.as_str()).unwrap()
+            } else if let Some(mdec) = m.get(6) {
+                AttributeValue::Integer(mdec.as_str().parse::<u64>().ok()?)
+            } else {
+                panic!("unexpected parser state")
+            };
+        Some((tail, av))
+    } else {
+        None
+    }
+}
+
+static RE_ATTRIBUTE_NAME : Lazy<Regex> = regex_static::lazy_regex!(r#"^([[:alpha:]-]+)="#);
+
+// On failure, the column of the returned error is relative to `value`
+pub fn parse_attributes<'a>(value: &'a str) -> Result<AttributeMap<'a>, SyntaxError> {
+    let column = |t: &str| value.len() - t.len();
+    let mut tail = value;
+    let mut result = AttributeMap::new();
+    while !tail.is_empty() {
+        let Some((mkey, t)) = consume(tail, &RE_ATTRIBUTE_NAME)
+        else { return Err(SyntaxError::expected(column(tail), Expected::AttributeName)) };
+        let key = mkey.get(1).unwrap().as_str();
+        let key_column = column(tail);
+        tail = t;
+        let Some((t, av)) = parse_attribute_value(tail)
+        else { return Err(SyntaxError::expected(column(tail), Expected::AttributeValue)) };
+        if !result.insert(key, av, &tail[..tail.len() - t.len()]) {
+            return Err(SyntaxError{column: key_column, kind: SyntaxErrorKind::DuplicateAttribute})
+        }
+        if t.is_empty() { break }
+        if !t.starts_with(",") { // consume trailing comma
+            return Err(SyntaxError::expected(column(t), Expected::Comma))
+        }
+        tail = &t[1..];
+    }
+    Ok(result)
+}
+
+static RE_TAG_NAME: Lazy<Regex> = regex_static::lazy_regex!(r#"^#(EXT[[:alnum:]-]*)($|:)"#);
+// Any line that is not blank and does not start with '#' is a URI
+static RE_URI: Lazy<Regex> = regex_static::lazy_regex!(r#"^[^#]"#);
+
+pub fn parse_line<'a>(line: &'a str) -> Result<ParsedLine<'a>, SyntaxError> {
+    if line.is_empty() {
+        return Ok(ParsedLine::Empty);
+    }
+    if line == "#EXTM3U" {
+        return Ok(ParsedLine::ExtM3U);
+    }
+    if let Some((mtag, tail)) = consume(line, &RE_TAG_NAME) {
+        let tag = mtag.get(1).unwrap().as_str();
+        if tail.is_empty() {
+            return Ok(ParsedLine::Tag(tag));
+        }
+        let kind = registry::tag(tag).map(|t| t.kind);
+        if kind == Some(TagKind::Value) {
+            return Ok(ParsedLine::TagWithValue(tag, tail));
+        }
+        return match parse_attributes(tail) {
+            Ok(attr) => Ok(ParsedLine::TagWithAttributes(tag, attr)),
+            // value of a tag we know nothing about, keep it as is
+            Err(_) if kind.is_none() => Ok(ParsedLine::TagWithValue(tag, tail)),
+            Err(e) => Err(SyntaxError{column: e.column + line.len() - tail.len(), ..e}),
+        }
+    }
+    if let Some(_) = RE_URI.captures(line) {
+        return Ok(ParsedLine::Uri(line))
+    }
+    Err(SyntaxError::expected(0, Expected::TagOrUri))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_attribute_value() {
+        if let Some((tail,AttributeValue::Float(d))) = parse_attribute_value("12.5,tail") {
+            assert_eq!(tail, ",tail");
+            assert_eq!(d, 12.5);
+        } else {
+            assert!(false)
+        }
+
+        if let Some((_,AttributeValue::DecimalResolution(w, h))) = parse_attribute_value("2560x1440") {
+            assert_eq!(w, 2560);
+            assert_eq!(h, 1440);
+        } else {
+            assert!(false)
+        }
+
+        if let Some((_,AttributeValue::Integer(v))) = parse_attribute_value("10058085") {
+            assert_eq!(v, 10058085);
+        } else {
+            assert!(false)
+        }
+
+        if let Some((_,AttributeValue::QuotedString(v))) = parse_attribute_value(r#""mp4a.40.2,hvc1.2.4.L150.90""#) {
+            assert_eq!(v, "mp4a.40.2,hvc1.2.4.L150.90");
+        } else {
+            assert!(false)
+        }
+
+
+        if let Some((tail,AttributeValue::EnumeratedString(v))) = parse_attribute_value("PQ,SOMETHING") {
+            assert_eq!(tail, ",SOMETHING");
+            assert_eq!(v, "PQ");
+        } else {
+            assert!(false)
+        }
+
+        if let Some((_,AttributeValue::EnumeratedString(v))) = parse_attribute_value("AES-128") {
+            assert_eq!(v, "AES-128");
+        } else {
+            assert!(false)
+        }
+
+        if let Some((tail,AttributeValue::HexadecimalSequence(v))) = parse_attribute_value("0x9c7db8778570d05c3177c349fd9236aa,") {
+            assert_eq!(tail, ",");
+            assert_eq!(v, "0x9c7db8778570d05c3177c349fd9236aa");
+        } else {
+            assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_parse_attribute_str() {
+        let astr = r#"BANDWIDTH=15811232,AVERAGE-BANDWIDTH=10058085,CODECS="mp4a.40.2,hvc1.2.4.L150.90",RESOLUTION=2560x1440,FRAME-RATE=23.97,VIDEO-RANGE=PQ,AUDIO="aac-128k",CLOSED-CAPTIONS=NONE"#;
+        let parsed = parse_attributes(astr);
+        assert!(parsed.is_ok());
+        let parsed = parsed.unwrap();
+        if let AttributeValue::Integer(bw) = &parsed["BANDWIDTH"] {
+            assert_eq!(*bw, 15811232);
+        } else {
+            assert!(false)
+        }
+        if let AttributeValue::Integer(bw) = &parsed["AVERAGE-BANDWIDTH"] {
+            assert_eq!(*bw, 10058085);
+        } else {
+            assert!(false)
+        }
+        if let AttributeValue::QuotedString(s) = &parsed["CODECS"] {
+            assert_eq!(*s, "mp4a.40.2,hvc1.2.4.L150.90");
+        } else {
+            assert!(false)
+        }
+        if let AttributeValue::EnumeratedString(s) = &parsed["CLOSED-CAPTIONS"] {
+            assert_eq!(*s, "NONE");
+        } else {
+            assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_parse_line() {
+        if let Ok(ParsedLine::Empty) = parse_line("") {
+            assert!(true);
+        } else {
+            assert!(false);
+        }
+
+        if let Ok(ParsedLine::ExtM3U) = parse_line("#EXTM3U") {
+            assert!(true);
+        } else {
+            assert!(false);
+        }
+
+        if let Ok(ParsedLine::Tag(tag)) = parse_line("#EXT-X-INDEPENDENT-SEGMENTS") {
+            assert_eq!(tag, "EXT-X-INDEPENDENT-SEGMENTS");
+        } else {
+            assert!(false);
+        }
+
+        let lmedia = r#"#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac-128k",NAME="English",LANGUAGE="en",DEFAULT=YES,AUTOSELECT=YES,CHANNELS="2",URI="audio/unenc/aac_128k/vod.m3u8""#;
+        if let Ok(ParsedLine::TagWithAttributes(tag, attrs)) = parse_line(lmedia) {
+            assert_eq!(tag, "EXT-X-MEDIA");
+            if let AttributeValue::EnumeratedString(s) = attrs["TYPE"] {
+                assert_eq!(s, "AUDIO");
+            } else {
+                assert!(false);
+            }
+            if let AttributeValue::QuotedString(s) = attrs["URI"] {
+                assert_eq!(s, "audio/unenc/aac_128k/vod.m3u8");
+            } else {
+                assert!(false);
+            }
+        } else {
+            assert!(false);
+        }
+
+        if let Ok(ParsedLine::Uri(u)) = parse_line("hdr10/unenc/1650k/vod.m3u8") {
+            assert_eq!(u, "hdr10/unenc/1650k/vod.m3u8");
+        } else {
+            assert!(false);
+        }
+
+        if let Ok(ParsedLine::Uri(u)) = parse_line("https://cdn.example.com/seg_001.ts?token=a-b") {
+            assert_eq!(u, "https://cdn.example.com/seg_001.ts?token=a-b");
+        } else {
+            assert!(false);
+        }
+
+        if let Ok(ParsedLine::TagWithValue(tag, v)) = parse_line("#EXTINF:9.009,title") {
+            assert_eq!(tag, "EXTINF");
+            assert_eq!(v, "9.009,title");
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn test_parse_custom_tag() {
+        let Ok(ParsedLine::TagWithAttributes(tag, attrs)) = parse_line(r#"#EXT-X-VENDOR-AD:ID="ad-1",DURATION=15.0"#) else {
+            assert!(false);
+            return
+        };
+        assert_eq!(tag, "EXT-X-VENDOR-AD");
+        assert_eq!(*attrs["ID"].as_quoted_string().unwrap(), "ad-1");
+        assert_eq!(*attrs["DURATION"].as_float().unwrap(), 15.0);
+
+        let Ok(ParsedLine::TagWithValue(tag, value)) = parse_line("#EXT-X-VENDOR-AD:ID") else {
+            assert!(false);
+            return
+        };
+        assert_eq!(tag, "EXT-X-VENDOR-AD");
+        assert_eq!(value, "ID");
+
+        let e = parse_line("#EXT-X-MEDIA:TYPE").unwrap_err();
+        assert_eq!(e, SyntaxError::expected(13, Expected::AttributeName));
+    }
+
+    #[test]
+    fn test_duplicate_attribute() {
+        let e = parse_line("#EXT-X-STREAM-INF:BANDWIDTH=1,CODECS=\"avc1\",BANDWIDTH=2").unwrap_err();
+        assert_eq!(e, SyntaxError{column: 44, kind: SyntaxErrorKind::DuplicateAttribute});
+    }
+
+    #[test]
+    fn test_attribute_order_and_raw() {
+        let parsed = parse_attributes(r#"URI="a.m3u8",BANDWIDTH=222552,FRAME-RATE=23.970,VIDEO-RANGE=PQ"#).unwrap();
+        let names = parsed.iter().map(|a| a.name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["URI", "BANDWIDTH", "FRAME-RATE", "VIDEO-RANGE"]);
+        let raw = parsed.iter().map(|a| a.raw).collect::<Vec<_>>();
+        assert_eq!(raw, vec![r#""a.m3u8""#, "222552", "23.970", "PQ"]);
+        assert_eq!(parsed.get_raw("FRAME-RATE"), Some("23.970"));
+        assert_eq!(parsed.len(), 4);
+        assert!(!parsed.contains_key("CODECS"));
+    }
+}
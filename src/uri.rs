@@ -0,0 +1,386 @@
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+// URI found in a playlist, absolute or relative. The text is kept as written so
+// playlists can be written back unchanged, next to its components as split by
+// RFC 3986 appendix B. Components are not percent-decoded.
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "serde", serde(into = "String", try_from = "String"))]
+pub struct PlaylistUri {
+    text: String,
+    scheme: Option<Range<usize>>,
+    authority: Option<Range<usize>>,
+    path: Range<usize>,
+    query: Option<Range<usize>>,
+    fragment: Option<Range<usize>>,
+}
+
+fn is_scheme(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic())
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+}
+
+// Percent-encode everything but unreserved characters
+fn encode(s: &str) -> String {
+    let mut ret = String::new();
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            ret.push(b as char);
+        } else {
+            ret += &format!("%{:02X}", b);
+        }
+    }
+    ret
+}
+
+/* RFC 3986 section 5.2.4. Leading ".." of relative paths are kept, they refer
+   above the base when resolving against a relative base. */
+fn remove_dot_segments(path: &str) -> String {
+    let absolute = path.starts_with('/');
+    let mut out: Vec<&str> = vec![];
+    let segments: Vec<&str> = path.split('/').collect();
+    for (i, seg) in segments.iter().enumerate() {
+        let last = i + 1 == segments.len();
+        match *seg {
+            "." => if last { out.push("") },
+            ".." => {
+                match out.last() {
+                    Some(&prev) if prev != ".." && !(prev.is_empty() && out.len() == 1 && absolute) => { out.pop(); },
+                    _ if absolute => (),
+                    _ => out.push(".."),
+                }
+                if last {
+                    out.push("");
+                }
+            },
+            _ => out.push(seg),
+        }
+    }
+    let ret = out.join("/");
+    if absolute && !ret.starts_with('/') {
+        format!("/{}", ret)
+    } else {
+        ret
+    }
+}
+
+impl PlaylistUri {
+    /*
+    Split the URI into its components. Control characters, tabs and line breaks included,
+    are not allowed. Spaces are, as some servers write them unencoded, see syntax_error.
+    */
+    pub fn parse(text: &str) -> Result<Self, String> {
+        if text.chars().any(|c| c.is_ascii_control()) {
+            return Err(format!("Invalid URI {:?}", text))
+        }
+        let mut at = 0;
+        let scheme = match text.find([':', '/', '?', '#']) {
+            Some(i) if text[i..].starts_with(':') && is_scheme(&text[..i]) => {
+                at = i + 1;
+                Some(0..i)
+            },
+            _ => None,
+        };
+        let authority = if text[at..].starts_with("//") {
+            let start = at + 2;
+            at = text[start..].find(['/', '?', '#']).map_or(text.len(), |i| start + i);
+            Some(start..at)
+        } else {
+            None
+        };
+        let path_end = text[at..].find(['?', '#']).map_or(text.len(), |i| at + i);
+        let path = at..path_end;
+        at = path_end;
+        let query = if text[at..].starts_with('?') {
+            let start = at + 1;
+            at = text[start..].find('#').map_or(text.len(), |i| start + i);
+            Some(start..at)
+        } else {
+            None
+        };
+        let fragment = text[at..].starts_with('#').then(|| at + 1..text.len());
+        Ok(Self{ text: text.to_string(), scheme, authority, path, query, fragment })
+    }
+
+    fn from_parts(scheme: Option<&str>, authority: Option<&str>, path: &str, query: Option<&str>,
+        fragment: Option<&str>) -> Self {
+        let mut text = String::new();
+        if let Some(s) = scheme {
+            text += s;
+            text.push(':');
+        }
+        if let Some(a) = authority {
+            text += "//";
+            text += a;
+        }
+        text += path;
+        if let Some(q) = query {
+            text.push('?');
+            text += q;
+        }
+        if let Some(f) = fragment {
+            text.push('#');
+            text += f;
+        }
+        Self::parse(&text).expect("components of a valid URI")
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn scheme(&self) -> Option<&str> {
+        self.scheme.clone().map(|r| &self.text[r])
+    }
+
+    pub fn authority(&self) -> Option<&str> {
+        self.authority.clone().map(|r| &self.text[r])
+    }
+
+    pub fn path(&self) -> &str {
+        &self.text[self.path.clone()]
+    }
+
+    pub fn query(&self) -> Option<&str> {
+        self.query.clone().map(|r| &self.text[r])
+    }
+
+    pub fn fragment(&self) -> Option<&str> {
+        self.fragment.clone().map(|r| &self.text[r])
+    }
+
+    /* Relative references have no scheme and are resolved against the playlist URI */
+    pub fn is_relative(&self) -> bool {
+        self.scheme.is_none()
+    }
+
+    /* Resolve against `base`, RFC 3986 section 5.2.2 */
+    pub fn join(&self, base: &PlaylistUri) -> PlaylistUri {
+        if !self.is_relative() {
+            return Self::from_parts(self.scheme(), self.authority(), &remove_dot_segments(self.path()),
+                self.query(), self.fragment())
+        }
+        if self.authority.is_some() {
+            return Self::from_parts(base.scheme(), self.authority(), &remove_dot_segments(self.path()),
+                self.query(), self.fragment())
+        }
+        let (path, query) = if self.path().is_empty() {
+            (base.path().to_string(), self.query().or(base.query()))
+        } else if self.path().starts_with('/') {
+            (remove_dot_segments(self.path()), self.query())
+        } else {
+            let merged = if base.authority.is_some() && base.path().is_empty() {
+                format!("/{}", self.path())
+            } else {
+                match base.path().rfind('/') {
+                    Some(i) => format!("{}{}", &base.path()[..=i], self.path()),
+                    None => self.path().to_string(),
+                }
+            };
+            (remove_dot_segments(&merged), self.query())
+        };
+        Self::from_parts(base.scheme(), base.authority(), &path, query, self.fragment())
+    }
+
+    /*
+    Why the URI is not well-formed by RFC 3986, or None. Characters must be unreserved,
+    reserved or part of a percent-encoding, so spaces are reported, brackets only in
+    the authority, and http and https URIs need a host.
+    */
+    pub fn syntax_error(&self) -> Option<String> {
+        let bytes = self.text.as_bytes();
+        for (i, c) in self.text.char_indices() {
+            if c == '%' {
+                let hex = |j: usize| bytes.get(j).is_some_and(|b| b.is_ascii_hexdigit());
+                if !hex(i + 1) || !hex(i + 2) {
+                    return Some(format!("invalid percent-encoding at {}", i))
+                }
+            } else if !(c.is_ascii_alphanumeric() || "-._~:/?#[]@!$&'()*+,;=".contains(c)) {
+                return Some(format!("character {:?} not allowed", c))
+            }
+        }
+        let outside_authority = [Some(self.path()), self.query(), self.fragment()];
+        if outside_authority.iter().flatten().any(|p| p.contains(['[', ']'])) {
+            return Some("brackets outside of the authority".to_string())
+        }
+        if self.fragment().is_some_and(|f| f.contains('#')) {
+            return Some("more than one #".to_string())
+        }
+        if matches!(self.scheme().map(|s| s.to_ascii_lowercase()).as_deref(), Some("http" | "https")) {
+            let host_port = self.authority().unwrap_or("").rsplit('@').next().unwrap_or("");
+            let (host, port) = match host_port.rsplit_once(':') {
+                Some((h, p)) if !p.contains(']') => (h, Some(p)),
+                _ => (host_port, None),
+            };
+            if host.is_empty() {
+                return Some("no host".to_string())
+            }
+            if port.is_some_and(|p| !p.bytes().all(|b| b.is_ascii_digit())) {
+                return Some(format!("invalid port {}", port.unwrap_or_default()))
+            }
+        }
+        None
+    }
+
+    /* Name and value of each parameter of the query, as written */
+    pub fn query_pairs(&self) -> Vec<(&str, &str)> {
+        self.query().unwrap_or("").split('&').filter(|p| !p.is_empty())
+            .map(|p| p.split_once('=').unwrap_or((p, "")))
+            .collect()
+    }
+
+    /* The URI with parameter `name` set to `value`, replacing previous values */
+    pub fn with_query_param(&self, name: &str, value: &str) -> PlaylistUri {
+        let name = encode(name);
+        let mut params: Vec<String> = self.query_pairs().into_iter().filter(|(n, _)| *n != name)
+            .map(|(n, v)| if v.is_empty() { n.to_string() } else { format!("{}={}", n, v) })
+            .collect();
+        params.push(format!("{}={}", name, encode(value)));
+        Self::from_parts(self.scheme(), self.authority(), self.path(), Some(&params.join("&")), self.fragment())
+    }
+
+    /* The URI without parameter `name`, and without query when none is left */
+    pub fn without_query_param(&self, name: &str) -> PlaylistUri {
+        let name = encode(name);
+        let params: Vec<String> = self.query_pairs().into_iter().filter(|(n, _)| *n != name)
+            .map(|(n, v)| if v.is_empty() { n.to_string() } else { format!("{}={}", n, v) })
+            .collect();
+        let query = params.join("&");
+        Self::from_parts(self.scheme(), self.authority(), self.path(),
+            (!query.is_empty()).then_some(query.as_str()), self.fragment())
+    }
+}
+
+impl fmt::Display for PlaylistUri {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl AsRef<str> for PlaylistUri {
+    fn as_ref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl FromStr for PlaylistUri {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<String> for PlaylistUri {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::parse(&s)
+    }
+}
+
+impl From<PlaylistUri> for String {
+    fn from(uri: PlaylistUri) -> Self {
+        uri.text
+    }
+}
+
+impl PartialEq<str> for PlaylistUri {
+    fn eq(&self, other: &str) -> bool {
+        self.text == other
+    }
+}
+
+impl PartialEq<&str> for PlaylistUri {
+    fn eq(&self, other: &&str) -> bool {
+        self.text == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PlaylistUri;
+
+    fn uri(s: &str) -> PlaylistUri {
+        PlaylistUri::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let u = uri("https://cdn.example.com/hls/master.m3u8?token=abc&x#frag");
+        assert_eq!(u.scheme(), Some("https"));
+        assert_eq!(u.authority(), Some("cdn.example.com"));
+        assert_eq!(u.path(), "/hls/master.m3u8");
+        assert_eq!(u.query(), Some("token=abc&x"));
+        assert_eq!(u.fragment(), Some("frag"));
+        assert_eq!(u.query_pairs(), [("token", "abc"), ("x", "")]);
+        assert!(!u.is_relative());
+        assert_eq!(u, "https://cdn.example.com/hls/master.m3u8?token=abc&x#frag");
+
+        let u = uri("../audio/en.m3u8");
+        assert!(u.is_relative());
+        assert_eq!((u.scheme(), u.authority(), u.path(), u.query()), (None, None, "../audio/en.m3u8", None));
+        assert_eq!(uri("skd://key-id").scheme(), Some("skd"));
+        assert_eq!(uri("a:b/c").scheme(), Some("a"));
+        assert_eq!(uri("1:seg.ts").scheme(), None);
+        assert!(PlaylistUri::parse("segment\t1.ts").is_err() && PlaylistUri::parse("segment\n1.ts").is_err());
+        assert_eq!(uri("segment 1.ts").path(), "segment 1.ts");
+        assert!(PlaylistUri::parse("").is_ok());
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        for ok in ["https://cdn.example.com:8443/hls/v.m3u8?a=1&b=%2F#t", "../audio/en.m3u8", "http://[::1]:80/v.m3u8",
+            "skd://key-id", "v;x=1/~seg(1).m3u8", ""] {
+            assert_eq!(uri(ok).syntax_error(), None, "{}", ok);
+        }
+        let error = |s: &str| uri(s).syntax_error().unwrap();
+        assert_eq!(error("v.m3u8?token=a%2"), "invalid percent-encoding at 14");
+        assert_eq!(error("caf\u{e9}/v.m3u8"), "character 'é' not allowed");
+        assert_eq!(error("v|1.m3u8"), "character '|' not allowed");
+        assert_eq!(error("video 1.m3u8"), "character ' ' not allowed");
+        assert_eq!(error("v[1].m3u8"), "brackets outside of the authority");
+        assert_eq!(error("v.m3u8#a#b"), "more than one #");
+        assert_eq!(error("https:///v.m3u8"), "no host");
+        assert_eq!(error("http://cdn.example.com:http/v.m3u8"), "invalid port http");
+    }
+
+    #[test]
+    fn test_join() {
+        // examples of RFC 3986 section 5.4
+        let base = uri("http://a/b/c/d;p?q");
+        let cases = [
+            ("g:h", "g:h"), ("g", "http://a/b/c/g"), ("./g", "http://a/b/c/g"), ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"), ("//g", "http://g"), ("?y", "http://a/b/c/d;p?y"), ("g?y", "http://a/b/c/g?y"),
+            ("#s", "http://a/b/c/d;p?q#s"), ("", "http://a/b/c/d;p?q"), (".", "http://a/b/c/"),
+            ("..", "http://a/b/"), ("../g", "http://a/b/g"), ("../..", "http://a/"), ("../../../g", "http://a/g"),
+            ("/./g", "http://a/g"), ("g/../h", "http://a/b/c/h"), ("g;x=1/./y", "http://a/b/c/g;x=1/y"),
+        ];
+        for (reference, expected) in cases {
+            assert_eq!(uri(reference).join(&base), expected, "{}", reference);
+        }
+        // relative bases stay relative
+        assert_eq!(uri("720p/index.m3u8").join(&uri("data/master.m3u8")), "data/720p/index.m3u8");
+        assert_eq!(uri("../seg.ts").join(&uri("../live/index.m3u8")), "../seg.ts");
+        assert_eq!(uri("seg.ts").join(&uri("index.m3u8")), "seg.ts");
+    }
+
+    #[test]
+    fn test_query_params() {
+        let u = uri("https://cdn.example.com/v.m3u8?a=1&token=old#t");
+        let signed = u.with_query_param("token", "x/y z");
+        assert_eq!(signed, "https://cdn.example.com/v.m3u8?a=1&token=x%2Fy%20z#t");
+        assert_eq!(signed.without_query_param("token"), "https://cdn.example.com/v.m3u8?a=1#t");
+        assert_eq!(uri("v.m3u8?a=1").without_query_param("a"), "v.m3u8");
+        assert_eq!(uri("v.m3u8").with_query_param("a", "1").query(), Some("a=1"));
+    }
+}
@@ -0,0 +1,961 @@
+// Incremental validation of a multivariant playlist. Entries are fed one at a time,
+// as they are parsed, and findings are returned as soon as they can be decided.
+// The validator owns all of its state, so it is Send + Sync and can be shared
+// between threads behind a Mutex or moved to a worker.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::codecs::{self, CodecFamily, Codecs};
+use crate::format::{AUDIO_ONLY_MAX_BANDWIDTH, ClosedCaptions, FrameRate, IFrameStreamInf, InstreamId, Media, MediaType, Resolution,
+    StreamInf, VideoRange};
+use crate::registry;
+use crate::uri::PlaylistUri;
+
+// Largest picture usually encoded with AVC Baseline, 720p
+const BASELINE_MAX_PIXELS: u64 = 1280 * 720;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error, Warning,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+// Tag a finding is about, `index` counting the tags of the same name from 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub tag: &'static str,
+    pub index: usize,
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}[{}]", self.tag, self.index)
+    }
+}
+
+// `rule` identifies the check, e.g. "bandwidth-zero", for filtering findings in CI.
+// Findings about the playlist as a whole have no location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub rule: &'static str,
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+impl Finding {
+    pub fn error(rule: &'static str, message: String) -> Self {
+        Finding{severity: Severity::Error, rule, message, location: None}
+    }
+
+    pub fn warning(rule: &'static str, message: String) -> Self {
+        Finding{severity: Severity::Warning, rule, message, location: None}
+    }
+
+    pub fn at(self, location: Location) -> Self {
+        Finding{location: Some(location), ..self}
+    }
+}
+
+// error[bandwidth-zero] EXT-X-STREAM-INF[0]: BANDWIDTH 0 in ...
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}[{}]", self.severity.as_str(), self.rule)?;
+        if let Some(location) = &self.location {
+            write!(f, " {}", location)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+// All the findings of a validation, in the order they were found
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ValidationReport {
+    pub findings: Vec<Finding>,
+}
+
+impl ValidationReport {
+    pub fn errors(&self) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(|f| f.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(|f| f.severity == Severity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    /* Err when there is at least one error, the report holding the warnings too */
+    pub fn into_result(self) -> Result<Self, Self> {
+        if self.has_errors() { Err(self) } else { Ok(self) }
+    }
+}
+
+impl From<Vec<Finding>> for ValidationReport {
+    fn from(findings: Vec<Finding>) -> Self {
+        ValidationReport{ findings }
+    }
+}
+
+// One finding per line, errors first
+impl std::fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines: Vec<String> = self.errors().chain(self.warnings()).map(|f| f.to_string()).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+// Plausible bits per pixel of video variants: BANDWIDTH, scaled by the efficiency
+// of the video codec relative to AVC, over pixels per second
+#[derive(Debug, Clone, PartialEq)]
+pub struct BandwidthBounds {
+    pub min_bpp: f64,
+    pub max_bpp: f64,
+    pub default_frame_rate: f64, // when FRAME-RATE is absent
+}
+
+impl Default for BandwidthBounds {
+    fn default() -> Self {
+        Self{ min_bpp: 0.01, max_bpp: 1.0, default_frame_rate: 30.0 }
+    }
+}
+
+impl BandwidthBounds {
+    /* BANDWIDTH to use instead of the declared one, when it is zero, below
+       AVERAGE-BANDWIDTH or outside of the bounds for the resolution and codec */
+    pub fn suggested_bandwidth(&self, si: &StreamInf) -> Option<u64> {
+        if si.bandwidth == 0 || si.average_bandwidth.is_some_and(|avg| avg > si.bandwidth) {
+            if let Some(avg) = si.average_bandwidth.filter(|avg| *avg > 0) {
+                return Some(avg)
+            }
+        }
+        let res = si.resolution.as_ref().filter(|_| si.has_video())?;
+        let pixels_per_second = res.pixels().max(1) as f64 * si.frame_rate.map_or(self.default_frame_rate, |fr| fr.as_f64());
+        let efficiency = codecs::video_efficiency(si.codecs.as_ref());
+        let bpp = si.bandwidth as f64 * efficiency / pixels_per_second;
+        let bound = if bpp < self.min_bpp {
+            self.min_bpp
+        } else if bpp > self.max_bpp {
+            self.max_bpp
+        } else {
+            return None
+        };
+        Some((bound * pixels_per_second / efficiency).round() as u64)
+    }
+}
+
+pub enum Entry<'a> {
+    Version(u64),
+    Media(&'a Media),
+    StreamInf(&'a StreamInf),
+    IFrameStreamInf(&'a IFrameStreamInf),
+}
+
+// Reference from EXT-X-STREAM-INF to a group that has not been seen yet
+struct PendingReference {
+    type_: MediaType,
+    group_id: String,
+    location: Location, // of the first tag referencing the group
+}
+
+impl PendingReference {
+    fn into_finding(self) -> Finding {
+        Finding::error("unknown-group", format!("Reference to unknown {} group {}", self.type_.as_str(), self.group_id))
+            .at(self.location)
+    }
+}
+
+/* 
+Checked rules:
+
+In EXT-X-STREAM-INF:
+*  AUDIO value MUST match the value of the
+    GROUP-ID attribute of an EXT-X-MEDIA tag elsewhere in the
+    Multivariant Playlist whose TYPE attribute is AUDIO. Likewise VIDEO
+    and SUBTITLES, with TYPE VIDEO and SUBTITLES. VIDEO of
+    EXT-X-I-FRAME-STREAM-INF is checked too.
+* CLOSED-CAPTIONS can be either a quoted-string or an enumerated-string
+    with the value NONE.  If the value is a quoted-string, it MUST
+    match the value of the GROUP-ID attribute of an EXT-X-MEDIA tag
+    elsewhere in the Playlist whose TYPE attribute is CLOSED-CAPTIONS
+
+* BANDWIDTH is the peak bitrate, it must be positive and cannot be below
+    AVERAGE-BANDWIDTH.
+* If any EXT-X-STREAM-INF has SCORE, all of them must.
+* STABLE-VARIANT-ID must be unique among EXT-X-STREAM-INF, and among
+    EXT-X-I-FRAME-STREAM-INF.
+* URIs must be well-formed, see PlaylistUri::syntax_error, once resolved
+    against the base URL if one was given.
+* Two EXT-X-STREAM-INF cannot have the same URI and renditions. The same
+    URI with other renditions, e.g. video with another AUDIO group, is fine.
+
+In EXT-X-MEDIA:
+* INSTREAM-ID is REQUIRED if the TYPE attribute is CLOSED-CAPTIONS,
+    otherwise it MUST NOT be specified. It must be unique within a group.
+* STABLE-RENDITION-ID must be unique among renditions.
+* All EXT-X-MEDIA tags in the same group MUST have different NAME attributes.
+
+As EXT-X-MEDIA may follow the EXT-X-STREAM-INF referencing it, unresolved
+references are only reported by finish().
+
+Warnings:
+* A playlist offering video should also offer an audio-only variant below
+    192 kbps for poor networks (Apple HLS authoring specification)
+* The BANDWIDTH of video variants should be plausible for their RESOLUTION,
+    FRAME-RATE and video codec, see BandwidthBounds
+* Variants with the same BANDWIDTH should have the same content, clients
+    cannot choose between them otherwise
+* URIs should not mix absolute and relative references, or schemes such as
+    http and https.
+* RESOLUTION and FRAME-RATE should be within the limits of the level of the
+    video codec, see Codec::level_error, and AVC Baseline is unusual above 720p.
+* Frame rates of a ladder should be of one family, multiples or fractions of
+    each other like 25 and 50, not 25 and 29.97.
+* Audio-only variants, whose CODECS has no video codec, should not have
+    RESOLUTION.
+* TYPE, VIDEO-RANGE and CHANNELS values unknown to this version are reported,
+    as errors in strict mode
+* Codec identifiers of CODECS must follow RFC 6381, see Codec::syntax_error,
+    and not be empty, e.g. after a trailing comma.
+    Unknown codecs and variants without CODECS are reported, as errors in
+    strict mode.
+
+In strict mode, the other MUST-level rules of RFC 8216bis:
+* BANDWIDTH of EXT-X-I-FRAME-STREAM-INF must be positive too.
+* If any EXT-X-STREAM-INF has CLOSED-CAPTIONS=NONE, all of them must.
+* A group MUST NOT have more than one member with DEFAULT=YES.
+* A playlist MUST NOT contain more than one EXT-X-VERSION tag, which the
+    parser reports as ParseErrorKind::DuplicateTag, and MUST
+    indicate the version required by the features it uses, e.g. 7 for
+    INSTREAM-ID SERVICE values. Without EXT-X-VERSION the version is 1.
+*/
+#[derive(Default)]
+pub struct Validator {
+    bounds: BandwidthBounds,
+    group_ids: HashMap<MediaType, HashSet<String>>,
+    pending: Vec<PendingReference>,
+    instream_ids: HashSet<(String, InstreamId)>, // per CLOSED-CAPTIONS group
+    stable_ids: HashSet<(&'static str, String)>, // per tag
+    has_video: bool,
+    has_audio_only_fallback: bool,
+    scores: (usize, usize), // EXT-X-STREAM-INF with and without SCORE
+    counts: HashMap<&'static str, usize>, // tags fed, by name
+    strict: bool,
+    version: Option<u64>, // declared
+    required_version: Option<(u64, String)>, // highest required, with the feature requiring it
+    names: HashSet<(MediaType, String, String)>, // of renditions, per group
+    defaults: HashSet<(MediaType, String)>, // groups with a DEFAULT=YES rendition
+    closed_captions_none: (usize, usize), // EXT-X-STREAM-INF with and without CLOSED-CAPTIONS=NONE
+    bandwidths: HashMap<u64, StreamInf>, // first EXT-X-STREAM-INF of each BANDWIDTH
+    variants: HashSet<VariantKey>,
+    base_url: Option<PlaylistUri>, // to resolve relative URIs against
+    uris: (usize, usize), // absolute and relative
+    schemes: BTreeSet<String>, // of the URIs, resolved
+    frame_rates: Vec<(FrameRate, PlaylistUri)>, // first EXT-X-STREAM-INF of each FRAME-RATE
+}
+
+// URI and groups of an EXT-X-STREAM-INF: AUDIO, VIDEO, SUBTITLES and CLOSED-CAPTIONS
+type VariantKey = (String, Option<String>, Option<String>, Option<String>, Option<ClosedCaptions>);
+
+// Frame rates of one family, such as 25 and 50 or 29.97 and 59.94, which a ladder can mix
+// Value of an enumerated attribute that the registry does not list for the tag
+fn is_unknown_value(tag: &str, attribute: &str, value: &str) -> bool {
+    registry::tag(tag).and_then(|t| t.attribute(attribute))
+        .is_some_and(|a| !a.allowed.is_empty() && !a.allowed.contains(&value))
+}
+
+fn compatible_frame_rates(a: FrameRate, b: FrameRate) -> bool {
+    let ratio = a.max(b).as_f64() / a.min(b).as_f64();
+    (ratio - ratio.round()).abs() < 0.0005
+}
+
+// Variants that differ only in URI, pathway and ids, e.g. backups on other servers
+fn same_content(a: &StreamInf, b: &StreamInf) -> bool {
+    a.codecs == b.codecs && a.resolution == b.resolution && a.frame_rate == b.frame_rate && a.video_range == b.video_range
+        && a.hdcp_level == b.hdcp_level && a.audio == b.audio && a.video == b.video && a.subtitles == b.subtitles
+        && a.closed_captions == b.closed_captions
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_bandwidth_bounds(bounds: BandwidthBounds) -> Self {
+        Self{ bounds, ..Self::default() }
+    }
+
+    /* Resolve relative URIs against `base` when checking them */
+    pub fn base_url(self, base: PlaylistUri) -> Self {
+        Self{ base_url: Some(base), ..self }
+    }
+
+    /* Report unknown values and missing CODECS as errors instead of warnings, and check the other MUST-level rules */
+    pub fn strict(self) -> Self {
+        Self{ strict: true, ..self }
+    }
+
+    // Warning, or error in strict mode
+    fn strict_error(&self, rule: &'static str, message: String) -> Finding {
+        if self.strict { Finding::error(rule, message) } else { Finding::warning(rule, message) }
+    }
+
+    // Location of the next tag of this name
+    fn next_location(&mut self, tag: &'static str) -> Location {
+        let count = self.counts.entry(tag).or_default();
+        *count += 1;
+        Location{ tag, index: *count - 1 }
+    }
+
+    // Feature of the playlist requiring at least this protocol version
+    fn require_version(&mut self, version: u64, feature: impl Fn() -> String) {
+        if self.required_version.as_ref().map_or(true, |(v, _)| *v < version) {
+            self.required_version = Some((version, feature()));
+        }
+    }
+
+    pub fn feed(&mut self, entry: Entry) -> Vec<Finding> {
+        match entry {
+            Entry::Version(v) => self.feed_version(v),
+            Entry::Media(m) => self.feed_media(m),
+            Entry::StreamInf(si) => self.feed_stream_inf(si),
+            Entry::IFrameStreamInf(ifsi) => self.feed_i_frame_stream_inf(ifsi),
+        }
+    }
+
+    pub fn feed_version(&mut self, version: u64) -> Vec<Finding> {
+        let location = self.next_location("EXT-X-VERSION");
+        let mut ret = vec![];
+        if self.strict && self.version.is_some() {
+            ret.push(Finding::error("version-duplicate", format!("More than one EXT-X-VERSION, {} after {}",
+                version, self.version.unwrap_or_default())).at(location));
+        }
+        self.version = Some(version);
+        ret
+    }
+
+    pub fn feed_media(&mut self, m: &Media) -> Vec<Finding> {
+        let location = self.next_location("EXT-X-MEDIA");
+        self.group_ids.entry(m.type_.clone()).or_default().insert(m.group_id.clone());
+        self.pending.retain(|p| p.type_ != m.type_ || p.group_id != m.group_id);
+        let mut ret = vec![];
+        if !self.names.insert((m.type_.clone(), m.group_id.clone(), m.name.clone())) {
+            ret.push(Finding::error("rendition-name-duplicate",
+                format!("Duplicate NAME {} in {} group {}", m.name, m.type_.as_str(), m.group_id)));
+        }
+        if self.strict && m.default && !self.defaults.insert((m.type_.clone(), m.group_id.clone())) {
+            ret.push(Finding::error("default-duplicate",
+                format!("More than one DEFAULT=YES rendition in {} group {}", m.type_.as_str(), m.group_id)));
+        }
+        if let Some(uri) = &m.uri {
+            ret.extend(self.check_uri("EXT-X-MEDIA", uri));
+        }
+        if is_unknown_value("EXT-X-MEDIA", "TYPE", m.type_.as_str()) {
+            ret.push(self.strict_error("unknown-type", format!("Unknown TYPE {} in rendition {}", m.type_.as_str(), m.name)));
+        }
+        if let Some(ch) = m.channels.as_ref().and_then(|ch| ch.other.as_ref()) {
+            ret.push(self.strict_error("unknown-channels", format!("Unknown CHANNELS {} in rendition {}", ch, m.name)));
+        }
+        match (&m.type_, &m.instream_id) {
+            (MediaType::ClosedCaptions, None) => ret.push(Finding::error("instream-id-missing",
+                format!("Missing INSTREAM-ID in CLOSED-CAPTIONS rendition {}", m.name))),
+            (MediaType::ClosedCaptions, Some(id)) => {
+                if let InstreamId::Cea708(_) = id {
+                    self.require_version(7, || format!("INSTREAM-ID {} in rendition {}", id, m.name));
+                }
+                if !self.instream_ids.insert((m.group_id.clone(), *id)) {
+                    ret.push(Finding::error("instream-id-duplicate",
+                        format!("Duplicate INSTREAM-ID {} in CLOSED-CAPTIONS group {}", id, m.group_id)));
+                }
+            },
+            (_, Some(_)) => ret.push(Finding::error("instream-id-unexpected",
+                format!("INSTREAM-ID in rendition {} which is not CLOSED-CAPTIONS", m.name))),
+            (_, None) => (),
+        }
+        if let Some(id) = &m.stable_rendition_id {
+            if !self.stable_ids.insert(("EXT-X-MEDIA", id.clone())) {
+                ret.push(Finding::error("stable-rendition-id-duplicate",
+                    format!("Duplicate STABLE-RENDITION-ID {} in rendition {}", id, m.name)));
+            }
+        }
+        ret.into_iter().map(|f| f.at(location)).collect()
+    }
+
+    fn check_uri(&mut self, tag: &str, uri: &PlaylistUri) -> Option<Finding> {
+        if uri.as_str().is_empty() {
+            return None
+        }
+        let resolved = match &self.base_url {
+            Some(base) if uri.is_relative() => uri.join(base),
+            _ => uri.clone(),
+        };
+        if uri.is_relative() {
+            self.uris.1 += 1;
+        } else {
+            self.uris.0 += 1;
+        }
+        if let Some(scheme) = resolved.scheme() {
+            self.schemes.insert(scheme.to_ascii_lowercase());
+        }
+        let e = resolved.syntax_error()?;
+        let resolved = if resolved == *uri { String::new() } else { format!(", resolved to {}", resolved) };
+        Some(Finding::error("uri-malformed", format!("Malformed URI {} in {}{}: {}", uri, tag, resolved, e)))
+    }
+
+    fn check_codecs(&self, tag: &str, codecs: &Option<Codecs>, uri: &PlaylistUri) -> Vec<Finding> {
+        let Some(codecs) = codecs else {
+            return vec![self.strict_error("codecs-missing", format!("No CODECS in {} {}", tag, uri))]
+        };
+        let mut ret = vec![];
+        if codecs.empty() > 0 {
+            ret.push(Finding::warning("codecs-empty", format!("Empty codec identifier in CODECS {} of {}", codecs, uri)));
+        }
+        for c in codecs.iter() {
+            if let Some(e) = c.syntax_error() {
+                ret.push(Finding::warning("malformed-codec", format!("Malformed codec {} in CODECS of {}: {}", c, uri, e)));
+            } else if c.family == CodecFamily::Unknown {
+                ret.push(self.strict_error("unknown-codec", format!("Unknown codec {} in CODECS of {}", c, uri)));
+            }
+        }
+        ret
+    }
+
+    // Resolution within the limits of the level of the video codec, and of its profile
+    fn check_level(&self, tag: &str, codecs: &Option<Codecs>, resolution: &Option<Resolution>, frame_rate: Option<FrameRate>,
+        uri: &PlaylistUri) -> Vec<Finding> {
+        let (Some(video), Some(res)) = (codecs.as_ref().and_then(|c| c.video()), resolution) else {
+            return vec![]
+        };
+        let mut ret = vec![];
+        if let Some(e) = video.level_error(res.w, res.h, frame_rate.map(|fr| fr.as_f64())) {
+            ret.push(Finding::warning("codec-level-exceeded", format!("CODECS {} of {} {}: {}", video, tag, uri, e)));
+        }
+        if video.family == CodecFamily::Avc && video.profile == Some(0x42) && res.pixels() > BASELINE_MAX_PIXELS {
+            ret.push(Finding::warning("codec-profile-unusual",
+                format!("Baseline profile of {} for {}x{} in {} {}", video, res.w, res.h, tag, uri)));
+        }
+        ret
+    }
+
+    fn duplicate_stable_variant_id(&mut self, tag: &'static str, id: &Option<String>, uri: &PlaylistUri) -> Option<Finding> {
+        let id = id.as_ref()?;
+        if self.stable_ids.insert((tag, id.clone())) {
+            return None
+        }
+        Some(Finding::error("stable-variant-id-duplicate", format!("Duplicate STABLE-VARIANT-ID {} in {} {}", id, tag, uri)))
+    }
+
+    pub fn feed_stream_inf(&mut self, si: &StreamInf) -> Vec<Finding> {
+        let location = self.next_location("EXT-X-STREAM-INF");
+        let mut ret = vec![];
+        let suggestion = self.bounds.suggested_bandwidth(si)
+            .map(|bw| format!(", suggested BANDWIDTH={}", bw)).unwrap_or_default();
+        match si.average_bandwidth {
+            _ if si.bandwidth == 0 =>
+                ret.push(Finding::error("bandwidth-zero", format!("BANDWIDTH 0 in EXT-X-STREAM-INF {}{}", si.uri, suggestion))),
+            Some(avg) if avg > si.bandwidth => ret.push(Finding::error("average-bandwidth-above-bandwidth",
+                format!("AVERAGE-BANDWIDTH {} above BANDWIDTH {} in EXT-X-STREAM-INF {}{}", avg, si.bandwidth, si.uri, suggestion))),
+            _ if !suggestion.is_empty() => ret.push(Finding::warning("bandwidth-implausible",
+                format!("BANDWIDTH {} implausible for the resolution and codec of {}{}", si.bandwidth, si.uri, suggestion))),
+            _ => (),
+        }
+        let variant = (si.uri.to_string(), si.audio.clone(), si.video.clone(), si.subtitles.clone(), si.closed_captions.clone());
+        if !self.variants.insert(variant) {
+            ret.push(Finding::error("variant-duplicate", format!("Duplicate EXT-X-STREAM-INF {} with the same renditions", si.uri)));
+        }
+        match self.bandwidths.get(&si.bandwidth) {
+            Some(first) if !same_content(first, si) => ret.push(Finding::warning("bandwidth-duplicate",
+                format!("BANDWIDTH {} of EXT-X-STREAM-INF {} same as {} with different content", si.bandwidth, si.uri, first.uri))),
+            Some(_) => (),
+            None => {
+                self.bandwidths.insert(si.bandwidth, si.clone());
+            },
+        }
+        if let Some(v) = si.video_range.as_ref().map(VideoRange::as_str)
+            .filter(|v| is_unknown_value("EXT-X-STREAM-INF", "VIDEO-RANGE", v)) {
+            ret.push(self.strict_error("unknown-video-range",
+                format!("Unknown VIDEO-RANGE {} in EXT-X-STREAM-INF {}", v, si.uri)));
+        }
+        ret.extend(self.check_uri("EXT-X-STREAM-INF", &si.uri));
+        ret.extend(self.check_codecs("EXT-X-STREAM-INF", &si.codecs, &si.uri));
+        ret.extend(self.check_level("EXT-X-STREAM-INF", &si.codecs, &si.resolution, si.frame_rate, &si.uri));
+        if let (Some(res), Some(codecs)) = (&si.resolution, &si.codecs) {
+            if codecs.iter().next().is_some() && !codecs.has_video() {
+                ret.push(Finding::warning("audio-only-resolution",
+                    format!("RESOLUTION {}x{} in audio-only EXT-X-STREAM-INF {}", res.w, res.h, si.uri)));
+            }
+        }
+        if let Some(fr) = si.frame_rate {
+            if !self.frame_rates.iter().any(|(seen, _)| *seen == fr) {
+                if let Some((seen, first)) = self.frame_rates.iter().find(|(seen, _)| !compatible_frame_rates(*seen, fr)) {
+                    ret.push(Finding::warning("frame-rate-mixed", format!(
+                        "FRAME-RATE {} of EXT-X-STREAM-INF {} is not a multiple or fraction of {} of {}", fr, si.uri, seen, first)));
+                }
+                self.frame_rates.push((fr, si.uri.clone()));
+            }
+        }
+        for (type_, group) in si.groups() {
+            self.reference(type_, group, location);
+        }
+        ret.extend(self.duplicate_stable_variant_id("EXT-X-STREAM-INF", &si.stable_variant_id, &si.uri));
+        if si.score.is_some() {
+            self.scores.0 += 1;
+        } else {
+            self.scores.1 += 1;
+        }
+        if si.closed_captions == Some(ClosedCaptions::None) {
+            self.closed_captions_none.0 += 1;
+        } else {
+            self.closed_captions_none.1 += 1;
+        }
+        if si.has_video() {
+            self.has_video = true;
+        } else if si.bandwidth <= AUDIO_ONLY_MAX_BANDWIDTH {
+            self.has_audio_only_fallback = true;
+        }
+        ret.into_iter().map(|f| f.at(location)).collect()
+    }
+
+    pub fn feed_i_frame_stream_inf(&mut self, ifsi: &IFrameStreamInf) -> Vec<Finding> {
+        let location = self.next_location("EXT-X-I-FRAME-STREAM-INF");
+        let mut ret = vec![];
+        if let Some(v) = ifsi.video_range.as_ref().map(VideoRange::as_str)
+            .filter(|v| is_unknown_value("EXT-X-I-FRAME-STREAM-INF", "VIDEO-RANGE", v)) {
+            ret.push(self.strict_error("unknown-video-range",
+                format!("Unknown VIDEO-RANGE {} in EXT-X-I-FRAME-STREAM-INF {}", v, ifsi.uri)));
+        }
+        if self.strict && ifsi.bandwidth == 0 {
+            ret.push(Finding::error("bandwidth-zero", format!("BANDWIDTH 0 in EXT-X-I-FRAME-STREAM-INF {}", ifsi.uri)));
+        }
+        ret.extend(self.check_level("EXT-X-I-FRAME-STREAM-INF", &ifsi.codecs, &ifsi.resolution, None, &ifsi.uri));
+        ret.extend(self.duplicate_stable_variant_id("EXT-X-I-FRAME-STREAM-INF", &ifsi.stable_variant_id, &ifsi.uri));
+        if let Some(video) = &ifsi.video {
+            self.reference(MediaType::Video, video, location);
+        }
+        ret.extend(self.check_uri("EXT-X-I-FRAME-STREAM-INF", &ifsi.uri));
+        ret.extend(self.check_codecs("EXT-X-I-FRAME-STREAM-INF", &ifsi.codecs, &ifsi.uri));
+        for c in ifsi.codecs.iter().flat_map(|c| c.iter()) {
+            // I-frame playlists carry no audio
+            if c.family.is_audio() {
+                ret.push(Finding::warning("i-frame-audio-codec",
+                    format!("Audio codec {} in CODECS of EXT-X-I-FRAME-STREAM-INF {}", c, ifsi.uri)));
+            }
+        }
+        ret.into_iter().map(|f| f.at(location)).collect()
+    }
+
+    // Findings that can only be decided when the whole playlist has been seen
+    pub fn finish(self) -> Vec<Finding> {
+        let mut ret: Vec<Finding> = self.pending.into_iter().map(|p| p.into_finding()).collect();
+        if let (with @ 1.., without @ 1..) = self.scores {
+            ret.push(Finding::error("score-partial", format!("SCORE in {} of {} EXT-X-STREAM-INF, it must be in all of them",
+                with, with + without)));
+        }
+        if self.strict {
+            if let (with @ 1.., without @ 1..) = self.closed_captions_none {
+                ret.push(Finding::error("closed-captions-none-partial", format!(
+                    "CLOSED-CAPTIONS=NONE in {} of {} EXT-X-STREAM-INF, it must be in all of them", with, with + without)));
+            }
+            let declared = self.version.unwrap_or(1);
+            if let Some((required, feature)) = self.required_version.filter(|(v, _)| *v > declared) {
+                ret.push(Finding::error("version-too-low", format!("{} requires EXT-X-VERSION {}, the playlist declares {}",
+                    feature, required, declared)));
+            }
+        }
+        if let (absolute @ 1.., relative @ 1..) = self.uris {
+            ret.push(Finding::warning("uri-mixed", format!("{} absolute and {} relative URIs", absolute, relative)));
+        }
+        if self.schemes.len() > 1 {
+            let schemes: Vec<&str> = self.schemes.iter().map(|s| s.as_str()).collect();
+            ret.push(Finding::warning("uri-mixed-schemes", format!("URIs with schemes {}", schemes.join(", "))));
+        }
+        if self.has_video && !self.has_audio_only_fallback {
+            ret.push(Finding::warning("audio-only-fallback", format!("No audio-only variant below {} kbps for poor networks",
+                AUDIO_ONLY_MAX_BANDWIDTH / 1000)));
+        }
+        ret
+    }
+
+    fn reference(&mut self, type_: MediaType, group_id: &str, location: Location) {
+        let known = self.group_ids.get(&type_).is_some_and(|s| s.contains(group_id));
+        let pending = self.pending.iter().any(|p| p.type_ == type_ && p.group_id == group_id);
+        if !known && !pending {
+            self.pending.push(PendingReference{type_, group_id: group_id.to_string(), location});
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{Channels, MultivariantPlaylist, Score};
+
+    fn playlist() -> MultivariantPlaylist {
+        crate::parser::parse_playlist(include_str!("../data/master_unenc_hdr10_all.m3u8")).unwrap()
+    }
+
+    #[test]
+    fn test_validator_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Validator>();
+    }
+
+    #[test]
+    fn test_forward_reference() {
+        let pl = playlist();
+        let mut v = Validator::new();
+        for si in &pl.stream_inf {
+            assert!(v.feed(Entry::StreamInf(si)).is_empty());
+        }
+        for m in &pl.media {
+            assert!(v.feed(Entry::Media(m)).is_empty());
+        }
+        assert!(v.finish().iter().all(|f| f.severity == Severity::Warning));
+    }
+
+    #[test]
+    fn test_unknown_group() {
+        let pl = playlist();
+        let mut v = Validator::new();
+        v.feed_media(&pl.media[0]);
+        for si in &pl.stream_inf {
+            v.feed_stream_inf(si);
+        }
+        let findings: Vec<Finding> = v.finish().into_iter().filter(|f| f.severity == Severity::Error).collect();
+        assert_eq!(findings.len(), 3); // aac-64k, eac3, atmos
+        let index = pl.stream_inf.iter().position(|si| si.audio.as_deref() == Some("aac-64k")).unwrap();
+        assert_eq!(findings[0], Finding::error("unknown-group", "Reference to unknown AUDIO group aac-64k".to_string())
+            .at(Location{ tag: "EXT-X-STREAM-INF", index }));
+    }
+
+    #[test]
+    fn test_validation_report() {
+        let mut pl = playlist();
+        pl.stream_inf[0].bandwidth = 0;
+        pl.stream_inf[2].video_range = Some(VideoRange::Other("XDR".to_string()));
+        pl.media.retain(|m| m.group_id != "atmos");
+        let report = pl.validate().unwrap_err();
+        let rules: Vec<_> = report.errors().map(|f| (f.rule, f.location)).collect();
+        assert_eq!(rules[0], ("bandwidth-zero", Some(Location{ tag: "EXT-X-STREAM-INF", index: 0 })));
+        assert_eq!(rules.last().unwrap().0, "unknown-group");
+        assert_eq!(report.warnings().filter(|f| f.rule == "unknown-video-range").count(), 1);
+        // errors first, one per line
+        let text = report.to_string();
+        assert!(text.starts_with(&format!("error[bandwidth-zero] EXT-X-STREAM-INF[0]: BANDWIDTH 0 in EXT-X-STREAM-INF {}",
+            pl.stream_inf[0].uri)), "{}", text);
+        assert_eq!(text.lines().count(), report.findings.len());
+        assert!(text.lines().last().unwrap().starts_with("warning["));
+
+        assert!(playlist().validate().unwrap().warnings().all(|f| f.location.is_none()));
+    }
+
+    #[test]
+    fn test_video_and_subtitles_references() {
+        let mut pl = playlist();
+        pl.stream_inf[0].video = Some("angles".to_string());
+        pl.stream_inf[1].subtitles = Some("subs".to_string());
+        pl.i_frame_stream_inf[0].video = Some("angles".to_string());
+        let errors = |pl: &MultivariantPlaylist| pl.findings().into_iter().filter(|f| f.severity == Severity::Error)
+            .map(|f| f.message).collect::<Vec<_>>();
+        assert_eq!(errors(&pl), ["Reference to unknown VIDEO group angles", "Reference to unknown SUBTITLES group subs"]);
+
+        let mut angle = pl.media[0].clone();
+        (angle.type_, angle.group_id, angle.name) = (MediaType::Video, "angles".to_string(), "Angle 1".to_string());
+        pl.media.push(angle.clone());
+        (angle.type_, angle.group_id) = (MediaType::Audio, "subs".to_string());
+        pl.media.push(angle);
+        assert_eq!(errors(&pl), ["Reference to unknown SUBTITLES group subs"]);
+    }
+
+    #[test]
+    fn test_unknown_values() {
+        let mut pl = playlist();
+        pl.media[0].type_ = MediaType::Other("X-HAPTICS".to_string());
+        pl.stream_inf[1].video_range = Some(VideoRange::Other("XDR".to_string()));
+        pl.media[0].channels = Some(Channels::parse_lenient("2/JOC/extra"));
+        let expected = ["Unknown TYPE X-HAPTICS in rendition English".to_string(),
+            "Unknown CHANNELS 2/JOC/extra in rendition English".to_string(),
+            format!("Unknown VIDEO-RANGE XDR in EXT-X-STREAM-INF {}", pl.stream_inf[1].uri)];
+        let unknown = |strict: bool, severity: Severity| pl.findings_with_options(&BandwidthBounds::default(), strict)
+            .into_iter().filter(|f| f.severity == severity && f.message.starts_with("Unknown")).map(|f| f.message).collect::<Vec<_>>();
+        assert_eq!(unknown(false, Severity::Warning), expected);
+        assert!(unknown(false, Severity::Error).is_empty());
+        assert_eq!(unknown(true, Severity::Error), expected);
+
+        // known values are those of the registry
+        assert!(!is_unknown_value("EXT-X-STREAM-INF", "VIDEO-RANGE", "PQ"));
+        assert!(is_unknown_value("EXT-X-I-FRAME-STREAM-INF", "VIDEO-RANGE", "XDR"));
+        assert!(!is_unknown_value("EXT-X-MEDIA", "NAME", "anything"));
+    }
+
+    #[test]
+    fn test_strict_rules() {
+        let mut pl = crate::parser::parse_playlist(include_str!("../data/captions.m3u8")).unwrap();
+        let errors = |pl: &MultivariantPlaylist, strict: bool| pl.findings_with_options(&BandwidthBounds::default(), strict)
+            .into_iter().filter(|f| f.severity == Severity::Error).collect::<Vec<_>>();
+        assert!(errors(&pl, false).is_empty());
+        assert_eq!(errors(&pl, true), [Finding::error("version-too-low",
+            "INSTREAM-ID SERVICE1 in rendition English (708) requires EXT-X-VERSION 7, the playlist declares 1".to_string())]);
+        pl.version = Some(7);
+        assert!(errors(&pl, true).is_empty());
+
+        let mut broken = pl.clone();
+        broken.media[1].name = "English".to_string();
+        broken.media[1].default = true;
+        broken.stream_inf[1].closed_captions = Some(ClosedCaptions::None);
+        broken.i_frame_stream_inf.push(IFrameStreamInf{ bandwidth: 0, ..playlist().i_frame_stream_inf[0].clone() });
+        let found: Vec<_> = errors(&broken, true).into_iter().map(|f| (f.rule, f.location.map(|l| l.index))).collect();
+        assert_eq!(found, [("rendition-name-duplicate", Some(1)), ("default-duplicate", Some(1)), ("bandwidth-zero", Some(0)),
+            ("closed-captions-none-partial", None)]);
+        // players reject renditions with the same NAME in a group anyway
+        let found: Vec<_> = errors(&broken, false).into_iter().map(|f| f.rule).collect();
+        assert_eq!(found, ["rendition-name-duplicate"]);
+
+        let mut v = Validator::new().strict();
+        assert!(v.feed(Entry::Version(7)).is_empty());
+        assert_eq!(v.feed(Entry::Version(6))[0].rule, "version-duplicate");
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let mut pl = playlist();
+        let errors = |pl: &MultivariantPlaylist| pl.findings().into_iter().filter(|f| f.severity == Severity::Error)
+            .map(|f| f.message).collect::<Vec<_>>();
+        // the same video with other audio groups
+        assert!(pl.stream_inf.iter().filter(|si| si.uri == pl.stream_inf[0].uri).count() > 1);
+        assert!(errors(&pl).is_empty());
+
+        pl.stream_inf.push(pl.stream_inf[0].clone());
+        pl.media.push(pl.media[0].clone());
+        assert_eq!(errors(&pl), [
+            format!("Duplicate NAME {} in AUDIO group {}", pl.media[0].name, pl.media[0].group_id),
+            format!("Duplicate EXT-X-STREAM-INF {} with the same renditions", pl.stream_inf[0].uri),
+        ]);
+    }
+
+    #[test]
+    fn test_uris() {
+        let mut pl = playlist();
+        let uri = |s: &str| PlaylistUri::parse(s).unwrap();
+        let rules = |pl: &MultivariantPlaylist, v: Validator| pl.findings_with_validator(v).into_iter()
+            .filter(|f| f.rule.starts_with("uri-")).map(|f| f.to_string()).collect::<Vec<_>>();
+        assert!(rules(&pl, Validator::new()).is_empty());
+
+        pl.stream_inf[0].uri = uri("v%2.m3u8");
+        assert_eq!(rules(&pl, Validator::new()), [
+            "error[uri-malformed] EXT-X-STREAM-INF[0]: Malformed URI v%2.m3u8 in EXT-X-STREAM-INF: invalid percent-encoding at 1",
+        ]);
+        // only without a scheme is the host optional
+        pl.stream_inf[0].uri = uri("//:8080/v.m3u8");
+        assert!(rules(&pl, Validator::new()).is_empty());
+        assert_eq!(rules(&pl, Validator::new().base_url(uri("https://cdn.example.com/hls/"))), [
+            "error[uri-malformed] EXT-X-STREAM-INF[0]: Malformed URI //:8080/v.m3u8 in EXT-X-STREAM-INF, resolved to https://:8080/v.m3u8: no host",
+        ]);
+        pl.stream_inf[0].uri = uri("v.m3u8");
+        let spaces = crate::parser::parse_playlist("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1\nvideo 1.m3u8\n").unwrap();
+        assert_eq!(rules(&spaces, Validator::new()), [
+            "error[uri-malformed] EXT-X-STREAM-INF[0]: Malformed URI video 1.m3u8 in EXT-X-STREAM-INF: character ' ' not allowed",
+        ]);
+
+        pl.stream_inf[1].uri = uri("https://cdn.example.com/hls/v.m3u8");
+        pl.i_frame_stream_inf[0].uri = uri("http://cdn.example.com/hls/iframe.m3u8");
+        let findings = rules(&pl, Validator::new());
+        assert_eq!(findings.len(), 2);
+        assert!(findings[0].starts_with("warning[uri-mixed]: 2 absolute and "), "{}", findings[0]);
+        assert_eq!(findings[1], "warning[uri-mixed-schemes]: URIs with schemes http, https");
+        // relative URIs take the scheme of the base
+        let findings = rules(&pl, Validator::new().base_url(uri("https://cdn.example.com/hls/")));
+        assert_eq!(findings.len(), 2);
+    }
+
+    #[test]
+    fn test_duplicate_stable_ids() {
+        let mut pl = playlist();
+        for si in &mut pl.stream_inf[..2] {
+            si.stable_variant_id = Some("hd".to_string());
+        }
+        pl.i_frame_stream_inf[0].stable_variant_id = Some("hd".to_string());
+        for m in &mut pl.media[..2] {
+            m.stable_rendition_id = Some("en".to_string());
+        }
+        let errors = pl.findings().into_iter().filter(|f| f.severity == Severity::Error).collect::<Vec<_>>();
+        assert_eq!(errors, [
+            Finding::error("stable-rendition-id-duplicate", format!("Duplicate STABLE-RENDITION-ID en in rendition {}", pl.media[1].name))
+                .at(Location{ tag: "EXT-X-MEDIA", index: 1 }),
+            Finding::error("stable-variant-id-duplicate", format!("Duplicate STABLE-VARIANT-ID hd in EXT-X-STREAM-INF {}", pl.stream_inf[1].uri))
+                .at(Location{ tag: "EXT-X-STREAM-INF", index: 1 }),
+        ]);
+    }
+
+    #[test]
+    fn test_score_in_all_variants() {
+        let mut pl = playlist();
+        pl.stream_inf[0].score = Score::parse("2");
+        let errors = |pl: &MultivariantPlaylist| pl.findings().into_iter().filter(|f| f.severity == Severity::Error).collect::<Vec<_>>();
+        assert_eq!(errors(&pl), [Finding::error("score-partial", format!("SCORE in 1 of {} EXT-X-STREAM-INF, it must be in all of them",
+            pl.stream_inf.len()))]);
+        for si in &mut pl.stream_inf {
+            si.score = Score::parse("1");
+        }
+        assert!(errors(&pl).is_empty());
+    }
+
+    #[test]
+    fn test_audio_only_fallback() {
+        let pl = playlist();
+        let findings = pl.findings();
+        assert_eq!(findings, [Finding::warning("audio-only-fallback", "No audio-only variant below 192 kbps for poor networks".to_string())]);
+
+        let fixed = pl.add_audio_only_fallback().unwrap();
+        assert!(fixed.findings().is_empty());
+        let si = fixed.stream_inf.last().unwrap();
+        assert_eq!(fixed.stream_inf.len(), pl.stream_inf.len() + 1);
+        assert_eq!(si.uri, "audio/unenc/aac_128k/vod.m3u8");
+        assert_eq!(si.codecs.as_ref().map(|c| c.to_string()).as_deref(), Some("mp4a.40.2"));
+        assert_eq!(si.bandwidth, 128000);
+        assert_eq!(si.infer_label(), "audio-only 128k");
+        // nothing to do the second time
+        assert_eq!(fixed.add_audio_only_fallback().unwrap().stream_inf.len(), fixed.stream_inf.len());
+
+        let mut no_audio = pl.clone();
+        no_audio.media.clear();
+        assert!(no_audio.add_audio_only_fallback().is_err());
+    }
+
+    #[test]
+    fn test_instream_id() {
+        let pl = crate::parser::parse_playlist(include_str!("../data/captions.m3u8")).unwrap();
+        let mut v = Validator::new();
+        for m in &pl.media {
+            assert!(v.feed_media(m).is_empty());
+        }
+        let mut cc = pl.media[3].clone();
+        cc.name = "English (CC1)".to_string();
+        assert_eq!(v.feed_media(&cc), [Finding::error("instream-id-duplicate", "Duplicate INSTREAM-ID CC1 in CLOSED-CAPTIONS group cc".to_string())
+            .at(Location{ tag: "EXT-X-MEDIA", index: pl.media.len() })]);
+        cc.instream_id = None;
+        cc.name = "English (no id)".to_string();
+        assert_eq!(v.feed_media(&cc), [Finding::error("instream-id-missing", "Missing INSTREAM-ID in CLOSED-CAPTIONS rendition English (no id)".to_string())
+            .at(Location{ tag: "EXT-X-MEDIA", index: pl.media.len() + 1 })]);
+        let mut audio = pl.media[0].clone();
+        audio.name = "English (CC2)".to_string();
+        audio.instream_id = Some(InstreamId::Cea608(2));
+        assert_eq!(v.feed_media(&audio).len(), 1);
+    }
+
+    #[test]
+    fn test_codecs() {
+        let pl = playlist();
+        let mut v = Validator::new();
+        let mut si = pl.stream_inf[0].clone();
+        si.codecs = Some(crate::codecs::Codecs::parse("mp4a.40.2,hvc1.2.4"));
+        assert_eq!(v.feed_stream_inf(&si), [Finding::warning("malformed-codec",
+            format!("Malformed codec hvc1.2.4 in CODECS of {}: no profile, tier and level", si.uri))
+            .at(Location{ tag: "EXT-X-STREAM-INF", index: 0 })]);
+        let mut ifsi = pl.i_frame_stream_inf[0].clone();
+        assert!(v.feed_i_frame_stream_inf(&ifsi).is_empty());
+        ifsi.codecs = Some(crate::codecs::Codecs::parse("hvc1.2.4.L93.90,mp4a.40.2"));
+        assert_eq!(v.feed_i_frame_stream_inf(&ifsi), [Finding::warning("i-frame-audio-codec", format!(
+            "Audio codec mp4a.40.2 in CODECS of EXT-X-I-FRAME-STREAM-INF {}", ifsi.uri)).at(Location{ tag: "EXT-X-I-FRAME-STREAM-INF", index: 1 })]);
+
+        si.codecs = Some(crate::codecs::Codecs::parse("avc1.64001f,mp4a.40.2_,xyz1.2"));
+        let mut v = Validator::new();
+        let found: Vec<_> = v.feed_stream_inf(&si).into_iter().map(|f| (f.severity, f.message)).collect();
+        assert_eq!(found, [
+            (Severity::Warning, format!("Malformed codec mp4a.40.2_ in CODECS of {}: element 2_ is not alphanumeric", si.uri)),
+            (Severity::Warning, format!("Unknown codec xyz1.2 in CODECS of {}", si.uri)),
+        ]);
+        // a trailing comma does not reject the variant
+        let pl = crate::parser::parse_playlist("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1000000,CODECS=\"avc1.64001f,mp4a.40.2,\"\nv.m3u8\n").unwrap();
+        assert_eq!(pl.stream_inf[0].codecs.as_ref().unwrap().iter().count(), 2);
+        assert!(pl.findings().iter().any(|f| f.rule == "codecs-empty"
+            && f.message == "Empty codec identifier in CODECS avc1.64001f,mp4a.40.2, of v.m3u8"));
+        si.codecs = None;
+        assert_eq!(Validator::new().feed_stream_inf(&si)[0].message, format!("No CODECS in EXT-X-STREAM-INF {}", si.uri));
+        assert_eq!(Validator::new().strict().feed_stream_inf(&si)[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_ladder_consistency() {
+        let data = "#EXTM3U\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=20000000,CODECS=\"avc1.42E033,mp4a.40.2\",RESOLUTION=3840x2160,FRAME-RATE=29.97\n4k.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=8000000,CODECS=\"avc1.640028,mp4a.40.2\",RESOLUTION=1920x1080,FRAME-RATE=59.94\n1080p.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=3000000,CODECS=\"avc1.64001f,mp4a.40.2\",RESOLUTION=1280x720,FRAME-RATE=25\n720p.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=128000,CODECS=\"mp4a.40.2\",RESOLUTION=640x360\naudio.m3u8\n\
+            #EXT-X-I-FRAME-STREAM-INF:BANDWIDTH=500000,CODECS=\"avc1.64001f\",RESOLUTION=1920x1080,URI=\"iframes.m3u8\"\n";
+        let pl = crate::parser::parse_playlist(data).unwrap();
+        let found: Vec<String> = pl.findings().into_iter()
+            .filter(|f| ["codec-level-exceeded", "codec-profile-unusual", "frame-rate-mixed", "audio-only-resolution"].contains(&f.rule))
+            .map(|f| f.to_string()).collect();
+        assert_eq!(found, [
+            "warning[codec-profile-unusual] EXT-X-STREAM-INF[0]: Baseline profile of avc1.42E033 for 3840x2160 in EXT-X-STREAM-INF 4k.m3u8",
+            "warning[codec-level-exceeded] EXT-X-STREAM-INF[1]: CODECS avc1.640028 of EXT-X-STREAM-INF 1080p.m3u8: \
+                1920x1080 at 59.94 fps above the sample rate of level 4",
+            "warning[frame-rate-mixed] EXT-X-STREAM-INF[2]: FRAME-RATE 25 of EXT-X-STREAM-INF 720p.m3u8 is not a multiple or fraction \
+                of 29.97 of 4k.m3u8",
+            "warning[audio-only-resolution] EXT-X-STREAM-INF[3]: RESOLUTION 640x360 in audio-only EXT-X-STREAM-INF audio.m3u8",
+            "warning[codec-level-exceeded] EXT-X-I-FRAME-STREAM-INF[0]: CODECS avc1.64001f of EXT-X-I-FRAME-STREAM-INF iframes.m3u8: \
+                1920x1080 above the largest picture of level 3.1",
+        ]);
+    }
+
+    #[test]
+    fn test_bandwidth() {
+        let pl = crate::parser::parse_playlist(include_str!("../data/master_unenc_hdr10_all.m3u8")).unwrap();
+        let bounds = BandwidthBounds::default();
+        assert!(pl.stream_inf.iter().all(|si| bounds.suggested_bandwidth(si).is_none()));
+
+        let mut si = pl.stream_inf[0].clone(); // 960x540 HEVC at 23.97 fps
+        let mut v = Validator::new();
+        si.average_bandwidth = Some(si.bandwidth + 1);
+        assert_eq!(v.feed_stream_inf(&si), [Finding::error("average-bandwidth-above-bandwidth", format!(
+            "AVERAGE-BANDWIDTH 2483790 above BANDWIDTH 2483789 in EXT-X-STREAM-INF {}, suggested BANDWIDTH=2483790", si.uri))
+            .at(Location{ tag: "EXT-X-STREAM-INF", index: 0 })]);
+        si.average_bandwidth = None;
+        si.bandwidth = 0;
+        assert_eq!(v.feed_stream_inf(&si)[0].message, format!(
+            "BANDWIDTH 0 in EXT-X-STREAM-INF {}, suggested BANDWIDTH=82840", si.uri));
+        // given in kbps
+        si.bandwidth = 2483;
+        assert_eq!(Validator::new().feed_stream_inf(&si), [Finding::warning("bandwidth-implausible", format!(
+            "BANDWIDTH 2483 implausible for the resolution and codec of {}, suggested BANDWIDTH=82840", si.uri))
+            .at(Location{ tag: "EXT-X-STREAM-INF", index: 0 })]);
+        let mut v = Validator::with_bandwidth_bounds(BandwidthBounds{ min_bpp: 0.0001, ..BandwidthBounds::default() });
+        assert!(v.feed_stream_inf(&si).is_empty());
+
+        // same BANDWIDTH, fine for a backup of the same content
+        let mut v = Validator::new();
+        let mut backup = pl.stream_inf[0].clone();
+        backup.uri = crate::uri::PlaylistUri::parse("https://backup.example.com/1650k.m3u8").unwrap();
+        assert!(v.feed_stream_inf(&pl.stream_inf[0]).is_empty());
+        assert!(v.feed_stream_inf(&backup).is_empty());
+        backup.audio = Some("aac-64k".to_string());
+        assert_eq!(v.feed_stream_inf(&backup), [Finding::warning("bandwidth-duplicate", format!(
+            "BANDWIDTH 2483789 of EXT-X-STREAM-INF {} same as {} with different content", backup.uri, pl.stream_inf[0].uri))
+            .at(Location{ tag: "EXT-X-STREAM-INF", index: 2 })]);
+
+        let mut broken = pl.clone();
+        broken.stream_inf[0].bandwidth = 0;
+        broken.stream_inf[1].average_bandwidth = Some(broken.stream_inf[1].bandwidth * 2);
+        // AVERAGE-BANDWIDTH is above too
+        broken.stream_inf[2].bandwidth /= 1000;
+        broken.stream_inf[3].average_bandwidth = None;
+        broken.stream_inf[3].bandwidth /= 1000;
+        let fixed = broken.fix_bandwidths(&bounds);
+        assert_eq!(fixed.stream_inf[0].bandwidth, pl.stream_inf[0].average_bandwidth.unwrap());
+        assert_eq!(fixed.stream_inf[1].bandwidth, pl.stream_inf[1].bandwidth * 2);
+        assert_eq!(fixed.stream_inf[2].bandwidth, pl.stream_inf[2].average_bandwidth.unwrap());
+        assert_eq!(fixed.stream_inf[3].bandwidth, broken.stream_inf[3].bandwidth);
+        assert!(fixed.validate().is_ok());
+    }
+}
@@ -0,0 +1,656 @@
+use std::collections::HashMap;
+
+use crate::format::{ClosedCaptions, IFrameStreamInf, Media, MultivariantPlaylist, Resolution, StreamInf, VideoRange};
+use crate::registry;
+use crate::tokenizer::{self, ParsedLine};
+
+// Writing of attribute-list tags, e.g. #EXT-X-STREAM-INF:BANDWIDTH=...,CODECS="...".
+// Tags are written by a TagWriter, which can be registered per tag name to follow
+// a house style: attribute order, float precision, optional attributes left out.
+
+// Owned attribute value, as written
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(u64),
+    HexadecimalSequence(String), // including the 0x prefix
+    Float(f64),
+    QuotedString(String),
+    EnumeratedString(String),
+    Resolution(u64, u64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagLine {
+    pub name: String, // without the leading '#'
+    pub attributes: Vec<(String, Value)>,
+}
+
+impl TagLine {
+    pub fn new(name: &str) -> Self {
+        Self{ name: name.to_string(), attributes: vec![] }
+    }
+
+    pub fn push(&mut self, name: &str, value: Value) {
+        self.attributes.push((name.to_string(), value));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.attributes.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    /* Order attributes as in the RFC, see registry. Unknown attributes go last. */
+    pub fn sort_attributes(&mut self) {
+        let Some(spec) = registry::tag(&self.name) else { return };
+        let position = |name: &str| spec.attributes.iter().position(|a| a.name == name).unwrap_or(spec.attributes.len());
+        self.attributes.sort_by_key(|(n, _)| position(n));
+    }
+}
+
+fn quoted(v: &str) -> Value {
+    Value::QuotedString(v.to_string())
+}
+
+fn enumerated(v: &str) -> Value {
+    Value::EnumeratedString(v.to_string())
+}
+
+fn resolution(r: &Resolution) -> Value {
+    Value::Resolution(r.w, r.h)
+}
+
+fn video_range(v: &VideoRange) -> Value {
+    enumerated(v.as_str())
+}
+
+/* EXT-X-MEDIA of the rendition. DEFAULT, AUTOSELECT and FORCED are only written when YES. */
+pub fn media_tag(m: &Media) -> TagLine {
+    let mut tag = TagLine::new("EXT-X-MEDIA");
+    tag.push("TYPE", enumerated(m.type_.as_str()));
+    if let Some(uri) = &m.uri {
+        tag.push("URI", quoted(uri.as_str()));
+    }
+    tag.push("GROUP-ID", quoted(&m.group_id));
+    let optional = [("LANGUAGE", &m.language), ("ASSOC-LANGUAGE", &m.assoc_language),
+        ("STABLE-RENDITION-ID", &m.stable_rendition_id)];
+    for (name, v) in optional {
+        if let Some(v) = v {
+            tag.push(name, quoted(v));
+        }
+    }
+    tag.push("NAME", quoted(&m.name));
+    for (name, v) in [("DEFAULT", m.default), ("AUTOSELECT", m.autoselect), ("FORCED", m.forced)] {
+        if v {
+            tag.push(name, enumerated("YES"));
+        }
+    }
+    if let Some(id) = &m.instream_id {
+        tag.push("INSTREAM-ID", quoted(&id.to_string()));
+    }
+    if let Some(v) = m.bit_depth {
+        tag.push("BIT-DEPTH", Value::Integer(v));
+    }
+    if let Some(v) = m.sample_rate {
+        tag.push("SAMPLE-RATE", Value::Integer(v));
+    }
+    if let Some(v) = &m.characteristics {
+        tag.push("CHARACTERISTICS", quoted(v));
+    }
+    if let Some(v) = &m.channels {
+        tag.push("CHANNELS", quoted(&v.to_string()));
+    }
+    tag
+}
+
+/* EXT-X-STREAM-INF of the variant, its URI goes on the next line */
+pub fn stream_inf_tag(si: &StreamInf) -> TagLine {
+    let mut tag = TagLine::new("EXT-X-STREAM-INF");
+    tag.push("BANDWIDTH", Value::Integer(si.bandwidth));
+    if let Some(v) = si.average_bandwidth {
+        tag.push("AVERAGE-BANDWIDTH", Value::Integer(v));
+    }
+    if let Some(v) = si.score {
+        tag.push("SCORE", Value::Float(v.as_f64()));
+    }
+    if let Some(v) = &si.codecs {
+        tag.push("CODECS", quoted(&v.to_string()));
+    }
+    if let Some(r) = &si.resolution {
+        tag.push("RESOLUTION", resolution(r));
+    }
+    if let Some(v) = si.frame_rate {
+        tag.push("FRAME-RATE", Value::Float(v.as_f64()));
+    }
+    if let Some(v) = si.hdcp_level {
+        tag.push("HDCP-LEVEL", enumerated(v.as_str()));
+    }
+    if let Some(v) = &si.allowed_cpc {
+        tag.push("ALLOWED-CPC", quoted(v));
+    }
+    if let Some(v) = &si.video_range {
+        tag.push("VIDEO-RANGE", video_range(v));
+    }
+    if let Some(v) = &si.stable_variant_id {
+        tag.push("STABLE-VARIANT-ID", quoted(v));
+    }
+    if let Some(v) = &si.audio {
+        tag.push("AUDIO", quoted(v));
+    }
+    if let Some(v) = &si.video {
+        tag.push("VIDEO", quoted(v));
+    }
+    if let Some(v) = &si.subtitles {
+        tag.push("SUBTITLES", quoted(v));
+    }
+    match &si.closed_captions {
+        Some(ClosedCaptions::Group(g)) => tag.push("CLOSED-CAPTIONS", quoted(g)),
+        Some(ClosedCaptions::None) => tag.push("CLOSED-CAPTIONS", enumerated("NONE")),
+        None => (),
+    }
+    if let Some(v) = &si.pathway_id {
+        tag.push("PATHWAY-ID", quoted(v));
+    }
+    tag
+}
+
+pub fn i_frame_stream_inf_tag(ifsi: &IFrameStreamInf) -> TagLine {
+    let mut tag = TagLine::new("EXT-X-I-FRAME-STREAM-INF");
+    tag.push("BANDWIDTH", Value::Integer(ifsi.bandwidth));
+    if let Some(v) = &ifsi.codecs {
+        tag.push("CODECS", quoted(&v.to_string()));
+    }
+    if let Some(r) = &ifsi.resolution {
+        tag.push("RESOLUTION", resolution(r));
+    }
+    if let Some(v) = ifsi.hdcp_level {
+        tag.push("HDCP-LEVEL", enumerated(v.as_str()));
+    }
+    if let Some(v) = &ifsi.video_range {
+        tag.push("VIDEO-RANGE", video_range(v));
+    }
+    if let Some(v) = &ifsi.stable_variant_id {
+        tag.push("STABLE-VARIANT-ID", quoted(v));
+    }
+    if let Some(v) = &ifsi.video {
+        tag.push("VIDEO", quoted(v));
+    }
+    if let Some(v) = &ifsi.pathway_id {
+        tag.push("PATHWAY-ID", quoted(v));
+    }
+    tag.push("URI", quoted(ifsi.uri.as_str()));
+    tag
+}
+
+/* Text of the value. Floats are written with `precision` decimals if given, otherwise
+   as short as possible. Quoted strings cannot contain double quotes nor line breaks. */
+pub fn write_value(value: &Value, precision: Option<usize>) -> Result<String, String> {
+    Ok(match value {
+        Value::Integer(v) => v.to_string(),
+        Value::HexadecimalSequence(v) => v.clone(),
+        Value::Float(v) => match precision {
+            Some(p) => format!("{:.*}", p, v),
+            None => v.to_string(),
+        },
+        Value::QuotedString(v) if v.contains(['"', '\r', '\n']) => return Err(format!("Cannot quote {:?}", v)),
+        Value::QuotedString(v) => format!("\"{}\"", v),
+        Value::EnumeratedString(v) => v.clone(),
+        Value::Resolution(w, h) => format!("{}x{}", w, h),
+    })
+}
+
+fn write_attributes<'a>(name: &str, attributes: impl Iterator<Item = &'a (String, Value)>, precision: Option<usize>)
+    -> Result<String, String> {
+    let mut ret = format!("#{}:", name);
+    for (i, (n, v)) in attributes.enumerate() {
+        if i > 0 {
+            ret.push(',');
+        }
+        let v = write_value(v, precision).map_err(|e| format!("{} of {}: {}", n, name, e))?;
+        ret += &format!("{}={}", n, v);
+    }
+    Ok(ret)
+}
+
+pub trait TagWriter: Send + Sync {
+    /* The line of the tag, without line break */
+    fn write(&self, tag: &TagLine) -> Result<String, String>;
+}
+
+// Attributes in the order of the TagLine, floats as short as possible
+pub struct DefaultTagWriter;
+
+impl TagWriter for DefaultTagWriter {
+    fn write(&self, tag: &TagLine) -> Result<String, String> {
+        write_attributes(&tag.name, tag.attributes.iter(), None)
+    }
+}
+
+// House style of an organization for a tag
+#[derive(Debug, Clone, Default)]
+pub struct HouseStyle {
+    pub order: Vec<String>, // attributes written first, in this order, the others follow as given
+    pub float_precision: Option<usize>,
+    pub omit: Vec<String>, // left out unless required by the tag
+}
+
+impl TagWriter for HouseStyle {
+    fn write(&self, tag: &TagLine) -> Result<String, String> {
+        let spec = registry::tag(&tag.name);
+        let required = |name: &str| spec.and_then(|s| s.attribute(name)).is_some_and(|a| a.required);
+        let mut attributes: Vec<&(String, Value)> = tag.attributes.iter()
+            .filter(|(n, _)| !self.omit.contains(n) || required(n))
+            .collect();
+        // stable, so attributes not in `order` keep their relative order
+        attributes.sort_by_key(|(n, _)| self.order.iter().position(|o| o == n).unwrap_or(self.order.len()));
+        write_attributes(&tag.name, attributes.into_iter(), self.float_precision)
+    }
+}
+
+/* The tag line `raw` of the source with the edits from `original` to `edited`: attributes
+   keep their position and text unless their value changed, new ones are appended */
+fn edit_line(raw: &str, original: &TagLine, edited: &TagLine) -> Result<String, String> {
+    let Ok(ParsedLine::TagWithAttributes(name, attr)) = tokenizer::parse_line(raw) else {
+        return Err(format!("Not a tag with attributes: {}", raw))
+    };
+    let text = |tag: &TagLine, n: &str| tag.get(n).map(|v| write_value(v, None)).transpose()
+        .map_err(|e| format!("{} of {}: {}", n, name, e));
+    let mut parts = vec![];
+    for a in attr.iter() {
+        let (before, after) = (text(original, a.name)?, text(edited, a.name)?);
+        if before == after {
+            // also attributes not interpreted, such as CLOSED-CAPTIONS=NONE
+            parts.push(format!("{}={}", a.name, a.raw));
+        } else if let Some(v) = after {
+            parts.push(format!("{}={}", a.name, v));
+        }
+    }
+    for (n, _) in edited.attributes.iter().filter(|(n, _)| !attr.contains_key(n)) {
+        parts.push(format!("{}={}", n, text(edited, n)?.unwrap_or_default()));
+    }
+    Ok(format!("#{}:{}", name, parts.join(",")))
+}
+
+// Replacement of a line of the source, None to leave it out, and lines added after it
+type OutputLine = (Option<String>, Vec<String>);
+
+// Where write_preserving puts the items of one kind
+struct Section<'a, T> {
+    originals: &'a [T],
+    slots: Vec<(usize, Option<usize>)>, // lines of the tag and URI of each original
+    edited: &'a [T],
+    same: fn(&T, &T) -> bool,
+    similar: fn(&T, &T) -> bool,
+    tag: fn(&T) -> TagLine,
+    uri: fn(&T) -> Option<String>,
+}
+
+/*
+Position in `after` of each entry of `before`, None when it was removed. Entries are
+paired by `same` first, e.g. equal stable ids, then by `similar` among those left,
+e.g. the same URI, so that entries edited in place are paired too.
+*/
+pub fn pair_entries<T>(before: &[T], after: &[T], same: impl Fn(&T, &T) -> bool, similar: impl Fn(&T, &T) -> bool)
+    -> Vec<Option<usize>> {
+    let mut ret: Vec<Option<usize>> = vec![None; before.len()];
+    let mut taken = vec![false; after.len()];
+    for matches in [&same as &dyn Fn(&T, &T) -> bool, &similar] {
+        for (i, b) in before.iter().enumerate() {
+            if ret[i].is_some() {
+                continue
+            }
+            if let Some(j) = (0..after.len()).find(|j| !taken[*j] && matches(b, &after[*j])) {
+                ret[i] = Some(j);
+                taken[j] = true;
+            }
+        }
+    }
+    ret
+}
+
+// Pairing of the entries of each kind, for pair_entries
+pub fn same_media(a: &Media, b: &Media) -> bool {
+    a.key() == b.key()
+}
+
+pub fn similar_media(a: &Media, b: &Media) -> bool {
+    a.type_ == b.type_ && a.uri.is_some() && a.uri == b.uri
+}
+
+pub fn same_stream_inf(a: &StreamInf, b: &StreamInf) -> bool {
+    a.stable_id() == b.stable_id()
+}
+
+pub fn similar_stream_inf(a: &StreamInf, b: &StreamInf) -> bool {
+    a.uri == b.uri
+}
+
+pub fn same_i_frame_stream_inf(a: &IFrameStreamInf, b: &IFrameStreamInf) -> bool {
+    a.stable_id() == b.stable_id()
+}
+
+pub fn similar_i_frame_stream_inf(a: &IFrameStreamInf, b: &IFrameStreamInf) -> bool {
+    a.uri == b.uri
+}
+
+// TagWriters by tag name, DefaultTagWriter for the other tags
+#[derive(Default)]
+pub struct Writer {
+    hooks: HashMap<String, Box<dyn TagWriter>>,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* Write tags named `tag` with `writer`, replacing any previously registered one */
+    pub fn register(&mut self, tag: &str, writer: impl TagWriter + 'static) -> &mut Self {
+        self.hooks.insert(tag.to_string(), Box::new(writer));
+        self
+    }
+
+    pub fn write_tag(&self, tag: &TagLine) -> Result<String, String> {
+        match self.hooks.get(&tag.name) {
+            Some(w) => w.write(tag),
+            None => DefaultTagWriter.write(tag),
+        }
+    }
+
+    /*
+    The playlist as m3u8 text: renditions, variants each followed by its URI, then
+    I-frame variants. Lines kept in passthrough mode are written before the entry that
+    followed them.
+    */
+    pub fn write_multivariant(&self, playlist: &MultivariantPlaylist) -> Result<String, String> {
+        let mut lines = vec!["#EXTM3U".to_string()];
+        if let Some(v) = playlist.version {
+            lines.push(format!("#EXT-X-VERSION:{}", v));
+        }
+        if playlist.independent_segments {
+            lines.push("#EXT-X-INDEPENDENT-SEGMENTS".to_string());
+        }
+        // unrecognized lines go back before the entry that followed them
+        let mut unrecognized = playlist.unrecognized.iter().collect::<Vec<_>>();
+        unrecognized.sort_by_key(|l| l.after);
+        let mut unrecognized = unrecognized.into_iter().peekable();
+        let mut entries = 0;
+        let mut next_entry = |lines: &mut Vec<String>| {
+            while let Some(l) = unrecognized.next_if(|l| l.after <= entries) {
+                lines.push(l.text.clone());
+            }
+            entries += 1;
+        };
+        for m in &playlist.media {
+            next_entry(&mut lines);
+            lines.push(self.write_tag(&media_tag(m))?);
+        }
+        for si in &playlist.stream_inf {
+            next_entry(&mut lines);
+            lines.push(self.write_tag(&stream_inf_tag(si))?);
+            if si.uri.as_str().is_empty() {
+                return Err("EXT-X-STREAM-INF without URI".to_string())
+            }
+            lines.push(si.uri.to_string());
+        }
+        for ifsi in &playlist.i_frame_stream_inf {
+            next_entry(&mut lines);
+            lines.push(self.write_tag(&i_frame_stream_inf_tag(ifsi))?);
+        }
+        lines.extend(unrecognized.map(|l| l.text.clone()));
+        lines.push(String::new());
+        Ok(lines.join("\n"))
+    }
+
+    /*
+    The playlist as in its source, see ParseOptions::preserve, changed only where it
+    was edited: lines of removed tags are left out, modified tags keep the text of
+    unchanged attributes, kept tags fill the places of the original ones in their
+    new order and added tags follow the last of their kind. Other lines, blank ones
+    included, are kept. Without source this is write_multivariant.
+    */
+    pub fn write_preserving(&self, playlist: &MultivariantPlaylist) -> Result<String, String> {
+        let Some(source) = &playlist.source else {
+            return self.write_multivariant(playlist)
+        };
+        let original = &source.original;
+        let lines: Vec<&str> = source.text.split('\n').collect();
+        let mut out: Vec<OutputLine> = lines.iter().map(|l| (Some(l.to_string()), vec![])).collect();
+
+        let versions: Vec<usize> = (0..lines.len()).filter(|i| lines[*i].starts_with("#EXT-X-VERSION:")).collect();
+        if playlist.version != original.version {
+            versions.iter().for_each(|i| out[*i].0 = None);
+            if let Some(v) = playlist.version {
+                out[0].1.push(format!("#EXT-X-VERSION:{}", v));
+            }
+        }
+        let independent: Vec<usize> = (0..lines.len()).filter(|i| lines[*i] == "#EXT-X-INDEPENDENT-SEGMENTS").collect();
+        if !playlist.independent_segments {
+            independent.iter().for_each(|i| out[*i].0 = None);
+        } else if independent.is_empty() {
+            out[0].1.push("#EXT-X-INDEPENDENT-SEGMENTS".to_string());
+        }
+        let media = self.place(&mut out, &lines, Section{
+            originals: &original.media,
+            slots: source.media_lines.iter().map(|l| (*l, None)).collect(),
+            edited: &playlist.media,
+            same: same_media,
+            similar: similar_media,
+            tag: media_tag,
+            uri: |_| None,
+        }, 0)?;
+        let stream_inf = self.place(&mut out, &lines, Section{
+            originals: &original.stream_inf,
+            slots: source.stream_inf_lines.iter().map(|(t, u)| (*t, Some(*u))).collect(),
+            edited: &playlist.stream_inf,
+            same: same_stream_inf,
+            similar: similar_stream_inf,
+            tag: stream_inf_tag,
+            uri: |si| Some(si.uri.to_string()),
+        }, media)?;
+        self.place(&mut out, &lines, Section{
+            originals: &original.i_frame_stream_inf,
+            slots: source.i_frame_stream_inf_lines.iter().map(|l| (*l, None)).collect(),
+            edited: &playlist.i_frame_stream_inf,
+            same: same_i_frame_stream_inf,
+            similar: similar_i_frame_stream_inf,
+            tag: i_frame_stream_inf_tag,
+            uri: |_| None,
+        }, stream_inf)?;
+
+        let mut ret = vec![];
+        for (line, added) in out {
+            ret.extend(line);
+            ret.extend(added);
+        }
+        Ok(ret.join("\n"))
+    }
+
+    // Fill in the lines of a section, returns the line after which the next section adds its tags
+    fn place<T>(&self, out: &mut [OutputLine], lines: &[&str], section: Section<T>, anchor: usize)
+        -> Result<usize, String> {
+        let Section{ originals, slots, edited, same, similar, tag, uri } = section;
+        let pairs = pair_entries(originals, edited, same, similar);
+        let find = |e: usize| pairs.iter().position(|p| *p == Some(e));
+        let kept: Vec<usize> = (0..originals.len()).filter(|i| pairs[*i].is_some()).collect();
+        for (_, (t, u)) in slots.iter().enumerate().filter(|(i, _)| !kept.contains(i)) {
+            out[*t].0 = None;
+            if let Some(u) = u {
+                out[*u].0 = None;
+            }
+        }
+        for ((o, e), slot) in (0..edited.len()).filter_map(|e| Some((find(e)?, &edited[e]))).zip(&kept) {
+            let (t, u) = slots[o];
+            let (tag_before, tag_after) = (tag(&originals[o]), tag(e));
+            out[slots[*slot].0].0 = Some(if tag_before == tag_after {
+                lines[t].to_string()
+            } else {
+                edit_line(lines[t], &tag_before, &tag_after)?
+            });
+            if let (Some(u), Some(slot_u)) = (u, slots[*slot].1) {
+                out[slot_u].0 = Some(if uri(&originals[o]) == uri(e) {
+                    lines[u].to_string()
+                } else {
+                    uri(e).unwrap_or_default()
+                });
+            }
+        }
+        let anchor = slots.last().map_or(anchor, |(t, u)| u.unwrap_or(*t));
+        for e in (0..edited.len()).filter(|e| find(*e).is_none()).map(|e| &edited[e]) {
+            out[anchor].1.push(self.write_tag(&tag(e))?);
+            out[anchor].1.extend(uri(e));
+        }
+        Ok(anchor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{self, ParseOptions};
+    use crate::validator::BandwidthBounds;
+    use super::{HouseStyle, TagLine, TagWriter, Value, Writer};
+
+    fn stream_inf() -> TagLine {
+        let mut tag = TagLine::new("EXT-X-STREAM-INF");
+        tag.push("BANDWIDTH", Value::Integer(2483789));
+        tag.push("CODECS", Value::QuotedString("mp4a.40.2,hvc1.2.4.L90.90".to_string()));
+        tag.push("RESOLUTION", Value::Resolution(960, 540));
+        tag.push("FRAME-RATE", Value::Float(23.976));
+        tag.push("CLOSED-CAPTIONS", Value::EnumeratedString("NONE".to_string()));
+        tag
+    }
+
+    #[test]
+    fn test_default_writer() {
+        let w = Writer::new();
+        assert_eq!(w.write_tag(&stream_inf()).unwrap(), "#EXT-X-STREAM-INF:BANDWIDTH=2483789,\
+            CODECS=\"mp4a.40.2,hvc1.2.4.L90.90\",RESOLUTION=960x540,FRAME-RATE=23.976,CLOSED-CAPTIONS=NONE");
+        let mut tag = TagLine::new("EXT-X-MEDIA");
+        tag.push("NAME", Value::QuotedString("say \"hi\"".to_string()));
+        assert_eq!(w.write_tag(&tag).unwrap_err(), "NAME of EXT-X-MEDIA: Cannot quote \"say \\\"hi\\\"\"");
+    }
+
+    // any TagWriter can be registered
+    struct Lowercase;
+
+    impl TagWriter for Lowercase {
+        fn write(&self, tag: &TagLine) -> Result<String, String> {
+            Ok(format!("#{}:{}", tag.name, tag.attributes.len()).to_lowercase())
+        }
+    }
+
+    #[test]
+    fn test_hooks() {
+        let mut w = Writer::new();
+        w.register("EXT-X-STREAM-INF", HouseStyle{
+            order: vec!["RESOLUTION".to_string(), "FRAME-RATE".to_string()],
+            float_precision: Some(3),
+            omit: vec!["CLOSED-CAPTIONS".to_string(), "BANDWIDTH".to_string()],
+        });
+        let mut tag = stream_inf();
+        tag.attributes[3].1 = Value::Float(24.0);
+        // BANDWIDTH is required
+        assert_eq!(w.write_tag(&tag).unwrap(), "#EXT-X-STREAM-INF:RESOLUTION=960x540,FRAME-RATE=24.000,\
+            BANDWIDTH=2483789,CODECS=\"mp4a.40.2,hvc1.2.4.L90.90\"");
+        w.register("EXT-X-STREAM-INF", Lowercase);
+        assert_eq!(w.write_tag(&tag).unwrap(), "#ext-x-stream-inf:5");
+        tag.name = "EXT-X-I-FRAME-STREAM-INF".to_string();
+        assert!(w.write_tag(&tag).unwrap().starts_with("#EXT-X-I-FRAME-STREAM-INF:BANDWIDTH=2483789,"));
+    }
+
+    #[test]
+    fn test_write_multivariant() {
+        let data = include_str!("../data/master_unenc_hdr10_all.m3u8");
+        let pl = parser::parse_playlist(data).unwrap();
+        let text = pl.to_m3u8().unwrap();
+        assert!(text.starts_with("#EXTM3U\n#EXT-X-INDEPENDENT-SEGMENTS\n#EXT-X-MEDIA:TYPE=AUDIO,"));
+        let mut versioned = pl.clone();
+        versioned.version = Some(7);
+        assert!(versioned.to_m3u8().unwrap().starts_with("#EXTM3U\n#EXT-X-VERSION:7\n#EXT-X-INDEPENDENT-SEGMENTS\n"));
+        assert!(text.contains("\n#EXT-X-STREAM-INF:BANDWIDTH=2483789,AVERAGE-BANDWIDTH=1762745,\
+            CODECS=\"mp4a.40.2,hvc1.2.4.L90.90\",RESOLUTION=960x540,FRAME-RATE=23.97,VIDEO-RANGE=PQ,AUDIO=\"aac-128k\",CLOSED-CAPTIONS=NONE\n\
+            hdr10/unenc/1650k/vod.m3u8\n"));
+        // written playlists parse back to the same
+        let again = parser::parse_playlist(&text).unwrap();
+        assert_eq!(again.media.len(), pl.media.len());
+        assert_eq!(again.stream_inf.len(), pl.stream_inf.len());
+        assert_eq!(again.i_frame_stream_inf.len(), pl.i_frame_stream_inf.len());
+        assert_eq!(again.to_m3u8().unwrap(), text);
+
+        let pl = parser::parse_playlist(include_str!("../data/captions.m3u8")).unwrap();
+        let text = pl.to_m3u8().unwrap();
+        assert!(text.contains("TYPE=CLOSED-CAPTIONS,GROUP-ID=\"cc\",LANGUAGE=\"en\",NAME=\"English\",\
+            DEFAULT=YES,AUTOSELECT=YES,INSTREAM-ID=\"CC1\""), "{}", text);
+        assert!(text.contains("AUDIO=\"aac\",SUBTITLES=\"subs\",CLOSED-CAPTIONS=\"cc\"\n"), "{}", text);
+        assert_eq!(parser::parse_playlist(&text).unwrap().to_m3u8().unwrap(), text);
+
+        // passthrough lines stay between the entries around them
+        let data = "#EXTM3U\n#EXT-X-SESSION-KEY:METHOD=AES-128,URI=\"k\"\n#EXT-X-STREAM-INF:BANDWIDTH=1\nv1.m3u8\n\
+            #EXT-X-VENDOR:next\n#EXT-X-STREAM-INF:BANDWIDTH=2\nv2.m3u8\n#EXT-X-VENDOR:last\n";
+        let pl = parser::parse_playlist_passthrough(data).unwrap();
+        assert_eq!(pl.to_m3u8().unwrap(), data);
+    }
+
+    #[test]
+    fn test_sort_attributes() {
+        let mut tag = TagLine::new("EXT-X-STREAM-INF");
+        tag.push("X-VENDOR", Value::Integer(1));
+        tag.push("CODECS", Value::QuotedString("avc1.64001f".to_string()));
+        tag.push("BANDWIDTH", Value::Integer(1));
+        tag.sort_attributes();
+        let names: Vec<&str> = tag.attributes.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["BANDWIDTH", "CODECS", "X-VENDOR"]);
+    }
+
+    #[test]
+    fn test_write_preserving() {
+        let options = ParseOptions{ preserve: true, ..Default::default() };
+        let data = include_str!("../data/master_unenc_hdr10_all.m3u8");
+        let (pl, _) = parser::parse_playlist_with_options(data, &options).unwrap();
+        assert_eq!(pl.to_m3u8().unwrap(), data);
+        // filtered, blank lines around removed tags are kept
+        let filtered = pl.select_max_bandwidth(1000000).unwrap();
+        let text = filtered.to_m3u8().unwrap();
+        let mut expected = vec![];
+        let mut lines = data.lines();
+        while let Some(line) = lines.next() {
+            let Some(attributes) = line.strip_prefix("#EXT-X-STREAM-INF:BANDWIDTH=") else {
+                expected.push(line);
+                continue
+            };
+            let uri = lines.next().unwrap();
+            if attributes.split(',').next().unwrap().parse::<u64>().unwrap() <= 1000000 {
+                expected.extend([line, uri]);
+            }
+        }
+        assert_eq!(text.lines().collect::<Vec<_>>(), expected);
+
+        let data = "#EXTM3U\n\
+            # encoder v2\n\
+            #EXT-X-MEDIA:NAME=\"English\",TYPE=AUDIO,GROUP-ID=\"aac\",DEFAULT=NO,URI=\"en.m3u8\"\n\
+            #EXT-X-STREAM-INF:CODECS=\"avc1.64001f,mp4a.40.2\",RESOLUTION=1280x720,BANDWIDTH=0,X-VENDOR=1,AUDIO=\"aac\"\n\
+            720p.m3u8\n";
+        let (pl, _) = parser::parse_playlist_with_options(data, &options).unwrap();
+        assert_eq!(pl.to_m3u8().unwrap(), data);
+        let fixed = pl.fix_bandwidths(&BandwidthBounds::default()).add_audio_only_fallback().unwrap();
+        let bandwidth = fixed.stream_inf[0].bandwidth;
+        assert_eq!(fixed.to_m3u8().unwrap(), format!("#EXTM3U\n\
+            # encoder v2\n\
+            #EXT-X-MEDIA:NAME=\"English\",TYPE=AUDIO,GROUP-ID=\"aac\",DEFAULT=NO,URI=\"en.m3u8\"\n\
+            #EXT-X-STREAM-INF:CODECS=\"avc1.64001f,mp4a.40.2\",RESOLUTION=1280x720,BANDWIDTH={},X-VENDOR=1,AUDIO=\"aac\"\n\
+            720p.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=128000,CODECS=\"mp4a.40.2\",AUDIO=\"aac\"\n\
+            en.m3u8\n", bandwidth));
+        // without source, the playlist is written from scratch
+        let mut pl = fixed.clone();
+        pl.source = None;
+        assert!(pl.to_m3u8().unwrap().starts_with("#EXTM3U\n# encoder v2\n#EXT-X-MEDIA:TYPE=AUDIO,URI=\"en.m3u8\",GROUP-ID=\"aac\",NAME="));
+
+        let data = "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1\nv.m3u8\n#EXT-X-VERSION:6\n";
+        let (mut pl, _) = parser::parse_playlist_with_options(data, &options).unwrap();
+        assert_eq!(pl.version, Some(6));
+        assert_eq!(pl.to_m3u8().unwrap(), data);
+        pl.version = Some(7);
+        assert_eq!(pl.to_m3u8().unwrap(), "#EXTM3U\n#EXT-X-VERSION:7\n#EXT-X-STREAM-INF:BANDWIDTH=1\nv.m3u8\n");
+        pl.version = None;
+        assert_eq!(pl.to_m3u8().unwrap(), "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1\nv.m3u8\n");
+    }
+}